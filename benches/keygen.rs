@@ -0,0 +1,54 @@
+//! Compares generating 100k actor keys the old way, one OS RNG draw per key,
+//! with the chunked ChaCha derivation in `src/keygen.rs`.
+//!
+//! Run with `cargo bench --bench keygen`. The keygen module is private to the
+//! library, so it is compiled into this benchmark directly.
+
+use alloy_primitives::B256;
+use alloy_signer_local::PrivateKeySigner;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+#[allow(dead_code)]
+#[path = "../src/keygen.rs"]
+mod keygen;
+
+/// Actor keys generated per iteration.
+const ACTORS: u64 = 100_000;
+
+/// The generation path before seeded streams: a fresh OS RNG key per actor.
+fn os_rng_keys() -> Vec<PrivateKeySigner> {
+    (0..ACTORS)
+        .into_par_iter()
+        .map(|_| PrivateKeySigner::random())
+        .collect()
+}
+
+fn chacha_keys(seed: B256) -> Vec<PrivateKeySigner> {
+    keygen::derive_keys(seed, 0..ACTORS, |_| {})
+}
+
+fn actor_keys(c: &mut Criterion) {
+    let seed = B256::repeat_byte(0x5e);
+    // Chunking must not change which key lands at which index.
+    let keys = chacha_keys(seed);
+    for index in [0, keygen::CHUNK_SIZE - 1, keygen::CHUNK_SIZE, ACTORS - 1] {
+        assert_eq!(
+            keys[index as usize].address(),
+            keygen::derive_key(seed, index).address(),
+            "key {index}"
+        );
+    }
+
+    let mut group = c.benchmark_group("generate_actor_keys");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(ACTORS));
+    group.bench_function(BenchmarkId::new("os_rng", ACTORS), |b| b.iter(os_rng_keys));
+    group.bench_function(BenchmarkId::new("chacha", ACTORS), |b| {
+        b.iter(|| chacha_keys(seed))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, actor_keys);
+criterion_main!(benches);
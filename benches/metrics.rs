@@ -0,0 +1,66 @@
+//! `SectionTimer` throughput with 16 threads timing sections at once, as the
+//! rayon generation and tokio execution threads do during a run.
+//!
+//! `disabled` times a single timer after `metrics::set_enabled(false)`, which
+//! should cost a handful of nanoseconds.
+//!
+//! Run with `cargo bench --bench metrics`. The metrics module is private to
+//! the library, so it is compiled into this benchmark directly.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[allow(dead_code, unused_macros, private_bounds)]
+#[path = "../src/metrics.rs"]
+mod metrics;
+
+use metrics::SectionTimer;
+
+const THREADS: usize = 16;
+
+/// Outer/inner span pairs each thread records per iteration.
+const SPANS_PER_THREAD: u64 = 1_000;
+
+/// Every thread in `pool` records `SPANS_PER_THREAD` nested span pairs, the
+/// inner one grouped under a block when `grouped` is set.
+fn hammer(pool: &rayon::ThreadPool, grouped: bool) {
+    pool.broadcast(|_| {
+        for _ in 0..SPANS_PER_THREAD {
+            let _outer = SectionTimer::new_static("outer");
+            let _inner = if grouped {
+                SectionTimer::new_grouped("block 1", "inner")
+            } else {
+                SectionTimer::new_static("inner")
+            };
+        }
+    });
+}
+
+fn section_timers(c: &mut Criterion) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(THREADS)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("section_timer");
+    group.throughput(Throughput::Elements(2 * THREADS as u64 * SPANS_PER_THREAD));
+    group.bench_function(BenchmarkId::new("flat", THREADS), |b| {
+        b.iter(|| hammer(&pool, false))
+    });
+    group.bench_function(BenchmarkId::new("block_grouped", THREADS), |b| {
+        b.iter(|| hammer(&pool, true))
+    });
+    group.finish();
+}
+
+fn disabled_timer(c: &mut Criterion) {
+    metrics::set_enabled(false);
+    c.bench_function("section_timer/disabled", |b| {
+        b.iter(|| SectionTimer::new_static(black_box("outer")))
+    });
+    metrics::set_enabled(true);
+}
+
+criterion_group!(benches, section_timers, disabled_timer);
+criterion_main!(benches);
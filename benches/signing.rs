@@ -0,0 +1,113 @@
+//! Compares the old `TransactionRequest` signing path with the template path
+//! in `src/transaction.rs`, on a batch of plain transfers.
+//!
+//! Run with `cargo bench --bench signing`. The signing module is private to
+//! the library, so it is compiled into this benchmark directly.
+
+use alloy_consensus::{EthereumTxEnvelope, TxEip4844, transaction::SignerRecoverable};
+use alloy_network::TxSignerSync;
+use alloy_primitives::{Address, TxKind, U256};
+use alloy_rpc_types_eth::{TransactionInput, TransactionRequest};
+use alloy_signer_local::{LocalSigner, PrivateKeySigner};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use k256::ecdsa::SigningKey;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use reth_ethereum::TransactionSigned;
+use reth_primitives_traits::Recovered;
+
+#[allow(dead_code)]
+#[path = "../src/error.rs"]
+mod error;
+#[allow(dead_code)]
+#[path = "../src/transaction.rs"]
+mod transaction;
+
+use transaction::{DEFAULT_CHAIN_ID, TRANSFER_GAS_LIMIT, TxTemplate, sign_batch};
+
+/// Transactions signed per iteration.
+const BATCH_SIZE: u64 = 1_000;
+
+/// Outputs of each path checked with full signature recovery before timing.
+const VERIFIED_SAMPLE: usize = 32;
+
+/// The signing path before templates: build a `TransactionRequest`, let
+/// `build_typed_tx` validate it, and sign through `TxSignerSync`.
+fn sign_request(
+    signer: &LocalSigner<SigningKey>,
+    nonce: u64,
+    to: Address,
+) -> Recovered<EthereumTxEnvelope<TxEip4844>> {
+    let request = TransactionRequest {
+        nonce: Some(nonce),
+        value: Some(U256::from(100)),
+        to: Some(TxKind::Call(to)),
+        gas: Some(TRANSFER_GAS_LIMIT),
+        max_fee_per_gas: Some(20e9 as u128),
+        max_priority_fee_per_gas: Some(20e9 as u128),
+        chain_id: Some(DEFAULT_CHAIN_ID),
+        input: TransactionInput {
+            input: None,
+            data: None,
+        },
+        ..Default::default()
+    };
+    let mut typed_tx = request.build_typed_tx().unwrap();
+    let signature = signer.sign_transaction_sync(&mut typed_tx).unwrap();
+    let signed_tx: TransactionSigned = typed_tx.into_envelope(signature).into();
+    Recovered::new_unchecked(signed_tx, signer.address())
+}
+
+fn request_batch(
+    signer: &LocalSigner<SigningKey>,
+) -> Vec<Recovered<EthereumTxEnvelope<TxEip4844>>> {
+    (0..BATCH_SIZE)
+        .into_par_iter()
+        .map(|nonce| sign_request(signer, nonce, Address::with_last_byte(1)))
+        .collect()
+}
+
+fn template_batch(
+    signer: &LocalSigner<SigningKey>,
+) -> Vec<Recovered<EthereumTxEnvelope<TxEip4844>>> {
+    let templates = (0..BATCH_SIZE)
+        .map(|nonce| {
+            TxTemplate::new(
+                nonce,
+                TxKind::Call(Address::with_last_byte(1)),
+                Some(U256::from(100)),
+                None,
+            )
+            .with_gas_limit(TRANSFER_GAS_LIMIT)
+        })
+        .collect();
+    sign_batch(signer, templates).unwrap()
+}
+
+/// Recover the sender of a sample of `txs` from their signatures rather than
+/// trusting the claimed signer.
+fn verify_senders(name: &str, signer: Address, txs: &[Recovered<EthereumTxEnvelope<TxEip4844>>]) {
+    for tx in txs.iter().take(VERIFIED_SAMPLE) {
+        let recovered = tx.inner().recover_signer().unwrap();
+        assert_eq!(recovered, signer, "{name}: recovered sender mismatch");
+        assert_eq!(tx.signer(), signer, "{name}: claimed sender mismatch");
+    }
+}
+
+fn signing(c: &mut Criterion) {
+    let signer = PrivateKeySigner::random();
+    verify_senders("request", signer.address(), &request_batch(&signer));
+    verify_senders("template", signer.address(), &template_batch(&signer));
+
+    let mut group = c.benchmark_group("sign_transfer_batch");
+    group.throughput(Throughput::Elements(BATCH_SIZE));
+    group.bench_function(BenchmarkId::new("request", BATCH_SIZE), |b| {
+        b.iter(|| request_batch(&signer))
+    });
+    group.bench_function(BenchmarkId::new("template", BATCH_SIZE), |b| {
+        b.iter(|| template_batch(&signer))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, signing);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+//! Exports `SANDBOX_GIT_DESCRIBE` so run manifests can record which source
+//! tree built the binary.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    println!("cargo:rerun-if-env-changed=SANDBOX_GIT_DESCRIBE");
+
+    // An explicit value wins, for builds from a tarball or a vendored tree.
+    if std::env::var_os("SANDBOX_GIT_DESCRIBE").is_some() {
+        return;
+    }
+
+    // Outside a git checkout, or without git installed, the manifest records
+    // `null` rather than failing the build.
+    let Ok(output) = Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+    else {
+        return;
+    };
+    if !output.status.success() {
+        return;
+    }
+    let describe = String::from_utf8_lossy(&output.stdout);
+    let describe = describe.trim();
+    if !describe.is_empty() {
+        println!("cargo:rustc-env=SANDBOX_GIT_DESCRIBE={describe}");
+    }
+}
@@ -0,0 +1,21 @@
+//! Feeds arbitrary bytes to the block file reader, which must return an error
+//! for anything it cannot read rather than panic.
+//!
+//! Run with `cargo +nightly fuzz run block_file_reader` from the repository
+//! root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reth_sandbox::block_file::BlockFileReader;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(reader) = BlockFileReader::from_reader(data) else {
+        return;
+    };
+    for block in reader {
+        if block.is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,116 @@
+#!/usr/bin/env python3
+"""Replay a sandbox payloads file against a node's Engine API.
+
+The node must be initialized with the genesis the run wrote (`GENESIS_OUT`,
+e.g. sandbox_genesis.json). Each `newPayload` request is sent in order and
+must return VALID; the node's head is then moved to that block with
+`forkchoiceUpdated`.
+
+    scripts/replay_payloads.py payloads.jsonl \
+        --engine-url http://127.0.0.1:8551 --jwt-secret /path/to/jwt.hex
+"""
+
+import argparse
+import base64
+import hashlib
+import hmac
+import json
+import sys
+import time
+import urllib.request
+
+# forkchoiceUpdated version matching each newPayload version.
+FORKCHOICE_METHOD = {
+    "engine_newPayloadV2": "engine_forkchoiceUpdatedV2",
+    "engine_newPayloadV3": "engine_forkchoiceUpdatedV3",
+    "engine_newPayloadV4": "engine_forkchoiceUpdatedV3",
+}
+
+
+def b64url(data: bytes) -> str:
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def jwt_token(secret: bytes) -> str:
+    header = b64url(json.dumps({"alg": "HS256", "typ": "JWT"}).encode())
+    claims = b64url(json.dumps({"iat": int(time.time())}).encode())
+    signing_input = f"{header}.{claims}".encode()
+    signature = hmac.new(secret, signing_input, hashlib.sha256).digest()
+    return f"{header}.{claims}.{b64url(signature)}"
+
+
+def call(url: str, secret: bytes, request: dict) -> dict:
+    http_request = urllib.request.Request(
+        url,
+        data=json.dumps(request).encode(),
+        headers={
+            "Content-Type": "application/json",
+            "Authorization": f"Bearer {jwt_token(secret)}",
+        },
+    )
+    with urllib.request.urlopen(http_request) as response:
+        body = json.load(response)
+    if "error" in body:
+        raise RuntimeError(f"{request['method']} failed: {body['error']}")
+    return body["result"]
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("payloads", help="payloads JSONL written by `sandbox run`")
+    parser.add_argument("--engine-url", default="http://127.0.0.1:8551")
+    parser.add_argument("--jwt-secret", required=True, help="file holding the hex JWT secret")
+    args = parser.parse_args()
+
+    with open(args.jwt_secret) as f:
+        secret = bytes.fromhex(f.read().strip().removeprefix("0x"))
+
+    with open(args.payloads) as f:
+        chain = json.loads(f.readline())
+
+        chain_id = int(call(args.engine_url, secret, {
+            "jsonrpc": "2.0", "id": 0, "method": "eth_chainId", "params": [],
+        }), 16)
+        genesis = call(args.engine_url, secret, {
+            "jsonrpc": "2.0", "id": 0, "method": "eth_getBlockByNumber", "params": ["0x0", False],
+        })
+        if chain_id != chain["chain_id"] or genesis["hash"] != chain["genesis_hash"]:
+            print(
+                f"node is on chain {chain_id} with genesis {genesis['hash']}, "
+                f"payloads expect chain {chain['chain_id']} with genesis {chain['genesis_hash']}",
+                file=sys.stderr,
+            )
+            return 1
+
+        forkchoice_method = FORKCHOICE_METHOD[chain["new_payload_method"]]
+        replayed = 0
+        for line in f:
+            if not line.strip():
+                continue
+            request = json.loads(line)
+            payload = request["params"][0]
+            number = int(payload["blockNumber"], 16)
+
+            status = call(args.engine_url, secret, request)
+            if status["status"] != "VALID":
+                print(f"block {number}: {status}", file=sys.stderr)
+                return 1
+
+            head = payload["blockHash"]
+            call(args.engine_url, secret, {
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "method": forkchoice_method,
+                "params": [
+                    {"headBlockHash": head, "safeBlockHash": head, "finalizedBlockHash": head},
+                    None,
+                ],
+            })
+            replayed += 1
+
+    print(f"replayed {replayed} payloads, all VALID")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -1,55 +1,215 @@
 //! Lightweight representation of EOAs used to sign the synthetic load.
 
-use alloy_primitives::{Address, hex};
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, B256, hex, map::HashMap};
 use alloy_signer_local::{LocalSigner, PrivateKeySigner};
 use k256::ecdsa::SigningKey;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::{
+    config::{FeeStrategy, FeeStrategyMix, SimulationConfig},
+    invalid::InvalidTxRegistry,
+    keygen, logging,
+    orchestrator::TX,
+    progress::RunProgress,
+};
 
 /// Maintains the deterministic deployer plus a collection of ephemeral EOAs
 /// that will drive transaction load.
 pub struct ActorPool {
     deployer: Actor,
+    /// Holder of the genesis allocation when it is not the deployer; it only
+    /// funds the deployer.
+    faucet: Option<Actor>,
+    /// Uniswap factory `feeToSetter` when it is not the deployer.
+    uniswap_owner: Option<Address>,
     actors: Vec<Actor>,
+    /// Reverse lookup from actor address to its index in `actors`, shared
+    /// with the builder through [`NonceCorrections`].
+    index_by_address: Arc<HashMap<Address, usize>>,
 }
 
 impl ActorPool {
     /// Instantiate the pool with a genesis deployer that has all the funds
-    pub fn new(private_key: &str, address: Address, chain_id: u64) -> Self {
+    pub fn new(signer: PrivateKeySigner, chain_id: u64) -> Self {
         let actors = Vec::new();
 
-        let mut privkey_bytes = [0u8; 32];
-        hex::decode_to_slice(private_key, &mut privkey_bytes).unwrap();
+        let deployer = Actor::with_signer(signer.with_chain_id(Some(chain_id)), 0);
 
-        let signing_key =
-            SigningKey::from_slice(&privkey_bytes).expect("failed to parse signing key");
+        Self {
+            deployer,
+            faucet: None,
+            uniswap_owner: None,
+            actors,
+            index_by_address: Arc::default(),
+        }
+    }
 
-        let signer = PrivateKeySigner::new_with_credential(signing_key, address, Some(chain_id));
+    /// The pool with the roles `config` assigns: its deployer, and the
+    /// genesis allocation holder as a separate faucet when the deployer has
+    /// a key of its own.
+    pub fn for_config(config: &SimulationConfig) -> Self {
+        let mut pool = Self::new(config.deployer_signer().clone(), config.chain_id);
+        pool.deployer.nonce = config.deployer_nonce;
+        if config.has_separate_deployer() {
+            let signer = config
+                .genesis_signer
+                .clone()
+                .with_chain_id(Some(config.chain_id));
+            pool.faucet = Some(Actor::with_signer(signer, config.faucet_nonce));
+        }
+        pool.uniswap_owner = config.uniswap_owner.as_ref().map(|owner| owner.address());
+        pool
+    }
 
-        let deployer = Actor {
-            signer: signer,
-            nonce: 0,
-        };
+    /// Populate the pool with EOAs whose keys are derived from `seed`, so the
+    /// same seed always yields the same actors (and addresses, and fee
+    /// strategies) across runs. Keys are derived in parallel chunks, each
+    /// counted into `progress` as it finishes.
+    pub fn generate_actors_from_seed(
+        &mut self,
+        seed: B256,
+        num_of_actors: u64,
+        fee_strategies: &FeeStrategyMix,
+        progress: &RunProgress,
+    ) {
+        let start = self.actors.len() as u64;
+        let actors = keygen::derive_keys(seed, start..start + num_of_actors, |keys| {
+            progress.record_actors_generated(keys)
+        })
+        .into_iter()
+        .map(|signer| Actor::with_signer(signer, 0).with_fee_strategy_from(fee_strategies))
+        .collect::<Vec<Actor>>();
+        self.extend_actors(actors);
+    }
 
-        Self { deployer, actors }
+    /// Addresses [`Self::generate_actors_from_seed`] would produce for the first
+    /// `num_of_actors` indices, without keeping the signers around.
+    pub fn seeded_addresses(seed: B256, num_of_actors: u64) -> Vec<Address> {
+        keygen::derive_keys(seed, 0..num_of_actors, |_| {})
+            .iter()
+            .map(PrivateKeySigner::address)
+            .collect()
     }
 
-    /// Populate the pool with fresh EOAs created in parallel.
-    pub fn generate_actors(&mut self, num_of_actors: u64) {
-        let actors = (0..num_of_actors)
-            .into_par_iter()
-            .map(|_| Actor::new())
-            .collect::<Vec<Actor>>();
+    /// Write every actor's address, private key, and last nonce to `path` as
+    /// JSON so the keys can be reused with external tools such as `cast`.
+    pub fn export(&self, path: &Path) -> eyre::Result<()> {
+        let actors = self
+            .actors
+            .iter()
+            .map(Actor::to_json)
+            .collect::<Vec<Value>>();
+        let file = json!({
+            "deployer": self.deployer.to_json(),
+            "faucet": self.faucet.as_ref().map(Actor::to_json),
+            "actors": actors,
+        });
+
+        fs::write(path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// Append the actors stored in a file previously produced by [`Self::export`],
+    /// and continue from the deployer and faucet nonces it records when it
+    /// was written for this pool's deployer and faucet.
+    pub fn import(&mut self, path: &Path) -> eyre::Result<()> {
+        let file: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        for role in ["deployer", "faucet"] {
+            if file[role].is_null() {
+                continue;
+            }
+            let recorded = Actor::from_json(&file[role])?;
+            if let Some(actor) = self.role_by_address(&recorded.address()) {
+                actor.nonce = actor.nonce.max(recorded.nonce);
+            }
+        }
+        let actors = file["actors"]
+            .as_array()
+            .ok_or_else(|| eyre::eyre!("actor file is missing the `actors` array"))?;
+
+        let actors = actors
+            .iter()
+            .map(Actor::from_json)
+            .collect::<eyre::Result<Vec<Actor>>>()?;
+        self.extend_actors(actors);
+        Ok(())
+    }
+
+    /// Append actors and record their addresses in the reverse index.
+    fn extend_actors(&mut self, actors: Vec<Actor>) {
+        let start = self.actors.len();
+        let index_by_address = Arc::make_mut(&mut self.index_by_address);
+        index_by_address.reserve(actors.len());
+        for (offset, actor) in actors.iter().enumerate() {
+            index_by_address.insert(actor.address(), start + offset);
+        }
         self.actors.extend(actors);
     }
 
-    /// Return signer + nonce info for an actor at index.
-    pub fn actor_info(&self, index: usize) -> (&LocalSigner<SigningKey>, u64) {
-        (self.actors[index].signer(), self.actors[index].nonce)
+    /// Index of the actor owning `address`, if it belongs to the pool.
+    ///
+    /// The deployer is not part of the actor list; use [`Self::is_deployer`]
+    /// or [`Self::deployer_by_address`] for it.
+    pub fn actor_index(&self, address: &Address) -> Option<usize> {
+        self.index_by_address.get(address).copied()
+    }
+
+    /// The address to index lookup as it stands, for sharing with the
+    /// builder. Actors added later are not in it.
+    pub fn address_index(&self) -> Arc<HashMap<Address, usize>> {
+        Arc::clone(&self.index_by_address)
+    }
+
+    /// Mutable access to the actor owning `address`, e.g. to fix up a nonce.
+    pub fn actor_by_address(&mut self, address: &Address) -> Option<&mut Actor> {
+        let index = self.actor_index(address)?;
+        self.actors.get_mut(index)
+    }
+
+    /// Whether `address` is the genesis deployer.
+    pub fn is_deployer(&self, address: &Address) -> bool {
+        self.deployer.address() == *address
+    }
+
+    /// Mutable access to the deployer if `address` matches it.
+    pub fn deployer_by_address(&mut self, address: &Address) -> Option<&mut Actor> {
+        self.is_deployer(address).then_some(&mut self.deployer)
+    }
+
+    /// Mutable access to the deployer or the faucet, whichever `address`
+    /// belongs to.
+    pub fn role_by_address(&mut self, address: &Address) -> Option<&mut Actor> {
+        if self.is_deployer(address) {
+            return Some(&mut self.deployer);
+        }
+        self.faucet
+            .as_mut()
+            .filter(|faucet| faucet.address() == *address)
+    }
+
+    /// Return signer + nonce info for an actor at index, if it exists.
+    pub fn actor_info(&self, index: usize) -> Option<(&LocalSigner<SigningKey>, u64)> {
+        self.actors
+            .get(index)
+            .map(|actor| (actor.signer(), actor.nonce))
+    }
+
+    /// Fee strategy of the actor at index, if it exists.
+    pub fn fee_strategy(&self, index: usize) -> Option<FeeStrategy> {
+        self.actors.get(index).map(Actor::fee_strategy)
     }
 
-    /// Convenience to access the actor's address.
-    pub fn actor_address(&self, index: usize) -> Address {
-        self.actors[index].address()
+    /// Convenience to access the actor's address, if it exists.
+    pub fn actor_address(&self, index: usize) -> Option<Address> {
+        self.actors.get(index).map(Actor::address)
     }
 
     /// Deployer accessor.
@@ -57,46 +217,217 @@ impl ActorPool {
         &self.deployer
     }
 
+    /// Mutable deployer access, for deployments that advance its nonce.
+    pub fn deployer_mut(&mut self) -> &mut Actor {
+        &mut self.deployer
+    }
+
     /// Returns signer + nonce pair for the deployer.
     pub fn deployer_info(&self) -> (&LocalSigner<SigningKey>, u64) {
         (self.deployer.signer(), self.deployer.nonce)
     }
 
+    /// Whether the genesis allocation is held by a faucet apart from the
+    /// deployer.
+    pub fn has_separate_faucet(&self) -> bool {
+        self.faucet.is_some()
+    }
+
+    /// The genesis allocation holder: the faucet, or the deployer itself.
+    pub fn faucet(&self) -> &Actor {
+        self.faucet.as_ref().unwrap_or(&self.deployer)
+    }
+
+    /// Mutable faucet access, for funding transfers that advance its nonce.
+    pub fn faucet_mut(&mut self) -> &mut Actor {
+        self.faucet.as_mut().unwrap_or(&mut self.deployer)
+    }
+
+    /// Address the Uniswap factory's `feeToSetter` is set to.
+    pub fn uniswap_owner(&self) -> Address {
+        self.uniswap_owner
+            .unwrap_or_else(|| self.deployer.address())
+    }
+
+    /// The actor at `index`, or the deployer when `None`.
+    pub fn owner(&self, index: Option<usize>) -> Option<&Actor> {
+        match index {
+            Some(index) => self.actors.get(index),
+            None => Some(&self.deployer),
+        }
+    }
+
+    /// Mutable access to the actor at `index`, or the deployer when `None`.
+    pub fn owner_mut(&mut self, index: Option<usize>) -> Option<&mut Actor> {
+        match index {
+            Some(index) => self.actors.get_mut(index),
+            None => Some(&mut self.deployer),
+        }
+    }
+
     /// Increment the deployer nonce after a batch of txs has been emitted.
     pub fn increment_deployer_nonce_by(&mut self, amount: u64) {
         self.deployer.increment_nonce_by(amount);
     }
 
     /// Atomically fetch the actor nonce and increment it by `amount`.
-    pub fn get_and_increment_nonce_by(&mut self, index: usize, amount: u64) -> u64 {
-        let nonce = self.actors[index].nonce();
-        self.actors[index].increment_nonce_by(amount);
-        nonce
+    ///
+    /// Returns `None` (and leaves every nonce untouched) for an unknown index.
+    pub fn get_and_increment_nonce_by(&mut self, index: usize, amount: u64) -> Option<u64> {
+        let actor = self.actors.get_mut(index)?;
+        let nonce = actor.nonce();
+        actor.increment_nonce_by(amount);
+        Some(nonce)
+    }
+
+    /// Roll the nonce of the actor, deployer, or faucet owning `address`
+    /// back to `nonce` if it is ahead of it, for transactions that were
+    /// signed but never sent. Unknown addresses are ignored.
+    pub fn rewind_nonce(&mut self, address: &Address, nonce: u64) {
+        if let Some(actor) = self.actor_by_address(address) {
+            actor.rewind_nonce_to(nonce);
+        } else if let Some(role) = self.role_by_address(address) {
+            role.rewind_nonce_to(nonce);
+        }
+    }
+
+    /// Rewind every sender in `txs` to the lowest nonce among its
+    /// transactions there, for transactions signed but never executed.
+    /// Injected invalid transactions are skipped since their nonces are
+    /// deliberately wrong.
+    pub fn rewind_unsent<'a>(
+        &mut self,
+        txs: impl IntoIterator<Item = &'a TX>,
+        invalid_txs: &InvalidTxRegistry,
+    ) {
+        for tx in txs {
+            if invalid_txs.is_injected(tx.hash()) {
+                continue;
+            }
+            self.rewind_nonce(&tx.signer(), tx.nonce());
+        }
+    }
+
+    /// Set each sender in `chain_nonces` to the nonce its account holds on
+    /// chain, for senders whose transactions were skipped and left the
+    /// tracked nonce ahead of it. Returns how many nonces changed.
+    pub fn reconcile_nonces(
+        &mut self,
+        chain_nonces: impl IntoIterator<Item = (Address, u64)>,
+    ) -> usize {
+        let mut corrected = 0;
+        for (address, chain_nonce) in chain_nonces {
+            let actor = if let Some(index) = self.actor_index(&address) {
+                &mut self.actors[index]
+            } else if let Some(role) = self.role_by_address(&address) {
+                role
+            } else {
+                continue;
+            };
+            let tracked = actor.nonce();
+            if tracked == chain_nonce {
+                continue;
+            }
+            actor.reset_nonce_to(chain_nonce);
+            corrected += 1;
+            info!(
+                target: logging::ACTOR,
+                %address,
+                tracked,
+                chain_nonce,
+                "reconciled nonce with chain"
+            );
+        }
+        corrected
+    }
+
+    /// Iterate over every actor in index order.
+    pub fn iter(&self) -> impl Iterator<Item = &Actor> {
+        self.actors.iter()
     }
 
     /// Total number of available actors.
     pub fn len(&self) -> usize {
         self.actors.len()
     }
+
+    /// Whether no actors have been generated yet.
+    pub fn is_empty(&self) -> bool {
+        self.actors.is_empty()
+    }
 }
 
 /// Simple wrapper around [`LocalSigner`] that tracks nonce mutations.
 #[derive(Debug, Clone)]
 pub struct Actor {
     signer: LocalSigner<SigningKey>,
+    /// The signer's address, kept so lookups never go through the signer.
+    address: Address,
     nonce: u64,
+    /// How the actor prices its load.
+    fee_strategy: FeeStrategy,
 }
 
 impl Actor {
-    /// Create a random local signer with zero nonce.
-    pub fn new() -> Self {
-        let signer = LocalSigner::random();
-        Self { signer, nonce: 0 }
+    fn with_signer(signer: LocalSigner<SigningKey>, nonce: u64) -> Self {
+        Self {
+            address: signer.address(),
+            signer,
+            nonce,
+            fee_strategy: FeeStrategy::default(),
+        }
+    }
+
+    /// Pick the actor's fee strategy from `mix`. The draw comes from the
+    /// address, so a seeded actor always gets the same strategy.
+    fn with_fee_strategy_from(mut self, mix: &FeeStrategyMix) -> Self {
+        let address = self.address();
+        let mut draw = [0u8; 8];
+        draw.copy_from_slice(&address[..8]);
+        self.fee_strategy = mix.pick(u64::from_be_bytes(draw));
+        self
+    }
+
+    /// Serialize address, private key, nonce, and fee strategy for
+    /// [`ActorPool::export`].
+    fn to_json(&self) -> Value {
+        json!({
+            "address": self.address().to_string(),
+            "private_key": hex::encode_prefixed(self.signer.to_bytes()),
+            "nonce": self.nonce,
+            "fee_strategy": self.fee_strategy.name(),
+        })
+    }
+
+    /// Rebuild an actor from an entry written by [`Self::to_json`].
+    fn from_json(entry: &Value) -> eyre::Result<Self> {
+        let private_key = entry["private_key"]
+            .as_str()
+            .ok_or_else(|| eyre::eyre!("actor entry is missing `private_key`"))?;
+        let signer: PrivateKeySigner = private_key.parse()?;
+
+        if let Some(address) = entry["address"].as_str() {
+            let address: Address = address.parse()?;
+            if address != signer.address() {
+                return Err(eyre::eyre!(
+                    "actor address {address} does not match its private key"
+                ));
+            }
+        }
+
+        let nonce = entry["nonce"].as_u64().unwrap_or(0);
+        let mut actor = Self::with_signer(signer, nonce);
+        // Files written before fee strategies existed have none.
+        if let Some(name) = entry["fee_strategy"].as_str() {
+            actor.fee_strategy = FeeStrategy::from_name(name)
+                .ok_or_else(|| eyre::eyre!("unknown fee strategy `{name}`"))?;
+        }
+        Ok(actor)
     }
 
     /// Returns the EOA address.
     pub fn address(&self) -> Address {
-        self.signer.address().clone()
+        self.address
     }
 
     /// Returns the signer so callers can sign transactions.
@@ -109,13 +440,184 @@ impl Actor {
         self.nonce
     }
 
+    /// How the actor prices its load.
+    pub fn fee_strategy(&self) -> FeeStrategy {
+        self.fee_strategy
+    }
+
     /// Bump the nonce by an arbitrary amount (handy for batched approvals).
     pub fn increment_nonce_by(&mut self, amount: u64) {
         self.nonce += amount;
     }
 
+    /// Lower the nonce to `nonce`; never raises it.
+    pub fn rewind_nonce_to(&mut self, nonce: u64) {
+        self.nonce = self.nonce.min(nonce);
+    }
+
+    /// Set the nonce to `nonce`, whether above or below the current one.
+    pub fn reset_nonce_to(&mut self, nonce: u64) {
+        self.nonce = nonce;
+    }
+
     /// Predict the contract address created by this actor at a specific nonce.
     pub fn contract_address(&self, nonce: u64) -> Address {
         self.address().create(nonce)
     }
 }
+
+/// On-chain nonces of senders the builder skipped transactions from, waiting
+/// for the orchestrator to reset its tracked nonces to them. Shared between
+/// the two through an `Arc`, along with the actor index lookup so the builder
+/// can name the actors it skips.
+#[derive(Debug, Default)]
+pub struct NonceCorrections {
+    pending: Mutex<Vec<(Address, u64)>>,
+    actors: Mutex<Arc<HashMap<Address, usize>>>,
+}
+
+impl NonceCorrections {
+    /// Record that `address` holds `nonce` on chain after a skipped transaction.
+    pub fn record(&self, address: Address, nonce: u64) {
+        self.pending.lock().unwrap().push((address, nonce));
+    }
+
+    /// Every correction recorded since the last call, oldest first.
+    pub fn take(&self) -> Vec<(Address, u64)> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+
+    /// Publish the actor pool's address lookup once its actors exist.
+    pub fn set_actors(&self, actors: &ActorPool) {
+        *self.actors.lock().unwrap() = actors.address_index();
+    }
+
+    /// Index of the actor owning `address`, once the actors are published.
+    pub fn actor_index(&self, address: &Address) -> Option<usize> {
+        self.actors.lock().unwrap().get(address).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(byte: u8) -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::repeat_byte(byte)).unwrap()
+    }
+
+    /// A deployer keyed `0x01` and three actors keyed `0x02..=0x04`.
+    fn pool() -> ActorPool {
+        let mut pool = ActorPool::new(signer(0x01), 2600);
+        pool.extend_actors(
+            (0x02..=0x04)
+                .map(|byte| Actor::with_signer(signer(byte), 0))
+                .collect(),
+        );
+        pool
+    }
+
+    #[test]
+    fn every_actor_is_found_by_address() {
+        let mut pool = pool();
+        for (index, byte) in (0x02..=0x04).enumerate() {
+            let address = signer(byte).address();
+            assert_eq!(pool.actor_index(&address), Some(index));
+            assert_eq!(pool.actor_address(index), Some(address));
+            let actor = pool.actor_by_address(&address).unwrap();
+            assert_eq!(actor.address(), address);
+            actor.increment_nonce_by(2);
+            assert_eq!(pool.actor_info(index).unwrap().1, 2);
+        }
+    }
+
+    #[test]
+    fn deployer_is_not_an_actor() {
+        let mut pool = pool();
+        let deployer = signer(0x01).address();
+        assert_eq!(pool.actor_index(&deployer), None);
+        assert!(pool.actor_by_address(&deployer).is_none());
+        assert!(pool.is_deployer(&deployer));
+        assert_eq!(
+            pool.deployer_by_address(&deployer).unwrap().address(),
+            deployer
+        );
+
+        // An actor's address never resolves to the deployer, and a deployer
+        // correction leaves every actor alone.
+        let actor = signer(0x02).address();
+        assert!(!pool.is_deployer(&actor));
+        assert!(pool.deployer_by_address(&actor).is_none());
+        assert_eq!(pool.reconcile_nonces([(deployer, 5)]), 1);
+        assert_eq!(pool.deployer().nonce(), 5);
+        assert!(pool.iter().all(|actor| actor.nonce() == 0));
+    }
+
+    #[test]
+    fn unknown_addresses_are_ignored() {
+        let mut pool = pool();
+        let unknown = signer(0x05).address();
+        assert_eq!(pool.actor_index(&unknown), None);
+        assert!(pool.actor_by_address(&unknown).is_none());
+        assert!(pool.deployer_by_address(&unknown).is_none());
+        assert!(pool.role_by_address(&unknown).is_none());
+
+        pool.rewind_nonce(&unknown, 0);
+        assert_eq!(pool.reconcile_nonces([(unknown, 7)]), 0);
+        assert_eq!(pool.deployer().nonce(), 0);
+        assert!(pool.iter().all(|actor| actor.nonce() == 0));
+    }
+
+    #[test]
+    fn corrections_name_actors_once_published() {
+        let pool = pool();
+        let corrections = NonceCorrections::default();
+        let actor = signer(0x03).address();
+        assert_eq!(corrections.actor_index(&actor), None);
+
+        corrections.set_actors(&pool);
+        assert_eq!(corrections.actor_index(&actor), Some(1));
+        assert_eq!(corrections.actor_index(&signer(0x01).address()), None);
+    }
+
+    #[test]
+    fn unsent_transactions_rewind_each_sender_to_its_lowest_nonce() {
+        use alloy_primitives::TxKind;
+
+        use crate::transaction::{TxTemplate, sign};
+
+        let mut pool = pool();
+        let (first, second) = (signer(0x02), signer(0x03));
+        let transfer = |signer: &PrivateKeySigner, nonce| {
+            sign(
+                signer,
+                TxTemplate::new(nonce, TxKind::Call(Address::ZERO), None, None),
+            )
+            .unwrap()
+        };
+        for byte in 0x02..=0x04 {
+            pool.actor_by_address(&signer(byte).address())
+                .unwrap()
+                .increment_nonce_by(10);
+        }
+
+        // The tail of a batch cut off mid-send, out of nonce order, plus an
+        // injected transaction whose nonce is deliberately wrong.
+        let invalid_txs = InvalidTxRegistry::default();
+        let injected = transfer(&second, 0);
+        invalid_txs.register(*injected.hash());
+        let unsent = [
+            transfer(&first, 8),
+            transfer(&first, 6),
+            transfer(&first, 7),
+            transfer(&second, 9),
+            injected,
+        ];
+        pool.rewind_unsent(&unsent, &invalid_txs);
+
+        assert_eq!(pool.actor_info(0).unwrap().1, 6);
+        assert_eq!(pool.actor_info(1).unwrap().1, 9);
+        // Senders with nothing unsent keep their nonce.
+        assert_eq!(pool.actor_info(2).unwrap().1, 10);
+    }
+}
@@ -0,0 +1,66 @@
+//! Estimated ETH balances of the actor pool, kept by the orchestrator so long
+//! runs can top actors up before their transactions start failing for lack of
+//! funds.
+
+use alloy_consensus::Transaction;
+use alloy_primitives::U256;
+
+use crate::{
+    actor::ActorPool,
+    labels::{LabeledTx, TxLabel},
+};
+
+/// A lower bound on each actor's balance, by actor index.
+///
+/// Every transaction is charged its full value plus `gas_limit *
+/// max_fee_per_gas`, and only plain value sent to an actor is credited, so the
+/// estimate never runs above the real balance. ETH an actor receives from a
+/// contract, such as a WETH withdrawal, is not counted.
+#[derive(Debug, Default)]
+pub struct BalanceEstimates {
+    balances: Vec<U256>,
+}
+
+impl BalanceEstimates {
+    /// Start `actors` actors at `initial` wei each.
+    pub fn new(actors: usize, initial: U256) -> Self {
+        Self {
+            balances: vec![initial; actors],
+        }
+    }
+
+    /// Charge each actor for what it sends in `batch` and credit the actors it
+    /// pays. Invalid transactions are never executed, so they are skipped.
+    pub fn record_batch(&mut self, batch: &[LabeledTx], actors: &ActorPool) {
+        for labeled in LabeledTx::flatten(batch) {
+            if labeled.label == TxLabel::Invalid {
+                continue;
+            }
+            let tx = &labeled.tx;
+            let value = tx.value();
+            if let Some(sender) = actors.actor_index(&tx.signer())
+                && let Some(balance) = self.balances.get_mut(sender)
+            {
+                let gas = U256::from(tx.gas_limit()) * U256::from(tx.max_fee_per_gas());
+                *balance = balance.saturating_sub(value.saturating_add(gas));
+            }
+            if !value.is_zero()
+                && let Some(receiver) = tx.to().and_then(|to| actors.actor_index(&to))
+                && let Some(balance) = self.balances.get_mut(receiver)
+            {
+                *balance = balance.saturating_add(value);
+            }
+        }
+    }
+
+    /// Actors estimated to hold less than `threshold`, with what each needs to
+    /// get back to `target`.
+    pub fn below(&self, threshold: U256, target: U256) -> Vec<(usize, U256)> {
+        self.balances
+            .iter()
+            .enumerate()
+            .filter(|(_, balance)| **balance < threshold)
+            .map(|(index, balance)| (index, target.saturating_sub(*balance)))
+            .collect()
+    }
+}
@@ -1,101 +1,841 @@
 //! Builds executed blocks from streamed transactions and persists them to disk.
 
-use std::sync::Arc;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use alloy_consensus::BlockHeader;
-use alloy_primitives::{Address, B256};
+use alloy_consensus::{BlockHeader, Transaction};
+use alloy_primitives::{
+    Address, B256, TxHash, U256,
+    map::{HashMap, HashSet},
+};
 use alloy_rlp::Encodable;
 
-use reth_chain_state::ExecutedBlock;
+use reth_chain_state::{ExecutedBlock, MemoryOverlayStateProvider};
 use reth_chainspec::ChainSpec;
-use reth_db::DatabaseEnv;
+use reth_db::{Database, DatabaseEnv, database_metrics::DatabaseMetrics};
 use reth_ethereum::EthPrimitives;
+use reth_ethereum_primitives::Receipt;
 use reth_evm::{
-    ConfigureEvm, NextBlockEnvAttributes,
-    execute::{BlockBuilder, BlockBuilderOutcome},
+    ConfigureEvm, Evm, NextBlockEnvAttributes,
+    block::CommitChanges,
+    execute::{
+        BlockAssembler, BlockAssemblerInput, BlockBuilder, BlockBuilderOutcome,
+        BlockExecutionError, BlockValidationError,
+    },
 };
 use reth_node_api::NodeTypesWithDBAdapter;
 use reth_node_ethereum::{EthEvmConfig, EthereumNode};
-use reth_primitives_traits::SealedHeader;
-use reth_provider::{ExecutionOutcome, ProviderFactory, StateProvider};
-use reth_revm::{State, database::StateProviderDatabase};
+use reth_primitives_traits::{RecoveredBlock, SealedHeader};
+use reth_provider::{
+    BlockExecutionResult, BlockNumReader, ExecutionOutcome, HashedPostStateProvider,
+    HeaderProvider, ProviderFactory, StateProvider, StateProviderBox, StateRootProvider,
+};
+use reth_revm::{
+    State,
+    database::StateProviderDatabase,
+    db::{BundleState, states::bundle_state::BundleRetention},
+    revm::context::result::InvalidTransaction,
+};
+use serde_json::{Value, json};
 use tokio::sync::mpsc::Receiver;
 use tracing::{debug, info, warn};
 
-use crate::{block_writer::BlockFileHeader, config::SimulationConfig};
-use crate::{block_writer::BlockFileWriter, orchestrator::TX};
+use crate::{
+    actor::NonceCorrections,
+    block_file::SegmentedBlockFileWriter,
+    block_json::BlockJsonWriter,
+    calibration::{GasCalibration, SenderTips},
+    call_trace,
+    deployments::ExpectedCode,
+    orchestrator::TX,
+    payloads::PayloadWriter,
+    transaction::FEE_PER_GAS,
+    tx_writer::TxFileWriter,
+};
+use crate::{
+    block_section_batch,
+    config::{LimitMode, SealReason, SimulationConfig, StopReason, TxOrdering},
+    counter, debug,
+    error::SandboxError,
+    gauge,
+    invalid::InvalidTxRegistry,
+    labels::{BlockLabelsWriter, LabelTotals, LabeledTx, SimulationPhase, TxLabel},
+    lanes::{self, LaneReport},
+    logging,
+    metrics::SectionBatch,
+    ordering::{self, BlockFeesWriter},
+    phase_checks::PhaseChecks,
+    progress::RunProgress,
+    retry::{self, RetryStats},
+    revert,
+    roots::RootsWriter,
+    senders::SenderDiversity,
+    simulation::SimulationPaths,
+    time_block_section, time_section,
+};
+
+/// Database a [`PF`] can sit on: MDBX under a datadir for normal runs, or
+/// reth's throwaway test database for `in_memory` ones.
+pub(crate) trait SandboxDatabase:
+    Database + DatabaseMetrics + Clone + Unpin + 'static
+{
+}
+
+impl<DB> SandboxDatabase for DB where DB: Database + DatabaseMetrics + Clone + Unpin + 'static {}
+
+/// Provider factory type used throughout the builder, over the MDBX
+/// environment unless stated otherwise.
+pub(crate) type PF<DB = Arc<DatabaseEnv>> =
+    ProviderFactory<NodeTypesWithDBAdapter<EthereumNode, DB>>;
+
+/// Latest committed state with `pending`, sealed blocks not yet committed
+/// (oldest first), layered on top.
+pub(crate) fn latest_state<DB: SandboxDatabase>(
+    provider_factory: &PF<DB>,
+    pending: &[ExecutedBlock],
+) -> eyre::Result<StateProviderBox> {
+    let latest = provider_factory.latest()?;
+    if pending.is_empty() {
+        return Ok(latest);
+    }
+    // The overlay expects the newest block first.
+    let in_memory = pending.iter().rev().cloned().collect();
+    Ok(Box::new(MemoryOverlayStateProvider::new(latest, in_memory)))
+}
+
+/// Time spent committing blocks to the database.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DbCommitStats {
+    commits: u64,
+    blocks: u64,
+    elapsed: Duration,
+}
+
+impl DbCommitStats {
+    /// Mean commit time per block, the figure that drops as
+    /// `db_commit_interval` grows.
+    pub fn per_block(&self) -> Duration {
+        self.elapsed
+            .checked_div(self.blocks as u32)
+            .unwrap_or_default()
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "commits": self.commits,
+            "blocks": self.blocks,
+            "elapsed_secs": self.elapsed.as_secs_f64(),
+            "per_block_ms": self.per_block().as_secs_f64() * 1000.0,
+        })
+    }
+
+    /// Print a one-line summary.
+    pub fn print(&self) {
+        if self.commits == 0 {
+            return;
+        }
+        println!(
+            "Commits:  {} blocks in {} commits, {:.3} ms per block",
+            self.blocks,
+            self.commits,
+            self.per_block().as_secs_f64() * 1000.0
+        );
+    }
+}
+
+/// How block base fees moved over a run. The default fill targets the
+/// elasticity-neutral gas, but the transaction that crosses the target
+/// overshoots it and under-filled blocks fall short, so the base fee drifts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BaseFeeDrift {
+    blocks: u64,
+    /// Blocks whose base fee differs from their parent's.
+    changed: u64,
+    first: Option<u64>,
+    last: u64,
+    min: u64,
+    max: u64,
+}
+
+impl BaseFeeDrift {
+    /// Account for a sealed block with `base_fee` whose parent had
+    /// `parent_base_fee`.
+    pub fn record_block(&mut self, parent_base_fee: u64, base_fee: u64) {
+        if self.first.is_none() {
+            self.min = base_fee;
+        }
+        self.blocks += 1;
+        self.changed += (base_fee != parent_base_fee) as u64;
+        self.first.get_or_insert(parent_base_fee);
+        self.last = base_fee;
+        self.min = self.min.min(base_fee);
+        self.max = self.max.max(base_fee);
+    }
+
+    /// Base fee of the last block minus that of the first block's parent.
+    pub fn drift(&self) -> i128 {
+        self.last as i128 - self.first.unwrap_or(self.last) as i128
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "blocks": self.blocks,
+            "changed": self.changed,
+            "first": self.first,
+            "last": self.last,
+            "min": self.min,
+            "max": self.max,
+            "drift": self.drift().to_string(),
+        })
+    }
+
+    /// Print a one-line summary.
+    pub fn print(&self) {
+        let Some(first) = self.first else {
+            return;
+        };
+        println!(
+            "Base fee: {} -> {} wei ({:+}), changed in {} of {} blocks, range {}..={}",
+            first,
+            self.last,
+            self.drift(),
+            self.changed,
+            self.blocks,
+            self.min,
+            self.max
+        );
+    }
+}
+
+/// A block being filled by [`SandboxBlockBuilder::build_block`], and what it
+/// has taken in so far.
+struct OpenBlock {
+    number: u64,
+    parent_header: SealedHeader,
+    fee_recipient: Address,
+    base_fee: u64,
+    gas_target: u64,
+    /// When the run started, for the deadline.
+    started: Instant,
+    /// Run totals before this block, unless a soft limit is draining the
+    /// channel.
+    totals: Option<(u64, u64)>,
+    gas_used: u64,
+    tx_count: u64,
+    tx_bytes: u64,
+    tips: U256,
+    /// Tip per gas of the first and last included transaction.
+    tip_range: Option<(u128, u128)>,
+    candidates_ordered: bool,
+    labels: LabelTotals,
+    senders: HashMap<Address, u64>,
+    /// Setup contracts this block deploys, with their transactions.
+    deployments: Vec<((&'static str, Address), TxHash)>,
+    /// Phases whose last transaction this block includes.
+    finished_phases: Vec<SimulationPhase>,
+    /// Indices of the failed transactions to trace once the block is
+    /// sealed, under `trace_failed_txs`.
+    failed_indices: Vec<usize>,
+    /// Transactions from senders already at
+    /// `max_txs_per_sender_per_block`, in arrival order.
+    held_back: Vec<LabeledTx>,
+    /// Senders with a valid transaction rejected in this block.
+    skipped_senders: HashSet<Address>,
+    /// Transactions to attempt again next block, and their senders.
+    retry: Vec<LabeledTx>,
+    retrying_senders: HashSet<Address>,
+    /// Followers of the group being included, taken ahead of anything
+    /// else; the block is not sealed until they are all through.
+    group: VecDeque<LabeledTx>,
+    /// Load transactions included so far, and how many the scenario
+    /// block being built holds.
+    load_txs: u64,
+    scenario_target: Option<u64>,
+    /// Execution time is summed here and recorded once per block, so the
+    /// per-transaction hot path never takes the metrics lock.
+    tx_execution: SectionBatch,
+}
 
-/// Concrete provider factory type used throughout the builder.
-type PF = ProviderFactory<NodeTypesWithDBAdapter<EthereumNode, Arc<DatabaseEnv>>>;
+/// Why a transaction waits for a later block rather than joining the open one.
+enum Deferral {
+    /// Its sender is at `max_txs_per_sender_per_block`; it is held back.
+    SenderCap,
+    /// It would break the block's limits; it is carried over to open the
+    /// next one, and the open one is sealed.
+    Carry(SealReason),
+}
 
 /// Consumes recovered transactions, executes them with Reth's block builder, and
 /// writes both RLP bytes and state updates to disk.
-pub struct SandboxBlockBuilder {
-    provider_factory: PF,
+pub struct SandboxBlockBuilder<DB: SandboxDatabase = Arc<DatabaseEnv>> {
+    provider_factory: PF<DB>,
     parent_header: SealedHeader,
     parent_timestamp: u64,
     gas_limit: u64,
     evm_config: EthEvmConfig,
-    receiver: Receiver<TX>,
-    block_writer: BlockFileWriter,
+    receiver: Receiver<LabeledTx>,
+    /// Transactions executed into a partial block that was never sealed.
+    unsealed: Vec<TX>,
+    /// Transactions held over from a sealed block, by the gas target,
+    /// `max_block_bytes`, `max_txs_per_sender_per_block`, or the start of a
+    /// scenario, in arrival order; they open the next block. Under priority
+    /// fee ordering, also the candidates taken from the channel, in the
+    /// order they are to be included.
+    carried: VecDeque<LabeledTx>,
+    /// RLP block file, when `output_format` includes it.
+    block_writer: Option<SegmentedBlockFileWriter>,
+    /// JSONL block file, when `output_format` includes it.
+    json_writer: Option<BlockJsonWriter>,
     simulation_config: SimulationConfig,
+    /// Where state diffs and failed transaction records go.
+    paths: SimulationPaths,
+    progress: Arc<RunProgress>,
+    /// Priority fees each fee recipient should have been credited.
+    expected_tips: HashMap<Address, U256>,
+    /// Hashes of deliberately invalid transactions injected by the orchestrator.
+    invalid_txs: Arc<InvalidTxRegistry>,
+    /// Setup contracts to look for once their deployment is in a block.
+    expected_code: Arc<ExpectedCode>,
+    /// State each setup phase should leave, checked once it is done.
+    phase_checks: Arc<PhaseChecks>,
+    /// Phase of the last included transaction.
+    included_phase: Option<SimulationPhase>,
+    /// On-chain nonces of senders with skipped transactions, handed to the
+    /// orchestrator to reset its tracked nonces.
+    nonce_corrections: Arc<NonceCorrections>,
+    /// On-chain nonce of each sender whose transactions are dropped until
+    /// one arrives at that nonce, since every other one would be skipped for
+    /// a nonce gap.
+    stalled_senders: HashMap<Address, u64>,
+    /// Failed attempts so far of each transaction queued for a retry.
+    retry_attempts: HashMap<TxHash, u32>,
+    /// Outcome of every transaction queued for a retry.
+    retry_stats: RetryStats,
+    /// Per-block roots written to `simulation_config.roots_out`.
+    roots_writer: RootsWriter,
+    /// Per-block label rows, when `simulation_config.block_labels_out` is set.
+    labels_writer: Option<BlockLabelsWriter>,
+    /// Included transactions, when `simulation_config.txs_out` is set.
+    tx_writer: Option<TxFileWriter>,
+    /// Engine API payloads, when `simulation_config.payloads_out` is set.
+    payload_writer: Option<PayloadWriter>,
+    /// First and last tip per block, when `simulation_config.block_fees_out`
+    /// is set.
+    fees_writer: Option<BlockFeesWriter>,
+    /// Per-label counts and gas over every sealed block.
+    label_totals: LabelTotals,
+    /// Gas used against gas limit for every included transaction, by label.
+    gas_calibration: GasCalibration,
+    /// Effective tips of every included transaction, by sender.
+    sender_tips: SenderTips,
+    /// Distinct senders per sealed block.
+    sender_diversity: SenderDiversity,
+    /// Lane utilization when building with `parallel_lanes`.
+    lane_report: LaneReport,
+    /// Sealed blocks waiting for the next database commit, oldest first.
+    pending: Vec<ExecutedBlock>,
+    db_commits: DbCommitStats,
+    base_fee_drift: BaseFeeDrift,
+    /// Gas the next block aims for under `hold_base_fee`: the target plus
+    /// whatever the last block fell short of it by, or minus its overshoot.
+    next_gas_target: u64,
+    /// Transactions in each scenario block still to be built, in order; empty
+    /// without a scenario.
+    scenario_blocks: VecDeque<u64>,
 }
 
-impl SandboxBlockBuilder {
-    /// Prepare the builder with the genesis header and file writer output path.
+impl<DB: SandboxDatabase> SandboxBlockBuilder<DB> {
+    /// Prepare the builder with the genesis header and open the block file at
+    /// `simulation_config.blocks_out`.
     pub fn new(
-        provider_factory: PF,
+        provider_factory: PF<DB>,
         chain: Arc<ChainSpec>,
-        receiver: Receiver<TX>,
+        receiver: Receiver<LabeledTx>,
         simulation_config: SimulationConfig,
-    ) -> Self {
-        let output_path = std::env::current_dir().unwrap().join("blocks.bin");
+        progress: Arc<RunProgress>,
+        invalid_txs: Arc<InvalidTxRegistry>,
+        expected_code: Arc<ExpectedCode>,
+        phase_checks: Arc<PhaseChecks>,
+        nonce_corrections: Arc<NonceCorrections>,
+        paths: SimulationPaths,
+    ) -> eyre::Result<Self> {
+        // A kept datadir may already hold blocks; build on its head and
+        // continue the block file it wrote rather than overwriting it.
+        let genesis_header =
+            SealedHeader::new(chain.genesis_header().clone(), chain.genesis_hash().into());
+        let best_block = provider_factory.best_block_number()?;
+        let resuming = best_block > genesis_header.number;
+        let parent_header = if resuming {
+            provider_factory.sealed_header(best_block)?.ok_or_else(|| {
+                eyre::eyre!("datadir has no header for its best block {best_block}")
+            })?
+        } else {
+            genesis_header
+        };
 
-        let block_writer =
-            BlockFileWriter::new(&output_path, BlockFileHeader::new(false, 0, 100)).unwrap();
+        let block_writer = if !simulation_config.output_format.writes_rlp() {
+            None
+        } else if resuming {
+            info!(
+                target: logging::BUILDER,
+                block = best_block,
+                path = %simulation_config.blocks_out.display(),
+                "resuming from datadir, appending to block file"
+            );
+            Some(SegmentedBlockFileWriter::append(
+                &simulation_config.blocks_out,
+                simulation_config.block_file_rotation,
+                best_block + 1,
+            )?)
+        } else {
+            Some(SegmentedBlockFileWriter::create(
+                &simulation_config.blocks_out,
+                simulation_config.block_file_rotation,
+                parent_header.number + 1,
+            )?)
+        };
+        let json_writer = simulation_config
+            .output_format
+            .writes_jsonl()
+            .then(|| BlockJsonWriter::new(&simulation_config.blocks_jsonl_out))
+            .transpose()?;
+
+        let roots_writer = RootsWriter::new(&simulation_config.roots_out)?;
+        let labels_writer = simulation_config
+            .block_labels_out
+            .as_deref()
+            .map(BlockLabelsWriter::new)
+            .transpose()?;
+        let tx_writer = simulation_config
+            .txs_out
+            .as_deref()
+            .map(TxFileWriter::new)
+            .transpose()?;
+        let payload_writer = simulation_config
+            .payloads_out
+            .as_deref()
+            .map(|path| {
+                PayloadWriter::new(
+                    path,
+                    chain.genesis_hash(),
+                    simulation_config.chain_id,
+                    simulation_config.hardfork,
+                )
+            })
+            .transpose()?;
+        let fees_writer = simulation_config
+            .block_fees_out
+            .as_deref()
+            .map(BlockFeesWriter::new)
+            .transpose()?;
 
         let evm_config = EthEvmConfig::new(chain.clone());
 
         let gas_limit = chain.genesis().gas_limit;
+        let next_gas_target = simulation_config.block_gas_target();
+        let scenario_blocks = simulation_config
+            .scenario
+            .as_ref()
+            .map(|scenario| scenario.block_tx_counts().into())
+            .unwrap_or_default();
 
-        let genesis_timestamp = chain.genesis_header().timestamp;
-
-        let genesis_header =
-            SealedHeader::new(chain.genesis_header().clone(), chain.genesis_hash().into());
-
-        Self {
+        Ok(Self {
             provider_factory,
-            parent_header: genesis_header,
-            parent_timestamp: genesis_timestamp,
+            parent_timestamp: parent_header.timestamp,
+            parent_header,
             gas_limit,
             evm_config,
             receiver,
+            unsealed: Vec::new(),
+            carried: VecDeque::new(),
             block_writer,
+            json_writer,
             simulation_config,
+            paths,
+            progress,
+            expected_tips: HashMap::default(),
+            invalid_txs,
+            expected_code,
+            phase_checks,
+            included_phase: None,
+            nonce_corrections,
+            stalled_senders: HashMap::default(),
+            retry_attempts: HashMap::default(),
+            retry_stats: RetryStats::default(),
+            roots_writer,
+            labels_writer,
+            tx_writer,
+            payload_writer,
+            fees_writer,
+            label_totals: LabelTotals::default(),
+            gas_calibration: GasCalibration::default(),
+            sender_tips: SenderTips::default(),
+            sender_diversity: SenderDiversity::default(),
+            lane_report: LaneReport::default(),
+            pending: Vec::new(),
+            db_commits: DbCommitStats::default(),
+            base_fee_drift: BaseFeeDrift::default(),
+            next_gas_target,
+            scenario_blocks,
+        })
+    }
+
+    /// Priority fees credited to each fee recipient according to the builder's
+    /// own accounting, for cross-checking against state after the run.
+    pub fn expected_tips(&self) -> &HashMap<Address, U256> {
+        &self.expected_tips
+    }
+
+    /// Per-label counts and gas over every sealed block.
+    pub fn label_totals(&self) -> &LabelTotals {
+        &self.label_totals
+    }
+
+    /// Gas used per included transaction, by label.
+    pub fn gas_calibration(&self) -> &GasCalibration {
+        &self.gas_calibration
+    }
+
+    /// Effective tips per included transaction, by sender.
+    pub fn sender_tips(&self) -> &SenderTips {
+        &self.sender_tips
+    }
+
+    /// Distinct senders per sealed block.
+    pub fn sender_diversity(&self) -> SenderDiversity {
+        self.sender_diversity
+    }
+
+    /// How the transactions queued for a retry turned out.
+    pub fn retry_stats(&self) -> RetryStats {
+        self.retry_stats
+    }
+
+    /// Lane utilization and estimated speedup when building with
+    /// `parallel_lanes`.
+    pub fn lane_report(&self) -> &LaneReport {
+        &self.lane_report
+    }
+
+    /// Time spent committing blocks to the database.
+    pub fn db_commits(&self) -> DbCommitStats {
+        self.db_commits
+    }
+
+    /// How far base fees moved over the sealed blocks.
+    pub fn base_fee_drift(&self) -> BaseFeeDrift {
+        self.base_fee_drift
+    }
+
+    /// Track the base fee of a sealed block against its parent's, warning
+    /// once it passes the fixed max fee every transaction is signed with.
+    fn record_base_fee(&mut self, block: u64, parent_base_fee: u64, base_fee: u64) {
+        self.base_fee_drift.record_block(parent_base_fee, base_fee);
+        gauge!("base_fee").set(base_fee);
+        if base_fee != parent_base_fee {
+            counter!("base_fee_changes").increment(1);
+            debug!(
+                target: logging::BUILDER,
+                block,
+                parent_base_fee,
+                base_fee,
+                drift = %self.base_fee_drift.drift(),
+                "base fee changed"
+            );
         }
+        let max_fee = FEE_PER_GAS as u64;
+        if parent_base_fee <= max_fee && base_fee > max_fee {
+            warn!(
+                target: logging::BUILDER,
+                block,
+                base_fee,
+                max_fee,
+                "base fee exceeds the max fee transactions are signed with; they will be rejected"
+            );
+        }
+    }
+
+    /// Close the channel and take every transaction that did not make it into
+    /// a sealed block: those executed into a block that was never sealed,
+    /// those held over for the next block, then those still queued. Call once the
+    /// orchestrator has stopped so nothing can be enqueued after.
+    pub fn drain_unexecuted(&mut self) -> Vec<TX> {
+        self.receiver.close();
+        let mut unexecuted = std::mem::take(&mut self.unsealed);
+        unexecuted.extend(
+            self.carried
+                .drain(..)
+                .flat_map(LabeledTx::into_members)
+                .map(|labeled| labeled.tx),
+        );
+        while let Ok(labeled) = self.receiver.try_recv() {
+            unexecuted.extend(labeled.into_members().map(|member| member.tx));
+        }
+        unexecuted
+    }
+
+    /// Flush any buffered block bytes and close the backing file handles,
+    /// returning the block files written, in block order: none when the run
+    /// writes no RLP block file.
+    pub fn finish_file_writer(self) -> eyre::Result<Vec<PathBuf>> {
+        let block_files = self
+            .block_writer
+            .map(SegmentedBlockFileWriter::finish)
+            .transpose()?
+            .unwrap_or_default();
+        if let Some(json_writer) = self.json_writer {
+            json_writer.finish()?;
+        }
+        self.roots_writer.finish()?;
+        if let Some(labels_writer) = self.labels_writer {
+            labels_writer.finish()?;
+        }
+        if let Some(tx_writer) = self.tx_writer {
+            tx_writer.finish()?;
+        }
+        if let Some(payload_writer) = self.payload_writer {
+            payload_writer.finish()?;
+        }
+        if let Some(fees_writer) = self.fees_writer {
+            fees_writer.finish()?;
+        }
+        Ok(block_files)
+    }
+
+    /// Top the candidate set up from the channel to `window` transactions, or
+    /// to a `gas_target`'s worth of gas limits without one, then order it by
+    /// priority fee at `base_fee`. Waits for the channel until the set is
+    /// full or the channel closes, so a seeded run orders the same candidates
+    /// however fast the orchestrator keeps up.
+    async fn fill_candidates(&mut self, window: Option<usize>, gas_target: u64, base_fee: u64) {
+        let mut gas: u64 = self.carried.iter().map(LabeledTx::group_gas_limit).sum();
+        while window.map_or(gas < gas_target, |window| self.carried.len() < window)
+            && let Some(labeled) = self.receiver.recv().await
+        {
+            gas += labeled.group_gas_limit();
+            self.carried.push_back(labeled);
+        }
+        ordering::order_by_priority_fee(&mut self.carried, base_fee);
+    }
+
+    /// Fail when a setup contract deployed in block `number` left no code at
+    /// the address the orchestrator predicted for it.
+    fn check_deployed(
+        &self,
+        number: u64,
+        deployed: &[((&'static str, Address), TxHash)],
+    ) -> eyre::Result<()> {
+        if deployed.is_empty() {
+            return Ok(());
+        }
+        let state_provider = latest_state(&self.provider_factory, &self.pending)?;
+        for &((label, address), hash) in deployed {
+            let has_code = state_provider
+                .basic_account(&address)?
+                .is_some_and(|account| account.has_bytecode());
+            if !has_code {
+                return Err(SandboxError::MissingContract {
+                    label,
+                    address,
+                    block: number,
+                    hash,
+                    outcome: "executed without creating it",
+                }
+                .into());
+            }
+        }
+        Ok(())
     }
 
-    /// Flush any buffered block bytes and close the backing file handle.
-    pub fn finish_file_writer(self) -> eyre::Result<()> {
-        self.block_writer.finish()?;
+    /// Note that a transaction from `phase` was included. Returns the phase
+    /// before it when this starts a new one: phases are generated in turn,
+    /// so every transaction of that one is in this block or an earlier one.
+    fn enter_phase(&mut self, phase: SimulationPhase) -> Option<SimulationPhase> {
+        if self.included_phase == Some(phase) {
+            return None;
+        }
+        self.included_phase.replace(phase)
+    }
+
+    /// Run the checks of every phase in `finished` against the state after
+    /// the block just sealed.
+    fn verify_finished_phases(&self, finished: &[SimulationPhase]) -> eyre::Result<()> {
+        if finished.is_empty() {
+            return Ok(());
+        }
+        let state_provider = latest_state(&self.provider_factory, &self.pending)?;
+        let caller = debug::StateCaller::new(
+            &self.evm_config,
+            &self.parent_header,
+            state_provider.as_ref(),
+        )?;
+        for &phase in finished {
+            self.phase_checks
+                .verify(phase, &caller, self.simulation_config.phase_check_sample)?;
+        }
         Ok(())
     }
 
+    /// Read the on-chain nonce of every sender in `skipped` after the block
+    /// just sealed, drop their later transactions until one arrives at it,
+    /// and pass it on for the orchestrator to reconcile its tracked nonce.
+    fn reconcile_skipped(&mut self, skipped: &HashSet<Address>) -> eyre::Result<()> {
+        if skipped.is_empty() {
+            return Ok(());
+        }
+        let state_provider = latest_state(&self.provider_factory, &self.pending)?;
+        for &sender in skipped {
+            let nonce = state_provider.account_nonce(&sender)?.unwrap_or_default();
+            match self.nonce_corrections.actor_index(&sender) {
+                Some(index) => warn!(
+                    target: logging::BUILDER,
+                    %sender,
+                    nonce,
+                    "actor #{index} nonce desynced, stalling it until reconciled"
+                ),
+                None => debug!(
+                    target: logging::BUILDER,
+                    %sender,
+                    nonce,
+                    "stalling sender until its nonce is reconciled"
+                ),
+            }
+            self.stalled_senders.insert(sender, nonce);
+            self.nonce_corrections.record(sender, nonce);
+        }
+        Ok(())
+    }
+
+    /// Queue `labeled`, which failed for `reason` only because it ran ahead
+    /// of a transaction it depends on, for another attempt next block. Its
+    /// sender's later transactions wait behind it.
+    fn queue_retry(
+        &mut self,
+        retry: &mut Vec<LabeledTx>,
+        retrying_senders: &mut HashSet<Address>,
+        labeled: LabeledTx,
+        reason: &'static str,
+    ) {
+        let hash = *labeled.tx.hash();
+        let attempts = self.retry_attempts.entry(hash).or_default();
+        if *attempts == 0 {
+            self.retry_stats.retried += 1;
+        }
+        *attempts += 1;
+        counter!("retried_transactions").increment(1);
+        debug!(
+            target: logging::BUILDER,
+            %hash,
+            from = %labeled.tx.signer(),
+            attempts = *attempts,
+            reason,
+            "queued transaction for retry"
+        );
+        retrying_senders.insert(labeled.tx.signer());
+        retry.push(labeled);
+    }
+
+    /// Note that a transaction queued for a retry is done with: included
+    /// and successful, or failed for good.
+    fn finish_retry(&mut self, hash: &TxHash, succeeded: bool) {
+        if self.retry_attempts.remove(hash).is_none() {
+            return;
+        }
+        if succeeded {
+            self.retry_stats.succeeded += 1;
+        } else {
+            self.retry_stats.failed += 1;
+        }
+    }
+
+    /// Put the transactions queued for a retry, then those held back, ahead
+    /// of anything still carried so the next block opens with them. Each
+    /// sender's go back in nonce order, so an approval that arrived after
+    /// the swap spending it now runs first.
+    fn requeue(&mut self, retry: Vec<LabeledTx>, held_back: Vec<LabeledTx>) {
+        let requeued = if retry.is_empty() {
+            held_back
+        } else {
+            let mut requeued = retry;
+            requeued.extend(held_back);
+            retry::order_by_nonce(requeued)
+        };
+        for labeled in requeued.into_iter().rev() {
+            self.carried.push_front(labeled);
+        }
+    }
+
+    /// Environment for block `number`, paying fees to `fee_recipient`.
+    fn next_block_attributes(&self, number: u64, fee_recipient: Address) -> NextBlockEnvAttributes {
+        NextBlockEnvAttributes {
+            timestamp: self.parent_timestamp + self.simulation_config.block_interval(),
+            suggested_fee_recipient: fee_recipient,
+            prev_randao: self.simulation_config.prev_randao(number),
+            gas_limit: self.gas_limit,
+            // Cancun requires a beacon root; the sandbox has no CL, so use zero.
+            parent_beacon_block_root: self
+                .simulation_config
+                .hardfork
+                .is_cancun_active()
+                .then_some(B256::ZERO),
+            withdrawals: None,
+        }
+    }
+
     /// Persist the executed block both to the binary file and the Reth database.
     async fn finish_block_and_commit(
         &mut self,
         outcome: BlockBuilderOutcome<EthPrimitives>,
-        mut state_db: State<StateProviderDatabase<&Box<dyn StateProvider>>>,
+        bundle_state: BundleState,
     ) -> eyre::Result<()> {
-        let bundle_state = state_db.take_bundle();
+        if self.simulation_config.dump_state_diffs {
+            let dir = self.paths.state_diffs_dir();
+            if let Err(err) =
+                debug::write_state_diff(&dir, outcome.block.header().number(), &bundle_state)
+            {
+                warn!(target: logging::WRITER, %err, "failed to write state diff");
+            }
+        }
 
+        self.roots_writer.record(outcome.block.sealed_header())?;
         self.parent_header = outcome.block.sealed_header().clone();
         self.parent_timestamp = outcome.block.sealed_header().timestamp;
 
         let block = outcome.block.clone().into_block();
         let block_number = outcome.block.header().number();
         let txs_in_block = outcome.block.body().transactions.len();
+        if let Some(payload_writer) = &mut self.payload_writer {
+            payload_writer.record(
+                outcome.block.hash(),
+                &block,
+                &outcome.execution_result.requests,
+            )?;
+        }
+        if let Some(tx_writer) = &mut self.tx_writer {
+            tx_writer.write_block(&block.body.transactions)?;
+        }
+        if let Some(json_writer) = &mut self.json_writer {
+            json_writer.record(
+                &outcome.block,
+                Some(outcome.execution_result.receipts.as_slice()),
+            )?;
+        }
 
+        // What this block adds to the pending blocks held until the next commit.
+        gauge!("bundle_accounts").set(bundle_state.state.len() as u64);
+        gauge!("bundle_storage_slots").set(
+            bundle_state
+                .state
+                .values()
+                .map(|account| account.storage.len() as u64)
+                .sum(),
+        );
         let execution_output = Arc::new(ExecutionOutcome {
             bundle: bundle_state,
             receipts: vec![outcome.execution_result.receipts],
@@ -110,174 +850,1018 @@ impl SandboxBlockBuilder {
             trie_updates: Arc::new(outcome.trie_updates),
         };
 
-        let mut buf = Vec::with_capacity(block.length());
-        block.encode(&mut buf);
+        // Sizing a block is cheap next to encoding it, which is skipped
+        // unless the block file is written.
+        let block_len = block.length();
+        gauge!("block_bytes").set(block_len as u64);
+        if let Some(block_writer) = &mut self.block_writer {
+            let mut buf = Vec::with_capacity(block_len);
+            {
+                let _t = time_block_section!(block_number, "rlp_encode");
+                block.encode(&mut buf);
+            }
+            {
+                let _t = time_block_section!(block_number, "write_block_file");
+                block_writer.write_block(&buf)?;
+            }
+            gauge!("blocks_bin_bytes").set(block_writer.bytes_written());
+            debug!(
+                target: logging::BUILDER,
+                block = block_number,
+                txs = txs_in_block,
+                bytes = buf.len(),
+                "wrote block bytes to file"
+            );
+        }
 
-        self.block_writer.write_block(&buf)?;
-        debug!(
-            target: "sandbox::block_builder",
-            block = block_number,
-            txs = txs_in_block,
-            "wrote block bytes to file"
-        );
+        self.pending.push(executed_block);
+        gauge!("pending_blocks").set(self.pending.len() as u64);
+        if self.pending.len() as u64 >= self.simulation_config.db_commit_interval {
+            self.commit_pending()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write every pending block to the database in a single commit.
+    fn commit_pending(&mut self) -> eyre::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let blocks = std::mem::take(&mut self.pending);
+        let count = blocks.len() as u64;
+        let last_block = blocks
+            .last()
+            .map_or(0, |block| block.recovered_block.header().number());
 
+        // A batched commit is charged to the block that triggered it.
+        let started = Instant::now();
         let provider_rw = self.provider_factory.provider_rw()?;
-        provider_rw.save_blocks(vec![executed_block])?;
-        provider_rw.commit()?;
+        {
+            let _t = time_block_section!(last_block, "save_blocks");
+            provider_rw.save_blocks(blocks)?;
+        }
+        {
+            let _t = time_block_section!(last_block, "commit");
+            provider_rw.commit()?;
+        }
+        let elapsed = started.elapsed();
+
+        self.db_commits.commits += 1;
+        self.db_commits.blocks += count;
+        self.db_commits.elapsed += elapsed;
         info!(
-            target: "sandbox::block_builder",
-            block = block_number,
-            txs = txs_in_block,
-            "persisted executed block to database"
+            target: logging::BUILDER,
+            last_block,
+            blocks = count,
+            elapsed_ms = elapsed.as_millis() as u64,
+            "persisted executed blocks to database"
         );
 
         Ok(())
     }
 
-    /// Pull transactions from the orchestrator, keep building blocks until the gas budget is
-    /// exhausted,
-    pub async fn start_building(&mut self) -> eyre::Result<()> {
+    /// Collect one block of transactions, execute them in `parallel_lanes`
+    /// sender-partitioned lanes on separate threads, and seal the merged
+    /// result. Returns the block's transaction count and gas used, or `None`
+    /// once the channel closes. `totals` holds the run's transactions and gas
+    /// so far, checked against its limits as the block fills; `None` while a
+    /// soft limit drains.
+    async fn build_lane_block(
+        &mut self,
+        started: Instant,
+        totals: Option<(u64, u64)>,
+    ) -> eyre::Result<Option<(u64, u64)>> {
+        let max_gas_for_block = self.simulation_config.block_gas_target();
+
+        // Lanes need the whole block up front, so seal on gas limits rather
+        // than gas used; the two are equal for the plain transfers lanes run.
+        let mut txs = Vec::new();
+        let mut gas_reserved = 0;
+        let mut tx_bytes = 0;
+        let seal_reason = loop {
+            let labeled = match self.carried.pop_front() {
+                Some(labeled) => labeled,
+                None => match self.receiver.recv().await {
+                    Some(labeled) => labeled,
+                    None if txs.is_empty() => return Ok(None),
+                    // The channel closed: build what was collected rather
+                    // than abandoning it.
+                    None => break SealReason::Shutdown,
+                },
+            };
+            // A group is reserved, and joins the block, as a whole.
+            let group_gas_limit = labeled.group_gas_limit();
+            if !txs.is_empty() && gas_reserved + group_gas_limit > max_gas_for_block {
+                self.carried.push_front(labeled);
+                break SealReason::GasTarget;
+            }
+            gas_reserved += group_gas_limit;
+            tx_bytes += labeled
+                .members()
+                .map(|member| member.tx.inner().length() as u64)
+                .sum::<u64>();
+            txs.extend(labeled.into_members());
+
+            let reason = if gas_reserved >= max_gas_for_block {
+                Some(SealReason::GasTarget)
+            } else {
+                self.simulation_config
+                    .fill_strategy
+                    .seal_reason(txs.len() as u64, tx_bytes)
+            }
+            .or_else(|| {
+                self.simulation_config
+                    .deadline_passed(started.elapsed())
+                    .then_some(SealReason::Deadline)
+            })
+            .or_else(|| {
+                let (total_txs, total_gas) = totals?;
+                self.simulation_config
+                    .volume_limit_hit(total_txs + txs.len() as u64, total_gas + gas_reserved)
+                    .map(|_| SealReason::RunLimit)
+            });
+            if let Some(reason) = reason {
+                break reason;
+            }
+        };
+
+        let parent_header = self.parent_header.clone();
+        let block_number = parent_header.number + 1;
+        let fee_recipient = self.simulation_config.fee_recipient.for_block(block_number);
+        lanes::check_conflict_free(&txs, fee_recipient)?;
+
+        let attributes = self.next_block_attributes(block_number, fee_recipient);
+        let evm_env = self.evm_config.next_evm_env(&parent_header, &attributes)?;
+        let block_base_fee = evm_env.block_env.basefee;
+
+        // System calls run once, serially, ahead of the lanes; lanes never
+        // touch the accounts they write.
+        let state_provider = latest_state(&self.provider_factory, &self.pending)?;
+        let mut state_db = State::builder()
+            .with_database(StateProviderDatabase::new(&state_provider))
+            .with_bundle_update()
+            .build();
+        self.evm_config
+            .builder_for_next_block(&mut state_db, &parent_header, attributes.clone())?
+            .apply_pre_execution_changes()?;
+        state_db.merge_transitions(BundleRetention::Reverts);
+        let pre_execution = state_db.take_bundle();
+
+        let lanes_started = Instant::now();
+        let outputs = {
+            let _t = time_section!("parallel_lanes");
+            let provider_factory = &self.provider_factory;
+            let pending = &self.pending;
+            let evm_config = &self.evm_config;
+            std::thread::scope(|scope| {
+                lanes::partition(txs, self.simulation_config.parallel_lanes)
+                    .into_iter()
+                    .map(|lane_txs| {
+                        let evm_env = evm_env.clone();
+                        scope.spawn(move || {
+                            lanes::execute_lane(
+                                provider_factory,
+                                pending,
+                                evm_config,
+                                evm_env,
+                                lane_txs,
+                            )
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .map_err(|_| eyre::eyre!("parallel lane panicked"))
+                            .and_then(|output| output)
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()
+            })?
+        };
+        self.lane_report
+            .record_block(&outputs, lanes_started.elapsed());
+
+        // The lanes' transactions go into the block one lane after another.
+        let mut transactions = Vec::new();
+        let mut senders = Vec::new();
+        let mut receipts = Vec::new();
+        let mut bundles = Vec::with_capacity(outputs.len());
+        let mut block_gas_used = 0;
+        let mut block_tips = U256::ZERO;
+        let mut block_labels = LabelTotals::default();
+        let mut block_senders = HashMap::<Address, u64>::default();
+        let mut finished_phases = Vec::new();
+        for output in outputs {
+            for (labeled, result) in output.txs.into_iter().zip(output.results) {
+                let gas_used = result.gas_used();
+                let success = result.is_success();
+                if !success {
+                    counter!("failed_transactions").increment(1);
+                }
+                let tip = labeled
+                    .tx
+                    .effective_tip_per_gas(block_base_fee)
+                    .unwrap_or_default();
+                block_gas_used += gas_used;
+                block_tips += U256::from(tip) * U256::from(gas_used);
+                block_labels.record_included(labeled.label, gas_used, !success);
+                finished_phases.extend(self.enter_phase(labeled.phase));
+                self.gas_calibration
+                    .record(labeled.label, gas_used, labeled.tx.gas_limit());
+                self.sender_tips.record(labeled.tx.signer(), tip);
+                *block_senders.entry(labeled.tx.signer()).or_default() += 1;
+                receipts.push(Receipt {
+                    tx_type: labeled.tx.tx_type(),
+                    success,
+                    cumulative_gas_used: block_gas_used,
+                    logs: result.into_logs(),
+                });
+                let (tx, sender) = labeled.tx.into_parts();
+                transactions.push(tx);
+                senders.push(sender);
+            }
+            bundles.push(output.bundle);
+        }
+        let block_tx_count = transactions.len() as u64;
+
+        let bundle = lanes::merge_bundles(pre_execution, bundles, fee_recipient);
+        let hashed_state = state_provider.hashed_post_state(&bundle);
+        let (state_root, trie_updates) =
+            state_provider.state_root_with_updates(hashed_state.clone())?;
+        let execution_result = BlockExecutionResult {
+            receipts,
+            requests: Default::default(),
+            gas_used: block_gas_used,
+        };
+        let block = self
+            .evm_config
+            .block_assembler()
+            .assemble_block(BlockAssemblerInput::new(
+                evm_env,
+                self.evm_config
+                    .context_for_next_block(&parent_header, attributes)?,
+                &parent_header,
+                transactions,
+                &execution_result,
+                &bundle,
+                state_provider.as_ref(),
+                state_root,
+            ))?;
+        let outcome = BlockBuilderOutcome {
+            execution_result,
+            hashed_state,
+            trie_updates,
+            block: RecoveredBlock::new_unhashed(block, senders),
+        };
+
+        info!(
+            target: logging::BUILDER,
+            block = block_number,
+            txs_in_block = block_tx_count,
+            gas_used = block_gas_used,
+            bytes = tx_bytes,
+            senders = block_senders.len(),
+            lanes = self.simulation_config.parallel_lanes,
+            %seal_reason,
+            "sealing lane-built block"
+        );
+        counter!(seal_reason.counter_key()).increment(1);
+        self.record_base_fee(
+            block_number,
+            parent_header.base_fee_per_gas.unwrap_or_default(),
+            block_base_fee,
+        );
+        gauge!("block_unique_senders").set(block_senders.len() as u64);
+        self.sender_diversity.record_block(
+            block_tx_count,
+            block_senders.len() as u64,
+            block_senders.values().copied().max().unwrap_or_default(),
+        );
+
+        self.finish_block_and_commit(outcome, bundle).await?;
+        self.verify_finished_phases(&finished_phases)?;
+        *self.expected_tips.entry(fee_recipient).or_default() += block_tips;
+        if let Some(labels_writer) = &mut self.labels_writer {
+            labels_writer.record(block_number, &block_labels)?;
+        }
+        self.label_totals.merge(&block_labels);
+        self.progress.record_block(block_tx_count, block_gas_used);
+
+        Ok(Some((block_tx_count, block_gas_used)))
+    }
+
+    /// Pull transactions from the orchestrator and keep building blocks until a
+    /// configured limit is hit or the channel closes, returning which one it was.
+    /// Blocks still waiting for a database commit are committed on the way out,
+    /// whether building stopped cleanly or not.
+    pub async fn start_building(&mut self) -> eyre::Result<StopReason> {
+        let stopped = self.build_blocks().await;
+        self.commit_pending()?;
+        stopped
+    }
+
+    async fn build_blocks(&mut self) -> eyre::Result<StopReason> {
+        let started = Instant::now();
         let mut total_tx_count = 0;
         let mut total_gas_used = 0;
         let mut total_blocks_built = 0;
 
-        let gas_limit = self.gas_limit;
-        // Keep at 50% so the base fee doesnt change
-        let max_gas_for_block = gas_limit * 50 / 100;
+        let gas_budget = self.simulation_config.gas_budget();
 
-        'block_building: loop {
-            if self
-                .simulation_config
-                .limits_hit(total_blocks_built, total_tx_count, total_gas_used)
+        // Set once a soft limit is hit: the channel is closed and the
+        // transactions it still holds are built into final blocks.
+        let mut draining = None;
+
+        loop {
+            if draining.is_none()
+                && let Some(reason) = self.simulation_config.limit_hit(
+                    total_blocks_built,
+                    total_tx_count,
+                    total_gas_used,
+                    started.elapsed(),
+                    self.block_writer
+                        .as_ref()
+                        .map_or(0, SegmentedBlockFileWriter::bytes_written),
+                )
             {
                 self.receiver.close();
+                if self.simulation_config.limit_mode == LimitMode::Hard {
+                    info!(
+                        target: logging::BUILDER,
+                        total_tx_count,
+                        total_gas_used,
+                        %reason,
+                        "simulation limits reached, stopping builder"
+                    );
+                    return Ok(reason);
+                }
                 info!(
-                    target: "sandbox::block_builder",
+                    target: logging::BUILDER,
                     total_tx_count,
                     total_gas_used,
-                    "simulation limits reached, stopping builder"
+                    %reason,
+                    queued = self.carried.len() + self.receiver.len(),
+                    "simulation limits reached, building queued transactions into final blocks"
                 );
-                return Ok(());
+                draining = Some(reason);
             }
 
+            let gas_progress = match gas_budget {
+                Some(budget) => format!("{total_gas_used} / {budget}"),
+                None => total_gas_used.to_string(),
+            };
             info!(
-                target: "sandbox::block_builder",
+                target: logging::BUILDER,
                 total_blocks_built,
                 total_tx_count,
                 total_gas_used,
-                "Simulation progress: {total_blocks_built} blocks built, {total_tx_count} transactions processed, {total_gas_used} gas used"
+                "Simulation progress: {total_blocks_built} blocks built, {total_tx_count} transactions processed, {gas_progress} gas used"
             );
 
-            let state_provider = self.provider_factory.latest()?;
-            let state = StateProviderDatabase::new(&state_provider);
-            let mut state_db: State<StateProviderDatabase<&Box<dyn StateProvider>>> =
-                State::builder()
-                    .with_database(state)
-                    .with_bundle_update()
-                    .build();
-
-            let parent_header = self.parent_header.clone();
+            let totals = draining
+                .is_none()
+                .then_some((total_tx_count, total_gas_used));
+            let built = if self.simulation_config.parallel_lanes > 1 {
+                self.build_lane_block(started, totals).await?
+            } else {
+                self.build_block(started, totals).await?
+            };
+            let Some((block_tx_count, block_gas_used)) = built else {
+                info!(
+                    target: logging::BUILDER,
+                    total_blocks_built,
+                    total_tx_count,
+                    "transaction channel closed, stopping builder"
+                );
+                return Ok(draining.unwrap_or(StopReason::ChannelClosed));
+            };
+            total_tx_count += block_tx_count;
+            total_gas_used += block_gas_used;
+            total_blocks_built += 1;
+        }
+    }
 
-            //base fee for the block
-            let base_fee = parent_header.base_fee_per_gas.unwrap_or(0);
+    /// Open a block on the parent, fill it by executing transactions one at a
+    /// time, then seal and persist it. Returns the block's transaction count
+    /// and gas used, or `None` once the channel closes with nothing executed
+    /// into it. `totals` is as for [`Self::build_lane_block`].
+    async fn build_block(
+        &mut self,
+        started: Instant,
+        totals: Option<(u64, u64)>,
+    ) -> eyre::Result<Option<(u64, u64)>> {
+        let parent_header = self.parent_header.clone();
 
-            info!(
-                target: "sandbox::block_builder",
-                base_fee = base_fee,
-                "base fee for the block"
-            );
+        let next_block_number = parent_header.number + 1;
+        let state_provider = {
+            let _t = time_block_section!(next_block_number, "state_provider");
+            latest_state(&self.provider_factory, &self.pending)?
+        };
+        let state = StateProviderDatabase::new(&state_provider);
+        let mut state_db: State<StateProviderDatabase<&Box<dyn StateProvider>>> = State::builder()
+            .with_database(state)
+            .with_bundle_update()
+            .build();
 
-            let next_block_number = parent_header.number + 1;
-            debug!(
-                target: "sandbox::block_builder",
-                parent = parent_header.number,
-                next = next_block_number,
-                timestamp = self.parent_timestamp + 1,
-                "initializing block builder"
-            );
+        let fee_recipient = self
+            .simulation_config
+            .fee_recipient
+            .for_block(next_block_number);
+        debug!(
+            target: logging::BUILDER,
+            parent = parent_header.number,
+            next = next_block_number,
+            timestamp = self.parent_timestamp + self.simulation_config.block_interval(),
+            "initializing block builder"
+        );
 
-            let mut builder = self
-                .evm_config
+        // The builder borrows the EVM config for as long as it lives, so it
+        // is built from a copy to leave `self` free for the helpers.
+        let evm_config = self.evm_config.clone();
+        let mut builder = {
+            let _t = time_block_section!(next_block_number, "builder_for_next_block");
+            evm_config
                 .builder_for_next_block(
                     &mut state_db,
                     &parent_header,
-                    NextBlockEnvAttributes {
-                        timestamp: self.parent_timestamp + 1,
-                        suggested_fee_recipient: Address::ZERO,
-                        prev_randao: B256::ZERO,
-                        gas_limit: self.gas_limit,
-                        parent_beacon_block_root: None,
-                        withdrawals: None,
-                    },
+                    self.next_block_attributes(next_block_number, fee_recipient),
                 )
                 .map_err(|err| {
-                    warn!(target: "sandbox", %err, "failed to create a builder");
+                    warn!(target: logging::BUILDER, %err, "failed to create a builder");
                     err
-                })?;
+                })?
+        };
 
-            let mut block_gas_used = 0;
-            let mut block_tx_count = 0;
+        // The parent's base fee only holds at a 50% fill; read the real one.
+        let base_fee = builder.evm().block().basefee;
+        let gas_target = if self.simulation_config.hold_base_fee {
+            self.next_gas_target
+        } else {
+            self.simulation_config.block_gas_target()
+        };
 
+        {
+            let _t = time_block_section!(next_block_number, "pre_execution_changes");
             builder.apply_pre_execution_changes().map_err(|err| {
-                warn!(target: "sandbox", %err, "failed to apply pre-execution changes");
+                warn!(target: logging::BUILDER, %err, "failed to apply pre-execution changes");
                 err
             })?;
-            debug!(
-                target: "sandbox::block_builder",
-                block = next_block_number,
-                "pre-execution changes applied"
-            );
+        }
+        let mut block = OpenBlock {
+            number: next_block_number,
+            parent_header,
+            fee_recipient,
+            base_fee,
+            gas_target,
+            started,
+            totals,
+            gas_used: 0,
+            tx_count: 0,
+            tx_bytes: 0,
+            tips: U256::ZERO,
+            tip_range: None,
+            candidates_ordered: false,
+            labels: LabelTotals::default(),
+            senders: HashMap::default(),
+            deployments: Vec::new(),
+            finished_phases: Vec::new(),
+            failed_indices: Vec::new(),
+            held_back: Vec::new(),
+            skipped_senders: HashSet::default(),
+            retry: Vec::new(),
+            retrying_senders: HashSet::default(),
+            group: VecDeque::new(),
+            load_txs: 0,
+            scenario_target: self.scenario_blocks.front().copied(),
+            tx_execution: block_section_batch!(next_block_number, "execute_transaction"),
+        };
+        debug!(
+            target: logging::BUILDER,
+            block = next_block_number,
+            "pre-execution changes applied"
+        );
 
-            while let Some(tx) = self.receiver.recv().await {
-                let gas_used = builder
-                    .execute_transaction_with_result_closure(tx.clone(), |res| {
-                        if !res.is_success() {
-                            info!(target: "sandbox", "transaction result: {:?}", res);
-                            info!(target: "sandbox", "transaction: {:?}", tx);
-                        }
-                    })
-                    .map_err(|err| {
-                        warn!(target: "sandbox", %err, "failed to execute transaction {:?}", tx);
-                        err
-                    })?;
+        let Some(seal_reason) = self.fill_block(&mut builder, &mut block).await? else {
+            // The channel closed and drained with nothing executed into this
+            // block, so there is nothing left to seal.
+            self.unsealed = builder.executed_transactions().to_vec();
+            self.requeue(block.retry, block.held_back);
+            return Ok(None);
+        };
 
-                block_gas_used += gas_used;
-                block_tx_count += 1;
+        //Last transaction in the block
+        info!(
+            target: logging::BUILDER,
+            block = next_block_number,
+            block_tx_count = block.tx_count,
+            block_gas_used = block.gas_used,
+        );
+
+        // Includes the state root computation.
+        let outcome = {
+            let _t = time_block_section!(next_block_number, "finish_block");
+            builder.finish(&state_provider).map_err(|err| {
+                warn!(target: logging::BUILDER, %err, "failed to finish building block");
+                err
+            })?
+        };
 
-                if block_gas_used >= max_gas_for_block {
-                    //finish the block
-                    //commit to the db
-                    //call build next block
+        self.finish_block(
+            &block,
+            seal_reason,
+            outcome,
+            state_db.take_bundle(),
+            state_provider.as_ref(),
+        )
+        .await?;
+        let built = (block.tx_count, block.gas_used);
+        self.post_block_reconcile(block)?;
+        Ok(Some(built))
+    }
 
-                    //Last transaction in the block
-                    info!(
-                        target: "sandbox::block_builder",
-                        block = next_block_number,
-                        block_tx_count,
-                        block_gas_used,
-                    );
+    /// Take transactions for `block`, the followers of a group first, then
+    /// those carried over, then the channel, and include them with `builder`
+    /// until the block is to be sealed. Returns why, or `None` once the
+    /// channel closes with nothing executed into the block.
+    async fn fill_block<B>(
+        &mut self,
+        builder: &mut B,
+        block: &mut OpenBlock,
+    ) -> eyre::Result<Option<SealReason>>
+    where
+        B: BlockBuilder<Primitives = EthPrimitives>,
+    {
+        loop {
+            // The candidates are ordered once as the block opens, and again
+            // whenever the block has used them all up.
+            if let TxOrdering::PriorityFee { window } = self.simulation_config.tx_ordering
+                && (!block.candidates_ordered || self.carried.is_empty())
+            {
+                self.fill_candidates(window, block.gas_target, block.base_fee)
+                    .await;
+                block.candidates_ordered = true;
+            }
+            let in_group = !block.group.is_empty();
+            let next = match block.group.pop_front() {
+                Some(member) => Some(member),
+                None => match self.carried.pop_front() {
+                    Some(labeled) => Some(labeled),
+                    None => self.receiver.recv().await,
+                },
+            };
+            let Some(labeled) = next else {
+                // The channel closed: seal what the block already holds
+                // rather than abandoning it.
+                return Ok((block.tx_count > 0).then_some(SealReason::Shutdown));
+            };
+            if let Some(seal_reason) =
+                self.include_transaction(builder, block, labeled, in_group)?
+            {
+                return Ok(Some(seal_reason));
+            }
+        }
+    }
 
-                    let outcome = builder.finish(&state_provider).map_err(|err| {
-                        warn!(target: "sandbox", %err, "failed to finish building block");
-                        err
-                    })?;
+    /// Include `labeled` in `block` unless it is stale, waits behind a retry,
+    /// or is deferred to a later block, and return why the block is to be
+    /// sealed after it, if it is. `in_group` is set for a group's follower,
+    /// which was admitted along with its lead.
+    fn include_transaction<B>(
+        &mut self,
+        builder: &mut B,
+        block: &mut OpenBlock,
+        labeled: LabeledTx,
+        in_group: bool,
+    ) -> eyre::Result<Option<SealReason>>
+    where
+        B: BlockBuilder<Primitives = EthPrimitives>,
+    {
+        let hash = *labeled.tx.hash();
+        let from = labeled.tx.signer();
+        let nonce = labeled.tx.nonce();
+        let tx_bytes = labeled.tx.inner().length() as u64;
+        let gas_limit = labeled.tx.gas_limit();
+        let tip = labeled
+            .tx
+            .effective_tip_per_gas(block.base_fee)
+            .unwrap_or_default();
+        let (label, phase) = (labeled.label, labeled.phase);
 
-                    info!(
-                        target: "sandbox::block_builder",
-                        block = next_block_number,
-                        txs_in_block = block_tx_count,
-                        gas_used = block_gas_used,
-                        "sealing full block"
-                    );
+        // Once a sender's transaction is skipped, its later ones
+        // would only hit the nonce gap it left. They are dropped
+        // until the orchestrator, reconciled with the chain, resends
+        // from the sender's on-chain nonce.
+        let stale = block.skipped_senders.contains(&from)
+            || self
+                .stalled_senders
+                .get(&from)
+                .is_some_and(|&expected| nonce != expected);
+        if stale {
+            for member in labeled.members() {
+                let hash = *member.tx.hash();
+                counter!("stale_nonce_drops").increment(1);
+                block.labels.record_rejected(member.label);
+                self.invalid_txs.record_rejected(&hash);
+                self.finish_retry(&hash, false);
+            }
+            return Ok(None);
+        }
+        self.stalled_senders.remove(&from);
+
+        // A transaction queued for a retry holds back the rest of its
+        // sender's, so they keep their nonce order when retried. Past
+        // the queue's bound they are attempted as usual.
+        let retry_capacity = self.simulation_config.channel_buffer_size;
+        if block.retrying_senders.contains(&from) && block.retry.len() < retry_capacity {
+            block.retry.push(labeled);
+            return Ok(None);
+        }
+
+        // A scenario block opens a block of its own and holds exactly
+        // its transactions, whatever the fill strategy says;
+        // `check_scenario` made sure their gas limits fit.
+        let scripted = block.scenario_target.is_some() && phase == SimulationPhase::TransactionLoad;
+        match self.deferral(block, &labeled, in_group, scripted) {
+            // Deferred as a whole, followers and all.
+            Some(Deferral::SenderCap) => {
+                block.held_back.push(labeled);
+                return Ok(
+                    (block.held_back.len() >= self.simulation_config.channel_buffer_size)
+                        .then_some(SealReason::SenderLimit),
+                );
+            }
+            Some(Deferral::Carry(seal_reason)) => {
+                self.carried.push_front(labeled);
+                return Ok(Some(seal_reason));
+            }
+            None => {}
+        }
+
+        let LabeledTx { tx, followers, .. } = labeled;
+        block.group.extend(followers);
+        let Some((gas_used, failed)) =
+            self.execute_transaction(builder, block, tx, label, phase)?
+        else {
+            return Ok(None);
+        };
+
+        if failed && self.simulation_config.trace_failed_txs {
+            block.failed_indices.push(block.tx_count as usize);
+        }
 
-                    self.finish_block_and_commit(outcome, state_db).await?;
+        block.gas_used += gas_used;
+        block.tx_count += 1;
+        block.tx_bytes += tx_bytes;
+        block.tips += U256::from(tip) * U256::from(gas_used);
+        block.tip_range = Some((block.tip_range.map_or(tip, |(first, _)| first), tip));
+        block.labels.record_included(label, gas_used, failed);
+        self.finish_retry(&hash, !failed);
+        if let Some(expected) = self.expected_code.take(&hash) {
+            block.deployments.push((expected, hash));
+        }
+        block.finished_phases.extend(self.enter_phase(phase));
+        self.gas_calibration.record(label, gas_used, gas_limit);
+        self.sender_tips.record(from, tip);
+        *block.senders.entry(from).or_default() += 1;
+        if phase == SimulationPhase::TransactionLoad {
+            block.load_txs += 1;
+        }
+
+        Ok(self.seal_reason(block, scripted))
+    }
+
+    /// Whether `labeled` has to wait for a later block rather than join
+    /// `block` now. A group is checked against the limits as a whole when its
+    /// lead comes up; its followers (`in_group`) were admitted with it.
+    fn deferral(
+        &self,
+        block: &OpenBlock,
+        labeled: &LabeledTx,
+        in_group: bool,
+        scripted: bool,
+    ) -> Option<Deferral> {
+        if in_group {
+            return None;
+        }
+        let group_bytes = labeled
+            .members()
+            .map(|member| member.tx.inner().length() as u64)
+            .sum::<u64>();
+        let group_gas_limit = labeled.group_gas_limit();
+        // A transaction that would push the block past `max_block_bytes`
+        // is held over to start the next one. A lone transaction larger
+        // than the cap still gets a block of its own.
+        let over_cap = block.tx_count > 0
+            && self
+                .simulation_config
+                .max_block_bytes
+                .is_some_and(|max| block.tx_bytes + group_bytes > max);
+        // Gas used is only known after execution, so the gas limit
+        // stands in for it: a transaction that could take the block
+        // past its gas target is held over to start the next one
+        // rather than overshooting. A lone transaction whose limit
+        // exceeds the target still gets a block of its own.
+        let over_gas_target =
+            block.tx_count > 0 && block.gas_used + group_gas_limit > block.gas_target;
+        // A sender already at its per-block cap waits for the next
+        // block, along with everything it sends after. Once a channel
+        // buffer's worth is waiting, the block is sealed so the
+        // held-back transactions cannot pile up without bound.
+        let max_per_sender = self.simulation_config.max_txs_per_sender_per_block;
+        let at_sender_cap = max_per_sender > 0
+            && block
+                .senders
+                .get(&labeled.tx.signer())
+                .is_some_and(|&txs| txs >= max_per_sender as u64);
+        if at_sender_cap {
+            Some(Deferral::SenderCap)
+        } else if over_cap {
+            Some(Deferral::Carry(SealReason::MaxBytes))
+        } else if scripted && block.tx_count > block.load_txs {
+            Some(Deferral::Carry(SealReason::Scenario))
+        } else if over_gas_target && !scripted {
+            Some(Deferral::Carry(SealReason::GasTarget))
+        } else {
+            None
+        }
+    }
+
+    /// Execute `tx` into the block with `builder`. Returns its gas used and
+    /// whether it failed once it is in the block, or `None` when it is left
+    /// out: rejected, or queued for a retry. A rejected setup deployment, or
+    /// an execution error, fails the run.
+    fn execute_transaction<B>(
+        &mut self,
+        builder: &mut B,
+        block: &mut OpenBlock,
+        tx: TX,
+        label: TxLabel,
+        phase: SimulationPhase,
+    ) -> eyre::Result<Option<(u64, bool)>>
+    where
+        B: BlockBuilder<Primitives = EthPrimitives>,
+    {
+        // The builder takes ownership of `tx`; keep only what is needed
+        // afterwards rather than cloning the whole envelope.
+        let hash = *tx.hash();
+        let from = tx.signer();
+        let nonce = tx.nonce();
+        let mut failed = false;
+        // A copy to re-send if it fails for ordering alone, while it
+        // has attempts left and the queue has room. Injected invalid
+        // transactions are meant to fail.
+        let attempts = self.retry_attempts.get(&hash).copied().unwrap_or_default();
+        let retry_copy = (attempts < self.simulation_config.max_tx_retries
+            && block.retry.len() < self.simulation_config.channel_buffer_size
+            && !self.invalid_txs.is_injected(&hash))
+        .then(|| tx.clone());
+        let executed = Instant::now();
+        let result = builder.execute_transaction_with_commit_condition(tx, |res| {
+            if !res.is_success() {
+                // Left out of the block, so its nonce stays free
+                // for the retry.
+                if retry_copy.is_some()
+                    && res
+                        .output()
+                        .is_some_and(|output| retry::is_allowance_revert(output))
+                {
+                    return CommitChanges::No;
+                }
+                failed = true;
+                counter!("failed_transactions").increment(1);
+                match res.output() {
+                    Some(output) => warn!(
+                        target: logging::TX_FAILURES,
+                        %hash,
+                        %from,
+                        label = label.name(),
+                        phase = phase.name(),
+                        reason = %revert::revert_reason(output),
+                        "transaction reverted"
+                    ),
+                    None => warn!(
+                        target: logging::TX_FAILURES,
+                        %hash,
+                        %from,
+                        label = label.name(),
+                        phase = phase.name(),
+                        "transaction halted: {:?}",
+                        res
+                    ),
+                }
+            }
+            CommitChanges::Yes
+        });
+        block.tx_execution.record(executed.elapsed());
 
-                    total_tx_count += block_tx_count;
-                    total_gas_used += block_gas_used;
-                    total_blocks_built += 1;
-                    continue 'block_building;
+        // Transactions that fail validation are skipped, not fatal; the
+        // registry tells deliberately injected ones from lost valid ones.
+        let gas_used = match result {
+            Ok(Some(gas_used)) => gas_used,
+            Ok(None) => {
+                if let Some(tx) = retry_copy {
+                    self.queue_retry(
+                        &mut block.retry,
+                        &mut block.retrying_senders,
+                        LabeledTx::new(tx, label, phase),
+                        "insufficient allowance",
+                    );
                 }
+                return Ok(None);
+            }
+            Err(BlockExecutionError::Validation(BlockValidationError::InvalidTx {
+                error, ..
+            })) => {
+                if let Some(tx) = retry_copy
+                    && matches!(
+                        error.as_invalid_tx_err(),
+                        Some(InvalidTransaction::NonceTooHigh { .. })
+                    )
+                {
+                    self.queue_retry(
+                        &mut block.retry,
+                        &mut block.retrying_senders,
+                        LabeledTx::new(tx, label, phase),
+                        "nonce too high",
+                    );
+                    return Ok(None);
+                }
+                self.finish_retry(&hash, false);
+                counter!("rejected_transactions").increment(1);
+                block.labels.record_rejected(label);
+                if let Some((contract, address)) = self.expected_code.take(&hash) {
+                    return Err(SandboxError::MissingContract {
+                        label: contract,
+                        address,
+                        block: block.number,
+                        hash,
+                        outcome: "was rejected",
+                    }
+                    .into());
+                }
+                if !self.invalid_txs.record_rejected(&hash) {
+                    warn!(
+                        target: logging::TX_FAILURES,
+                        %hash,
+                        %from,
+                        nonce,
+                        label = label.name(),
+                        phase = phase.name(),
+                        %error,
+                        "valid transaction rejected"
+                    );
+                    block.skipped_senders.insert(from);
+                }
+                return Ok(None);
+            }
+            Err(err) => {
+                warn!(
+                    target: logging::BUILDER,
+                    %err,
+                    %hash,
+                    %from,
+                    nonce,
+                    label = label.name(),
+                    phase = phase.name(),
+                    "failed to execute transaction"
+                );
+                return Err(SandboxError::Execution {
+                    block: block.number,
+                    hash,
+                    source: err,
+                }
+                .into());
+            }
+        };
+        if self.simulation_config.invalid_tx_rate > 0.0 && self.invalid_txs.record_accepted(&hash) {
+            warn!(target: logging::TX_FAILURES, %hash, "injected invalid transaction was included");
+        }
+        Ok(Some((gas_used, failed)))
+    }
+
+    /// Why `block` is to be sealed after the transaction just included, if
+    /// it is. `scripted` is set while it holds a scenario block.
+    fn seal_reason(&self, block: &OpenBlock, scripted: bool) -> Option<SealReason> {
+        // Seal early once the deadline passes so the run ends on a
+        // complete block rather than dropping the partial one.
+        // Nothing seals the block with a group part-way through.
+        if !block.group.is_empty() {
+            None
+        } else if scripted {
+            (Some(block.load_txs) == block.scenario_target).then_some(SealReason::Scenario)
+        } else if block.gas_used >= block.gas_target {
+            Some(SealReason::GasTarget)
+        } else {
+            self.simulation_config
+                .fill_strategy
+                .seal_reason(block.tx_count, block.tx_bytes)
+        }
+        .or_else(|| {
+            self.simulation_config
+                .deadline_passed(block.started.elapsed())
+                .then_some(SealReason::Deadline)
+        })
+        // The transaction and gas limits are checked per
+        // transaction, so the run stops on them exactly
+        // rather than a whole block late. A soft limit,
+        // already draining, builds what is queued.
+        .or_else(|| {
+            let (total_txs, total_gas) = block.totals?;
+            (block.group.is_empty()
+                && self
+                    .simulation_config
+                    .volume_limit_hit(total_txs + block.tx_count, total_gas + block.gas_used)
+                    .is_some())
+            .then_some(SealReason::RunLimit)
+        })
+    }
+
+    /// Log and account for `block`, sealed for `seal_reason` as `outcome`,
+    /// and persist it with its `bundle_state`. Failed transactions are traced
+    /// first, against `state_provider`, the state the block was built on.
+    async fn finish_block(
+        &mut self,
+        block: &OpenBlock,
+        seal_reason: SealReason,
+        outcome: BlockBuilderOutcome<EthPrimitives>,
+        bundle_state: BundleState,
+        state_provider: &dyn StateProvider,
+    ) -> eyre::Result<()> {
+        // The replay reads the parent state, so it runs before the
+        // block joins the pending ones.
+        if !block.failed_indices.is_empty() {
+            let _t = time_block_section!(block.number, "trace_failed_txs");
+            if let Err(err) = call_trace::trace_failed(
+                &self.evm_config,
+                state_provider,
+                &block.parent_header,
+                self.next_block_attributes(block.number, block.fee_recipient),
+                &outcome.block,
+                &block.failed_indices,
+                &self.paths.failed_traces_dir(),
+            ) {
+                warn!(target: logging::WRITER, %err, "failed to trace failed transactions");
             }
         }
+
+        info!(
+            target: logging::BUILDER,
+            block = block.number,
+            txs_in_block = block.tx_count,
+            gas_used = block.gas_used,
+            bytes = block.tx_bytes,
+            senders = block.senders.len(),
+            first_tip = ?block.tip_range.map(|(first, _)| first),
+            last_tip = ?block.tip_range.map(|(_, last)| last),
+            %seal_reason,
+            "sealing full block"
+        );
+        counter!(seal_reason.counter_key()).increment(1);
+        self.record_base_fee(
+            block.number,
+            block.parent_header.base_fee_per_gas.unwrap_or_default(),
+            block.base_fee,
+        );
+        if self.simulation_config.hold_base_fee {
+            // Aim the next block at the gas that brings the two
+            // blocks' average back to the target.
+            self.next_gas_target = (2 * self.simulation_config.block_gas_target())
+                .saturating_sub(block.gas_used)
+                .min(self.gas_limit);
+        }
+        gauge!("block_unique_senders").set(block.senders.len() as u64);
+        self.sender_diversity.record_block(
+            block.tx_count,
+            block.senders.len() as u64,
+            block.senders.values().copied().max().unwrap_or_default(),
+        );
+
+        self.finish_block_and_commit(outcome, bundle_state).await
+    }
+
+    /// Bring the builder's bookkeeping up to date with `block`, now sealed
+    /// and pending: check what it deployed and the phases it finished,
+    /// stall the senders it skipped, record its tips and labels, and queue
+    /// the transactions it held back or is retrying to open the next block.
+    fn post_block_reconcile(&mut self, block: OpenBlock) -> eyre::Result<()> {
+        self.check_deployed(block.number, &block.deployments)?;
+        self.verify_finished_phases(&block.finished_phases)?;
+        self.reconcile_skipped(&block.skipped_senders)?;
+        *self.expected_tips.entry(block.fee_recipient).or_default() += block.tips;
+        if let Some(labels_writer) = &mut self.labels_writer {
+            labels_writer.record(block.number, &block.labels)?;
+        }
+        if let Some(fees_writer) = &mut self.fees_writer {
+            fees_writer.record(block.number, block.tx_count, block.tip_range)?;
+        }
+        self.label_totals.merge(&block.labels);
+        if block.scenario_target == Some(block.load_txs) {
+            self.scenario_blocks.pop_front();
+        }
+        self.requeue(block.retry, block.held_back);
+        self.progress.record_block(block.tx_count, block.gas_used);
+        Ok(())
     }
 }
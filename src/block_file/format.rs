@@ -0,0 +1,204 @@
+//! The block file format, the one definition the writers and readers share.
+//!
+//! A block file is a fixed-size header followed by one record per block:
+//!
+//! | Offset | Bytes | Field |
+//! |---|---|---|
+//! | 0 | 4 | [`MAGIC`], `RETH` |
+//! | 4 | 1 | Format [`VERSION`] |
+//! | 5 | 1 | [`BlockType`]: `0` Ethereum, `1` Optimism |
+//! | 6 | 8 | First block number, little-endian |
+//! | 14 | 8 | Last block number, little-endian |
+//!
+//! Each record is a little-endian `u32` length followed by that many bytes of
+//! RLP-encoded block. The file ends after the last complete record; anything
+//! else trailing it is a truncated record and an error.
+
+use std::io::{ErrorKind, Read, Write};
+
+/// Identifies a block file.
+pub const MAGIC: [u8; 4] = *b"RETH";
+
+/// The only format version written or read.
+pub const VERSION: u8 = 1;
+
+/// Encoded header length: magic, version, block type, and two block numbers.
+pub const HEADER_LEN: usize = MAGIC.len() + 1 + 1 + 8 + 8;
+
+/// Bytes in the length prefix of every record.
+pub const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Distinguishes between raw Ethereum blocks and any future rollup variants.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum BlockType {
+    Ethereum = 0,
+    Optimism = 1,
+}
+
+impl BlockType {
+    /// The block type encoded as `byte`.
+    pub fn from_byte(byte: u8) -> eyre::Result<Self> {
+        match byte {
+            0 => Ok(Self::Ethereum),
+            1 => Ok(Self::Optimism),
+            _ => Err(eyre::eyre!("Unknown block type: {byte}")),
+        }
+    }
+}
+
+/// Metadata written once at the head of the block file so later tools can
+/// verify compatibility.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockFileHeader {
+    pub(crate) version: u8,
+    pub(crate) block_type: BlockType,
+    pub(crate) from_block: u64,
+    pub(crate) to_block: u64,
+}
+
+impl BlockFileHeader {
+    /// Build a header describing the chain range contained in the file.
+    pub fn new(is_optimism: bool, from_block: u64, to_block: u64) -> Self {
+        Self {
+            version: VERSION,
+            block_type: if is_optimism {
+                BlockType::Optimism
+            } else {
+                BlockType::Ethereum
+            },
+            from_block,
+            to_block,
+        }
+    }
+
+    /// Get the block type from the header
+    pub fn block_type(&self) -> BlockType {
+        self.block_type
+    }
+
+    /// File format version.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// First block number the header declares.
+    pub fn from_block(&self) -> u64 {
+        self.from_block
+    }
+
+    /// Last block number the header declares.
+    pub fn to_block(&self) -> u64 {
+        self.to_block
+    }
+
+    /// The header's bytes, laid out as in the table above.
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4] = self.version;
+        bytes[5] = self.block_type as u8;
+        bytes[6..14].copy_from_slice(&self.from_block.to_le_bytes());
+        bytes[14..].copy_from_slice(&self.to_block.to_le_bytes());
+        bytes
+    }
+
+    /// Parse the bytes [`Self::encode`] produces, rejecting another format
+    /// or version.
+    pub fn decode(bytes: &[u8; HEADER_LEN]) -> eyre::Result<Self> {
+        if bytes[..4] != MAGIC {
+            return Err(eyre::eyre!("Invalid file format"));
+        }
+        let version = bytes[4];
+        if version != VERSION {
+            return Err(eyre::eyre!("Unsupported file version: {version}"));
+        }
+        let block_number = |range: std::ops::Range<usize>| {
+            u64::from_le_bytes(bytes[range].try_into().expect("eight header bytes"))
+        };
+        Ok(Self {
+            version,
+            block_type: BlockType::from_byte(bytes[5])?,
+            from_block: block_number(6..14),
+            to_block: block_number(14..HEADER_LEN),
+        })
+    }
+
+    /// Serialize the header to the provided writer.
+    pub fn write_to(&self, writer: &mut impl Write) -> eyre::Result<()> {
+        writer.write_all(&self.encode())?;
+        Ok(())
+    }
+
+    /// Parse a header from the start of `reader`.
+    pub fn read_from(reader: &mut impl Read) -> eyre::Result<Self> {
+        let mut bytes = [0u8; HEADER_LEN];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|err| match err.kind() {
+                ErrorKind::UnexpectedEof => eyre::eyre!("truncated block file header"),
+                _ => err.into(),
+            })?;
+        Self::decode(&bytes)
+    }
+}
+
+/// Bytes a record holding `block_len` bytes of block takes on disk.
+pub fn record_len(block_len: usize) -> u64 {
+    (LENGTH_PREFIX_LEN + block_len) as u64
+}
+
+/// Write `block` as one length-prefixed record, returning the bytes written.
+/// A block too long for its length prefix is an error, not truncated.
+pub fn write_record(writer: &mut impl Write, block: &[u8]) -> eyre::Result<u64> {
+    let len = u32::try_from(block.len()).map_err(|_| {
+        eyre::eyre!(
+            "block of {} bytes does not fit a record's {LENGTH_PREFIX_LEN}-byte length prefix",
+            block.len()
+        )
+    })?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(block)?;
+    Ok(record_len(block.len()))
+}
+
+/// Read the next record's length prefix, or `None` when `reader` is at a
+/// clean end of file. A partial prefix is an error.
+pub fn read_length_prefix(reader: &mut impl Read) -> eyre::Result<Option<u32>> {
+    let mut prefix = [0u8; LENGTH_PREFIX_LEN];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(eyre::eyre!(
+                    "truncated length prefix: {filled} of {LENGTH_PREFIX_LEN} bytes"
+                ));
+            }
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(Some(u32::from_le_bytes(prefix)))
+}
+
+/// Read the next record's block, or `None` when `reader` is at a clean end
+/// of file. The block is read as it arrives rather than allocated from its
+/// length prefix, so a corrupt prefix cannot demand gigabytes up front.
+pub fn read_record(reader: &mut impl Read) -> eyre::Result<Option<Vec<u8>>> {
+    let Some(len) = read_length_prefix(reader)? else {
+        return Ok(None);
+    };
+    let mut block = Vec::new();
+    reader
+        .by_ref()
+        .take(u64::from(len))
+        .read_to_end(&mut block)?;
+    eyre::ensure!(
+        block.len() == len as usize,
+        "truncated block in file: {} of {len} bytes",
+        block.len()
+    );
+    Ok(Some(block))
+}
@@ -0,0 +1,498 @@
+//! Block file writers and readers. The layout they share, from the header
+//! bytes to the record framing, is defined once in [`format`].
+
+pub mod format;
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use self::format::{BlockFileHeader, BlockType, HEADER_LEN};
+use crate::{config::Rotation, error::SandboxError};
+
+/// Streams block blobs to a sink (a file, for later replay by `reth-bench`).
+/// The header is written up front and rewritten with the real block range on
+/// [`finish`](Self::finish), hence the `Seek` bound.
+pub struct BlockFileWriter<W: Write + Seek = BufWriter<File>> {
+    writer: W,
+    header: BlockFileHeader,
+    blocks_written: usize,
+    bytes_written: u64,
+}
+
+impl BlockFileWriter {
+    /// Create the file at `path` and write the header.
+    pub fn create(path: &Path, header: BlockFileHeader) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        Self::from_writer(BufWriter::new(file), header)
+    }
+
+    /// Continue the block file at `path`: validate its header, walk its
+    /// records to make sure the last one is complete, and position writes at
+    /// the end. The header range is updated on [`finish`](Self::finish).
+    pub fn append(path: &Path) -> eyre::Result<Self> {
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|err| SandboxError::io(path, err))?;
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(&mut file);
+        let header = BlockFileHeader::read_from(&mut reader)?;
+
+        let mut offset = HEADER_LEN as u64;
+        let mut blocks_written = 0usize;
+        while offset < len {
+            let Some(block_len) = format::read_length_prefix(&mut reader)
+                .map_err(|err| eyre::eyre!("{}: at offset {offset}: {err}", path.display()))?
+            else {
+                break;
+            };
+            let record_len = format::record_len(block_len as usize);
+            eyre::ensure!(
+                offset + record_len <= len,
+                "{}: block {} at offset {offset} is truncated ({} of {block_len} bytes)",
+                path.display(),
+                header.from_block + blocks_written as u64,
+                len - offset - format::LENGTH_PREFIX_LEN as u64
+            );
+            reader.seek_relative(i64::from(block_len))?;
+            offset += record_len;
+            blocks_written += 1;
+        }
+        drop(reader);
+        if blocks_written > 0 {
+            eyre::ensure!(
+                header.to_block == header.from_block + blocks_written as u64 - 1,
+                "{}: header declares blocks {}..={} but the file holds {blocks_written}",
+                path.display(),
+                header.from_block,
+                header.to_block
+            );
+        }
+
+        let mut writer = BufWriter::new(file);
+        writer.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            writer,
+            header,
+            blocks_written,
+            bytes_written: len,
+        })
+    }
+}
+
+impl<W: Write + Seek> BlockFileWriter<W> {
+    /// Write the header to `writer`, positioned at the start of the file.
+    /// `header.from_block` is the number of the first block to be written.
+    pub fn from_writer(mut writer: W, header: BlockFileHeader) -> eyre::Result<Self> {
+        header.write_to(&mut writer)?;
+
+        Ok(Self {
+            writer,
+            header,
+            blocks_written: 0,
+            bytes_written: HEADER_LEN as u64,
+        })
+    }
+
+    /// Write a single length-prefixed RLP blob to the output file.
+    pub fn write_block(&mut self, rlp_data: &[u8]) -> eyre::Result<()> {
+        self.bytes_written += format::write_record(&mut self.writer, rlp_data)?;
+        self.blocks_written += 1;
+        Ok(())
+    }
+
+    /// Bytes written so far, header and length prefixes included (whether
+    /// or not they have been flushed yet).
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Blocks written so far.
+    pub fn blocks_written(&self) -> usize {
+        self.blocks_written
+    }
+
+    /// Number the next block written must have to keep the file contiguous.
+    pub fn next_block(&self) -> u64 {
+        self.header.from_block + self.blocks_written as u64
+    }
+
+    /// Rewrite the header with the range of blocks actually written, flush,
+    /// and hand back the sink.
+    pub fn finish(mut self) -> eyre::Result<W> {
+        self.header.to_block =
+            self.header.from_block + (self.blocks_written as u64).saturating_sub(1);
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.header.write_to(&mut self.writer)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes blocks to a single file, or to consecutive segment files when a
+/// [`Rotation`] is set. Callers only see `write_block`.
+///
+/// Segments are named `{stem}_{from}_{to}.{ext}` after the configured path
+/// and written next to it. The segment being filled is `{stem}_{from}.partial`
+/// until it is finished and its range is known.
+pub struct SegmentedBlockFileWriter {
+    path: PathBuf,
+    rotation: Rotation,
+    current: BlockFileWriter,
+    /// Where `current` is being written.
+    current_path: PathBuf,
+    /// Number of the first block in `current`.
+    current_from: u64,
+    /// Finished segments, in block order.
+    finished: Vec<PathBuf>,
+    /// Bytes in finished segments.
+    finished_bytes: u64,
+}
+
+impl SegmentedBlockFileWriter {
+    /// Open the first file for blocks starting at `first_block`.
+    pub fn create(path: &Path, rotation: Rotation, first_block: u64) -> eyre::Result<Self> {
+        let current_path = match rotation {
+            Rotation::None => path.to_path_buf(),
+            Rotation::Blocks(_) | Rotation::Bytes(_) => partial_segment_path(path, first_block),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            current: Self::open(&current_path, first_block)?,
+            current_path,
+            current_from: first_block,
+            finished: Vec::new(),
+            finished_bytes: 0,
+        })
+    }
+
+    /// Continue the single file at `path` with block `first_block`, which
+    /// must directly follow its last block.
+    pub fn append(path: &Path, rotation: Rotation, first_block: u64) -> eyre::Result<Self> {
+        if rotation != Rotation::None {
+            return Err(SandboxError::Config(
+                "appending to a rotated block file is not supported; resume without rotation"
+                    .to_string(),
+            )
+            .into());
+        }
+        let current = BlockFileWriter::append(path)?;
+        eyre::ensure!(
+            current.next_block() == first_block,
+            "{} continues at block {}, but the datadir resumes at block {first_block}; \
+             move it aside or point `blocks_out` elsewhere",
+            path.display(),
+            current.next_block()
+        );
+        eyre::ensure!(
+            current.header.block_type == BlockType::Ethereum,
+            "{} holds {:?} blocks",
+            path.display(),
+            current.header.block_type
+        );
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            current_from: current.header.from_block,
+            current,
+            current_path: path.to_path_buf(),
+            finished: Vec::new(),
+            finished_bytes: 0,
+        })
+    }
+
+    /// Start an Ethereum block file at `first_block`, the only kind the
+    /// sandbox builds.
+    fn open(path: &Path, first_block: u64) -> eyre::Result<BlockFileWriter> {
+        // The range is rewritten once the segment knows its last block.
+        BlockFileWriter::create(path, BlockFileHeader::new(false, first_block, first_block))
+    }
+
+    /// Write a block, first starting a new segment if the rotation calls for
+    /// one.
+    pub fn write_block(&mut self, rlp_data: &[u8]) -> eyre::Result<()> {
+        let blocks = self.current.blocks_written() as u64;
+        let rotate = blocks > 0
+            && match self.rotation {
+                Rotation::None => false,
+                Rotation::Blocks(max) => blocks >= max,
+                Rotation::Bytes(max) => {
+                    self.current.bytes_written() + format::record_len(rlp_data.len()) > max
+                }
+            };
+        if rotate {
+            let next_from = self.current_from + blocks;
+            let next_path = partial_segment_path(&self.path, next_from);
+            let next = Self::open(&next_path, next_from)?;
+            let finished = std::mem::replace(&mut self.current, next);
+            let finished_path = std::mem::replace(&mut self.current_path, next_path);
+            let finished_from = std::mem::replace(&mut self.current_from, next_from);
+            self.finished_bytes += finished.bytes_written();
+            self.finished.push(Self::close(
+                finished,
+                &finished_path,
+                &self.path,
+                finished_from,
+            )?);
+        }
+        self.current.write_block(rlp_data)
+    }
+
+    /// Finish the segment `writer` filled at `partial`, starting at block
+    /// `from`, and move it to its final `{stem}_{from}_{to}` name next to
+    /// `path`.
+    fn close(
+        writer: BlockFileWriter,
+        partial: &Path,
+        path: &Path,
+        from: u64,
+    ) -> eyre::Result<PathBuf> {
+        let blocks = writer.blocks_written() as u64;
+        drop(writer.finish()?);
+        let segment = segment_path(path, from, from + blocks.saturating_sub(1));
+        fs::rename(partial, &segment).map_err(|err| SandboxError::io(&segment, err))?;
+        Ok(segment)
+    }
+
+    /// Bytes written over every segment, headers and length prefixes included.
+    pub fn bytes_written(&self) -> u64 {
+        self.finished_bytes + self.current.bytes_written()
+    }
+
+    /// Finish the last segment and return every file written, in block order.
+    /// An empty trailing segment is removed rather than kept.
+    pub fn finish(mut self) -> eyre::Result<Vec<PathBuf>> {
+        let current = self.current;
+        let partial = self.current_path;
+        if self.rotation == Rotation::None {
+            drop(current.finish()?);
+            self.finished.push(partial);
+        } else if current.blocks_written() == 0 {
+            drop(current.finish()?);
+            fs::remove_file(&partial).map_err(|err| SandboxError::io(&partial, err))?;
+        } else {
+            self.finished.push(Self::close(
+                current,
+                &partial,
+                &self.path,
+                self.current_from,
+            )?);
+        }
+        Ok(self.finished)
+    }
+}
+
+/// `{stem}_{from}_{to}.{ext}` next to `path`.
+fn segment_path(path: &Path, from: u64, to: u64) -> PathBuf {
+    let (stem, ext) = stem_and_extension(path);
+    path.with_file_name(format!("{stem}_{from}_{to}.{ext}"))
+}
+
+/// Name of the segment starting at `from` while it is still being written.
+fn partial_segment_path(path: &Path, from: u64) -> PathBuf {
+    let (stem, _) = stem_and_extension(path);
+    path.with_file_name(format!("{stem}_{from}.partial"))
+}
+
+fn stem_and_extension(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "blocks".to_string());
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "bin".to_string());
+    (stem, ext)
+}
+
+/// Reads back a file produced by [`BlockFileWriter`], yielding one RLP blob per block.
+pub struct BlockFileReader<R: Read = BufReader<File>> {
+    reader: R,
+    header: BlockFileHeader,
+}
+
+impl BlockFileReader {
+    /// Open the file and validate its header.
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let file = File::open(path).map_err(|err| SandboxError::io(path, err))?;
+        Self::from_reader(BufReader::new(file))
+    }
+}
+
+impl<R: Read> BlockFileReader<R> {
+    /// Read and validate the header from the start of `reader`.
+    pub fn from_reader(mut reader: R) -> eyre::Result<Self> {
+        let header = BlockFileHeader::read_from(&mut reader)?;
+        Ok(Self { reader, header })
+    }
+
+    /// Header written at the start of the file.
+    pub fn header(&self) -> &BlockFileHeader {
+        &self.header
+    }
+
+    /// Read the next length-prefixed RLP blob, or `None` at a clean end of
+    /// file. A truncated record is an error.
+    pub fn next_block(&mut self) -> eyre::Result<Option<Vec<u8>>> {
+        format::read_record(&mut self.reader)
+    }
+}
+
+impl<R: Read> Iterator for BlockFileReader<R> {
+    type Item = eyre::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+/// Reads a block file, or the segments a rotating writer split it into, as
+/// one stream of blocks.
+pub struct MultiFileReader {
+    segments: Vec<PathBuf>,
+    /// Header spanning every segment.
+    header: BlockFileHeader,
+    current: Option<BlockFileReader>,
+    next_segment: usize,
+}
+
+impl MultiFileReader {
+    /// Open `path` if it is a file; otherwise collect the
+    /// `{stem}_{from}_{to}.{ext}` segments next to it. Fails if a segment's
+    /// header disagrees with its name or the segments leave a gap.
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        if path.is_file() {
+            let reader = BlockFileReader::open(path)?;
+            let header = reader.header;
+            return Ok(Self {
+                segments: vec![path.to_path_buf()],
+                header,
+                current: None,
+                next_segment: 0,
+            });
+        }
+
+        let segments = find_segments(path)?;
+        let mut header: Option<BlockFileHeader> = None;
+        for (from, to, segment) in &segments {
+            let segment_header = BlockFileReader::open(segment)?.header;
+            eyre::ensure!(
+                segment_header.from_block == *from && segment_header.to_block == *to,
+                "{} declares blocks {}..={} in its header",
+                segment.display(),
+                segment_header.from_block,
+                segment_header.to_block
+            );
+            header = Some(match header {
+                None => segment_header,
+                Some(mut spanned) => {
+                    eyre::ensure!(
+                        *from == spanned.to_block + 1,
+                        "block segments are not contiguous: blocks {}..={} are missing before {}",
+                        spanned.to_block + 1,
+                        from.saturating_sub(1),
+                        segment.display()
+                    );
+                    eyre::ensure!(
+                        segment_header.block_type == spanned.block_type,
+                        "{} holds {:?} blocks, earlier segments hold {:?} blocks",
+                        segment.display(),
+                        segment_header.block_type,
+                        spanned.block_type
+                    );
+                    spanned.to_block = *to;
+                    spanned
+                }
+            });
+        }
+
+        Ok(Self {
+            segments: segments
+                .into_iter()
+                .map(|(_, _, segment)| segment)
+                .collect(),
+            header: header.expect("find_segments returns at least one segment"),
+            current: None,
+            next_segment: 0,
+        })
+    }
+
+    /// Header covering every segment: the first segment's, with the last
+    /// segment's final block.
+    pub fn header(&self) -> &BlockFileHeader {
+        &self.header
+    }
+
+    /// Files read, in block order.
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Read the next block, moving on to the next segment at the end of each
+    /// one, or `None` after the last.
+    pub fn next_block(&mut self) -> eyre::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some(reader) = self.current.as_mut()
+                && let Some(rlp_data) = reader.next_block()?
+            {
+                return Ok(Some(rlp_data));
+            }
+            let Some(segment) = self.segments.get(self.next_segment) else {
+                return Ok(None);
+            };
+            self.current = Some(BlockFileReader::open(segment)?);
+            self.next_segment += 1;
+        }
+    }
+}
+
+impl Iterator for MultiFileReader {
+    type Item = eyre::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+/// Segments named after `path`, as `(from, to, path)` sorted by first block.
+fn find_segments(path: &Path) -> eyre::Result<Vec<(u64, u64, PathBuf)>> {
+    let (stem, ext) = stem_and_extension(path);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let entries = fs::read_dir(dir).map_err(|err| SandboxError::io(dir, err))?;
+
+    let mut segments = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(range) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&format!("{stem}_")))
+            .and_then(|name| name.strip_suffix(&format!(".{ext}")))
+        else {
+            continue;
+        };
+        let Some((from, to)) = range.split_once('_') else {
+            continue;
+        };
+        if let (Ok(from), Ok(to)) = (from.parse::<u64>(), to.parse::<u64>()) {
+            segments.push((from, to, entry.path()));
+        }
+    }
+    eyre::ensure!(
+        !segments.is_empty(),
+        "no block file at {} and no {stem}_<from>_<to>.{ext} segments next to it",
+        path.display()
+    );
+    segments.sort_unstable_by_key(|(from, _, _)| *from);
+    Ok(segments)
+}
@@ -0,0 +1,102 @@
+//! One JSON object per built block, for reading runs with `jq` instead of
+//! decoding RLP. Shared by the builder's JSONL output and `sandbox inspect
+//! --json`.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alloy_consensus::{Transaction, TxReceipt};
+use flate2::{Compression, write::GzEncoder};
+use reth_ethereum_primitives::{Block, Receipt};
+use reth_primitives_traits::RecoveredBlock;
+use serde_json::{Value, json};
+
+use crate::error::SandboxError;
+
+/// Header fields (as alloy serializes them), the block hash, and one entry
+/// per transaction. `receipts`, when known, add each transaction's status,
+/// gas used, and log count.
+pub fn block_to_json(
+    block: &RecoveredBlock<Block>,
+    receipts: Option<&[Receipt]>,
+) -> eyre::Result<Value> {
+    let mut previous_cumulative_gas = 0;
+    let transactions = block
+        .transactions_with_sender()
+        .enumerate()
+        .map(|(index, (from, tx))| {
+            let mut entry = json!({
+                "hash": tx.tx_hash(),
+                "type": tx.ty(),
+                "from": from,
+                "to": tx.to(),
+                "value": tx.value(),
+                "nonce": tx.nonce(),
+                "gas_limit": tx.gas_limit(),
+            });
+            if let Some(receipt) = receipts.and_then(|receipts| receipts.get(index)) {
+                entry["status"] = json!(receipt.status());
+                entry["gas_used"] = json!(receipt.cumulative_gas_used - previous_cumulative_gas);
+                entry["logs"] = json!(receipt.logs.len());
+                previous_cumulative_gas = receipt.cumulative_gas_used;
+            }
+            entry
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "hash": block.hash(),
+        "header": serde_json::to_value(block.header())?,
+        "transactions": transactions,
+    }))
+}
+
+/// Appends one [`block_to_json`] line per built block, gzipped when the path
+/// ends in `.gz`.
+pub struct BlockJsonWriter {
+    sink: JsonSink,
+}
+
+enum JsonSink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl BlockJsonWriter {
+    /// Create the file at `path`.
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let file = BufWriter::new(File::create(path).map_err(|err| SandboxError::io(path, err))?);
+        let sink = if path.extension().is_some_and(|ext| ext == "gz") {
+            JsonSink::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            JsonSink::Plain(file)
+        };
+        Ok(Self { sink })
+    }
+
+    /// Append the line for `block`.
+    pub fn record(
+        &mut self,
+        block: &RecoveredBlock<Block>,
+        receipts: Option<&[Receipt]>,
+    ) -> eyre::Result<()> {
+        let line = block_to_json(block, receipts)?;
+        match &mut self.sink {
+            JsonSink::Plain(writer) => writeln!(writer, "{line}")?,
+            JsonSink::Gzip(writer) => writeln!(writer, "{line}")?,
+        }
+        Ok(())
+    }
+
+    /// Flush buffered lines and, for gzip, write the stream trailer.
+    pub fn finish(self) -> eyre::Result<()> {
+        match self.sink {
+            JsonSink::Plain(mut writer) => writer.flush()?,
+            JsonSink::Gzip(encoder) => encoder.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+//! Gas actually used per transaction label against the gas limit it was sent
+//! with, so a later run can size its gas limits from what an earlier one
+//! measured; and the tip each fee strategy actually earned the block.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use alloy_primitives::{Address, map::HashMap};
+use serde_json::{Value, json};
+
+use crate::{
+    actor::ActorPool,
+    config::{AmountRange, FeeStrategy},
+    error::SandboxError,
+    labels::TxLabel,
+};
+
+/// Headroom added on top of the most gas a label was seen to use when it
+/// becomes that label's gas limit. Refunds and the 63/64 rule mean a
+/// transaction needs more gas during execution than it ends up using.
+const CALIBRATED_HEADROOM_PERCENT: u64 = 25;
+
+/// Gas used by every included transaction, per label.
+#[derive(Debug, Clone, Default)]
+pub struct GasCalibration(BTreeMap<TxLabel, LabelGas>);
+
+/// Samples for one label.
+#[derive(Debug, Clone, Default)]
+struct LabelGas {
+    gas_used: Vec<u64>,
+    gas_limit: u64,
+}
+
+impl LabelGas {
+    fn avg_gas_used(&self) -> u64 {
+        let total: u64 = self.gas_used.iter().sum();
+        total
+            .checked_div(self.gas_used.len() as u64)
+            .unwrap_or_default()
+    }
+
+    fn avg_gas_limit(&self) -> u64 {
+        self.gas_limit
+            .checked_div(self.gas_used.len() as u64)
+            .unwrap_or_default()
+    }
+
+    /// Nearest-rank percentile of the sorted samples.
+    fn percentile(sorted: &[u64], percent: usize) -> u64 {
+        if sorted.is_empty() {
+            return 0;
+        }
+        let rank = (sorted.len() * percent).div_ceil(100).max(1);
+        sorted[rank - 1]
+    }
+
+    /// Share of the assigned gas limit the label actually used.
+    fn utilization_percent(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0;
+        }
+        self.gas_used.iter().sum::<u64>() as f64 * 100.0 / self.gas_limit as f64
+    }
+}
+
+impl GasCalibration {
+    /// Account for an included transaction sent with `gas_limit`.
+    pub fn record(&mut self, label: TxLabel, gas_used: u64, gas_limit: u64) {
+        let gas = self.0.entry(label).or_default();
+        gas.gas_used.push(gas_used);
+        gas.gas_limit += gas_limit;
+    }
+
+    /// One row per label: count, average, p95, and max gas used, average gas
+    /// limit, and utilization.
+    fn rows(&self) -> Vec<CalibrationRow> {
+        self.0
+            .iter()
+            .map(|(label, gas)| {
+                let mut sorted = gas.gas_used.clone();
+                sorted.sort_unstable();
+                CalibrationRow {
+                    label: label.name(),
+                    txs: sorted.len() as u64,
+                    avg_gas_used: gas.avg_gas_used(),
+                    p95_gas_used: LabelGas::percentile(&sorted, 95),
+                    max_gas_used: sorted.last().copied().unwrap_or_default(),
+                    avg_gas_limit: gas.avg_gas_limit(),
+                    utilization_percent: gas.utilization_percent(),
+                }
+            })
+            .collect()
+    }
+
+    /// Render the table for the run manifest, keyed by label. This is what
+    /// [`load_gas_limits`] reads back.
+    pub fn to_json(&self) -> Value {
+        self.rows()
+            .into_iter()
+            .map(|row| {
+                (
+                    row.label.to_string(),
+                    json!({
+                        "txs": row.txs,
+                        "avg_gas_used": row.avg_gas_used,
+                        "p95_gas_used": row.p95_gas_used,
+                        "max_gas_used": row.max_gas_used,
+                        "avg_gas_limit": row.avg_gas_limit,
+                        "utilization_percent": row.utilization_percent,
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Print a label / txs / avg gas / p95 gas / avg limit / utilization table.
+    pub fn print(&self) {
+        let rows = self.rows();
+        if rows.is_empty() {
+            return;
+        }
+
+        let name_w = rows
+            .iter()
+            .map(|row| row.label.len())
+            .max()
+            .unwrap_or_default()
+            .max("Label".len());
+
+        println!("\nGas calibration:");
+        println!("{:-<1$}", "", name_w + 74);
+        println!(
+            "{:<name_w$}  {:>12}  {:>12}  {:>12}  {:>14}  {:>12}",
+            "Label", "Txs", "Avg gas", "P95 gas", "Avg limit", "Utilization"
+        );
+        println!("{:-<1$}", "", name_w + 74);
+        for row in &rows {
+            println!(
+                "{:<name_w$}  {:>12}  {:>12}  {:>12}  {:>14}  {:>11.1}%",
+                row.label,
+                row.txs,
+                row.avg_gas_used,
+                row.p95_gas_used,
+                row.avg_gas_limit,
+                row.utilization_percent
+            );
+        }
+        println!("{:-<1$}", "", name_w + 74);
+    }
+}
+
+/// One label's line of the calibration table.
+struct CalibrationRow {
+    label: &'static str,
+    txs: u64,
+    avg_gas_used: u64,
+    p95_gas_used: u64,
+    max_gas_used: u64,
+    avg_gas_limit: u64,
+    utilization_percent: f64,
+}
+
+/// Included transactions and their summed effective tips per gas, by sender.
+/// The builder does not know fee strategies, so they are joined in by
+/// [`TipCalibration::new`] once the actor pool is back.
+#[derive(Debug, Clone, Default)]
+pub struct SenderTips(HashMap<Address, StrategyTips>);
+
+impl SenderTips {
+    /// Account for an included transaction from `sender` paying `tip` per gas.
+    pub fn record(&mut self, sender: Address, tip: u128) {
+        let tips = self.0.entry(sender).or_default();
+        tips.txs += 1;
+        tips.tip_sum += tip;
+    }
+}
+
+/// Transactions and summed tips per gas.
+#[derive(Debug, Clone, Copy, Default)]
+struct StrategyTips {
+    txs: u64,
+    tip_sum: u128,
+}
+
+/// Average tip per gas actors of each fee strategy achieved, against what
+/// the strategy offers, so a run can confirm the strategies reached the
+/// chain. The achieved tip only falls short of the offer when the base fee
+/// eats into the max fee.
+#[derive(Debug, Clone)]
+pub struct TipCalibration {
+    strategies: BTreeMap<FeeStrategy, StrategyTips>,
+    range: AmountRange,
+}
+
+impl TipCalibration {
+    /// Group `tips` by the fee strategy of each sender in `actors`, for
+    /// strategies priced within `range`. The deployer's transactions are
+    /// left out.
+    pub fn new(tips: &SenderTips, actors: &ActorPool, range: AmountRange) -> Self {
+        let mut strategies = BTreeMap::<FeeStrategy, StrategyTips>::new();
+        for (sender, sender_tips) in &tips.0 {
+            let Some(strategy) = actors
+                .actor_index(sender)
+                .and_then(|index| actors.fee_strategy(index))
+            else {
+                continue;
+            };
+            let totals = strategies.entry(strategy).or_default();
+            totals.txs += sender_tips.txs;
+            totals.tip_sum += sender_tips.tip_sum;
+        }
+        Self { strategies, range }
+    }
+
+    /// One row per strategy: transactions, offered tip, average achieved tip.
+    fn rows(&self) -> impl Iterator<Item = (FeeStrategy, u64, u128, u128)> + '_ {
+        self.strategies.iter().map(|(strategy, tips)| {
+            (
+                *strategy,
+                tips.txs,
+                strategy.priority_fee(self.range),
+                tips.tip_sum
+                    .checked_div(tips.txs as u128)
+                    .unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Render the table for the run manifest, keyed by strategy name.
+    pub fn to_json(&self) -> Value {
+        self.rows()
+            .map(|(strategy, txs, offered, avg_tip)| {
+                (
+                    strategy.name().to_string(),
+                    json!({
+                        "txs": txs,
+                        "offered_tip": offered.to_string(),
+                        "avg_tip": avg_tip.to_string(),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Print a strategy / txs / offered tip / average tip table.
+    pub fn print(&self) {
+        if self.strategies.is_empty() {
+            return;
+        }
+        println!("\nTip calibration (wei per gas):");
+        println!("{:-<1$}", "", 58);
+        println!(
+            "{:<12}  {:>12}  {:>14}  {:>14}",
+            "Strategy", "Txs", "Offered tip", "Avg tip"
+        );
+        println!("{:-<1$}", "", 58);
+        for (strategy, txs, offered, avg_tip) in self.rows() {
+            println!(
+                "{:<12}  {:>12}  {:>14}  {:>14}",
+                strategy.name(),
+                txs,
+                offered,
+                avg_tip
+            );
+        }
+        println!("{:-<1$}", "", 58);
+    }
+}
+
+/// Per-label gas limits from the `gas_calibration` table of an earlier run's
+/// manifest: the most gas each label used there, plus
+/// [`CALIBRATED_HEADROOM_PERCENT`]. The limits only fit a run with the same
+/// workload shape, e.g. the same `multicall_calls_per_tx`.
+pub fn load_gas_limits(manifest: &Path) -> eyre::Result<BTreeMap<String, u64>> {
+    let contents = fs::read_to_string(manifest).map_err(|err| SandboxError::io(manifest, err))?;
+    let manifest_json: Value = serde_json::from_str(&contents)?;
+    let table = manifest_json["gas_calibration"]
+        .as_object()
+        .ok_or_else(|| {
+            SandboxError::Config(format!(
+                "{} has no `gas_calibration` table",
+                manifest.display()
+            ))
+        })?;
+
+    table
+        .iter()
+        .map(|(label, row)| {
+            let max_gas_used = row["max_gas_used"].as_u64().ok_or_else(|| {
+                SandboxError::Config(format!(
+                    "{}: `{label}` has no `max_gas_used`",
+                    manifest.display()
+                ))
+            })?;
+            let limit = max_gas_used + max_gas_used * CALIBRATED_HEADROOM_PERCENT / 100;
+            Ok((label.clone(), limit))
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+//! Call trees of failed transactions, for `trace_failed_txs`. A sealed block
+//! is replayed from its parent's state up to its last failed transaction,
+//! with a call tracer attached to each failed one, so the record shows which
+//! internal call reverted rather than only the top-level output.
+
+use std::path::Path;
+
+use alloy_consensus::Transaction;
+use alloy_primitives::hex;
+use alloy_rpc_types_trace::geth::{CallConfig, CallFrame};
+use reth_ethereum_primitives::Block;
+use reth_evm::{ConfigureEvm, Evm, NextBlockEnvAttributes, execute::BlockBuilder};
+use reth_node_ethereum::EthEvmConfig;
+use reth_primitives_traits::{RecoveredBlock, SealedHeader};
+use reth_provider::StateProvider;
+use reth_revm::{State, database::StateProviderDatabase};
+use revm_inspectors::tracing::{TracingInspector, TracingInspectorConfig};
+use serde_json::{Value, json};
+
+use crate::{debug, revert};
+
+/// Re-execute `block` on `state_provider`, the state of its parent with
+/// `parent_header`, and write a record with the call tree of each
+/// transaction at an index in `failed` (ascending) to
+/// `<dir>/<block>_<index>.json`. Transactions before the last failed one are
+/// replayed so each runs on the state it saw in the block.
+pub fn trace_failed(
+    evm_config: &EthEvmConfig,
+    state_provider: &dyn StateProvider,
+    parent_header: &SealedHeader,
+    attributes: NextBlockEnvAttributes,
+    block: &RecoveredBlock<Block>,
+    failed: &[usize],
+    dir: &Path,
+) -> eyre::Result<()> {
+    let Some(&last) = failed.last() else {
+        return Ok(());
+    };
+    let number = parent_header.number + 1;
+    let mut state_db = State::builder()
+        .with_database(StateProviderDatabase::new(state_provider))
+        .with_bundle_update()
+        .build();
+    let evm_env = evm_config.next_evm_env(parent_header, &attributes)?;
+    evm_config
+        .builder_for_next_block(&mut state_db, parent_header, attributes)?
+        .apply_pre_execution_changes()?;
+
+    let config = CallConfig::default();
+    for (index, tx) in block.transactions_recovered().enumerate().take(last + 1) {
+        if failed.binary_search(&index).is_err() {
+            evm_config
+                .evm_with_env(&mut state_db, evm_env.clone())
+                .transact_commit(tx)
+                .map_err(|err| {
+                    eyre::eyre!("failed to replay transaction {index} of block {number}: {err}")
+                })?;
+            continue;
+        }
+
+        let tx = tx.cloned();
+        let mut inspector =
+            TracingInspector::new(TracingInspectorConfig::from_geth_call_config(&config))
+                .with_transaction_gas_limit(tx.gas_limit());
+        let result = evm_config
+            .evm_with_env_and_inspector(&mut state_db, evm_env.clone(), &mut inspector)
+            .transact_commit(tx.as_recovered_ref())
+            .map_err(|err| {
+                eyre::eyre!("failed to trace transaction {index} of block {number}: {err}")
+            })?;
+        let frame = inspector
+            .into_geth_builder()
+            .geth_call_traces(config.clone(), result.gas_used());
+        debug::write_failed_transaction(
+            dir,
+            number,
+            index as u64,
+            &tx,
+            &result,
+            call_tree(&frame),
+        )?;
+    }
+    Ok(())
+}
+
+/// Render `frame` and every call under it: target, selector, gas, and the
+/// decoded reason of any call that reverted.
+fn call_tree(frame: &CallFrame) -> Value {
+    let input = &frame.input;
+    let revert_reason = frame
+        .error
+        .as_ref()
+        .and(frame.output.as_ref())
+        .map(|output| revert::revert_reason(output));
+    json!({
+        "type": frame.typ,
+        "from": frame.from.to_string(),
+        "to": frame.to.map(|to| to.to_string()),
+        "value": frame.value.map(|value| value.to_string()),
+        "selector": (input.len() >= 4).then(|| hex::encode_prefixed(&input[..4])),
+        "gas": frame.gas.saturating_to::<u64>(),
+        "gas_used": frame.gas_used.saturating_to::<u64>(),
+        "error": frame.error,
+        "revert_reason": revert_reason,
+        "calls": frame.calls.iter().map(call_tree).collect::<Vec<_>>(),
+    })
+}
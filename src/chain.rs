@@ -1,69 +1,230 @@
 //! Generates a custom genesis file tuned for high-gas sandbox simulations.
 
-use alloy_genesis::Genesis;
-use alloy_primitives::{Address, U256};
+use alloy_eips::{eip2935, eip4788, eip7002, eip7251};
+use alloy_genesis::{ChainConfig, EthashConfig, Genesis, GenesisAccount};
+use alloy_primitives::{Address, B256, Bytes, U256};
 use reth_chainspec::ChainSpec;
-use std::{fs, path::PathBuf, sync::Arc};
-use tracing::{info, warn};
-
-/// Build a bespoke `ChainSpec` and write the corresponding JSON next to the
-/// binary for easy reuse with `reth`.
-pub fn custom_chain(gas_limit: u64, chain_id: u64, genesis_address: Address) -> Arc<ChainSpec> {
-    let balance = U256::MAX;
-
-    // Construct genesis JSON
-    let custom_genesis = format!(
-        r#"{{
-    "nonce": "0x42",
-    "timestamp": "0x0",
-    "extraData": "0x5343",
-    "gasLimit": "0x{:x}",
-    "difficulty": "0x400000000",
-    "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
-    "coinbase": "0x0000000000000000000000000000000000000000",
-    "alloc": {{
-        "{:x}": {{
-            "balance": "0x{:x}"
-        }}
-    }},
-    "number": "0x0",
-    "gasUsed": "0x0",
-    "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
-    "config": {{
-        "ethash": {{}},
-        "chainId": {},
-        "homesteadBlock": 0,
-        "eip150Block": 0,
-        "eip155Block": 0,
-        "eip158Block": 0,
-        "byzantiumBlock": 0,
-        "constantinopleBlock": 0,
-        "petersburgBlock": 0,
-        "istanbulBlock": 0,
-        "berlinBlock": 0,
-        "londonBlock": 0,
-        "terminalTotalDifficulty": 0,
-        "terminalTotalDifficultyPassed": true,
-        "shanghaiTime": 0
-    }}
-}}"#,
-        gas_limit, genesis_address, balance, chain_id
+use std::{fs, path::Path, sync::Arc};
+use tracing::info;
+
+use crate::config::{Hardfork, SimulationConfig};
+use crate::logging;
+
+/// Build a bespoke `ChainSpec`, optionally writing the genesis JSON to
+/// `genesis_out` for reuse with `reth`.
+pub fn custom_chain(
+    gas_limit: u64,
+    chain_id: u64,
+    alloc: &[(Address, U256)],
+    hardfork: Hardfork,
+    genesis_timestamp: u64,
+    genesis_out: Option<&Path>,
+) -> eyre::Result<Arc<ChainSpec>> {
+    let genesis = sandbox_genesis(gas_limit, chain_id, alloc, hardfork, genesis_timestamp);
+
+    if let Some(path) = genesis_out {
+        fs::write(path, serde_json::to_string_pretty(&genesis)?)
+            .map_err(|err| eyre::eyre!("failed to write genesis file {}: {err}", path.display()))?;
+        info!(target: logging::CHAIN, path = %path.display(), "wrote genesis file");
+    }
+
+    Ok(Arc::new(genesis.into()))
+}
+
+/// Load a user-supplied genesis file instead of generating one.
+///
+/// The file's chain id must match `config`, and the address of
+/// `genesis_signer` must hold a balance in the alloc since it funds every
+/// setup transaction, directly or through a separate deployer. Every fork the
+/// file schedules must be active at its genesis timestamp. The gas limit,
+/// latest hardfork active at genesis, and the deployer's and faucet's nonces
+/// are adopted into `config`.
+pub fn chain_from_file(path: &Path, config: &mut SimulationConfig) -> eyre::Result<Arc<ChainSpec>> {
+    let genesis: Genesis = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|err| eyre::eyre!("failed to parse genesis file {}: {err}", path.display()))?;
+
+    eyre::ensure!(
+        !config.prefund_actors_in_genesis,
+        "prefund_actors_in_genesis cannot be used with a genesis file"
     );
 
-    // ✅ Write to genesis.json in current directory
-    let output_path = std::env::current_dir()
-        .unwrap_or_else(|_| PathBuf::from("."))
-        .join("sandbox_genesis.json");
+    // Transactions are signed for the configured chain id, so it has to match.
+    eyre::ensure!(
+        genesis.config.chain_id == config.chain_id,
+        "genesis file {} has chain id {}, but the sandbox is configured for {}",
+        path.display(),
+        genesis.config.chain_id,
+        config.chain_id
+    );
+
+    let holder = config.genesis_address();
+    eyre::ensure!(
+        genesis
+            .alloc
+            .get(&holder)
+            .is_some_and(|account| !account.balance.is_zero()),
+        "genesis file {} does not fund the genesis signer {holder}",
+        path.display()
+    );
+    // A state exported after a run has the deployer's nonce past zero.
+    let nonce = |address| {
+        genesis
+            .alloc
+            .get(&address)
+            .and_then(|account| account.nonce)
+            .unwrap_or_default()
+    };
+    config.deployer_nonce = nonce(config.deployer_address());
+    config.faucet_nonce = nonce(holder);
+
+    // Blocks are filled relative to the file's gas limit.
+    config.gas_limit = genesis.gas_limit;
 
-    if let Err(e) = fs::write(&output_path, &custom_genesis) {
-        warn!("⚠️ Failed to write genesis file: {}", e);
+    // Blocks are built under one fork for the whole run, so a fork the file
+    // schedules for later would switch the rules mid-chain without the sandbox
+    // noticing. Only forks already active at genesis are accepted.
+    let active_at_genesis = |fork: &str, time: Option<u64>| match time {
+        None => Ok(false),
+        Some(time) if time <= genesis.timestamp => Ok(true),
+        Some(time) => Err(eyre::eyre!(
+            "genesis file {} activates {fork} at {time}, after its genesis timestamp {}; \
+             the sandbox only runs forks active from genesis",
+            path.display(),
+            genesis.timestamp
+        )),
+    };
+    eyre::ensure!(
+        active_at_genesis("Shanghai", genesis.config.shanghai_time)?,
+        "genesis file {} does not activate Shanghai at genesis",
+        path.display()
+    );
+    let cancun = active_at_genesis("Cancun", genesis.config.cancun_time)?;
+    let prague = active_at_genesis("Prague", genesis.config.prague_time)?;
+    config.hardfork = if prague {
+        Hardfork::Prague
+    } else if cancun {
+        Hardfork::Cancun
     } else {
-        info!("✅ Wrote genesis file to {:?}", output_path);
+        Hardfork::Shanghai
+    };
+
+    info!(
+        target: logging::CHAIN,
+        path = %path.display(),
+        chain_id = config.chain_id,
+        hardfork = %config.hardfork,
+        "loaded genesis file"
+    );
+    Ok(Arc::new(genesis.into()))
+}
+
+/// Typed genesis for the sandbox chain: every fork up to `hardfork` active at
+/// genesis (stamped `timestamp`) and each `alloc` entry funded with its balance. Cancun and Prague
+/// also predeploy the system contracts their pre/post-block calls target.
+pub fn sandbox_genesis(
+    gas_limit: u64,
+    chain_id: u64,
+    alloc: &[(Address, U256)],
+    hardfork: Hardfork,
+    timestamp: u64,
+) -> Genesis {
+    let config = ChainConfig {
+        chain_id,
+        homestead_block: Some(0),
+        eip150_block: Some(0),
+        eip155_block: Some(0),
+        eip158_block: Some(0),
+        byzantium_block: Some(0),
+        constantinople_block: Some(0),
+        petersburg_block: Some(0),
+        istanbul_block: Some(0),
+        berlin_block: Some(0),
+        london_block: Some(0),
+        terminal_total_difficulty: Some(U256::ZERO),
+        terminal_total_difficulty_passed: true,
+        shanghai_time: Some(0),
+        cancun_time: hardfork.is_cancun_active().then_some(0),
+        prague_time: hardfork.is_prague_active().then_some(0),
+        ethash: Some(EthashConfig {}),
+        ..Default::default()
+    };
+
+    let mut genesis = Genesis::default()
+        .with_nonce(0x42)
+        .with_timestamp(timestamp)
+        .with_extra_data(Bytes::from_static(b"SC"))
+        .with_gas_limit(gas_limit)
+        .with_difficulty(U256::from(0x400000000u64))
+        .with_mix_hash(B256::ZERO)
+        .with_coinbase(Address::ZERO)
+        .extend_accounts(alloc.iter().map(|(address, balance)| {
+            (*address, GenesisAccount::default().with_balance(*balance))
+        }));
+    genesis.config = config;
+    genesis.number = Some(0);
+
+    if hardfork.is_cancun_active() {
+        genesis = genesis.extend_accounts([system_contract(
+            eip4788::BEACON_ROOTS_ADDRESS,
+            eip4788::BEACON_ROOTS_CODE.clone(),
+        )]);
     }
 
-    // Parse JSON into Genesis → ChainSpec
-    let genesis: Genesis =
-        serde_json::from_str(&custom_genesis).expect("Failed to parse custom genesis JSON");
+    if hardfork.is_prague_active() {
+        genesis = genesis.extend_accounts([
+            system_contract(
+                eip2935::HISTORY_STORAGE_ADDRESS,
+                eip2935::HISTORY_STORAGE_CODE.clone(),
+            ),
+            system_contract(
+                eip7002::WITHDRAWAL_REQUEST_PREDEPLOY_ADDRESS,
+                eip7002::WITHDRAWAL_REQUEST_PREDEPLOY_CODE.clone(),
+            ),
+            system_contract(
+                eip7251::CONSOLIDATION_REQUEST_PREDEPLOY_ADDRESS,
+                eip7251::CONSOLIDATION_REQUEST_PREDEPLOY_CODE.clone(),
+            ),
+        ]);
+    }
+
+    genesis
+}
+
+/// Alloc entry for a system contract predeploy (nonce 1, as on mainnet).
+fn system_contract(address: Address, code: Bytes) -> (Address, GenesisAccount) {
+    (
+        address,
+        GenesisAccount::default()
+            .with_nonce(Some(1))
+            .with_code(Some(code)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, b256};
+
+    use super::*;
 
-    Arc::new(genesis.into())
+    /// Address of the default genesis key.
+    const GENESIS_HOLDER: Address = address!("0xFaa235fA90514d9083d0aa61878eBEb5Cf94FCD7");
+
+    /// Genesis hash the original string-built genesis produced for the
+    /// defaults it shipped with: chain 2600, a 1G gas limit, Shanghai, and
+    /// `U256::MAX` held by the genesis key.
+    const BASELINE_GENESIS_HASH: B256 =
+        b256!("0x21bfa52962ca9c473e9d364c886967bad3bc439ad6c1271df31792dc433f0e3f");
+
+    #[test]
+    fn default_genesis_hash_is_unchanged() {
+        let genesis = sandbox_genesis(
+            1_000_000_000,
+            2600,
+            &[(GENESIS_HOLDER, U256::MAX)],
+            Hardfork::Shanghai,
+            0,
+        );
+        let chain: ChainSpec = genesis.into();
+        assert_eq!(chain.genesis_hash(), BASELINE_GENESIS_HASH);
+    }
 }
@@ -0,0 +1,69 @@
+//! `sandbox export-state`: dump the plain state of a datadir as a genesis alloc.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use alloy_genesis::GenesisAccount;
+use alloy_primitives::{Address, B256, KECCAK256_EMPTY};
+use reth_db::{
+    Database,
+    cursor::{DbCursorRO, DbDupCursorRO},
+    tables,
+    transaction::DbTx,
+};
+use tracing::info;
+
+use crate::logging;
+
+/// Walk the plain account, storage, and bytecode tables of `datadir` and
+/// write them to `out` in genesis `alloc` format.
+pub fn run(datadir: &Path, out: &Path) -> eyre::Result<()> {
+    let db_args = reth_node_core::args::DatabaseArgs::default().database_args();
+    let db = reth_db::open_db_read_only(&datadir.join("db"), db_args)?;
+
+    let alloc = db.view(|tx| -> eyre::Result<BTreeMap<Address, GenesisAccount>> {
+        let mut alloc = BTreeMap::new();
+
+        let mut accounts = tx.cursor_read::<tables::PlainAccountState>()?;
+        for entry in accounts.walk(None)? {
+            let (address, account) = entry?;
+            let code = match account.bytecode_hash {
+                Some(hash) if hash != KECCAK256_EMPTY => tx
+                    .get::<tables::Bytecodes>(hash)?
+                    .map(|bytecode| bytecode.original_bytes()),
+                _ => None,
+            };
+            alloc.insert(
+                address,
+                GenesisAccount::default()
+                    .with_balance(account.balance)
+                    .with_nonce(Some(account.nonce))
+                    .with_code(code),
+            );
+        }
+
+        let mut storage = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+        for entry in storage.walk(None)? {
+            let (address, slot) = entry?;
+            if slot.value.is_zero() {
+                continue;
+            }
+            alloc
+                .entry(address)
+                .or_default()
+                .storage
+                .get_or_insert_with(BTreeMap::new)
+                .insert(slot.key, B256::from(slot.value));
+        }
+
+        Ok(alloc)
+    })??;
+
+    fs::write(out, serde_json::to_string_pretty(&alloc)?)?;
+    info!(
+        target: logging::CLI,
+        accounts = alloc.len(),
+        path = %out.display(),
+        "wrote state alloc"
+    );
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! `sandbox inspect`: summarize a block file without touching a database.
+
+use std::path::Path;
+
+use alloy_rlp::Decodable;
+use reth_ethereum_primitives::Block;
+use reth_primitives_traits::Block as _;
+
+use crate::{block_file::MultiFileReader, block_json};
+
+/// Print one JSON line per block, as `--output-format jsonl` would have
+/// written it minus receipt fields.
+pub fn print_json(path: &Path) -> eyre::Result<()> {
+    for (index, rlp_data) in MultiFileReader::open(path)?.enumerate() {
+        let block = Block::decode(&mut rlp_data?.as_slice())
+            .map_err(|err| eyre::eyre!("failed to decode block #{index} in file: {err}"))?;
+        let number = block.header.number;
+        let block = block
+            .try_into_recovered()
+            .map_err(|err| eyre::eyre!("block {number}: failed to recover signers: {err}"))?;
+        println!("{}", block_json::block_to_json(&block, None)?);
+    }
+    Ok(())
+}
+
+/// Print the file header followed by one row per block and the totals.
+pub fn run(path: &Path) -> eyre::Result<()> {
+    let mut reader = MultiFileReader::open(path)?;
+    let header = reader.header();
+
+    println!("File:       {}", path.display());
+    if reader.segments().len() > 1 {
+        println!("Segments:   {}", reader.segments().len());
+    }
+    println!("Version:    {}", header.version());
+    println!("Block type: {:?}", header.block_type());
+    println!(
+        "Range:      {}..={} (as declared in the header)",
+        header.from_block(),
+        header.to_block()
+    );
+    println!();
+    println!(
+        "{:>10} {:>10} {:>16} {:>16} {:>7}",
+        "Block", "Txs", "Gas used", "Gas limit", "Fill"
+    );
+
+    let mut blocks = 0u64;
+    let mut txs = 0u64;
+    let mut gas_used = 0u64;
+    while let Some(rlp_data) = reader.next_block()? {
+        let block = Block::decode(&mut rlp_data.as_slice())
+            .map_err(|err| eyre::eyre!("failed to decode block #{blocks} in file: {err}"))?;
+        let block_txs = block.body.transactions.len() as u64;
+
+        println!(
+            "{:>10} {:>10} {:>16} {:>16} {:>6.1}%",
+            block.header.number,
+            block_txs,
+            block.header.gas_used,
+            block.header.gas_limit,
+            block.header.gas_used as f64 * 100.0 / block.header.gas_limit.max(1) as f64
+        );
+
+        blocks += 1;
+        txs += block_txs;
+        gas_used += block.header.gas_used;
+    }
+
+    println!();
+    println!("Blocks:   {blocks}");
+    println!("Txs:      {txs}");
+    println!("Gas used: {gas_used}");
+    if blocks > 0 {
+        println!("Avg txs/block: {:.1}", txs as f64 / blocks as f64);
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Command-line entry points. Each subcommand lives in its own module; setup
+//! shared between them (logging) lives here.
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    config::Stage,
+    logging::{self, LogFormat},
+};
+
+mod export_state;
+mod inspect;
+mod replay_txs;
+mod run;
+mod verify;
+
+/// Generate, inspect, and verify synthetic Reth load.
+#[derive(Debug, Parser)]
+#[command(name = "sandbox", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// How log lines are rendered.
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Full)]
+    log_format: LogFormat,
+    /// Which events to log, as an `EnvFilter` directive; `RUST_LOG` is used
+    /// when unset. For example, `info,sandbox::tx_failures=off` keeps block
+    /// summaries but drops per-transaction failures. Targets are listed in
+    /// the `logging` module.
+    #[arg(long, global = true, value_name = "DIRECTIVE")]
+    log_filter: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the simulation and write `blocks.bin`.
+    Run(run::RunArgs),
+    /// Run only the setup phases (funding, deploys, pools) and keep the
+    /// datadir, genesis, actors, deployments, and block file in `dir`.
+    Setup {
+        /// Directory to write the setup to; must not already hold one.
+        dir: PathBuf,
+        #[command(flatten)]
+        args: run::RunArgs,
+    },
+    /// Run only the load on a copy of a setup written by `sandbox setup`.
+    Load {
+        /// Directory written by `sandbox setup`; left untouched.
+        dir: PathBuf,
+        #[command(flatten)]
+        args: run::RunArgs,
+    },
+    /// Print the header and per-block tx counts and gas of a block file.
+    Inspect {
+        /// Block file produced by `sandbox run`; for a rotated run, the
+        /// `blocks_out` path its segments are named after.
+        file: PathBuf,
+        /// Print each block as a JSON line instead, in the same shape as the
+        /// JSONL output (without receipts).
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-execute a block file on top of its genesis and compare the results.
+    Verify {
+        /// Block file produced by `sandbox run`; for a rotated run, the
+        /// `blocks_out` path its segments are named after.
+        file: PathBuf,
+        /// Genesis JSON the blocks were built on (see `genesis_out`).
+        #[arg(long)]
+        genesis: PathBuf,
+    },
+    /// Submit a transaction file (see `txs_out`) to a node's txpool.
+    ReplayTxs {
+        /// Transaction file produced by `sandbox run`.
+        file: PathBuf,
+        /// JSON-RPC endpoint to send `eth_sendRawTransaction` to.
+        #[arg(long, default_value = "http://127.0.0.1:8545")]
+        rpc_url: String,
+        /// Most transactions sent per second; `0` is unlimited.
+        #[arg(long, default_value_t = 0)]
+        rate: u64,
+    },
+    /// Dump the latest state of a datadir as a genesis `alloc` JSON object.
+    ExportState {
+        /// Datadir kept from `sandbox run` (see `datadir`).
+        datadir: PathBuf,
+        /// Where to write the alloc JSON.
+        #[arg(long, default_value = "alloc.json")]
+        out: PathBuf,
+    },
+}
+
+impl Cli {
+    /// Dispatch to the selected subcommand.
+    pub async fn execute(self) -> eyre::Result<()> {
+        logging::init(self.log_format, self.log_filter.as_deref())?;
+        match self.command {
+            Command::Run(args) => run::run(args, Stage::Full, None).await,
+            Command::Setup { dir, args } => run::run(args, Stage::Setup, Some(dir)).await,
+            Command::Load { dir, args } => run::run(args, Stage::Load, Some(dir)).await,
+            Command::Inspect { file, json } if json => inspect::print_json(&file),
+            Command::Inspect { file, .. } => inspect::run(&file),
+            Command::Verify { file, genesis } => verify::run(&file, &genesis),
+            Command::ReplayTxs {
+                file,
+                rpc_url,
+                rate,
+            } => replay_txs::run(&file, &rpc_url, rate).await,
+            Command::ExportState { datadir, out } => export_state::run(&datadir, &out),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! `sandbox replay-txs`: submit a transaction file to a node over JSON-RPC.
+
+use std::{path::Path, time::Duration};
+
+use alloy_primitives::Bytes;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::logging;
+use crate::tx_writer::{TxFileReader, TxRecord};
+
+/// Send every transaction in `file` to `rpc_url` with `eth_sendRawTransaction`,
+/// in file order, at most `rate` per second (`0` is unlimited). Rejected
+/// transactions are logged and counted rather than stopping the replay.
+pub async fn run(file: &Path, rpc_url: &str, rate: u64) -> eyre::Result<()> {
+    let client = reqwest::Client::new();
+    let mut ticker = (rate > 0).then(|| {
+        let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / rate as f64));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker
+    });
+
+    let mut sent = 0u64;
+    let mut rejected = 0u64;
+    let mut blocks = 0u64;
+    for record in TxFileReader::open(file)? {
+        let raw = match record? {
+            TxRecord::Tx(raw) => raw,
+            TxRecord::BlockEnd => {
+                blocks += 1;
+                continue;
+            }
+        };
+        if let Some(ticker) = ticker.as_mut() {
+            ticker.tick().await;
+        }
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": sent,
+            "method": "eth_sendRawTransaction",
+            "params": [Bytes::from(raw)],
+        });
+        let response: Value = client
+            .post(rpc_url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            rejected += 1;
+            warn!(target: logging::CLI, tx = sent, block = blocks, %error, "transaction rejected");
+        }
+        sent += 1;
+    }
+
+    println!("Sent:     {sent} transactions from {blocks} blocks");
+    println!("Rejected: {rejected}");
+    Ok(())
+}
@@ -0,0 +1,412 @@
+//! `sandbox run`: wire together orchestration and block building for one
+//! simulation.
+
+use alloy_primitives::{Address, B256, U256, utils::Unit};
+use alloy_signer_local::PrivateKeySigner;
+use clap::Args;
+use eyre::WrapErr;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    calibration,
+    config::{
+        AmountRange, BundlerMode, DeployVia, FeeOnTransfer, FeeRecipient, FeeStrategyMix,
+        FillStrategy, GENESIS_PRIVATE_KEY, Hardfork, HotTokens, LimitMode, OutputFormat, Rotation,
+        SenderSelection, SimulationConfig, Stage, TxOrdering, Workload, parse_genesis_key,
+    },
+    error::SandboxError,
+    roots, run_manifest,
+    scenario::Scenario,
+    simulation::{Simulation, SimulationPaths},
+};
+
+const NUM_OF_BLOCKS: Option<u64> = None;
+const NUM_OF_TRANSACTIONS: Option<u64> = Some(1_000_000);
+const GAS_LIMIT: u64 = 1_000_000_000;
+const CHAIN_ID: u64 = 2600;
+const UNIQUE_ACCOUNTS: u64 = 100_000;
+const UNIQUE_TOKENS: u64 = 1000;
+const CHANNEL_BUFFER_SIZE: usize = 1000;
+const STD_BATCH_SIZE: u64 = 1000;
+/// How often to sample the channel depth gauge; `0` disables sampling.
+const CHANNEL_SAMPLE_INTERVAL_MS: u64 = 100;
+/// Resize orchestrator batches based on how full the channel is.
+const ADAPTIVE_BATCH_SIZE: bool = false;
+/// Seconds between progress heartbeat lines; `0` disables them.
+const PROGRESS_INTERVAL_SECS: u64 = 10;
+/// Draw a progress bar on stdout when it is a terminal. Heartbeat lines still
+/// print alongside it, so lengthen the interval above to keep the bar tidy.
+const PROGRESS_BAR: bool = false;
+/// Warn once resident memory reaches this many MiB, so a long run can be
+/// resized before it is OOM-killed.
+const RSS_WARN_MIB: Option<u64> = None;
+/// Time sections for the summary table; `false` makes every timer a no-op.
+const METRICS: bool = true;
+/// Dump a Chrome trace (chrome://tracing / Perfetto) of every timed section here.
+const TRACE_OUT: Option<&str> = None;
+/// Replay every failed transaction under a call tracer and write its call
+/// tree to `failed_traces/`. Slow: each block with a failure is re-executed.
+const TRACE_FAILED_TXS: bool = false;
+/// Write `balances.csv` with final balances and nonces of every actor, and
+/// each setup token's holdings to `token_holdings/`.
+const BALANCE_REPORT: bool = false;
+/// Write `pools.csv` with final reserves of every Uniswap pair.
+const POOL_REPORT: bool = false;
+/// Write a JSON state diff per block to `state_diffs/`.
+const DUMP_STATE_DIFFS: bool = false;
+/// Print MDBX table statistics and datadir size after the run.
+const DB_STATS: bool = false;
+/// Latest hardfork active from genesis.
+const HARDFORK: Hardfork = Hardfork::Shanghai;
+/// `Workload::TransfersOnly` skips every contract phase for a clean ETH-transfer baseline.
+const WORKLOAD: Workload = Workload::Mixed;
+/// Derive actor keys and every random choice from this seed, so keys can be
+/// recovered after the run and a rerun builds the same blocks.
+const ACTOR_SEED: Option<B256> = None;
+/// Write `actors.json` (address, private key, nonce) next to `blocks.bin`.
+const EXPORT_ACTORS: bool = false;
+/// Fund the actors and deploy the setup contracts from this key, which the
+/// genesis key funds first. `None` deploys from the genesis key itself.
+const DEPLOYER_KEY: Option<&str> = None;
+/// Ether the genesis key sends a separate `DEPLOYER_KEY`.
+const DEPLOYER_FUNDING_ETH: u64 = 1_000_000_000_000;
+/// Key whose address becomes the Uniswap factory's `feeToSetter`. `None`
+/// leaves it with the deployer.
+const UNISWAP_OWNER_KEY: Option<&str> = None;
+/// Ether given to each actor, by transfer or genesis allocation.
+const ACTOR_FUNDING_ETH: u64 = 1_000_000;
+/// Allocate actor balances in genesis and skip the funding phase.
+const PREFUND_ACTORS_IN_GENESIS: bool = false;
+/// Every this many blocks, top up actors whose estimated balance has fallen
+/// below `TOP_UP_THRESHOLD_ETH`. `None` never tops up.
+const TOP_UP_EVERY_BLOCKS: Option<u64> = None;
+/// Ether below which an actor is topped back up to its funding amount.
+const TOP_UP_THRESHOLD_ETH: u64 = 100_000;
+/// Build on top of this genesis file instead of generating one.
+const GENESIS_FILE: Option<&str> = None;
+/// Write the generated genesis JSON here (e.g. `sandbox_genesis.json`).
+const GENESIS_OUT: Option<&str> = None;
+/// Destination of the RLP block file.
+const BLOCKS_OUT: &str = "blocks.bin";
+/// `Rotation::Blocks(n)` or `Rotation::Bytes(n)` splits the block file into
+/// `blocks_{from}_{to}.bin` segments.
+const BLOCK_FILE_ROTATION: Rotation = Rotation::None;
+/// Destination of the JSONL block file with `--output-format jsonl|both`; a
+/// `.gz` suffix compresses it.
+const BLOCKS_JSONL_OUT: &str = "blocks.jsonl";
+/// Destination of the per-block roots CSV (see `sandbox run --compare`).
+const ROOTS_OUT: &str = "roots.csv";
+/// Destination of the per-block, per-label CSV (e.g. `block_labels.csv`).
+const BLOCK_LABELS_OUT: Option<&str> = None;
+/// Destination of the raw transaction file (e.g. `txs.bin`), for
+/// `sandbox replay-txs`.
+const TXS_OUT: Option<&str> = None;
+/// Destination of the Engine API payloads JSONL (e.g. `payloads.jsonl`), for
+/// replaying the run against a node with `scripts/replay_payloads.py`.
+const PAYLOADS_OUT: Option<&str> = None;
+/// Destination of the per-block first and last tip CSV (e.g. `block_fees.csv`).
+const BLOCK_FEES_OUT: Option<&str> = None;
+/// Free-form label embedded in `run_manifest.json`, and in the name of the
+/// default output directory.
+const TAG: Option<&str> = None;
+/// Directory each run's default output directory, `<started_at>-<tag>`, is
+/// created under. Every relative output path above resolves into it.
+const RUNS_DIR: &str = "runs";
+
+/// Keep the Reth datadir here after the run (for `sandbox export-state`);
+/// a temporary directory is used and deleted when unset.
+const DATADIR: Option<&str> = None;
+/// Run on reth's throwaway test database with no datadir (cannot be combined
+/// with `DATADIR`).
+const IN_MEMORY: bool = false;
+/// Stop building after this many seconds of wall time.
+const MAX_DURATION_SECS: Option<u64> = None;
+/// `LimitMode::Soft` builds the transactions queued when a limit is hit into
+/// final blocks instead of discarding them.
+const LIMIT_MODE: LimitMode = LimitMode::Hard;
+/// Cap on the transaction bytes in a block; the next transaction starts a new one.
+const MAX_BLOCK_BYTES: Option<u64> = None;
+/// Stop once `blocks.bin` reaches this many bytes.
+const MAX_OUTPUT_BYTES: Option<u64> = None;
+/// When to seal a block. `GasTarget(50)` keeps the base fee roughly constant;
+/// `TxCount(n)` produces uniform n-transaction blocks.
+const FILL_STRATEGY: FillStrategy = FillStrategy::GasTarget(50);
+/// Make up each block's shortfall against the gas target in the next one, so
+/// the base fee does not drift over long runs.
+const HOLD_BASE_FEE: bool = false;
+/// `TxOrdering::PriorityFee` includes the best-paying candidates first, as a
+/// mainnet builder would; pair it with a `PRIORITY_FEE` range.
+const TX_ORDERING: TxOrdering = TxOrdering::Arrival;
+/// Priority fees per gas actors offer on their load, in wei: cheapskates offer
+/// the low end, aggressive actors the high end, and the rest the log-scale
+/// middle. At most the 20 gwei max fee.
+const PRIORITY_FEE: AmountRange = AmountRange::new(20_000_000_000, 20_000_000_000);
+/// Proportions of cheapskate, median, and aggressive actors.
+const FEE_STRATEGY_MIX: FeeStrategyMix = FeeStrategyMix {
+    cheapskate: 0,
+    median: 1,
+    aggressive: 0,
+};
+/// Skew token transfers and swaps toward the first few setup tokens, e.g.
+/// `Some(HotTokens { tokens: 3, traffic_percent: 80 })`; `None` spreads
+/// them evenly.
+const HOT_TOKENS: Option<HotTokens> = None;
+/// Deploy a share of the setup tokens as fee-on-transfer tokens, e.g.
+/// `Some(FeeOnTransfer { token_percent: 20, fee_bps: 100 })` for a 1% fee on
+/// every fifth token; `None` deploys plain tokens only.
+const FEE_ON_TRANSFER: Option<FeeOnTransfer> = None;
+/// Coinbase of every block; `FeeRecipient::Rotate` cycles through a list.
+const FEE_RECIPIENT: FeeRecipient = FeeRecipient::Fixed(Address::ZERO);
+/// Seconds between block timestamps (12 mimics mainnet slots).
+const BLOCK_TIME_SECS: u64 = 1;
+/// Timestamp of the generated genesis block.
+const GENESIS_TIMESTAMP: u64 = 0;
+/// Fraction of load transactions followed by a deliberately invalid one.
+const INVALID_TX_RATE: f64 = 0.0;
+/// Further attempts for a transaction rejected for a nonce gap or reverted
+/// for lack of allowance.
+const MAX_TX_RETRIES: u32 = 0;
+/// Accounts per setup phase checked against state once the phase is done.
+const PHASE_CHECK_SAMPLE: usize = 256;
+/// EIP-2612 permit-based liquidity removals the deployer adds to each mixed batch.
+const PERMIT_REMOVALS_PER_BATCH: u64 = 0;
+/// Self-destructing contract lifecycles the deployer adds to each mixed batch.
+const SELFDESTRUCTS_PER_BATCH: u64 = 0;
+/// Token calls fanned out by each multicall load transaction; `0` disables them.
+const MULTICALL_CALLS_PER_TX: u64 = 0;
+/// Fraction of mixed-load transactions where an actor deploys its own token.
+const CONTRACT_DEPLOY_RATE: f64 = 0.01;
+/// Wei per ETH transfer, drawn log-uniformly.
+const ETH_TRANSFER_AMOUNT: AmountRange = AmountRange::new(100, 10_000_000_000_000_000);
+/// Token units per token transfer and per multicall call.
+const TOKEN_TRANSFER_AMOUNT: AmountRange = AmountRange::new(100, 1_000_000_000_000_000_000);
+/// Wei per WETH deposit.
+const WETH_DEPOSIT_AMOUNT: AmountRange = AmountRange::new(1, 1_000_000_000_000_000);
+/// Wei spent per ETH-for-token swap.
+const SWAP_ETH_AMOUNT: AmountRange = AmountRange::new(100, 1_000_000_000_000_000_000);
+/// Token units spent per token-for-ETH swap.
+const SWAP_TOKEN_AMOUNT: AmountRange = AmountRange::new(100, 1_000_000_000_000_000_000);
+/// No swap spends more than this fraction of the pool reserve it pays into.
+const MAX_SWAP_RESERVE_RATIO: f64 = 0.0001;
+/// `SenderSelection::RoundRobin` spreads each block over as many senders as
+/// possible.
+const SENDER_SELECTION: SenderSelection = SenderSelection::Random;
+/// Load transactions one actor may send per batch; `0` is unlimited.
+const MAX_TXS_PER_SENDER_PER_BATCH: u32 = 0;
+/// Transactions one sender may have in a block; `0` is unlimited.
+const MAX_TXS_PER_SENDER_PER_BLOCK: u32 = 0;
+/// Bundlers and operations per bundle, e.g.
+/// `Some(BundlerMode { bundlers: 4, bundle_size: 50 })`; needs the mixed
+/// workload.
+const BUNDLER_MODE: Option<BundlerMode> = None;
+/// Let an actor transfer to itself.
+const ALLOW_SELF_TRANSFER: bool = true;
+/// Sealed blocks written to the database per commit.
+const DB_COMMIT_INTERVAL: u64 = 1;
+/// `DeployVia::Create2` deploys setup tokens at salt-derived addresses.
+const DEPLOY_VIA: DeployVia = DeployVia::Create;
+/// Deploy setup tokens from the first this many actors; `0` keeps them all on
+/// the genesis deployer.
+const TOKEN_DEPLOYERS: u64 = 0;
+/// Recover every generated transaction's sender from its signature; on by
+/// default in debug builds.
+const VERIFY_SENDERS: bool = cfg!(debug_assertions);
+/// With `VERIFY_SENDERS`, check one in this many transactions.
+const VERIFY_SENDERS_ONE_IN: u64 = 1;
+
+/// Options for `sandbox run`, `sandbox setup`, and `sandbox load`.
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Baseline `roots.csv` from an earlier run; exit non-zero if this run's
+    /// blocks do not start with the same hashes and roots.
+    #[arg(long)]
+    compare: Option<PathBuf>,
+    /// Hex private key owning the genesis allocation; its address is derived
+    /// from it. Defaults to the built-in sandbox key.
+    #[arg(long, conflicts_with = "genesis_key_file")]
+    genesis_key: Option<String>,
+    /// File containing the hex private key owning the genesis allocation.
+    #[arg(long)]
+    genesis_key_file: Option<PathBuf>,
+    /// Experimental: execute each block in this many lanes partitioned by
+    /// sender. Requires the transfers-only workload.
+    #[arg(long, default_value_t = 1)]
+    parallel_lanes: usize,
+    /// Block files to write: RLP for replay, JSONL for reading with `jq`,
+    /// both, or off to measure execution alone.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Rlp)]
+    output_format: OutputFormat,
+    /// Run manifest of an earlier run; size each mixed-load transaction's
+    /// gas limit from the gas its label used there.
+    #[arg(long, value_name = "MANIFEST")]
+    calibrated_gas_limits: Option<PathBuf>,
+    /// Scenario file listing the load blocks to build, step by step, in
+    /// place of the generated load. See `scenarios/example.json`.
+    #[arg(long, value_name = "FILE")]
+    scenario: Option<PathBuf>,
+    /// Record every transaction sent to the builder, setup included, to
+    /// `txstream.bin`.
+    #[arg(long, conflicts_with = "replay_tx_stream")]
+    tee_tx_stream: bool,
+    /// Feed the builder a stream recorded with `--tee-tx-stream` instead of
+    /// generating transactions. Run with the same settings as the recording.
+    #[arg(long, value_name = "FILE", conflicts_with = "scenario")]
+    replay_tx_stream: Option<PathBuf>,
+    /// Directory every artifact is written into. Defaults to
+    /// `runs/<started_at>-<tag>/`.
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+    /// Write into `--out-dir` even if it already holds files.
+    #[arg(long)]
+    force: bool,
+}
+
+impl RunArgs {
+    /// Signer for the genesis allocation, from whichever key source was given.
+    fn genesis_signer(&self) -> eyre::Result<PrivateKeySigner> {
+        match (&self.genesis_key, &self.genesis_key_file) {
+            (Some(key), _) => Ok(parse_genesis_key(key)?),
+            (None, Some(path)) => {
+                let key = fs::read_to_string(path).map_err(|err| SandboxError::io(path, err))?;
+                parse_genesis_key(&key).wrap_err_with(|| path.display().to_string())
+            }
+            (None, None) => Ok(parse_genesis_key(GENESIS_PRIVATE_KEY)?),
+        }
+    }
+}
+
+/// Assemble the config from the constants above and `args`, run `stage` of
+/// the simulation, and check it against the `--compare` baseline.
+pub async fn run(args: RunArgs, stage: Stage, setup_dir: Option<PathBuf>) -> eyre::Result<()> {
+    // Setup runs until the load would start, whatever the limits say.
+    let limited = stage != Stage::Setup;
+
+    // Read the baseline up front: it may be the very file this run overwrites.
+    let baseline = args
+        .compare
+        .as_deref()
+        .map(|path| roots::read(path).map(|rows| (path, rows)))
+        .transpose()?;
+
+    let calibrated_gas_limits = args
+        .calibrated_gas_limits
+        .as_deref()
+        .map(calibration::load_gas_limits)
+        .transpose()?
+        .unwrap_or_default();
+    let scenario = args.scenario.as_deref().map(Scenario::read).transpose()?;
+
+    let out_dir = args.out_dir.clone().unwrap_or_else(|| {
+        SimulationPaths::run_dir(Path::new(RUNS_DIR), run_manifest::unix_now(), TAG)
+    });
+    let paths = SimulationPaths::create(out_dir, args.force)?;
+    let sim_config = SimulationConfig::new(
+        CHAIN_ID,
+        NUM_OF_BLOCKS.filter(|_| limited),
+        NUM_OF_TRANSACTIONS.filter(|_| limited),
+        UNIQUE_ACCOUNTS,
+        UNIQUE_TOKENS,
+        GAS_LIMIT,
+        args.genesis_signer()?,
+        STD_BATCH_SIZE,
+    )
+    .with_actor_seed(ACTOR_SEED)
+    .with_workload(WORKLOAD)
+    .with_hardfork(HARDFORK)
+    .with_channel_buffer_size(CHANNEL_BUFFER_SIZE)
+    .with_channel_sample_interval_ms(CHANNEL_SAMPLE_INTERVAL_MS)
+    .with_adaptive_batch_size(ADAPTIVE_BATCH_SIZE)
+    .with_progress_interval_secs(PROGRESS_INTERVAL_SECS)
+    .with_progress_bar(PROGRESS_BAR)
+    .with_rss_warn_mib(RSS_WARN_MIB)
+    .with_metrics(METRICS)
+    .with_trace_out(TRACE_OUT.map(PathBuf::from))
+    .with_trace_failed_txs(TRACE_FAILED_TXS)
+    .with_balance_report(BALANCE_REPORT)
+    .with_pool_report(POOL_REPORT)
+    .with_dump_state_diffs(DUMP_STATE_DIFFS)
+    .with_db_stats(DB_STATS)
+    .with_deployer_signer(DEPLOYER_KEY.map(parse_genesis_key).transpose()?)
+    .with_deployer_funding_amount(U256::from(DEPLOYER_FUNDING_ETH) * Unit::ETHER.wei())
+    .with_uniswap_owner(UNISWAP_OWNER_KEY.map(parse_genesis_key).transpose()?)
+    .with_actor_funding_amount(U256::from(ACTOR_FUNDING_ETH) * Unit::ETHER.wei())
+    .with_prefund_actors_in_genesis(PREFUND_ACTORS_IN_GENESIS)
+    .with_top_up_every_blocks(TOP_UP_EVERY_BLOCKS)
+    .with_top_up_threshold(U256::from(TOP_UP_THRESHOLD_ETH) * Unit::ETHER.wei())
+    .with_genesis_path(GENESIS_FILE.map(PathBuf::from))
+    .with_genesis_out(GENESIS_OUT.map(PathBuf::from))
+    .with_blocks_out(PathBuf::from(BLOCKS_OUT))
+    .with_block_file_rotation(BLOCK_FILE_ROTATION)
+    .with_output_format(args.output_format, PathBuf::from(BLOCKS_JSONL_OUT))
+    .with_roots_out(PathBuf::from(ROOTS_OUT))
+    .with_block_labels_out(BLOCK_LABELS_OUT.map(PathBuf::from))
+    .with_txs_out(TXS_OUT.map(PathBuf::from))
+    .with_payloads_out(PAYLOADS_OUT.map(PathBuf::from))
+    .with_block_fees_out(BLOCK_FEES_OUT.map(PathBuf::from))
+    .with_tx_stream_out(args.tee_tx_stream.then(|| PathBuf::from("txstream.bin")))
+    .with_replay_tx_stream(args.replay_tx_stream)
+    .with_tag(TAG.map(String::from))
+    .with_datadir(DATADIR.map(PathBuf::from))
+    .with_in_memory(IN_MEMORY)
+    .with_max_duration(
+        MAX_DURATION_SECS
+            .filter(|_| limited)
+            .map(Duration::from_secs),
+    )
+    .with_limit_mode(LIMIT_MODE)
+    .with_max_block_bytes(MAX_BLOCK_BYTES)
+    .with_max_output_bytes(MAX_OUTPUT_BYTES.filter(|_| limited))
+    .with_fill_strategy(FILL_STRATEGY)
+    .with_hold_base_fee(HOLD_BASE_FEE)
+    .with_tx_ordering(TX_ORDERING)
+    .with_priority_fee(PRIORITY_FEE)
+    .with_fee_strategy_mix(FEE_STRATEGY_MIX)
+    .with_hot_tokens(HOT_TOKENS)
+    .with_fee_on_transfer(FEE_ON_TRANSFER)
+    .with_fee_recipient(FEE_RECIPIENT)
+    .with_block_time_secs(BLOCK_TIME_SECS)
+    .with_genesis_timestamp(GENESIS_TIMESTAMP)
+    .with_invalid_tx_rate(INVALID_TX_RATE)
+    .with_max_tx_retries(MAX_TX_RETRIES)
+    .with_phase_check_sample(PHASE_CHECK_SAMPLE)
+    .with_permit_removals_per_batch(PERMIT_REMOVALS_PER_BATCH)
+    .with_selfdestructs_per_batch(SELFDESTRUCTS_PER_BATCH)
+    .with_multicall_calls_per_tx(MULTICALL_CALLS_PER_TX)
+    .with_contract_deploy_rate(CONTRACT_DEPLOY_RATE)
+    .with_transfer_amounts(ETH_TRANSFER_AMOUNT, TOKEN_TRANSFER_AMOUNT)
+    .with_weth_deposit_amount(WETH_DEPOSIT_AMOUNT)
+    .with_swap_amounts(SWAP_ETH_AMOUNT, SWAP_TOKEN_AMOUNT, MAX_SWAP_RESERVE_RATIO)
+    .with_sender_selection(SENDER_SELECTION, MAX_TXS_PER_SENDER_PER_BATCH)
+    .with_max_txs_per_sender_per_block(MAX_TXS_PER_SENDER_PER_BLOCK)
+    .with_bundler_mode(BUNDLER_MODE)
+    .with_calibrated_gas_limits(calibrated_gas_limits)
+    .with_scenario(scenario)
+    .with_allow_self_transfer(ALLOW_SELF_TRANSFER)
+    .with_parallel_lanes(args.parallel_lanes)
+    .with_db_commit_interval(DB_COMMIT_INTERVAL)
+    .with_deploy_via(DEPLOY_VIA)
+    .with_token_deployers(TOKEN_DEPLOYERS)
+    .with_verify_senders(VERIFY_SENDERS, VERIFY_SENDERS_ONE_IN)
+    .with_actors_export_path(EXPORT_ACTORS.then(|| PathBuf::from("actors.json")))
+    .with_stage(stage, setup_dir);
+
+    println!("Output:   {}", paths.output_dir.display());
+    let simulation = Simulation::new(sim_config, paths)?;
+    let roots_out = simulation.config().roots_out.clone();
+    let result = simulation.run().await?;
+    result.print_summary();
+
+    if let Some((baseline, baseline_roots)) = baseline {
+        roots::compare(&baseline_roots, &roots::read(&roots_out)?)?;
+        println!(
+            "Compare:  {} blocks match {}",
+            baseline_roots.len(),
+            baseline.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! `sandbox verify`: re-execute a block file on a fresh database and check
+//! that every block reproduces its header's gas used and receipts root.
+
+use std::{fs, path::Path, sync::Arc};
+
+use alloy_genesis::Genesis;
+use alloy_rlp::Decodable;
+use reth_chainspec::ChainSpec;
+use reth_ethereum_primitives::{Block, Receipt};
+use reth_evm::{ConfigureEvm, execute::Executor};
+use reth_node_ethereum::EthEvmConfig;
+use reth_primitives_traits::Block as _;
+use reth_revm::database::StateProviderDatabase;
+use tempfile::TempDir;
+use tracing::warn;
+
+use crate::block_file::MultiFileReader;
+use crate::logging;
+
+/// Execute every block in `file` on top of `genesis` and report mismatches.
+///
+/// State roots are not recomputed; gas used and the receipts root already
+/// cover every transaction's outcome.
+pub fn run(file: &Path, genesis: &Path) -> eyre::Result<()> {
+    let genesis: Genesis = serde_json::from_str(&fs::read_to_string(genesis)?)?;
+    let chain: Arc<ChainSpec> = Arc::new(genesis.into());
+
+    let temp_dir = TempDir::new()?;
+    let (provider_factory, _db) =
+        crate::simulation::init_provider_factory(chain.clone(), temp_dir.path())?;
+    let evm_config = EthEvmConfig::new(chain.clone());
+    let state_provider = provider_factory.latest()?;
+    let mut executor = evm_config.batch_executor(StateProviderDatabase::new(&state_provider));
+
+    let mut parent_hash = chain.genesis_hash();
+    let mut blocks = 0u64;
+    let mut mismatches = 0u64;
+    for rlp_data in MultiFileReader::open(file)? {
+        let block = Block::decode(&mut rlp_data?.as_slice())?;
+        let number = block.header.number;
+
+        eyre::ensure!(
+            block.header.parent_hash == parent_hash,
+            "block {number} does not extend the previous block; was the file built on this genesis?"
+        );
+        parent_hash = block.header.hash_slow();
+
+        let expected_gas = block.header.gas_used;
+        let expected_receipts_root = block.header.receipts_root;
+        let block = block
+            .try_into_recovered()
+            .map_err(|err| eyre::eyre!("block {number}: failed to recover signers: {err}"))?;
+
+        let result = executor.execute_one(&block)?;
+        let receipts_root = Receipt::calculate_receipt_root_no_memo(&result.receipts);
+
+        if result.gas_used != expected_gas || receipts_root != expected_receipts_root {
+            mismatches += 1;
+            warn!(
+                target: logging::CLI,
+                block = number,
+                expected_gas,
+                gas_used = result.gas_used,
+                %expected_receipts_root,
+                %receipts_root,
+                "re-execution mismatch"
+            );
+        }
+        blocks += 1;
+    }
+
+    println!("Verified {blocks} blocks, {mismatches} mismatches");
+    eyre::ensure!(
+        mismatches == 0,
+        "{mismatches} of {blocks} blocks did not re-execute identically"
+    );
+    Ok(())
+}
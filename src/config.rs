@@ -1,7 +1,705 @@
 //! Simulation-wide knobs that describe how aggressively the sandbox should
 //! generate state and transactions.
 
-use alloy_primitives::Address;
+use std::{collections::BTreeMap, fmt, path::PathBuf, time::Duration};
+
+use alloy_primitives::{Address, B256, U256, keccak256, utils::Unit};
+use alloy_signer_local::PrivateKeySigner;
+use rand::Rng;
+use serde_json::{Value, json};
+
+use crate::{
+    error::SandboxError,
+    scenario::Scenario,
+    token::FEE_BPS_DENOMINATOR,
+    transaction::{DEFAULT_GAS_LIMIT, FEE_PER_GAS, TRANSFER_GAS_LIMIT},
+};
+
+/// Default private key owning the pre-funded genesis allocation, used unless
+/// `--genesis-key` or `--genesis-key-file` supplies another. Its address is
+/// always derived from the key, never hard-coded.
+pub const GENESIS_PRIVATE_KEY: &str =
+    "5ba8b410b0d2161dacd190f8aa6dfbc54ad1c84c67ee3e80611d92cc3fda8abd";
+
+/// Parse a hex private key (with or without `0x`, surrounding whitespace
+/// ignored) into the signer that owns the genesis allocation.
+pub fn parse_genesis_key(key: &str) -> Result<PrivateKeySigner, SandboxError> {
+    let key = key.trim();
+    let key = key.strip_prefix("0x").unwrap_or(key);
+    key.parse::<PrivateKeySigner>().map_err(|err| {
+        SandboxError::Config(format!(
+            "invalid genesis private key (expected 32 bytes of hex): {err}"
+        ))
+    })
+}
+
+/// Shape of the steady-state load emitted once setup is complete.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Workload {
+    /// Token transfers, Uniswap swaps, and ETH transfers on top of deployed contracts.
+    #[default]
+    Mixed,
+    /// 21k-gas ETH transfers only; the token and Uniswap phases are skipped.
+    TransfersOnly,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mixed => f.write_str("mixed"),
+            Self::TransfersOnly => f.write_str("transfers-only"),
+        }
+    }
+}
+
+/// Latest hardfork the sandbox chain activates at genesis.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hardfork {
+    /// Everything up to and including Shanghai.
+    #[default]
+    Shanghai,
+    /// Adds blobs (EIP-4844), transient storage, MCOPY, and beacon roots.
+    Cancun,
+    /// Adds EIP-7702, EIP-2935 block hashes, and execution-layer requests.
+    Prague,
+}
+
+impl Hardfork {
+    /// Whether Cancun rules (and the beacon roots system call) apply.
+    pub fn is_cancun_active(&self) -> bool {
+        *self >= Self::Cancun
+    }
+
+    /// Whether Prague rules (and its system contracts) apply.
+    pub fn is_prague_active(&self) -> bool {
+        *self >= Self::Prague
+    }
+}
+
+impl fmt::Display for Hardfork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shanghai => f.write_str("shanghai"),
+            Self::Cancun => f.write_str("cancun"),
+            Self::Prague => f.write_str("prague"),
+        }
+    }
+}
+
+/// Which block files a run writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Length-prefixed RLP at `blocks_out`.
+    #[default]
+    Rlp,
+    /// One JSON object per block at `blocks_jsonl_out`.
+    Jsonl,
+    /// Both.
+    Both,
+    /// No block files at all, for benchmarking execution alone.
+    Off,
+}
+
+impl OutputFormat {
+    /// Whether the RLP block file is written.
+    pub fn writes_rlp(&self) -> bool {
+        matches!(self, Self::Rlp | Self::Both)
+    }
+
+    /// Whether the JSONL block file is written.
+    pub fn writes_jsonl(&self) -> bool {
+        matches!(self, Self::Jsonl | Self::Both)
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rlp => f.write_str("rlp"),
+            Self::Jsonl => f.write_str("jsonl"),
+            Self::Both => f.write_str("both"),
+            Self::Off => f.write_str("off"),
+        }
+    }
+}
+
+/// When the block file is split into segments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// Everything goes to a single file at `blocks_out`.
+    #[default]
+    None,
+    /// Start a new segment after this many blocks.
+    Blocks(u64),
+    /// Start a new segment before a block would take the current one past
+    /// this many bytes. A block larger than the limit gets a segment of its own.
+    Bytes(u64),
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Blocks(blocks) => write!(f, "blocks:{blocks}"),
+            Self::Bytes(bytes) => write!(f, "bytes:{bytes}"),
+        }
+    }
+}
+
+/// When the builder seals a block. Blocks are always sealed once the gas
+/// target is reached; without a `GasTarget` criterion that target is the full
+/// gas limit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FillStrategy {
+    /// Seal once this percent of the gas limit is used.
+    GasTarget(u64),
+    /// Seal after this many transactions.
+    TxCount(u64),
+    /// Seal once the RLP length of the included transactions reaches this many bytes.
+    ByteSize(u64),
+    /// Seal as soon as any of the inner criteria is met.
+    Composite(Vec<FillStrategy>),
+}
+
+impl Default for FillStrategy {
+    fn default() -> Self {
+        Self::GasTarget(50)
+    }
+}
+
+impl FillStrategy {
+    /// Smallest gas target percent among the active criteria, if any.
+    pub fn gas_target_percent(&self) -> Option<u64> {
+        match self {
+            Self::GasTarget(percent) => Some((*percent).clamp(1, 100)),
+            Self::TxCount(_) | Self::ByteSize(_) => None,
+            Self::Composite(strategies) => {
+                strategies.iter().filter_map(Self::gas_target_percent).min()
+            }
+        }
+    }
+
+    /// Non-gas criterion met by a block holding `txs` transactions of `bytes`
+    /// total RLP length, if any. Gas is checked against
+    /// [`SimulationConfig::block_gas_target`] by the builder.
+    pub fn seal_reason(&self, txs: u64, bytes: u64) -> Option<SealReason> {
+        match self {
+            Self::GasTarget(_) => None,
+            Self::TxCount(max) => (txs >= *max).then_some(SealReason::TxCount),
+            Self::ByteSize(max) => (bytes >= *max).then_some(SealReason::ByteSize),
+            Self::Composite(strategies) => strategies
+                .iter()
+                .find_map(|strategy| strategy.seal_reason(txs, bytes)),
+        }
+    }
+}
+
+impl fmt::Display for FillStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GasTarget(percent) => write!(f, "gas:{percent}%"),
+            Self::TxCount(txs) => write!(f, "txs:{txs}"),
+            Self::ByteSize(bytes) => write!(f, "bytes:{bytes}"),
+            Self::Composite(strategies) => {
+                for (i, strategy) in strategies.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    write!(f, "{strategy}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Which criterion sealed a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealReason {
+    /// The block reached its gas target.
+    GasTarget,
+    /// The block reached `FillStrategy::TxCount`.
+    TxCount,
+    /// The block reached `FillStrategy::ByteSize`.
+    ByteSize,
+    /// `max_duration` elapsed mid-block.
+    Deadline,
+    /// The next transaction would have pushed the block past `max_block_bytes`.
+    MaxBytes,
+    /// A channel buffer's worth of transactions was held back by
+    /// `max_txs_per_sender_per_block`.
+    SenderLimit,
+    /// The block holds every transaction of its scenario block, or the
+    /// scenario's first block is about to start after setup.
+    Scenario,
+    /// The run reached its transaction or gas limit mid-block.
+    RunLimit,
+    /// The channel closed with the block partly filled.
+    Shutdown,
+}
+
+impl SealReason {
+    /// Counter bumped once per block sealed for this reason.
+    pub fn counter_key(&self) -> &'static str {
+        match self {
+            Self::GasTarget => "blocks_sealed_by_gas",
+            Self::TxCount => "blocks_sealed_by_tx_count",
+            Self::ByteSize => "blocks_sealed_by_byte_size",
+            Self::Deadline => "blocks_sealed_by_deadline",
+            Self::MaxBytes => "blocks_sealed_by_max_bytes",
+            Self::SenderLimit => "blocks_sealed_by_sender_limit",
+            Self::Scenario => "blocks_sealed_by_scenario",
+            Self::RunLimit => "blocks_sealed_by_run_limit",
+            Self::Shutdown => "blocks_sealed_by_shutdown",
+        }
+    }
+}
+
+impl fmt::Display for SealReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GasTarget => f.write_str("gas target"),
+            Self::TxCount => f.write_str("tx count"),
+            Self::ByteSize => f.write_str("byte size"),
+            Self::Deadline => f.write_str("deadline"),
+            Self::MaxBytes => f.write_str("max bytes"),
+            Self::SenderLimit => f.write_str("sender limit"),
+            Self::Scenario => f.write_str("scenario"),
+            Self::RunLimit => f.write_str("run limit"),
+            Self::Shutdown => f.write_str("shutdown"),
+        }
+    }
+}
+
+/// Which address each block credits priority fees to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FeeRecipient {
+    /// Every block uses the same recipient.
+    Fixed(Address),
+    /// Block `n` uses `addresses[n % addresses.len()]`.
+    Rotate(Vec<Address>),
+}
+
+impl Default for FeeRecipient {
+    fn default() -> Self {
+        Self::Fixed(Address::ZERO)
+    }
+}
+
+impl FeeRecipient {
+    /// Recipient for `block_number`.
+    pub fn for_block(&self, block_number: u64) -> Address {
+        match self {
+            Self::Fixed(address) => *address,
+            Self::Rotate(addresses) if addresses.is_empty() => Address::ZERO,
+            Self::Rotate(addresses) => addresses[(block_number % addresses.len() as u64) as usize],
+        }
+    }
+}
+
+/// How the token deployment phase creates the setup tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeployVia {
+    /// Plain `CREATE` transactions from the deployer; addresses follow its nonce.
+    #[default]
+    Create,
+    /// Calls to the `Create2Deployer` factory; addresses follow a per-token salt.
+    Create2,
+}
+
+impl fmt::Display for DeployVia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Create => f.write_str("create"),
+            Self::Create2 => f.write_str("create2"),
+        }
+    }
+}
+
+/// How load batches choose their senders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SenderSelection {
+    /// Uniformly random actors; one may send several transactions per batch.
+    #[default]
+    Random,
+    /// Walk the actor list in order, carrying the position across batches,
+    /// so consecutive transactions come from different accounts.
+    RoundRobin,
+}
+
+impl fmt::Display for SenderSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => f.write_str("random"),
+            Self::RoundRobin => f.write_str("round-robin"),
+        }
+    }
+}
+
+/// Order in which the builder includes the transactions it receives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxOrdering {
+    /// Channel order.
+    #[default]
+    Arrival,
+    /// Buffer up to `window` transactions, or those whose gas limits reach
+    /// the block's gas target when unset, and include the highest effective
+    /// priority fee first. A sender's transactions keep their nonce order.
+    PriorityFee { window: Option<usize> },
+}
+
+impl fmt::Display for TxOrdering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Arrival => f.write_str("arrival"),
+            Self::PriorityFee { window: None } => f.write_str("priority-fee"),
+            Self::PriorityFee {
+                window: Some(window),
+            } => write!(f, "priority-fee:{window}"),
+        }
+    }
+}
+
+/// How an actor prices its load, within the configured `priority_fee` range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FeeStrategy {
+    /// Offers the bottom of the range.
+    Cheapskate,
+    /// Offers the middle of the range, on a log scale.
+    #[default]
+    Median,
+    /// Offers the top of the range.
+    Aggressive,
+}
+
+impl FeeStrategy {
+    pub const ALL: [Self; 3] = [Self::Cheapskate, Self::Median, Self::Aggressive];
+
+    /// Name used in actor files, the manifest, and the summary.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cheapskate => "cheapskate",
+            Self::Median => "median",
+            Self::Aggressive => "aggressive",
+        }
+    }
+
+    /// Strategy called `name`, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+
+    /// Priority fee per gas the strategy offers within `range`.
+    pub fn priority_fee(&self, range: AmountRange) -> u128 {
+        match self {
+            Self::Cheapskate => range.min as u128,
+            Self::Median => {
+                let low = (range.min.max(1) as f64).ln();
+                let high = (range.max.max(1) as f64).ln();
+                (((low + high) / 2.0).exp() as u64).clamp(range.min, range.max) as u128
+            }
+            Self::Aggressive => range.max as u128,
+        }
+    }
+}
+
+/// Proportions of actors given each [`FeeStrategy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeStrategyMix {
+    pub cheapskate: u32,
+    pub median: u32,
+    pub aggressive: u32,
+}
+
+impl Default for FeeStrategyMix {
+    fn default() -> Self {
+        Self {
+            cheapskate: 0,
+            median: 1,
+            aggressive: 0,
+        }
+    }
+}
+
+impl FeeStrategyMix {
+    /// Sum of the weights.
+    pub fn total(&self) -> u64 {
+        self.cheapskate as u64 + self.median as u64 + self.aggressive as u64
+    }
+
+    /// Strategy for an actor whose `draw` is uniform over `u64`; the default
+    /// when every weight is 0.
+    pub fn pick(&self, draw: u64) -> FeeStrategy {
+        let total = self.total();
+        if total == 0 {
+            return FeeStrategy::default();
+        }
+        let mut point = draw % total;
+        let weights = [self.cheapskate, self.median, self.aggressive];
+        for (strategy, weight) in FeeStrategy::ALL.into_iter().zip(weights) {
+            if point < weight as u64 {
+                return strategy;
+            }
+            point -= weight as u64;
+        }
+        FeeStrategy::default()
+    }
+
+    /// Render the proportions for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "cheapskate": self.cheapskate,
+            "median": self.median,
+            "aggressive": self.aggressive,
+        })
+    }
+}
+
+/// ERC-4337-style bundling: the first `bundlers` actors stop sending direct
+/// load and instead send one batcher transaction per load batch, each
+/// carrying `bundle_size` token transfers to other actors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BundlerMode {
+    pub bundlers: u32,
+    pub bundle_size: u32,
+}
+
+impl BundlerMode {
+    /// Render the mode for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "bundlers": self.bundlers,
+            "bundle_size": self.bundle_size,
+        })
+    }
+}
+
+/// Skewed token choice: the first `tokens` setup tokens take
+/// `traffic_percent` of the token transfers and swaps, the rest share what
+/// is left, as a few pools dominate mainnet volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HotTokens {
+    pub tokens: u64,
+    pub traffic_percent: u32,
+}
+
+impl HotTokens {
+    /// Index of the setup token, out of `num_tokens`, a load transaction
+    /// uses. Every token is hot when there are no more than `tokens`.
+    pub fn pick(&self, num_tokens: u64, rng: &mut impl Rng) -> u64 {
+        let hot = self.tokens.clamp(1, num_tokens);
+        if hot == num_tokens || rng.random_bool(self.traffic_percent.min(100) as f64 / 100.0) {
+            rng.random_range(0..hot)
+        } else {
+            rng.random_range(hot..num_tokens)
+        }
+    }
+
+    /// Render the hot set for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "tokens": self.tokens,
+            "traffic_percent": self.traffic_percent,
+        })
+    }
+}
+
+/// Which contract a token is deployed from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TokenTemplate {
+    /// `SandboxToken`: every transfer arrives in full.
+    #[default]
+    Standard,
+    /// `FeeToken`: `fee_bps` basis points of every transfer are burned.
+    FeeOnTransfer { fee_bps: u32 },
+}
+
+impl TokenTemplate {
+    /// Name the template is recorded under in `deployments.json`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::FeeOnTransfer { .. } => "fee-on-transfer",
+        }
+    }
+
+    /// Parse a template recorded by [`Self::name`], with the fee it charges.
+    pub fn from_name(name: &str, fee_bps: u32) -> Option<Self> {
+        match name {
+            "standard" => Some(Self::Standard),
+            "fee-on-transfer" => Some(Self::FeeOnTransfer { fee_bps }),
+            _ => None,
+        }
+    }
+
+    /// Basis points burned out of every transfer.
+    pub fn fee_bps(&self) -> u32 {
+        match self {
+            Self::Standard => 0,
+            Self::FeeOnTransfer { fee_bps } => *fee_bps,
+        }
+    }
+
+    /// Whether transfers arrive short, so swaps paying in the token need the
+    /// router's `SupportingFeeOnTransferTokens` variants.
+    pub fn charges_fee(&self) -> bool {
+        self.fee_bps() > 0
+    }
+}
+
+/// Setup tokens deployed from the fee-on-transfer template: `token_percent`
+/// of them, spread evenly through the deployment order, burn `fee_bps`
+/// basis points of every transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeOnTransfer {
+    pub token_percent: u32,
+    pub fee_bps: u32,
+}
+
+impl FeeOnTransfer {
+    /// Template of setup token `index`.
+    pub fn template(&self, index: u64) -> TokenTemplate {
+        let percent = u64::from(self.token_percent.min(100));
+        // Token `index` charges a fee when it takes the running share of fee
+        // tokens past another whole token.
+        if (index + 1) * percent / 100 > index * percent / 100 {
+            TokenTemplate::FeeOnTransfer {
+                fee_bps: self.fee_bps,
+            }
+        } else {
+            TokenTemplate::Standard
+        }
+    }
+
+    /// Render the fee token share for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "token_percent": self.token_percent,
+            "fee_bps": self.fee_bps,
+        })
+    }
+}
+
+/// Inclusive bounds a load amount is drawn from. Draws are log-uniform, so
+/// every order of magnitude between the bounds is equally likely.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AmountRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl AmountRange {
+    /// Amounts between `min` and `max`, swapped if given backwards.
+    pub const fn new(min: u64, max: u64) -> Self {
+        if min <= max {
+            Self { min, max }
+        } else {
+            Self { min: max, max: min }
+        }
+    }
+
+    /// The same range with `max` lowered to `cap`, never below `min`.
+    pub fn capped(self, cap: u64) -> Self {
+        Self {
+            min: self.min,
+            max: self.max.min(cap).max(self.min),
+        }
+    }
+
+    /// Draw an amount.
+    pub fn sample(&self, rng: &mut impl Rng) -> U256 {
+        if self.min == self.max {
+            return U256::from(self.min);
+        }
+        // ln(0) is undefined; zero can only come from the lower bound.
+        let low = (self.min.max(1) as f64).ln();
+        let high = (self.max as f64).ln();
+        let amount = rng.random_range(low..=high).exp() as u64;
+        U256::from(amount.clamp(self.min, self.max))
+    }
+
+    /// Render the bounds for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({ "min": self.min, "max": self.max })
+    }
+}
+
+/// Which limit ended a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// `num_of_blocks` blocks were built.
+    Blocks,
+    /// `num_of_transactions` transactions were processed.
+    Transactions,
+    /// The gas budget was used up.
+    Gas,
+    /// `max_duration` elapsed.
+    Time,
+    /// The block file reached `max_output_bytes`.
+    OutputSize,
+    /// The orchestrator ran out of work and closed the channel.
+    ChannelClosed,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocks => f.write_str("blocks"),
+            Self::Transactions => f.write_str("transactions"),
+            Self::Gas => f.write_str("gas"),
+            Self::Time => f.write_str("time"),
+            Self::OutputSize => f.write_str("output size"),
+            Self::ChannelClosed => f.write_str("channel closed"),
+        }
+    }
+}
+
+/// What happens to transactions already generated when a run limit is hit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitMode {
+    /// Stop at the limit. Transactions still queued are reported as not
+    /// executed and their nonces rewound.
+    #[default]
+    Hard,
+    /// Stop generating at the limit, then build everything still queued into
+    /// final blocks. The run overshoots the limit by at most a channel
+    /// buffer's worth of transactions.
+    Soft,
+}
+
+impl fmt::Display for LimitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hard => f.write_str("hard"),
+            Self::Soft => f.write_str("soft"),
+        }
+    }
+}
+
+/// Which part of the pipeline a run executes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Stage {
+    /// The setup phases, then the load.
+    #[default]
+    Full,
+    /// Only the setup phases: stop where the load would start and leave the
+    /// datadir, genesis, actors, deployments, and block file in `setup_dir`.
+    Setup,
+    /// Only the load, on a copy of what a setup stage left in `setup_dir`.
+    Load,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => f.write_str("full"),
+            Self::Setup => f.write_str("setup"),
+            Self::Load => f.write_str("load"),
+        }
+    }
+}
 
 /// Captures all tunable parameters the orchestrator and block builder need in
 /// order to synthesize accounts, tokens, and blocks deterministically.
@@ -19,12 +717,244 @@ pub struct SimulationConfig {
     pub unique_tokens: u64,
     /// Per-block gas limit, also used to cap the global simulation budget.
     pub gas_limit: u64,
-    /// Private key that owns the pre-funded genesis allocation.
-    pub genesis_private_key: &'static str,
-    /// Address that corresponds to `genesis_private_key`.
-    pub genesis_address: Address,
+    /// Signer that owns the pre-funded genesis allocation and, unless
+    /// `deployer_signer` is set, deploys every setup contract.
+    pub genesis_signer: PrivateKeySigner,
+    /// Signer that funds the actors and deploys every setup contract in place
+    /// of `genesis_signer`, which then acts only as a faucet sending it
+    /// `deployer_funding_amount` before anything else.
+    pub deployer_signer: Option<PrivateKeySigner>,
+    /// Wei the faucet sends a separate deployer.
+    pub deployer_funding_amount: U256,
+    /// Account the Uniswap factory's `feeToSetter` is set to; the deployer
+    /// when unset.
+    pub uniswap_owner: Option<PrivateKeySigner>,
     /// Batch size used by the orchestrator when emitting homogeneous work.
     pub std_batch_size: u64,
+    /// Seed used to derive actor keys, `prev_randao`, and every random choice
+    /// the orchestrator makes; random keys and choices when `None`.
+    pub actor_seed: Option<B256>,
+    /// Where to write the actor key file once orchestration stops, if anywhere.
+    pub actors_export_path: Option<PathBuf>,
+    /// Supply minted to the deployer by each token constructor.
+    pub token_initial_supply: U256,
+    /// Which transactions the load phase emits.
+    pub workload: Workload,
+    /// Build the load blocks a scenario lists instead of the workload's
+    /// generated load; the run ends with the scenario's last block.
+    pub scenario: Option<Scenario>,
+    /// Capacity of the orchestrator → builder transaction channel.
+    pub channel_buffer_size: usize,
+    /// How often the channel depth gauge is sampled; `0` disables sampling.
+    pub channel_sample_interval_ms: u64,
+    /// Grow the batch size while the channel is starved and shrink it while full.
+    pub adaptive_batch_size: bool,
+    /// Seconds between progress heartbeat lines; `0` disables them.
+    pub progress_interval_secs: u64,
+    /// Draw a progress bar on stdout while the run is in flight. Ignored when
+    /// stdout is not a terminal.
+    pub progress_bar: bool,
+    /// Warn once resident memory reaches this many MiB, if set. Checked on
+    /// every progress heartbeat.
+    pub rss_warn_mib: Option<u64>,
+    /// Time builder and orchestrator sections for the summary table.
+    pub metrics: bool,
+    /// Where to write a Chrome trace of every timed section, if anywhere.
+    pub trace_out: Option<PathBuf>,
+    /// Replay every failed transaction under a call tracer and write its
+    /// record, with the call tree, to `failed_traces/`. Each block with a
+    /// failure is re-executed up to its last one, so this is slow.
+    pub trace_failed_txs: bool,
+    /// Write `balances.csv` with every actor's final balance and nonce, and
+    /// each setup token's holdings to `token_holdings/`.
+    pub balance_report: bool,
+    /// Write `pools.csv` with the final reserves of every Uniswap pair.
+    pub pool_report: bool,
+    /// Write a JSON state diff per block to `state_diffs/`.
+    pub dump_state_diffs: bool,
+    /// Print MDBX table statistics and datadir size after the run.
+    pub db_stats: bool,
+    /// Latest hardfork active from genesis.
+    pub hardfork: Hardfork,
+    /// Wei sent to (or allocated to) each actor before the load phase.
+    pub actor_funding_amount: U256,
+    /// Allocate `actor_funding_amount` to every actor in genesis and skip the
+    /// ActorFunding phase. Actor keys are then derived from `actor_seed`, which
+    /// is picked at random when unset.
+    pub prefund_actors_in_genesis: bool,
+    /// Every this many blocks during load, send actors whose estimated balance
+    /// is below `top_up_threshold` back up to `actor_funding_amount`. Top-ups
+    /// are interleaved with the load. `None` never tops up.
+    pub top_up_every_blocks: Option<u64>,
+    /// Estimated balance, in wei, below which an actor is topped up.
+    pub top_up_threshold: U256,
+    /// Additional (address, balance) pairs to fund in genesis.
+    pub extra_genesis_accounts: Vec<(Address, U256)>,
+    /// Load the chain from this genesis file instead of generating one.
+    pub genesis_path: Option<PathBuf>,
+    /// Nonce of the deployer's first transaction: `0` on a generated genesis,
+    /// or the nonce a genesis file's alloc gives it.
+    pub deployer_nonce: u64,
+    /// Nonce of a separate faucet's first transaction, taken from a genesis
+    /// file's alloc like `deployer_nonce`.
+    pub faucet_nonce: u64,
+    /// Write the generated genesis JSON here; nothing is written when unset.
+    pub genesis_out: Option<PathBuf>,
+    /// Destination of the RLP block file.
+    pub blocks_out: PathBuf,
+    /// Split the block file into `{stem}_{from}_{to}.bin` segments next to
+    /// `blocks_out`.
+    pub block_file_rotation: Rotation,
+    /// Block files to write.
+    pub output_format: OutputFormat,
+    /// Destination of the JSONL block file; gzipped when it ends in `.gz`.
+    pub blocks_jsonl_out: PathBuf,
+    /// Per-block hashes and roots, as CSV.
+    pub roots_out: PathBuf,
+    /// Per-block, per-label transaction counts and gas, as CSV; nothing is
+    /// written when unset.
+    pub block_labels_out: Option<PathBuf>,
+    /// Every included transaction as EIP-2718 bytes, in execution order;
+    /// nothing is written when unset.
+    pub txs_out: Option<PathBuf>,
+    /// Engine API `newPayload` requests for every built block, as JSONL;
+    /// nothing is written when unset.
+    pub payloads_out: Option<PathBuf>,
+    /// Every transaction sent into the channel, setup included, as a stream
+    /// a later run can replay; nothing is written when unset.
+    pub tx_stream_out: Option<PathBuf>,
+    /// Feed the builder the transaction stream at this path instead of
+    /// generating anything.
+    pub replay_tx_stream: Option<PathBuf>,
+    /// Free-form label embedded in the run manifest.
+    pub tag: Option<String>,
+    /// Persistent Reth datadir; a temporary one is used when unset.
+    pub datadir: Option<PathBuf>,
+    /// Back the provider factory with reth's throwaway test database and skip
+    /// the datadir and static file setup entirely.
+    pub in_memory: bool,
+    /// Part of the pipeline to run.
+    pub stage: Stage,
+    /// Where a setup stage writes its artifacts and a load stage reads them.
+    pub setup_dir: Option<PathBuf>,
+    /// Wall-clock budget for block building.
+    pub max_duration: Option<Duration>,
+    /// Whether transactions queued when a limit is hit are still built.
+    pub limit_mode: LimitMode,
+    /// Cap on the RLP length of the transactions in a block; a transaction that
+    /// would exceed it starts the next block instead.
+    pub max_block_bytes: Option<u64>,
+    /// Stop the run once the block file holds this many bytes. The last block
+    /// may take it past the cap.
+    pub max_output_bytes: Option<u64>,
+    /// When the builder seals a block. Defaults to 50% of the gas limit so the
+    /// base fee stays roughly constant. A transaction whose gas limit could
+    /// take a block past the target starts the next block instead, so blocks
+    /// under-fill slightly and the base fee still drifts (see
+    /// `hold_base_fee`).
+    pub fill_strategy: FillStrategy,
+    /// Keep the base fee from drifting: aim each block's gas target at the
+    /// gas that brings it and the block before back to an average of the
+    /// target. Tight gas limits (see `calibrated_gas_limits`) keep blocks
+    /// closer to their target. Parallel lanes ignore it.
+    pub hold_base_fee: bool,
+    /// Coinbase of each block.
+    pub fee_recipient: FeeRecipient,
+    /// Order in which the builder includes transactions.
+    pub tx_ordering: TxOrdering,
+    /// Priority fees per gas, in wei, actors offer on their load: each
+    /// offers the point of the range its [`FeeStrategy`] picks. Setup,
+    /// top-ups, bundles, and injected transactions offer [`FEE_PER_GAS`],
+    /// which also caps the range.
+    pub priority_fee: AmountRange,
+    /// Proportions of actors given each fee strategy when generated.
+    pub fee_strategy_mix: FeeStrategyMix,
+    /// Per-block tip of the first and last included transaction, as CSV;
+    /// nothing is written when unset.
+    pub block_fees_out: Option<PathBuf>,
+    /// Seconds between consecutive block timestamps.
+    pub block_time_secs: u64,
+    /// Timestamp of the generated genesis block.
+    pub genesis_timestamp: u64,
+    /// Fraction of load transactions followed by a deliberately invalid one.
+    pub invalid_tx_rate: f64,
+    /// Times a transaction rejected for a nonce gap, or reverted for lack of
+    /// allowance, is attempted again at the start of a later block before it
+    /// counts as failed; `0` fails it at once. When set, every transaction is
+    /// copied before execution so a failed one can be re-sent.
+    pub max_tx_retries: u32,
+    /// Accounts per setup phase checked against state once the phase is in
+    /// blocks: funded actors, token code, pool reserves; `0` skips the
+    /// checks.
+    pub phase_check_sample: usize,
+    /// Permit-authorized liquidity removals pool owners add to each mixed batch.
+    pub permit_removals_per_batch: u64,
+    /// Self-destructing contract lifecycles the deployer adds to each mixed batch.
+    pub selfdestructs_per_batch: u64,
+    /// Calls each multicall load transaction fans out to; `0` disables them
+    /// and skips deploying the batcher.
+    pub multicall_calls_per_tx: u64,
+    /// Fraction of mixed-load transactions that deploy a fresh token from the
+    /// sending actor.
+    pub contract_deploy_rate: f64,
+    /// Wei sent by each ETH transfer.
+    pub eth_transfer_amount: AmountRange,
+    /// Token units moved by each token transfer, and by each call a multicall
+    /// fans out.
+    pub token_transfer_amount: AmountRange,
+    /// Wei wrapped by each WETH deposit. Withdrawals draw from one to the
+    /// actor's tracked WETH balance.
+    pub weth_deposit_amount: AmountRange,
+    /// Wei spent by each ETH-for-token swap.
+    pub swap_eth_amount: AmountRange,
+    /// Token units spent by each token-for-ETH swap.
+    pub swap_token_amount: AmountRange,
+    /// Cap on any single swap as a fraction of the reserve its input side is
+    /// seeded with, so pools are not drained.
+    pub max_swap_reserve_ratio: f64,
+    /// How load senders are chosen. Several transactions from one sender in
+    /// a block must run in nonce order, so parallel-execution benchmarks
+    /// want `RoundRobin` or a low `max_txs_per_sender_per_batch`.
+    pub sender_selection: SenderSelection,
+    /// Most load transactions one actor sends per batch; `0` is unlimited.
+    /// A batch comes up short once every actor is at the cap.
+    pub max_txs_per_sender_per_batch: u32,
+    /// Most transactions one sender may have in a block; `0` is unlimited.
+    /// The builder holds later ones back for the next block, as a block
+    /// builder capping per-account inclusion would.
+    pub max_txs_per_sender_per_block: u32,
+    /// Send part of the mixed load as bundles from dedicated bundler actors.
+    pub bundler_mode: Option<BundlerMode>,
+    /// Concentrate token transfers and swaps on a few setup tokens, and so
+    /// on their pairs; uniform over every token when unset.
+    pub hot_tokens: Option<HotTokens>,
+    /// Deploy part of the setup tokens as fee-on-transfer tokens; every
+    /// token is a plain `SandboxToken` when unset.
+    pub fee_on_transfer: Option<FeeOnTransfer>,
+    /// Gas limit for mixed-load transactions, by label name, in place of the
+    /// generator's defaults; usually measured by an earlier run.
+    pub calibrated_gas_limits: BTreeMap<String, u64>,
+    /// Let a transfer's receiver be its sender.
+    pub allow_self_transfer: bool,
+    /// Experimental: execute each block in this many sender-partitioned lanes
+    /// on separate threads and merge their state. `1` builds serially.
+    pub parallel_lanes: usize,
+    /// Sealed blocks written to the database per commit. Blocks waiting to
+    /// be committed are overlaid on the database state the next block runs
+    /// against.
+    pub db_commit_interval: u64,
+    /// How setup tokens are deployed.
+    pub deploy_via: DeployVia,
+    /// Spread setup token deployments round-robin over the first this many
+    /// actors, each of which then owns its tokens' pools; `0` keeps every
+    /// token on the genesis deployer. Owners seed each pool with 10,000 ETH,
+    /// so `actor_funding_amount` must cover their share of the pools.
+    pub token_deployers: u64,
+    /// Recover the sender of generated transactions from their signatures and
+    /// fail the run on a mismatch. On by default in debug builds.
+    pub verify_senders: bool,
+    /// With `verify_senders`, check one in this many transactions.
+    pub verify_senders_one_in: u64,
 }
 
 impl SimulationConfig {
@@ -36,8 +966,7 @@ impl SimulationConfig {
         unique_accounts: u64,
         unique_tokens: u64,
         gas_limit: u64,
-        genesis_private_key: &'static str,
-        genesis_address: Address,
+        genesis_signer: PrivateKeySigner,
         std_batch_size: u64,
     ) -> Self {
         Self {
@@ -47,12 +976,1120 @@ impl SimulationConfig {
             unique_accounts,
             unique_tokens,
             gas_limit,
-            genesis_private_key,
-            genesis_address,
+            genesis_signer,
+            deployer_signer: None,
+            deployer_funding_amount: U256::from(u128::MAX),
+            uniswap_owner: None,
             std_batch_size,
+            actor_seed: None,
+            actors_export_path: None,
+            token_initial_supply: Unit::ETHER.wei(),
+            workload: Workload::default(),
+            scenario: None,
+            channel_buffer_size: 1000,
+            channel_sample_interval_ms: 100,
+            adaptive_batch_size: false,
+            progress_interval_secs: 10,
+            progress_bar: false,
+            rss_warn_mib: None,
+            metrics: true,
+            trace_out: None,
+            trace_failed_txs: false,
+            balance_report: false,
+            pool_report: false,
+            dump_state_diffs: false,
+            db_stats: false,
+            hardfork: Hardfork::default(),
+            actor_funding_amount: U256::from(1_000_000) * Unit::ETHER.wei(),
+            prefund_actors_in_genesis: false,
+            top_up_every_blocks: None,
+            top_up_threshold: U256::from(100_000) * Unit::ETHER.wei(),
+            extra_genesis_accounts: Vec::new(),
+            genesis_path: None,
+            deployer_nonce: 0,
+            faucet_nonce: 0,
+            genesis_out: None,
+            blocks_out: PathBuf::from("blocks.bin"),
+            block_file_rotation: Rotation::None,
+            output_format: OutputFormat::Rlp,
+            blocks_jsonl_out: PathBuf::from("blocks.jsonl"),
+            roots_out: PathBuf::from("roots.csv"),
+            block_labels_out: None,
+            txs_out: None,
+            payloads_out: None,
+            tx_stream_out: None,
+            replay_tx_stream: None,
+            tag: None,
+            datadir: None,
+            in_memory: false,
+            stage: Stage::Full,
+            setup_dir: None,
+            max_duration: None,
+            limit_mode: LimitMode::default(),
+            max_block_bytes: None,
+            max_output_bytes: None,
+            fill_strategy: FillStrategy::default(),
+            hold_base_fee: false,
+            fee_recipient: FeeRecipient::default(),
+            tx_ordering: TxOrdering::default(),
+            priority_fee: AmountRange::new(FEE_PER_GAS as u64, FEE_PER_GAS as u64),
+            fee_strategy_mix: FeeStrategyMix::default(),
+            block_fees_out: None,
+            block_time_secs: 1,
+            genesis_timestamp: 0,
+            invalid_tx_rate: 0.0,
+            max_tx_retries: 0,
+            phase_check_sample: 256,
+            permit_removals_per_batch: 0,
+            selfdestructs_per_batch: 0,
+            multicall_calls_per_tx: 0,
+            contract_deploy_rate: 0.01,
+            eth_transfer_amount: AmountRange::new(100, 10_000_000_000_000_000),
+            token_transfer_amount: AmountRange::new(100, 1_000_000_000_000_000_000),
+            weth_deposit_amount: AmountRange::new(1, 1_000_000_000_000_000),
+            swap_eth_amount: AmountRange::new(100, 1_000_000_000_000_000_000),
+            swap_token_amount: AmountRange::new(100, 1_000_000_000_000_000_000),
+            max_swap_reserve_ratio: 0.0001,
+            sender_selection: SenderSelection::default(),
+            max_txs_per_sender_per_batch: 0,
+            max_txs_per_sender_per_block: 0,
+            bundler_mode: None,
+            hot_tokens: None,
+            fee_on_transfer: None,
+            calibrated_gas_limits: BTreeMap::new(),
+            allow_self_transfer: true,
+            parallel_lanes: 1,
+            db_commit_interval: 1,
+            deploy_via: DeployVia::default(),
+            token_deployers: 0,
+            verify_senders: cfg!(debug_assertions),
+            verify_senders_one_in: 1,
         }
     }
 
+    /// Send multicall load transactions that each forward `calls` token calls.
+    pub fn with_multicall_calls_per_tx(mut self, calls: u64) -> Self {
+        self.multicall_calls_per_tx = calls;
+        self
+    }
+
+    /// Add `count` EIP-2612 permit-based liquidity removals to each mixed batch.
+    pub fn with_permit_removals_per_batch(mut self, count: u64) -> Self {
+        self.permit_removals_per_batch = count;
+        self
+    }
+
+    /// Add `count` deploy, write, and self-destruct lifecycles to each mixed batch.
+    pub fn with_selfdestructs_per_batch(mut self, count: u64) -> Self {
+        self.selfdestructs_per_batch = count;
+        self
+    }
+
+    /// Have actors deploy tokens at `rate` (clamped to `0.0..=1.0`).
+    pub fn with_contract_deploy_rate(mut self, rate: f64) -> Self {
+        self.contract_deploy_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Draw ETH and token transfer amounts from these ranges.
+    pub fn with_transfer_amounts(mut self, eth: AmountRange, token: AmountRange) -> Self {
+        self.eth_transfer_amount = eth;
+        self.token_transfer_amount = token;
+        self
+    }
+
+    /// Draw WETH deposit amounts from `range`.
+    pub fn with_weth_deposit_amount(mut self, range: AmountRange) -> Self {
+        self.weth_deposit_amount = range;
+        self
+    }
+
+    /// Draw swap inputs from these ranges, capping each at `max_reserve_ratio`
+    /// (clamped to `0.0..=1.0`) of the input side's seeded reserve.
+    pub fn with_swap_amounts(
+        mut self,
+        eth: AmountRange,
+        token: AmountRange,
+        max_reserve_ratio: f64,
+    ) -> Self {
+        self.swap_eth_amount = eth;
+        self.swap_token_amount = token;
+        self.max_swap_reserve_ratio = max_reserve_ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Choose load senders with `selection`, at most `max_per_batch` load
+    /// transactions per sender per batch (`0` for no limit).
+    pub fn with_sender_selection(mut self, selection: SenderSelection, max_per_batch: u32) -> Self {
+        self.sender_selection = selection;
+        self.max_txs_per_sender_per_batch = max_per_batch;
+        self
+    }
+
+    /// Include at most `max` transactions per sender in each block (`0` for
+    /// no limit).
+    pub fn with_max_txs_per_sender_per_block(mut self, max: u32) -> Self {
+        self.max_txs_per_sender_per_block = max;
+        self
+    }
+
+    /// Turn the first actors into bundlers, if `mode` is set.
+    pub fn with_bundler_mode(mut self, mode: Option<BundlerMode>) -> Self {
+        self.bundler_mode = mode;
+        self
+    }
+
+    /// Send most token traffic to the hot set, if `hot_tokens` is set.
+    pub fn with_hot_tokens(mut self, hot_tokens: Option<HotTokens>) -> Self {
+        self.hot_tokens = hot_tokens;
+        self
+    }
+
+    /// Deploy a share of the setup tokens from the fee-on-transfer template,
+    /// if `fee_on_transfer` is set.
+    pub fn with_fee_on_transfer(mut self, fee_on_transfer: Option<FeeOnTransfer>) -> Self {
+        self.fee_on_transfer = fee_on_transfer;
+        self
+    }
+
+    /// Template setup token `index` is deployed from.
+    pub fn token_template(&self, index: u64) -> TokenTemplate {
+        self.fee_on_transfer
+            .map_or(TokenTemplate::Standard, |fee| fee.template(index))
+    }
+
+    /// Send mixed-load transactions with `limits`, keyed by label name;
+    /// labels without an entry keep their default gas limit.
+    pub fn with_calibrated_gas_limits(mut self, limits: BTreeMap<String, u64>) -> Self {
+        self.calibrated_gas_limits = limits;
+        self
+    }
+
+    /// Build the load from `scenario` rather than the workload's generator.
+    pub fn with_scenario(mut self, scenario: Option<Scenario>) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
+    /// Reject settings that would otherwise fail deep into a run, or never
+    /// finish, with a message saying what to change. Checks that depend on the
+    /// chain, like a genesis file's hardfork or balances, run once it is built.
+    pub fn validate(&self) -> Result<(), SandboxError> {
+        let problem = if self.unique_accounts == 0 {
+            Some(
+                "`unique_accounts` is 0, but every transaction after setup is sent by an actor; use at least 1",
+            )
+        } else if self.std_batch_size == 0 {
+            Some(
+                "`std_batch_size` is 0, so the orchestrator would never produce a transaction; use at least 1",
+            )
+        } else if self.channel_buffer_size == 0 {
+            Some(
+                "`channel_buffer_size` is 0, but the transaction channel needs room for at least one transaction",
+            )
+        } else if self.num_of_blocks == Some(0) {
+            Some(
+                "`num_of_blocks` is 0, so the run would stop before building a block; use at least 1, or no block limit",
+            )
+        } else if self.num_of_transactions == Some(0) {
+            Some(
+                "`num_of_transactions` is 0, so the run would stop before building a block; use at least 1, or no transaction limit",
+            )
+        } else if self.max_duration == Some(Duration::ZERO) {
+            Some(
+                "`max_duration` is zero, so the run would stop before building a block; raise it, or set no time limit",
+            )
+        } else if self.top_up_every_blocks == Some(0) {
+            Some("`top_up_every_blocks` is 0; use at least 1, or `None` to never top up")
+        } else if self.top_up_every_blocks.is_some()
+            && self.top_up_threshold >= self.actor_funding_amount
+        {
+            Some(
+                "`top_up_threshold` is not below `actor_funding_amount`, so topped-up actors would stay below it; lower the threshold",
+            )
+        } else if self.replay_tx_stream.is_some() && self.tx_stream_out.is_some() {
+            Some(
+                "`replay_tx_stream` and `tx_stream_out` are both set, but a replay generates nothing new to record; unset one",
+            )
+        } else if self.replay_tx_stream.is_some() && self.stage != Stage::Full {
+            Some(
+                "`replay_tx_stream` feeds a whole recorded run, setup included; it cannot run as a setup or load stage",
+            )
+        } else if self.replay_tx_stream.is_some() && self.scenario.is_some() {
+            Some("`replay_tx_stream` replaces the load a scenario would build; unset one of them")
+        } else if self.priority_fee.max as u128 > FEE_PER_GAS {
+            Some(
+                "`priority_fee` goes above the max fee every transaction is signed with, which caps the tip; lower its max",
+            )
+        } else if self.fee_strategy_mix.total() == 0 {
+            Some(
+                "`fee_strategy_mix` gives every strategy a weight of 0; give at least one a weight",
+            )
+        } else if self.tx_ordering == (TxOrdering::PriorityFee { window: Some(0) }) {
+            Some(
+                "the priority fee ordering window is 0, so no transaction would ever be a candidate; use at least 1",
+            )
+        } else if self.max_output_bytes == Some(0) {
+            Some(
+                "`max_output_bytes` is 0, so the run would stop before building a block; raise it, or set no output limit",
+            )
+        } else if self.max_output_bytes.is_some() && !self.output_format.writes_rlp() {
+            Some(
+                "`max_output_bytes` limits the RLP block file, which `output_format` does not write; write RLP, or set no output limit",
+            )
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(SandboxError::Config(problem.to_string()));
+        }
+        self.check_stage()?;
+        self.check_gas_limit()?;
+        self.check_bundler_mode()?;
+        self.check_hot_tokens()?;
+        self.check_fee_on_transfer()?;
+        // A load stage's actor and token counts come from its setup, so its
+        // scenario is checked once they are read.
+        if self.stage == Stage::Load {
+            return Ok(());
+        }
+        self.check_scenario()
+    }
+
+    /// Reject settings a setup or load stage decides for itself: both keep
+    /// their chain in `setup_dir`, and setup runs until the load would start.
+    pub fn check_stage(&self) -> Result<(), SandboxError> {
+        if self.stage == Stage::Full {
+            return Ok(());
+        }
+        let conflict = if self.setup_dir.is_none() {
+            Some("needs a `setup_dir`")
+        } else if self.in_memory {
+            Some("keeps its chain in a datadir; unset `in_memory`")
+        } else if self.genesis_path.is_some() {
+            Some("takes its genesis from `setup_dir`; unset `genesis_path`")
+        } else if self.stage == Stage::Setup && self.datadir.is_some() {
+            Some("keeps its datadir in `setup_dir`; unset `datadir`")
+        } else if self.stage == Stage::Setup
+            && (self.num_of_blocks.is_some()
+                || self.num_of_transactions.is_some()
+                || self.max_duration.is_some()
+                || self.max_output_bytes.is_some())
+        {
+            Some("runs until setup is done; unset the block, transaction, time, and output limits")
+        } else if self.stage == Stage::Load && self.prefund_actors_in_genesis {
+            Some("reuses the setup's actors; unset `prefund_actors_in_genesis`")
+        } else {
+            None
+        };
+        match conflict {
+            Some(conflict) => Err(SandboxError::Config(format!(
+                "a {} stage {conflict}",
+                self.stage
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject a block gas limit below the gas limit of a transaction the run
+    /// sends, which could never be included.
+    pub fn check_gas_limit(&self) -> Result<(), SandboxError> {
+        // Setup transactions, actor funding included, and the mixed load are
+        // signed with the default gas limit; only a transfers-only run with
+        // pre-funded actors sends nothing but transfers.
+        let largest_tx =
+            if self.prefund_actors_in_genesis && self.workload == Workload::TransfersOnly {
+                TRANSFER_GAS_LIMIT
+            } else {
+                DEFAULT_GAS_LIMIT
+            };
+        let largest_tx = self
+            .calibrated_gas_limits
+            .values()
+            .copied()
+            .fold(largest_tx, u64::max);
+        if self.gas_limit >= largest_tx {
+            return Ok(());
+        }
+        Err(SandboxError::Config(format!(
+            "`gas_limit` is {}, but this run sends transactions with a gas limit of {largest_tx}, \
+             which no block could include; raise `gas_limit` to at least {largest_tx}",
+            self.gas_limit
+        )))
+    }
+
+    /// Reject a scenario the run cannot build block for block: its steps
+    /// must fit the actors, tokens, and gas limit, and nothing else may add
+    /// transactions to its blocks or split them.
+    pub fn check_scenario(&self) -> Result<(), SandboxError> {
+        let Some(scenario) = &self.scenario else {
+            return Ok(());
+        };
+        let conflict = if self.stage == Stage::Setup {
+            Some("a setup stage, which stops before the load")
+        } else if self.invalid_tx_rate > 0.0 {
+            Some("`invalid_tx_rate`")
+        } else if self.top_up_every_blocks.is_some() {
+            Some("`top_up_every_blocks`")
+        } else if self.bundler_mode.is_some() {
+            Some("`bundler_mode`")
+        } else if self.max_txs_per_sender_per_block > 0 {
+            Some("`max_txs_per_sender_per_block`")
+        } else if self.max_block_bytes.is_some() {
+            Some("`max_block_bytes`")
+        } else if self.parallel_lanes > 1 {
+            Some("`parallel_lanes`")
+        } else if self.tx_ordering != TxOrdering::Arrival {
+            Some("a `tx_ordering` other than arrival")
+        } else {
+            None
+        };
+        if let Some(conflict) = conflict {
+            return Err(SandboxError::Config(format!(
+                "a scenario cannot be combined with {conflict}"
+            )));
+        }
+        scenario.check(self)
+    }
+
+    /// Reject a bundler mode the mixed workload cannot honor: bundles move
+    /// setup tokens, and at least one actor must be left to send direct load.
+    pub fn check_bundler_mode(&self) -> Result<(), SandboxError> {
+        let Some(mode) = self.bundler_mode else {
+            return Ok(());
+        };
+        let conflict = if !self.deploys_contracts() {
+            Some("the mixed workload with at least one token")
+        } else if mode.bundlers == 0 || mode.bundle_size == 0 {
+            Some("at least one bundler and one operation per bundle")
+        } else if mode.bundlers as u64 >= self.unique_accounts {
+            Some("fewer bundlers than `unique_accounts`")
+        } else {
+            None
+        };
+        match conflict {
+            Some(needed) => Err(SandboxError::Config(format!("bundler mode needs {needed}"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject a hot token set the mixed workload cannot honor: it picks
+    /// among setup tokens, and needs at least one of them and a traffic
+    /// share that is a percentage.
+    pub fn check_hot_tokens(&self) -> Result<(), SandboxError> {
+        let Some(hot) = self.hot_tokens else {
+            return Ok(());
+        };
+        let conflict = if !self.deploys_contracts() {
+            Some("the mixed workload with at least one token")
+        } else if hot.tokens == 0 {
+            Some("at least one hot token")
+        } else if hot.traffic_percent > 100 {
+            Some("a `traffic_percent` of at most 100")
+        } else {
+            None
+        };
+        match conflict {
+            Some(needed) => Err(SandboxError::Config(format!("hot tokens need {needed}"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject a fee token share that is not a percentage, or a fee that
+    /// would leave nothing of a transfer to arrive.
+    pub fn check_fee_on_transfer(&self) -> Result<(), SandboxError> {
+        let Some(fee) = self.fee_on_transfer else {
+            return Ok(());
+        };
+        let conflict = if fee.token_percent > 100 {
+            Some("a `token_percent` of at most 100")
+        } else if fee.fee_bps >= FEE_BPS_DENOMINATOR {
+            Some("a `fee_bps` below 10000")
+        } else {
+            None
+        };
+        match conflict {
+            Some(needed) => Err(SandboxError::Config(format!(
+                "fee-on-transfer tokens need {needed}"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reject a run whose deployer cannot fund every actor with
+    /// `actor_funding_amount` and pay for the funding transfers. The genesis
+    /// signer holds `genesis_balance`; a separate deployer holds what the
+    /// genesis signer can send it. Nothing is checked when actors are funded
+    /// in genesis or by the setup a load stage runs on.
+    pub fn check_deployer_funding(&self, genesis_balance: U256) -> Result<(), SandboxError> {
+        if self.prefund_actors_in_genesis || self.stage == Stage::Load {
+            return Ok(());
+        }
+        let fee = U256::from(FEE_PER_GAS);
+        let deployer_balance = if self.has_separate_deployer() {
+            // The faucet's one transfer is signed with the default gas limit.
+            let needed = self
+                .deployer_funding_amount
+                .checked_add(U256::from(DEFAULT_GAS_LIMIT) * fee);
+            if !needed.is_some_and(|needed| genesis_balance >= needed) {
+                return Err(SandboxError::Config(format!(
+                    "the genesis signer {} holds {genesis_balance} wei, too little to send the \
+                     deployer {} its `deployer_funding_amount` of {} wei and pay for the transfer",
+                    self.genesis_address(),
+                    self.deployer_address(),
+                    self.deployer_funding_amount,
+                )));
+            }
+            self.deployer_funding_amount
+        } else {
+            genesis_balance
+        };
+        let accounts = U256::from(self.unique_accounts);
+        let per_actor = self
+            .actor_funding_amount
+            .checked_add(U256::from(TRANSFER_GAS_LIMIT) * fee);
+        // Each funding transfer is signed with the default gas limit, which the
+        // deployer must be able to cover up front even though only a
+        // transfer's worth of gas is spent.
+        let upfront = U256::from(DEFAULT_GAS_LIMIT) * fee;
+        let needed = per_actor
+            .and_then(|per_actor| per_actor.checked_mul(accounts))
+            .and_then(|needed| needed.checked_add(upfront));
+        if needed.is_some_and(|needed| deployer_balance >= needed) {
+            return Ok(());
+        }
+        Err(SandboxError::Config(format!(
+            "the deployer {} holds {deployer_balance} wei, but funding {} actors with {} wei \
+             each needs {} wei including gas; lower `actor_funding_amount` or \
+             `unique_accounts`",
+            self.deployer_address(),
+            self.unique_accounts,
+            self.actor_funding_amount,
+            needed.map_or_else(
+                || "more than U256::MAX".to_string(),
+                |needed| needed.to_string()
+            ),
+        )))
+    }
+
+    /// Allow or forbid transfers from an actor to itself.
+    pub fn with_allow_self_transfer(mut self, allow: bool) -> Self {
+        self.allow_self_transfer = allow;
+        self
+    }
+
+    /// Build blocks in `lanes` parallel lanes (at least 1).
+    pub fn with_parallel_lanes(mut self, lanes: usize) -> Self {
+        self.parallel_lanes = lanes.max(1);
+        self
+    }
+
+    /// Commit sealed blocks to the database every `interval` blocks (at
+    /// least 1).
+    pub fn with_db_commit_interval(mut self, interval: u64) -> Self {
+        self.db_commit_interval = interval.max(1);
+        self
+    }
+
+    /// Reject settings parallel lanes cannot honor. Lanes do not see each
+    /// other's writes, so they need a workload where no two transactions
+    /// touch the same account.
+    pub fn check_parallel_lanes(&self) -> Result<(), SandboxError> {
+        if self.parallel_lanes <= 1 {
+            return Ok(());
+        }
+        let conflict = if self.workload != Workload::TransfersOnly {
+            Some(
+                "the transfers-only workload, the only one whose transactions never share accounts",
+            )
+        } else if self.invalid_tx_rate > 0.0 {
+            Some("no invalid transaction injection")
+        } else if self.hardfork.is_prague_active() {
+            Some("a pre-Prague hardfork, since lanes skip post-execution system calls")
+        } else if self.max_block_bytes.is_some() {
+            Some("no `max_block_bytes`")
+        } else if self.max_txs_per_sender_per_block > 0 {
+            Some("no `max_txs_per_sender_per_block`")
+        } else if self.tx_ordering != TxOrdering::Arrival {
+            Some("arrival `tx_ordering`")
+        } else if self.block_fees_out.is_some() {
+            Some("no `block_fees_out`")
+        } else {
+            None
+        };
+        match conflict {
+            Some(needed) => Err(SandboxError::Config(format!(
+                "{} parallel lanes need {needed}",
+                self.parallel_lanes
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Inject invalid transactions at `rate` (clamped to `0.0..=1.0`).
+    pub fn with_invalid_tx_rate(mut self, rate: f64) -> Self {
+        self.invalid_tx_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Attempt transactions failing on ordering up to `retries` more times.
+    pub fn with_max_tx_retries(mut self, retries: u32) -> Self {
+        self.max_tx_retries = retries;
+        self
+    }
+
+    /// Check up to `sample` accounts per setup phase once it is done.
+    pub fn with_phase_check_sample(mut self, sample: usize) -> Self {
+        self.phase_check_sample = sample;
+        self
+    }
+
+    /// Advance block timestamps by `secs` per block.
+    pub fn with_block_time_secs(mut self, secs: u64) -> Self {
+        self.block_time_secs = secs;
+        self
+    }
+
+    /// Stamp the generated genesis block with `timestamp`.
+    pub fn with_genesis_timestamp(mut self, timestamp: u64) -> Self {
+        self.genesis_timestamp = timestamp;
+        self
+    }
+
+    /// Seconds added to the parent timestamp for each block, at least 1 since
+    /// timestamps must strictly increase.
+    pub fn block_interval(&self) -> u64 {
+        self.block_time_secs.max(1)
+    }
+
+    /// Recover and check the sender of one in `one_in` generated transactions
+    /// (clamped to at least 1) when `enabled`.
+    pub fn with_verify_senders(mut self, enabled: bool, one_in: u64) -> Self {
+        self.verify_senders = enabled;
+        self.verify_senders_one_in = one_in.max(1);
+        self
+    }
+
+    /// Deploy setup tokens from the first `count` actors instead of the
+    /// genesis deployer. Capped at `unique_accounts`.
+    pub fn with_token_deployers(mut self, count: u64) -> Self {
+        self.token_deployers = count.min(self.unique_accounts);
+        self
+    }
+
+    /// Deploy setup tokens via `deploy_via`.
+    pub fn with_deploy_via(mut self, deploy_via: DeployVia) -> Self {
+        self.deploy_via = deploy_via;
+        self
+    }
+
+    /// Credit priority fees to `recipient`.
+    pub fn with_fee_recipient(mut self, recipient: FeeRecipient) -> Self {
+        self.fee_recipient = recipient;
+        self
+    }
+
+    /// Include transactions in `ordering`.
+    pub fn with_tx_ordering(mut self, ordering: TxOrdering) -> Self {
+        self.tx_ordering = ordering;
+        self
+    }
+
+    /// Have actors offer priority fees within `range`.
+    pub fn with_priority_fee(mut self, range: AmountRange) -> Self {
+        self.priority_fee = range;
+        self
+    }
+
+    /// Give generated actors fee strategies in the proportions of `mix`.
+    pub fn with_fee_strategy_mix(mut self, mix: FeeStrategyMix) -> Self {
+        self.fee_strategy_mix = mix;
+        self
+    }
+
+    /// PREVRANDAO for `block_number`: `keccak256(actor_seed || block_number)`,
+    /// so it is non-zero and reproducible for a seeded run.
+    pub fn prev_randao(&self, block_number: u64) -> B256 {
+        let seed = self.actor_seed.unwrap_or_default();
+        keccak256([seed.as_slice(), &block_number.to_be_bytes()].concat())
+    }
+
+    /// Seal blocks according to `strategy`.
+    pub fn with_fill_strategy(mut self, strategy: FillStrategy) -> Self {
+        self.fill_strategy = strategy;
+        self
+    }
+
+    /// Make up each block's gas shortfall in the next one, so the base fee
+    /// holds steady over long runs.
+    pub fn with_hold_base_fee(mut self, enabled: bool) -> Self {
+        self.hold_base_fee = enabled;
+        self
+    }
+
+    /// Gas after which the builder always seals a block: the strategy's gas
+    /// target, or the full gas limit when it has none.
+    pub fn block_gas_target(&self) -> u64 {
+        let percent = self.fill_strategy.gas_target_percent().unwrap_or(100);
+        (self.gas_limit as u128 * percent as u128 / 100) as u64
+    }
+
+    /// Cap the transaction bytes in each block.
+    pub fn with_max_block_bytes(mut self, max_block_bytes: Option<u64>) -> Self {
+        self.max_block_bytes = max_block_bytes;
+        self
+    }
+
+    /// Stop once the block file reaches `max_output_bytes`.
+    pub fn with_max_output_bytes(mut self, max_output_bytes: Option<u64>) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Stop building after `duration` of wall time.
+    pub fn with_max_duration(mut self, duration: Option<Duration>) -> Self {
+        self.max_duration = duration;
+        self
+    }
+
+    /// Run only `stage` of the pipeline, keeping its artifacts in
+    /// `setup_dir`. A full run needs no directory.
+    pub fn with_stage(mut self, stage: Stage, setup_dir: Option<PathBuf>) -> Self {
+        self.stage = stage;
+        self.setup_dir = setup_dir;
+        self
+    }
+
+    /// Treat run limits as `mode` limits.
+    pub fn with_limit_mode(mut self, mode: LimitMode) -> Self {
+        self.limit_mode = mode;
+        self
+    }
+
+    /// Keep the Reth datadir at `path` instead of a temporary directory.
+    pub fn with_datadir(mut self, path: Option<PathBuf>) -> Self {
+        self.datadir = path;
+        self
+    }
+
+    /// Run against reth's test database instead of a datadir. Conflicts with
+    /// `datadir`, and `db_stats` has nothing to report.
+    pub fn with_in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Label the run in its manifest.
+    pub fn with_tag(mut self, tag: Option<String>) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Every knob as JSON for the run manifest. The genesis private key is
+    /// replaced by its keccak256 hash so manifests can be shared.
+    pub fn to_json(&self) -> Value {
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|path| path.display().to_string());
+        json!({
+            "chain_id": self.chain_id,
+            "num_of_blocks": self.num_of_blocks,
+            "num_of_transactions": self.num_of_transactions,
+            "unique_accounts": self.unique_accounts,
+            "unique_tokens": self.unique_tokens,
+            "gas_limit": self.gas_limit,
+            "genesis_private_key_hash": keccak256(self.genesis_signer.to_bytes()).to_string(),
+            "genesis_address": self.genesis_address().to_string(),
+            "deployer_address": self.deployer_address().to_string(),
+            "deployer_funding_amount": self.deployer_funding_amount.to_string(),
+            "uniswap_owner": self.uniswap_owner.as_ref().map(|owner| owner.address().to_string()),
+            "std_batch_size": self.std_batch_size,
+            "actor_seed": self.actor_seed.map(|seed| seed.to_string()),
+            "actors_export_path": path(&self.actors_export_path),
+            "token_initial_supply": self.token_initial_supply.to_string(),
+            "workload": self.workload.to_string(),
+            "scenario": self.scenario.as_ref().map(Scenario::to_json),
+            "channel_buffer_size": self.channel_buffer_size,
+            "channel_sample_interval_ms": self.channel_sample_interval_ms,
+            "adaptive_batch_size": self.adaptive_batch_size,
+            "progress_interval_secs": self.progress_interval_secs,
+            "progress_bar": self.progress_bar,
+            "rss_warn_mib": self.rss_warn_mib,
+            "metrics": self.metrics,
+            "trace_out": path(&self.trace_out),
+            "trace_failed_txs": self.trace_failed_txs,
+            "balance_report": self.balance_report,
+            "pool_report": self.pool_report,
+            "dump_state_diffs": self.dump_state_diffs,
+            "db_stats": self.db_stats,
+            "hardfork": self.hardfork.to_string(),
+            "actor_funding_amount": self.actor_funding_amount.to_string(),
+            "prefund_actors_in_genesis": self.prefund_actors_in_genesis,
+            "top_up_every_blocks": self.top_up_every_blocks,
+            "top_up_threshold": self.top_up_threshold.to_string(),
+            "extra_genesis_accounts": self
+                .extra_genesis_accounts
+                .iter()
+                .map(|(address, balance)| json!({
+                    "address": address.to_string(),
+                    "balance": balance.to_string(),
+                }))
+                .collect::<Vec<Value>>(),
+            "genesis_path": path(&self.genesis_path),
+            "deployer_nonce": self.deployer_nonce,
+            "faucet_nonce": self.faucet_nonce,
+            "genesis_out": path(&self.genesis_out),
+            "blocks_out": self.blocks_out.display().to_string(),
+            "block_file_rotation": self.block_file_rotation.to_string(),
+            "output_format": self.output_format.to_string(),
+            "blocks_jsonl_out": self.blocks_jsonl_out.display().to_string(),
+            "roots_out": self.roots_out.display().to_string(),
+            "block_labels_out": self.block_labels_out.as_ref().map(|path| path.display().to_string()),
+            "txs_out": path(&self.txs_out),
+            "payloads_out": path(&self.payloads_out),
+            "block_fees_out": path(&self.block_fees_out),
+            "tx_stream_out": path(&self.tx_stream_out),
+            "replay_tx_stream": path(&self.replay_tx_stream),
+            "tag": self.tag,
+            "datadir": path(&self.datadir),
+            "in_memory": self.in_memory,
+            "stage": self.stage.to_string(),
+            "setup_dir": path(&self.setup_dir),
+            "max_duration_secs": self.max_duration.map(|duration| duration.as_secs_f64()),
+            "limit_mode": self.limit_mode.to_string(),
+            "fill_strategy": self.fill_strategy.to_string(),
+            "hold_base_fee": self.hold_base_fee,
+            "tx_ordering": self.tx_ordering.to_string(),
+            "priority_fee": self.priority_fee.to_json(),
+            "fee_strategy_mix": self.fee_strategy_mix.to_json(),
+            "max_block_bytes": self.max_block_bytes,
+            "max_output_bytes": self.max_output_bytes,
+            "block_time_secs": self.block_time_secs,
+            "genesis_timestamp": self.genesis_timestamp,
+            "invalid_tx_rate": self.invalid_tx_rate,
+            "max_tx_retries": self.max_tx_retries,
+            "phase_check_sample": self.phase_check_sample,
+            "permit_removals_per_batch": self.permit_removals_per_batch,
+            "selfdestructs_per_batch": self.selfdestructs_per_batch,
+            "multicall_calls_per_tx": self.multicall_calls_per_tx,
+            "contract_deploy_rate": self.contract_deploy_rate,
+            "eth_transfer_amount": self.eth_transfer_amount.to_json(),
+            "token_transfer_amount": self.token_transfer_amount.to_json(),
+            "weth_deposit_amount": self.weth_deposit_amount.to_json(),
+            "swap_eth_amount": self.swap_eth_amount.to_json(),
+            "swap_token_amount": self.swap_token_amount.to_json(),
+            "max_swap_reserve_ratio": self.max_swap_reserve_ratio,
+            "sender_selection": self.sender_selection.to_string(),
+            "max_txs_per_sender_per_batch": self.max_txs_per_sender_per_batch,
+            "max_txs_per_sender_per_block": self.max_txs_per_sender_per_block,
+            "bundler_mode": self.bundler_mode.map(|mode| mode.to_json()),
+            "hot_tokens": self.hot_tokens.map(|hot| hot.to_json()),
+            "fee_on_transfer": self.fee_on_transfer.map(|fee| fee.to_json()),
+            "calibrated_gas_limits": self.calibrated_gas_limits,
+            "allow_self_transfer": self.allow_self_transfer,
+            "parallel_lanes": self.parallel_lanes,
+            "db_commit_interval": self.db_commit_interval,
+            "deploy_via": self.deploy_via.to_string(),
+            "token_deployers": self.token_deployers,
+            "verify_senders": self.verify_senders,
+            "verify_senders_one_in": self.verify_senders_one_in,
+            "fee_recipient": match &self.fee_recipient {
+                FeeRecipient::Fixed(address) => vec![address.to_string()],
+                FeeRecipient::Rotate(addresses) => {
+                    addresses.iter().map(Address::to_string).collect()
+                }
+            },
+        })
+    }
+
+    /// Write the generated genesis JSON to `path`.
+    pub fn with_genesis_out(mut self, path: Option<PathBuf>) -> Self {
+        self.genesis_out = path;
+        self
+    }
+
+    /// Write RLP-encoded blocks to `path`.
+    pub fn with_blocks_out(mut self, path: PathBuf) -> Self {
+        self.blocks_out = path;
+        self
+    }
+
+    /// Write `format` block files, the JSONL one to `jsonl_path`.
+    pub fn with_output_format(mut self, format: OutputFormat, jsonl_path: PathBuf) -> Self {
+        self.output_format = format;
+        self.blocks_jsonl_out = jsonl_path;
+        self
+    }
+
+    /// Split the block file into segments according to `rotation`.
+    pub fn with_block_file_rotation(mut self, rotation: Rotation) -> Self {
+        self.block_file_rotation = rotation;
+        self
+    }
+
+    /// Write each block's hash, state root, receipts root, and gas used to
+    /// `path`.
+    pub fn with_roots_out(mut self, path: PathBuf) -> Self {
+        self.roots_out = path;
+        self
+    }
+
+    /// Write per-block, per-label counts and gas to `path`, if set.
+    pub fn with_block_labels_out(mut self, path: Option<PathBuf>) -> Self {
+        self.block_labels_out = path;
+        self
+    }
+
+    /// Write every included transaction to the transaction file at `path`,
+    /// if set.
+    pub fn with_txs_out(mut self, path: Option<PathBuf>) -> Self {
+        self.txs_out = path;
+        self
+    }
+
+    /// Write an Engine API `newPayload` request per block to `path`, if set.
+    pub fn with_payloads_out(mut self, path: Option<PathBuf>) -> Self {
+        self.payloads_out = path;
+        self
+    }
+
+    /// Write each block's first and last included tip to `path`, if set.
+    pub fn with_block_fees_out(mut self, path: Option<PathBuf>) -> Self {
+        self.block_fees_out = path;
+        self
+    }
+
+    /// Record every transaction sent into the channel to `path`.
+    pub fn with_tx_stream_out(mut self, path: Option<PathBuf>) -> Self {
+        self.tx_stream_out = path;
+        self
+    }
+
+    /// Replay the transaction stream recorded at `path` instead of
+    /// generating transactions.
+    pub fn with_replay_tx_stream(mut self, path: Option<PathBuf>) -> Self {
+        self.replay_tx_stream = path;
+        self
+    }
+
+    /// Build on top of the genesis at `path` instead of a generated one.
+    pub fn with_genesis_path(mut self, path: Option<PathBuf>) -> Self {
+        self.genesis_path = path;
+        self
+    }
+
+    /// Start the deployer at `nonce`, for a chain where it has already sent
+    /// transactions.
+    pub fn with_deployer_nonce(mut self, nonce: u64) -> Self {
+        self.deployer_nonce = nonce;
+        self
+    }
+
+    /// Fund the actors and deploy the setup contracts from `signer`, funded
+    /// by the genesis signer, instead of from the genesis signer itself.
+    pub fn with_deployer_signer(mut self, signer: Option<PrivateKeySigner>) -> Self {
+        self.deployer_signer = signer;
+        self
+    }
+
+    /// Send a separate deployer `amount` wei from the genesis signer.
+    pub fn with_deployer_funding_amount(mut self, amount: U256) -> Self {
+        self.deployer_funding_amount = amount;
+        self
+    }
+
+    /// Set the Uniswap factory's `feeToSetter` to `owner`'s address.
+    pub fn with_uniswap_owner(mut self, owner: Option<PrivateKeySigner>) -> Self {
+        self.uniswap_owner = owner;
+        self
+    }
+
+    /// Fund every actor with `amount` wei.
+    pub fn with_actor_funding_amount(mut self, amount: U256) -> Self {
+        self.actor_funding_amount = amount;
+        self
+    }
+
+    /// Allocate actor balances in genesis instead of sending funding transactions.
+    pub fn with_prefund_actors_in_genesis(mut self, enabled: bool) -> Self {
+        self.prefund_actors_in_genesis = enabled;
+        self
+    }
+
+    /// Check actor balances every `blocks` blocks and top up those running low.
+    pub fn with_top_up_every_blocks(mut self, blocks: Option<u64>) -> Self {
+        self.top_up_every_blocks = blocks;
+        self
+    }
+
+    /// Top up actors whose estimated balance falls below `threshold` wei.
+    pub fn with_top_up_threshold(mut self, threshold: U256) -> Self {
+        self.top_up_threshold = threshold;
+        self
+    }
+
+    /// Pre-fund additional accounts in genesis.
+    pub fn with_extra_genesis_accounts(mut self, accounts: Vec<(Address, U256)>) -> Self {
+        self.extra_genesis_accounts = accounts;
+        self
+    }
+
+    /// Address of [`Self::genesis_signer`].
+    pub fn genesis_address(&self) -> Address {
+        self.genesis_signer.address()
+    }
+
+    /// Signer of every setup transaction: [`Self::deployer_signer`], or the
+    /// genesis signer without one.
+    pub fn deployer_signer(&self) -> &PrivateKeySigner {
+        self.deployer_signer
+            .as_ref()
+            .unwrap_or(&self.genesis_signer)
+    }
+
+    /// Address of [`Self::deployer_signer`].
+    pub fn deployer_address(&self) -> Address {
+        self.deployer_signer().address()
+    }
+
+    /// Whether the deployer is an account apart from the genesis allocation
+    /// holder, which then has to fund it first.
+    pub fn has_separate_deployer(&self) -> bool {
+        self.deployer_address() != self.genesis_address()
+    }
+
+    /// Every (address, balance) pair the genesis alloc should contain: the
+    /// deployer with `U256::MAX`, any extra accounts, and `actor_addresses` at
+    /// `actor_funding_amount` when actors are pre-funded.
+    pub fn genesis_alloc(&self, actor_addresses: &[Address]) -> Vec<(Address, U256)> {
+        let mut alloc =
+            Vec::with_capacity(1 + self.extra_genesis_accounts.len() + actor_addresses.len());
+        alloc.push((self.genesis_address(), U256::MAX));
+        alloc.extend(self.extra_genesis_accounts.iter().copied());
+        if self.prefund_actors_in_genesis {
+            alloc.extend(
+                actor_addresses
+                    .iter()
+                    .map(|address| (*address, self.actor_funding_amount)),
+            );
+        }
+        alloc
+    }
+
+    /// Activate every fork up to `hardfork` at genesis.
+    pub fn with_hardfork(mut self, hardfork: Hardfork) -> Self {
+        self.hardfork = hardfork;
+        self
+    }
+
+    /// Print database table sizes at the end of the run.
+    pub fn with_db_stats(mut self, enabled: bool) -> Self {
+        self.db_stats = enabled;
+        self
+    }
+
+    /// Dump what each block changed to `state_diffs/<block>.json`.
+    pub fn with_dump_state_diffs(mut self, enabled: bool) -> Self {
+        self.dump_state_diffs = enabled;
+        self
+    }
+
+    /// Dump final Uniswap pair reserves to `pools.csv`.
+    pub fn with_pool_report(mut self, enabled: bool) -> Self {
+        self.pool_report = enabled;
+        self
+    }
+
+    /// Dump final actor balances and nonces to `balances.csv`, and token
+    /// holdings to `token_holdings/`.
+    pub fn with_balance_report(mut self, enabled: bool) -> Self {
+        self.balance_report = enabled;
+        self
+    }
+
+    /// Trace every failed transaction into `failed_traces/`.
+    pub fn with_trace_failed_txs(mut self, enabled: bool) -> Self {
+        self.trace_failed_txs = enabled;
+        self
+    }
+
+    /// Time sections for the summary table. Disabling it makes every timer a
+    /// no-op, and leaves a Chrome trace empty.
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+
+    /// Record every timed section and dump a Chrome trace to `path` at exit.
+    pub fn with_trace_out(mut self, path: Option<PathBuf>) -> Self {
+        self.trace_out = path;
+        self
+    }
+
+    /// Log a progress heartbeat every `interval_secs` seconds (`0` disables).
+    pub fn with_progress_interval_secs(mut self, interval_secs: u64) -> Self {
+        self.progress_interval_secs = interval_secs;
+        self
+    }
+
+    /// Draw a progress bar on stdout, when it is a terminal.
+    pub fn with_progress_bar(mut self, enabled: bool) -> Self {
+        self.progress_bar = enabled;
+        self
+    }
+
+    /// Warn once resident memory reaches `mib` MiB (`None` disables).
+    pub fn with_rss_warn_mib(mut self, mib: Option<u64>) -> Self {
+        self.rss_warn_mib = mib;
+        self
+    }
+
+    /// Size the transaction channel buffer.
+    pub fn with_channel_buffer_size(mut self, size: usize) -> Self {
+        self.channel_buffer_size = size;
+        self
+    }
+
+    /// Sample the channel depth every `interval_ms` milliseconds (`0` disables).
+    pub fn with_channel_sample_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.channel_sample_interval_ms = interval_ms;
+        self
+    }
+
+    /// Let the orchestrator resize its batches based on channel depth.
+    pub fn with_adaptive_batch_size(mut self, enabled: bool) -> Self {
+        self.adaptive_batch_size = enabled;
+        self
+    }
+
+    /// Select the load-phase workload.
+    pub fn with_workload(mut self, workload: Workload) -> Self {
+        self.workload = workload;
+        self
+    }
+
+    /// Whether the setup phases deploy tokens and Uniswap before the load phase.
+    pub fn deploys_contracts(&self) -> bool {
+        self.workload == Workload::Mixed && self.unique_tokens > 0
+    }
+
+    /// Derive actor keys and the generator's random choices from `seed`
+    /// instead of fresh entropy, making the run reproducible.
+    pub fn with_actor_seed(mut self, seed: Option<B256>) -> Self {
+        self.actor_seed = seed;
+        self
+    }
+
+    /// Mint `supply` to the deployer in every token constructor.
+    pub fn with_token_initial_supply(mut self, supply: U256) -> Self {
+        self.token_initial_supply = supply;
+        self
+    }
+
+    /// Export actor keys and nonces to `path` when the run finishes.
+    pub fn with_actors_export_path(mut self, path: Option<PathBuf>) -> Self {
+        self.actors_export_path = path;
+        self
+    }
+
     pub fn max_blocks(&self) -> Option<u64> {
         self.num_of_blocks
     }
@@ -61,31 +2098,65 @@ impl SimulationConfig {
         self.num_of_transactions
     }
 
-    /// Optional total gas budget: gas_limit * num_blocks, if num_blocks is set.
+    /// Optional total gas budget: the per-block fill target times num_blocks,
+    /// if num_blocks is set.
     pub fn gas_budget(&self) -> Option<u64> {
         self.num_of_blocks
-            .map(|blocks| self.gas_limit.saturating_mul(blocks))
+            .map(|blocks| self.block_gas_target().saturating_mul(blocks))
     }
 
-    pub fn limits_hit(&self, blocks: u64, txs: u64, gas_used: u64) -> bool {
+    /// The first limit reached by a run that has built `blocks` blocks with
+    /// `txs` transactions and `gas_used` gas over `elapsed`, writing
+    /// `output_bytes` to the block file, if any.
+    pub fn limit_hit(
+        &self,
+        blocks: u64,
+        txs: u64,
+        gas_used: u64,
+        elapsed: Duration,
+        output_bytes: u64,
+    ) -> Option<StopReason> {
         if let Some(max_blocks) = self.max_blocks() {
             if blocks >= max_blocks {
-                return true;
+                return Some(StopReason::Blocks);
             }
         }
 
-        if let Some(max_txs) = self.max_transactions() {
-            if txs >= max_txs {
-                return true;
-            }
+        if let Some(reason) = self.volume_limit_hit(txs, gas_used) {
+            return Some(reason);
         }
 
-        if let Some(budget) = self.gas_budget() {
-            if gas_used >= budget {
-                return true;
-            }
+        if self.deadline_passed(elapsed) {
+            return Some(StopReason::Time);
         }
 
-        false
+        if self.max_output_bytes.is_some_and(|max| output_bytes >= max) {
+            return Some(StopReason::OutputSize);
+        }
+
+        None
+    }
+
+    /// The transaction-count or gas-budget limit reached by `txs`
+    /// transactions using `gas_used` gas, if any. Unlike the block count,
+    /// either can be reached part-way through a block.
+    pub fn volume_limit_hit(&self, txs: u64, gas_used: u64) -> Option<StopReason> {
+        if self
+            .max_transactions()
+            .is_some_and(|max_txs| txs >= max_txs)
+        {
+            return Some(StopReason::Transactions);
+        }
+
+        if self.gas_budget().is_some_and(|budget| gas_used >= budget) {
+            return Some(StopReason::Gas);
+        }
+
+        None
+    }
+
+    /// Whether `elapsed` has reached `max_duration`.
+    pub fn deadline_passed(&self, elapsed: Duration) -> bool {
+        self.max_duration.is_some_and(|max| elapsed >= max)
     }
 }
@@ -0,0 +1,85 @@
+//! CREATE2 factory used to deploy contracts at salt-derived addresses that do
+//! not depend on the deployer's nonce.
+//!
+//! `Create2Deployer` is the runtime of the widely used deterministic
+//! deployment proxy behind a minimal constructor. It takes packed calldata,
+//! `salt (32 bytes) || init_code`, runs `CREATE2` with the call value, returns
+//! the new 20-byte address, and reverts if creation fails.
+
+use alloy_primitives::{Address, B256, Bytes};
+use alloy_sol_macro::sol;
+
+sol!(
+    #[allow(missing_docs)]
+    Create2Deployer,
+    "artifacts/Create2Deployer.json"
+);
+
+/// Encoders for deploying and calling [`Create2Deployer`].
+pub struct Create2DeployerHelper;
+
+impl Create2DeployerHelper {
+    /// Creation bytecode.
+    pub fn deploy() -> Bytes {
+        Create2Deployer::BYTECODE.clone()
+    }
+
+    /// Calldata that deploys `init_code` under `salt`.
+    pub fn deploy_call(salt: B256, init_code: &[u8]) -> Bytes {
+        let mut out = Vec::with_capacity(32 + init_code.len());
+        out.extend_from_slice(salt.as_slice());
+        out.extend_from_slice(init_code);
+        out.into()
+    }
+}
+
+/// Address `deployer` creates for `init_code_hash` under `salt`.
+pub fn create2_address(deployer: Address, salt: B256, init_code_hash: B256) -> Address {
+    deployer.create2(salt, init_code_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, b256, bytes, keccak256};
+
+    use super::*;
+
+    #[test]
+    fn addresses_match_the_eip_1014_examples() {
+        let cases = [
+            (
+                Address::ZERO,
+                B256::ZERO,
+                bytes!("00"),
+                address!("0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38"),
+            ),
+            (
+                address!("0xdeadbeef00000000000000000000000000000000"),
+                B256::ZERO,
+                bytes!("00"),
+                address!("0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3"),
+            ),
+            (
+                address!("0x00000000000000000000000000000000deadbeef"),
+                b256!("0x00000000000000000000000000000000000000000000000000000000cafebabe"),
+                bytes!("deadbeef"),
+                address!("0x60f3f640a8508fC6a86d45DF051962668E1e8AC7"),
+            ),
+        ];
+        for (deployer, salt, init_code, expected) in cases {
+            assert_eq!(
+                create2_address(deployer, salt, keccak256(&init_code)),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn deploy_call_is_the_salt_then_the_init_code() {
+        let salt = B256::repeat_byte(0xab);
+        let call = Create2DeployerHelper::deploy_call(salt, &[1, 2, 3]);
+        assert_eq!(call.len(), 35);
+        assert_eq!(&call[..32], salt.as_slice());
+        assert_eq!(&call[32..], &[1, 2, 3]);
+    }
+}
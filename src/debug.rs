@@ -1,17 +1,43 @@
 //! Quick inspection helpers invoked while iterating on the sandbox.
 
-use alloy_primitives::{Address, map::HashMap};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alloy_consensus::{Header, Transaction, constants::KECCAK_EMPTY};
+use alloy_primitives::{Address, B256, Bytes, U256, hex, map::HashMap};
+use alloy_sol_types::SolCall;
 use reth_db::cursor::{DbCursorRO, DbDupCursorRO};
-use reth_db::{tables, transaction::DbTx};
+use reth_db::{Database, DatabaseEnv, Tables, tables, transaction::DbTx};
+use reth_evm::{ConfigureEvm, Evm, EvmEnvFor};
+use reth_node_ethereum::EthEvmConfig;
 use reth_provider::{DBProvider, StateProvider};
-use tracing::info;
+use reth_revm::{
+    database::StateProviderDatabase, db::BundleState, revm::context::result::ExecutionResult,
+};
+use serde_json::{Map, Value, json};
+use tracing::{info, warn};
+
+use crate::{
+    actor::ActorPool,
+    deployments::DeploymentManifest,
+    logging,
+    orchestrator::TX,
+    revert,
+    stats::TokenTraffic,
+    token::{SandboxToken, SandboxTokenHelper},
+    uniswap::UniswapV2Pair,
+};
 
 /// Log the account metadata for the provided address.
 pub fn get_basic_account_info(state_provider: &dyn StateProvider, address: Address) {
     let account = state_provider.basic_account(&address).unwrap();
     let Some(account) = account else {
         info!(
-            target: "sandbox::debug",
+            target: logging::DEBUG,
             "Account not found: {}",
             address
         );
@@ -19,7 +45,7 @@ pub fn get_basic_account_info(state_provider: &dyn StateProvider, address: Addre
     };
 
     info!(
-        target: "sandbox::debug",
+        target: logging::DEBUG,
         "Account info: {:?}",
         account,
     );
@@ -34,7 +60,7 @@ pub fn get_contract_storage(
     let account = state_provider.basic_account(&contract).unwrap();
     let Some(account) = account else {
         info!(
-            target: "sandbox::debug",
+            target: logging::DEBUG,
             "Contract not found: {}",
             contract
         );
@@ -42,7 +68,7 @@ pub fn get_contract_storage(
     };
 
     info!(
-        target: "sandbox::debug",
+        target: logging::DEBUG,
         "Contract info: {:?}",
         account,
     );
@@ -62,7 +88,7 @@ pub fn get_contract_storage(
     }
 
     info!(
-        target: "sandbox::debug",
+        target: logging::DEBUG,
         "Storage slots: {:?}",
         storage.len(),
     );
@@ -70,10 +96,635 @@ pub fn get_contract_storage(
     //Pretty print the storage
     for (key, value) in storage.iter() {
         info!(
-            target: "sandbox::debug",
+            target: logging::DEBUG,
             "{}: {}",
             key,
             value
         );
     }
 }
+
+/// Write a structured record of a failed transaction to
+/// `<dir>/<block>_<index>.json`: sender, nonce, target, selector, gas, calldata,
+/// whatever the execution result carried (revert output or halt reason), and
+/// the `call_tree` it was traced with.
+pub fn write_failed_transaction<H: std::fmt::Debug>(
+    dir: &Path,
+    block: u64,
+    index: u64,
+    tx: &TX,
+    result: &ExecutionResult<H>,
+    call_tree: Value,
+) -> eyre::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let input = tx.input();
+    let output = result.output();
+    let record = json!({
+        "block": block,
+        "index": index,
+        "hash": tx.hash().to_string(),
+        "from": tx.signer().to_string(),
+        "nonce": tx.nonce(),
+        "to": tx.to().map(|to| to.to_string()),
+        "value": tx.value().to_string(),
+        "selector": (input.len() >= 4).then(|| hex::encode_prefixed(&input[..4])),
+        "gas_limit": tx.gas_limit(),
+        "gas_used": result.gas_used(),
+        "input": hex::encode_prefixed(input),
+        "output": output.map(hex::encode_prefixed),
+        "revert_reason": output.map(|output| revert::revert_reason(output)),
+        "result": format!("{result:?}"),
+        "call_tree": call_tree,
+    });
+
+    let path = dir.join(format!("{block}_{index}.json"));
+    fs::write(&path, serde_json::to_string_pretty(&record)?)?;
+    info!(
+        target: logging::DEBUG,
+        path = %path.display(),
+        "wrote failed transaction trace"
+    );
+    Ok(())
+}
+
+/// Aggregates printed alongside `balances.csv`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BalanceReport {
+    /// Sum of ETH held by pool actors (excludes the deployer and contracts).
+    pub total_actor_balance: U256,
+    /// Actors whose on-chain nonce differs from the nonce the pool tracked.
+    pub nonce_mismatches: u64,
+    /// Rows whose account does not exist in state.
+    pub missing_accounts: u64,
+}
+
+/// Write the final ETH balance and nonce of every actor, the deployer, and the
+/// provided `contracts` (label, address) to a CSV at `path`, and return totals.
+///
+/// Tracked nonces are compared against state so generator bugs show up as
+/// `nonce_mismatches`; accounts missing from state are reported with zeros.
+pub fn dump_actor_balances(
+    state_provider: &dyn StateProvider,
+    actor_pool: &ActorPool,
+    contracts: &[(String, Address)],
+    path: &Path,
+) -> eyre::Result<BalanceReport> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "label,address,balance_wei,nonce,tracked_nonce")?;
+
+    let mut report = BalanceReport::default();
+
+    let deployer = actor_pool.deployer();
+    let rows = std::iter::once((
+        "deployer".to_string(),
+        deployer.address(),
+        Some(deployer.nonce()),
+    ))
+    .chain(actor_pool.iter().enumerate().map(|(index, actor)| {
+        (
+            format!("actor_{index}"),
+            actor.address(),
+            Some(actor.nonce()),
+        )
+    }))
+    .chain(
+        contracts
+            .iter()
+            .map(|(label, address)| (label.clone(), *address, None)),
+    );
+
+    for (label, address, tracked_nonce) in rows {
+        let account = state_provider.basic_account(&address)?;
+        let (balance, nonce) = match account {
+            Some(account) => (account.balance, account.nonce),
+            None => {
+                report.missing_accounts += 1;
+                (U256::ZERO, 0)
+            }
+        };
+
+        if label.starts_with("actor_") {
+            report.total_actor_balance += balance;
+            if tracked_nonce != Some(nonce) {
+                report.nonce_mismatches += 1;
+            }
+        }
+
+        writeln!(
+            writer,
+            "{label},{address},{balance},{nonce},{}",
+            tracked_nonce.map(|n| n.to_string()).unwrap_or_default()
+        )?;
+    }
+    writer.flush()?;
+
+    info!(
+        target: logging::DEBUG,
+        path = %path.display(),
+        actors = actor_pool.len(),
+        total_actor_balance = %report.total_actor_balance,
+        nonce_mismatches = report.nonce_mismatches,
+        missing_accounts = report.missing_accounts,
+        "wrote actor balance report"
+    );
+    Ok(report)
+}
+
+/// Read `holder`'s `SandboxToken` balance straight from contract storage.
+///
+/// Only valid for tokens with the `SandboxToken` storage layout (see
+/// [`crate::token::SANDBOX_TOKEN_BALANCES_SLOT`]); unset slots read as zero.
+pub fn erc20_balance(
+    state_provider: &dyn StateProvider,
+    token: Address,
+    holder: Address,
+) -> eyre::Result<U256> {
+    let slot = SandboxTokenHelper::balance_slot(holder);
+    Ok(state_provider.storage(token, slot)?.unwrap_or_default())
+}
+
+/// Write every actor's balance of `token` (plus the deployer's) to a CSV at `path`
+/// and return the summed holdings.
+pub fn dump_token_holdings(
+    state_provider: &dyn StateProvider,
+    token: Address,
+    actor_pool: &ActorPool,
+    path: &Path,
+) -> eyre::Result<U256> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "label,address,balance")?;
+
+    let deployer = actor_pool.deployer().address();
+    let holders = std::iter::once(("deployer".to_string(), deployer)).chain(
+        actor_pool
+            .iter()
+            .enumerate()
+            .map(|(index, actor)| (format!("actor_{index}"), actor.address())),
+    );
+
+    let mut total = U256::ZERO;
+    for (label, holder) in holders {
+        let balance = erc20_balance(state_provider, token, holder)?;
+        total += balance;
+        writeln!(writer, "{label},{holder},{balance}")?;
+    }
+    writer.flush()?;
+
+    info!(
+        target: logging::DEBUG,
+        %token,
+        path = %path.display(),
+        total = %total,
+        "wrote token holdings report"
+    );
+    Ok(total)
+}
+
+/// Read-only contract calls against one state, executed the way a node's
+/// `eth_call` at that block would: in its block environment, with no nonce,
+/// balance, or base fee checks, and with every state change discarded.
+pub struct StateCaller<'a> {
+    evm_config: EthEvmConfig,
+    evm_env: EvmEnvFor<EthEvmConfig>,
+    state_provider: &'a dyn StateProvider,
+}
+
+impl<'a> StateCaller<'a> {
+    /// Call against `state_provider`, the state after the block with `header`.
+    pub fn new(
+        evm_config: &EthEvmConfig,
+        header: &Header,
+        state_provider: &'a dyn StateProvider,
+    ) -> eyre::Result<Self> {
+        Ok(Self {
+            evm_config: evm_config.clone(),
+            evm_env: evm_config.evm_env(header)?,
+            state_provider,
+        })
+    }
+
+    /// The state calls run against.
+    pub fn state_provider(&self) -> &'a dyn StateProvider {
+        self.state_provider
+    }
+
+    /// Execute `calldata` against `to` and return its output. A revert or
+    /// halt is an error; a call to an account without code returns nothing.
+    pub fn eth_call(&self, to: Address, calldata: Bytes) -> eyre::Result<Bytes> {
+        let mut db = StateProviderDatabase::new(self.state_provider);
+        let mut evm = self.evm_config.evm_with_env(&mut db, self.evm_env.clone());
+        let result = evm
+            .transact_system_call(Address::ZERO, to, calldata)
+            .map_err(|err| eyre::eyre!("call to {to} failed: {err}"))?
+            .result;
+        match result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, .. } => Err(eyre::eyre!(
+                "call to {to} reverted: {}",
+                revert::revert_reason(&output)
+            )),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(eyre::eyre!("call to {to} halted: {reason:?}"))
+            }
+        }
+    }
+
+    /// `token.balanceOf(holder)`.
+    pub fn erc20_balance_of(&self, token: Address, holder: Address) -> eyre::Result<U256> {
+        let output = self.eth_call(
+            token,
+            SandboxToken::balanceOfCall::new((holder,))
+                .abi_encode()
+                .into(),
+        )?;
+        Ok(SandboxToken::balanceOfCall::abi_decode_returns(&output)?)
+    }
+
+    /// `token.allowance(owner, spender)`.
+    pub fn erc20_allowance(
+        &self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+    ) -> eyre::Result<U256> {
+        let output = self.eth_call(
+            token,
+            SandboxToken::allowanceCall::new((owner, spender))
+                .abi_encode()
+                .into(),
+        )?;
+        Ok(SandboxToken::allowanceCall::abi_decode_returns(&output)?)
+    }
+
+    /// `(reserve0, reserve1)` from `pair.getReserves()`. A pair that was
+    /// never created reads as `(0, 0)`.
+    pub fn pair_get_reserves(&self, pair: Address) -> eyre::Result<(U256, U256)> {
+        let output = self.eth_call(
+            pair,
+            UniswapV2Pair::getReservesCall::new(()).abi_encode().into(),
+        )?;
+        if output.is_empty() {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+        let reserves = UniswapV2Pair::getReservesCall::abi_decode_returns(&output)?;
+        Ok((
+            U256::from(reserves._reserve0),
+            U256::from(reserves._reserve1),
+        ))
+    }
+}
+
+/// Storage slot `UniswapV2Pair` packs `reserve0` (low 112 bits), `reserve1`
+/// (next 112 bits), and `blockTimestampLast` (top 32 bits) into.
+pub const PAIR_RESERVES_SLOT: U256 = U256::from_limbs([8, 0, 0, 0]);
+
+/// `(reserve0, reserve1)` of `pair`, read straight from its reserves slot. A
+/// pair that was never created reads as `(0, 0)`.
+pub fn pair_reserves(
+    state_provider: &dyn StateProvider,
+    pair: Address,
+) -> eyre::Result<(U256, U256)> {
+    let slot = state_provider
+        .storage(pair, B256::from(PAIR_RESERVES_SLOT))?
+        .unwrap_or_default();
+    let (reserve0, reserve1, _) = unpack_reserves(slot);
+    Ok((reserve0, reserve1))
+}
+
+/// Split the reserves slot into `(reserve0, reserve1, blockTimestampLast)`.
+fn unpack_reserves(slot: U256) -> (U256, U256, u32) {
+    let mask = (U256::from(1) << 112) - U256::from(1);
+    (slot & mask, (slot >> 112) & mask, (slot >> 224).to::<u32>())
+}
+
+/// Write reserves, constant product, implied price, and the load sent to
+/// every WETH/token pair in `manifest` to a CSV at `path`, reading reserves
+/// from each pair's storage. `traffic` holds the load transactions generated
+/// per token, with what its swaps paid in. Returns how many pools are empty on either side (drained or never
+/// funded).
+pub fn pool_report(
+    caller: &StateCaller<'_>,
+    manifest: &DeploymentManifest,
+    traffic: &BTreeMap<Address, TokenTraffic>,
+    path: &Path,
+) -> eyre::Result<u64> {
+    let Some(uniswap) = manifest.uniswap else {
+        return Ok(0);
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "token,pair,reserve_weth,reserve_token,k,price_weth_per_token,txs,swaps,weth_in,token_in"
+    )?;
+
+    let mut empty_pools = 0;
+    for (token, pair) in manifest.tokens.iter().zip(&manifest.pairs) {
+        let token = &token.address;
+        let (reserve0, reserve1) = pair_reserves(caller.state_provider(), *pair)?;
+        // Pairs order their tokens by address.
+        let (reserve_weth, reserve_token) = if uniswap.weth < *token {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
+
+        if reserve_weth.is_zero() || reserve_token.is_zero() {
+            empty_pools += 1;
+        }
+
+        let price = if reserve_token.is_zero() {
+            0.0
+        } else {
+            f64::from(reserve_weth) / f64::from(reserve_token)
+        };
+        let traffic = traffic.get(token).copied().unwrap_or_default();
+        writeln!(
+            writer,
+            "{token},{pair},{reserve_weth},{reserve_token},{},{price},{},{},{},{}",
+            reserve_weth.saturating_mul(reserve_token),
+            traffic.txs,
+            traffic.swaps,
+            traffic.weth_in,
+            traffic.token_in
+        )?;
+    }
+    writer.flush()?;
+
+    info!(
+        target: logging::DEBUG,
+        path = %path.display(),
+        pools = manifest.pairs.len(),
+        empty_pools,
+        "wrote pool report"
+    );
+    Ok(empty_pools)
+}
+
+/// Accounts written per block before the state diff is truncated.
+const MAX_DIFF_ACCOUNTS: usize = 10_000;
+
+/// Storage slots written per account before its slot list is truncated.
+const MAX_DIFF_SLOTS_PER_ACCOUNT: usize = 1_000;
+
+/// Whether an account went from no code to some, given its code hash before
+/// and after. Accounts without code hash to `KECCAK_EMPTY`, so an account a
+/// plain transfer creates deploys nothing.
+fn code_deployed(before: Option<B256>, after: Option<B256>) -> bool {
+    after.is_some_and(|after| after != KECCAK_EMPTY && before.unwrap_or(KECCAK_EMPTY) != after)
+}
+
+/// Write what a block changed to `<dir>/<block>.json`: for each touched account
+/// the balance and nonce before/after, whether code was deployed, whether it
+/// was self-destructed (`was_destroyed` also covers accounts created earlier
+/// in the same block), and every storage slot as `[before, after]`. Large
+/// blocks are capped per block and per account, with `truncated` markers where
+/// entries were dropped.
+pub fn write_state_diff(dir: &Path, block: u64, bundle: &BundleState) -> eyre::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut accounts = Map::new();
+    for (address, account) in bundle.state().iter().take(MAX_DIFF_ACCOUNTS) {
+        let before = account.original_info.as_ref();
+        let after = account.info.as_ref();
+
+        let mut slots = Map::new();
+        for (key, slot) in account.storage.iter().take(MAX_DIFF_SLOTS_PER_ACCOUNT) {
+            slots.insert(
+                format!("{key:#x}"),
+                json!([
+                    format!("{:#x}", slot.previous_or_original_value),
+                    format!("{:#x}", slot.present_value)
+                ]),
+            );
+        }
+
+        accounts.insert(
+            address.to_string(),
+            json!({
+                "balance_before": before.map(|info| info.balance.to_string()),
+                "balance_after": after.map(|info| info.balance.to_string()),
+                "nonce_before": before.map(|info| info.nonce),
+                "nonce_after": after.map(|info| info.nonce),
+                "code_deployed": code_deployed(
+                    before.map(|info| info.code_hash),
+                    after.map(|info| info.code_hash),
+                ),
+                "destroyed": before.is_some() && after.is_none(),
+                "was_destroyed": account.was_destroyed(),
+                "slots": Value::Object(slots),
+                "slots_truncated": account.storage.len() > MAX_DIFF_SLOTS_PER_ACCOUNT,
+            }),
+        );
+    }
+
+    let diff = json!({
+        "block": block,
+        "accounts_touched": bundle.state().len(),
+        "accounts_truncated": bundle.state().len() > MAX_DIFF_ACCOUNTS,
+        "contracts_deployed": bundle.contracts.len(),
+        "accounts": Value::Object(accounts),
+    });
+
+    fs::write(
+        dir.join(format!("{block}.json")),
+        serde_json::to_string(&diff)?,
+    )?;
+    Ok(())
+}
+
+/// Size and entry count of a single MDBX table.
+#[derive(Debug, Clone)]
+pub struct TableStat {
+    pub name: &'static str,
+    pub entries: usize,
+    /// Pages in use (leaf + branch + overflow) times the page size.
+    pub size_bytes: usize,
+}
+
+/// Collect entry counts and on-disk sizes for every reth table via MDBX stats.
+pub fn db_stats(db: &DatabaseEnv) -> eyre::Result<Vec<TableStat>> {
+    let stats = db.view(|tx| -> eyre::Result<Vec<TableStat>> {
+        let mut stats = Vec::with_capacity(Tables::ALL.len());
+        for table in Tables::ALL {
+            let table_db = tx.inner.open_db(Some(table.name()))?;
+            let stat = tx.inner.db_stat(&table_db)?;
+            let pages = stat.leaf_pages() + stat.branch_pages() + stat.overflow_pages();
+            stats.push(TableStat {
+                name: table.name(),
+                entries: stat.entries(),
+                size_bytes: pages * stat.page_size() as usize,
+            });
+        }
+        Ok(stats)
+    })??;
+    Ok(stats)
+}
+
+/// Total size in bytes of every file under `path`.
+pub fn dir_size(path: &Path) -> eyre::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Print table statistics (largest first) plus the total datadir size.
+pub fn print_db_stats(stats: &[TableStat], datadir_size: u64) {
+    let mut rows: Vec<_> = stats.iter().collect();
+    rows.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
+
+    let name_w = rows
+        .iter()
+        .map(|stat| stat.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Table".len());
+
+    println!("\nDatabase tables:");
+    println!("{:-<1$}", "", name_w + 32);
+    println!(
+        "{:<name_w$}  {:>14}  {:>14}",
+        "Table",
+        "Entries",
+        "Size (KiB)",
+        name_w = name_w
+    );
+    println!("{:-<1$}", "", name_w + 32);
+    for stat in rows {
+        println!(
+            "{:<name_w$}  {:>14}  {:>14.1}",
+            stat.name,
+            stat.entries,
+            stat.size_bytes as f64 / 1024.0,
+            name_w = name_w
+        );
+    }
+    println!("{:-<1$}", "", name_w + 32);
+    println!(
+        "Datadir size: {:.1} MiB",
+        datadir_size as f64 / (1024.0 * 1024.0)
+    );
+}
+
+/// Compare each fee recipient's final balance with the priority fees the
+/// builder credited to it and print one line per recipient.
+///
+/// Recipients that also receive transfers (e.g. actors) will show a surplus;
+/// a shortfall always indicates an accounting bug. Returns the number of
+/// recipients whose balance is below the expected tips.
+pub fn check_fee_recipients(
+    state_provider: &dyn StateProvider,
+    expected_tips: &HashMap<Address, U256>,
+) -> eyre::Result<usize> {
+    let mut shortfalls = 0;
+    println!();
+    println!(
+        "{:<44} {:>30} {:>30}",
+        "Fee recipient", "Expected tips (wei)", "Balance (wei)"
+    );
+    for (address, expected) in expected_tips {
+        let balance = state_provider.account_balance(address)?.unwrap_or_default();
+        let marker = if balance < *expected {
+            shortfalls += 1;
+            "  <- short"
+        } else {
+            ""
+        };
+        println!("{address:<44} {expected:>30} {balance:>30}{marker}");
+    }
+    Ok(shortfalls)
+}
+
+/// Confirm every contract in `manifest` has code at its precomputed address,
+/// logging each one that does not. Returns how many are missing.
+pub fn check_contract_code(
+    state_provider: &dyn StateProvider,
+    manifest: &DeploymentManifest,
+) -> eyre::Result<usize> {
+    let mut missing = 0;
+    for (label, address) in manifest.labeled_contracts() {
+        let has_code = state_provider
+            .basic_account(&address)?
+            .is_some_and(|account| account.has_bytecode());
+        if !has_code {
+            missing += 1;
+            warn!(target: logging::DEBUG, %label, %address, "no code at deployed address");
+        }
+    }
+    Ok(missing)
+}
+
+/// Addresses from `destroyed` that still have an entry in `PlainAccountState`
+/// or any slot left in `PlainStorageState`, i.e. deletions that never reached
+/// the database.
+pub fn check_destroyed_accounts(
+    provider: &impl DBProvider,
+    destroyed: &[Address],
+) -> eyre::Result<Vec<Address>> {
+    let tx = provider.tx_ref();
+    let mut storage_cursor = tx.cursor_dup_read::<tables::PlainStorageState>()?;
+    let mut survivors = Vec::new();
+    for address in destroyed {
+        let has_account = tx.get::<tables::PlainAccountState>(*address)?.is_some();
+        let has_storage = storage_cursor.seek_exact(*address)?.is_some();
+        if has_account || has_storage {
+            warn!(
+                target: logging::DEBUG,
+                %address,
+                has_account,
+                has_storage,
+                "destroyed account still in plain state"
+            );
+            survivors.push(*address);
+        }
+    }
+    Ok(survivors)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::uint;
+
+    use super::*;
+
+    #[test]
+    fn only_new_code_counts_as_deployed() {
+        let code = B256::repeat_byte(0xc0);
+        // A transfer creating an account, or touching an existing one.
+        assert!(!code_deployed(None, Some(KECCAK_EMPTY)));
+        assert!(!code_deployed(Some(KECCAK_EMPTY), Some(KECCAK_EMPTY)));
+        // A contract created in a new account, or at an address that already
+        // held ETH.
+        assert!(code_deployed(None, Some(code)));
+        assert!(code_deployed(Some(KECCAK_EMPTY), Some(code)));
+        // A contract called, or destroyed.
+        assert!(!code_deployed(Some(code), Some(code)));
+        assert!(!code_deployed(Some(code), None));
+    }
+
+    #[test]
+    fn reserves_slot_unpacks_low_bits_first() {
+        // Slot 8 of a pair holding 500 tokens (token0) and 3 WETH right after
+        // `addLiquidityETH`, at timestamp 1_700_000_000.
+        let slot = uint!(0x6553f10000000000000029a2241af62c000000000000001b1ae4d6e2ef500000_U256);
+        let (reserve0, reserve1, timestamp) = unpack_reserves(slot);
+        assert_eq!(reserve0, U256::from(500u128 * 10u128.pow(18)));
+        assert_eq!(reserve1, U256::from(3u128 * 10u128.pow(18)));
+        assert_eq!(timestamp, 1_700_000_000);
+
+        assert_eq!(unpack_reserves(U256::ZERO), (U256::ZERO, U256::ZERO, 0));
+        let (reserve0, reserve1, timestamp) = unpack_reserves(U256::MAX);
+        assert_eq!(reserve0, (U256::from(1) << 112) - U256::from(1));
+        assert_eq!(reserve1, reserve0);
+        assert_eq!(timestamp, u32::MAX);
+    }
+}
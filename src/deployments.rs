@@ -0,0 +1,272 @@
+//! Record of every contract the setup phases deployed, written to disk so the
+//! produced datadir can be inspected, or reused by a load stage, after the run.
+
+use std::{fs, path::Path, sync::Mutex};
+
+use alloy_primitives::{Address, B256, TxHash, map::HashMap};
+use serde_json::{Value, json};
+
+use crate::{
+    actor::ActorPool,
+    config::TokenTemplate,
+    error::SandboxError,
+    token::{TokenMetadata, TokenPool},
+    uniswap::Uniswap,
+};
+
+/// Addresses produced by the setup phases, keyed the way `deployments.json`
+/// lays them out.
+#[derive(Debug, Clone)]
+pub struct DeploymentManifest {
+    /// Chain the contracts were deployed on.
+    pub chain_id: u64,
+    /// Genesis hash of that chain, filled in by whoever owns the chain spec.
+    pub genesis_hash: Option<B256>,
+    /// Account that signed the Uniswap deployments.
+    pub deployer: Address,
+    /// Every setup token, in deployment order.
+    pub tokens: Vec<DeployedToken>,
+    /// Uniswap factory, router, and WETH, if they were deployed.
+    pub uniswap: Option<UniswapDeployment>,
+    /// WETH/token pair addresses in the same order as `tokens`.
+    pub pairs: Vec<Address>,
+    /// Batcher contract, if multicall or bundler load deployed one.
+    pub batcher: Option<Address>,
+    /// CREATE2 factory, if contracts were deployed through one.
+    pub create2_deployer: Option<Address>,
+}
+
+/// A setup token, the account and nonce that created it, and the template it
+/// was deployed from.
+#[derive(Debug, Clone, Copy)]
+pub struct DeployedToken {
+    pub address: Address,
+    pub deployer: Address,
+    pub nonce: u64,
+    pub template: TokenTemplate,
+}
+
+/// Uniswap core addresses captured for the manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct UniswapDeployment {
+    pub factory: Address,
+    pub router: Address,
+    pub weth: Address,
+}
+
+impl DeploymentManifest {
+    /// Snapshot the token pool and contract deployments owned by the
+    /// orchestrator.
+    pub fn new(
+        chain_id: u64,
+        actors: &ActorPool,
+        tokens: &TokenPool,
+        uniswap: Option<&Uniswap>,
+        batcher: Option<Address>,
+        create2_deployer: Option<Address>,
+    ) -> Self {
+        let deployer = actors.deployer().address();
+        let tokens = tokens
+            .iter()
+            .map(|token| DeployedToken {
+                address: token.address(),
+                deployer: actors
+                    .owner(token.deployer())
+                    .map_or(deployer, |owner| owner.address()),
+                nonce: token.deployment_nonce(),
+                template: token.template(),
+            })
+            .collect::<Vec<_>>();
+
+        let pairs = uniswap
+            .map(|uniswap| {
+                tokens
+                    .iter()
+                    .map(|token| uniswap.pair_address(token.address))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            chain_id,
+            genesis_hash: None,
+            deployer,
+            tokens,
+            uniswap: uniswap.map(|uniswap| UniswapDeployment {
+                factory: uniswap.factory(),
+                router: uniswap.router(),
+                weth: uniswap.weth(),
+            }),
+            pairs,
+            batcher,
+            create2_deployer,
+        }
+    }
+
+    /// Every deployed contract as a (label, address) pair, for reports.
+    pub fn labeled_contracts(&self) -> Vec<(String, Address)> {
+        let mut contracts = Vec::with_capacity(3 + self.tokens.len() + self.pairs.len());
+        if let Some(uniswap) = self.uniswap {
+            contracts.push(("factory".to_string(), uniswap.factory));
+            contracts.push(("router".to_string(), uniswap.router));
+            contracts.push(("weth".to_string(), uniswap.weth));
+        }
+        contracts.extend(
+            self.tokens
+                .iter()
+                .enumerate()
+                .map(|(index, token)| (format!("token_{index}"), token.address)),
+        );
+        contracts.extend(
+            self.pairs
+                .iter()
+                .enumerate()
+                .map(|(index, address)| (format!("pair_{index}"), *address)),
+        );
+        contracts
+    }
+
+    /// Render the manifest as the JSON document written to disk.
+    pub fn to_json(&self) -> Value {
+        let tokens = self
+            .tokens
+            .iter()
+            .zip(0..)
+            .map(|(token, index)| {
+                json!({
+                    "address": token.address.to_string(),
+                    "deployer": token.deployer.to_string(),
+                    "nonce": token.nonce,
+                    "template": token.template.name(),
+                    "fee_bps": token.template.fee_bps(),
+                    "metadata": TokenMetadata::new(token.template, index).to_json(),
+                })
+            })
+            .collect::<Vec<Value>>();
+
+        json!({
+            "chain_id": self.chain_id,
+            "genesis_hash": self.genesis_hash.map(|hash| hash.to_string()),
+            "deployer": self.deployer.to_string(),
+            "tokens": tokens,
+            "uniswap": self.uniswap.map(|uniswap| json!({
+                "factory": uniswap.factory.to_string(),
+                "router": uniswap.router.to_string(),
+                "weth": uniswap.weth.to_string(),
+            })),
+            "pairs": self.pairs.iter().map(Address::to_string).collect::<Vec<_>>(),
+            "batcher": self.batcher.map(|address| address.to_string()),
+            "create2_deployer": self.create2_deployer.map(|address| address.to_string()),
+        })
+    }
+
+    /// Read a manifest written by [`Self::write`].
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let file = fs::read_to_string(path).map_err(|err| SandboxError::io(path, err))?;
+        Self::from_json(&serde_json::from_str(&file)?)
+            .map_err(|err| eyre::eyre!("{}: {err}", path.display()))
+    }
+
+    /// Rebuild a manifest from the JSON produced by [`Self::to_json`].
+    fn from_json(json: &Value) -> eyre::Result<Self> {
+        let address = |value: &Value, field: &str| -> eyre::Result<Address> {
+            Ok(value
+                .as_str()
+                .ok_or_else(|| eyre::eyre!("`{field}` is not an address"))?
+                .parse()?)
+        };
+        let optional = |value: &Value, field: &str| -> eyre::Result<Option<Address>> {
+            if value.is_null() {
+                Ok(None)
+            } else {
+                address(value, field).map(Some)
+            }
+        };
+
+        let tokens = json["tokens"]
+            .as_array()
+            .ok_or_else(|| eyre::eyre!("missing the `tokens` array"))?
+            .iter()
+            .map(|token| {
+                Ok(DeployedToken {
+                    address: address(&token["address"], "tokens.address")?,
+                    deployer: address(&token["deployer"], "tokens.deployer")?,
+                    nonce: token["nonce"]
+                        .as_u64()
+                        .ok_or_else(|| eyre::eyre!("`tokens.nonce` is not a number"))?,
+                    // Manifests from before fee tokens only hold standard ones.
+                    template: match token["template"].as_str() {
+                        None => TokenTemplate::Standard,
+                        Some(name) => {
+                            let fee_bps = token["fee_bps"].as_u64().unwrap_or_default() as u32;
+                            TokenTemplate::from_name(name, fee_bps).ok_or_else(|| {
+                                eyre::eyre!("`tokens.template` {name:?} is not a token template")
+                            })?
+                        }
+                    },
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let uniswap = match &json["uniswap"] {
+            Value::Null => None,
+            uniswap => Some(UniswapDeployment {
+                factory: address(&uniswap["factory"], "uniswap.factory")?,
+                router: address(&uniswap["router"], "uniswap.router")?,
+                weth: address(&uniswap["weth"], "uniswap.weth")?,
+            }),
+        };
+        let pairs = json["pairs"]
+            .as_array()
+            .ok_or_else(|| eyre::eyre!("missing the `pairs` array"))?
+            .iter()
+            .map(|pair| address(pair, "pairs"))
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            chain_id: json["chain_id"]
+                .as_u64()
+                .ok_or_else(|| eyre::eyre!("`chain_id` is not a number"))?,
+            genesis_hash: match &json["genesis_hash"] {
+                Value::Null => None,
+                hash => Some(
+                    hash.as_str()
+                        .ok_or_else(|| eyre::eyre!("`genesis_hash` is not a hash"))?
+                        .parse()?,
+                ),
+            },
+            deployer: address(&json["deployer"], "deployer")?,
+            tokens,
+            uniswap,
+            pairs,
+            batcher: optional(&json["batcher"], "batcher")?,
+            create2_deployer: optional(&json["create2_deployer"], "create2_deployer")?,
+        })
+    }
+
+    /// Write the manifest to `path` as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_json())?)?;
+        Ok(())
+    }
+}
+
+/// Contracts the orchestrator predicted an address for, keyed by the
+/// transaction that deploys them. Shared with the builder through an `Arc`;
+/// it checks each address for code once the transaction is in a block.
+#[derive(Debug, Default)]
+pub struct ExpectedCode {
+    pending: Mutex<HashMap<TxHash, (&'static str, Address)>>,
+}
+
+impl ExpectedCode {
+    /// Expect `hash` to create the contract named `label` at `address`.
+    pub fn expect(&self, hash: TxHash, label: &'static str, address: Address) {
+        self.pending.lock().unwrap().insert(hash, (label, address));
+    }
+
+    /// The contract `hash` should create, if it deploys one; it is only
+    /// checked once.
+    pub fn take(&self, hash: &TxHash) -> Option<(&'static str, Address)> {
+        self.pending.lock().unwrap().remove(hash)
+    }
+}
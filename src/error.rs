@@ -0,0 +1,93 @@
+//! Crate error type for failures that come from user input or the
+//! environment rather than bugs, so they surface as errors with context
+//! instead of panics (often inside a rayon worker).
+
+use std::path::PathBuf;
+
+use alloy_primitives::{Address, TxHash};
+use reth_evm::execute::BlockExecutionError;
+
+/// Errors raised while configuring the sandbox, signing its transactions,
+/// writing its outputs, or executing its blocks.
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    /// A transaction could not be built or signed.
+    #[error("failed to sign transaction from {sender} at nonce {nonce}: {reason}")]
+    Signing {
+        sender: Address,
+        nonce: u64,
+        reason: String,
+    },
+    /// A signed transaction does not recover to the signer it claims.
+    #[error(
+        "transaction {hash} (nonce {nonce}, chain id {chain_id:?}) claims sender {claimed} but recovers to {recovered:?}"
+    )]
+    SenderMismatch {
+        hash: TxHash,
+        nonce: u64,
+        chain_id: Option<u64>,
+        claimed: Address,
+        recovered: Option<Address>,
+    },
+    /// A user-supplied setting is malformed.
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    /// Reading or writing a file failed.
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// A transaction would make parallel lanes touch the same account.
+    #[error(
+        "transaction {hash} cannot run in a parallel lane: {reason}; lanes only support ETH transfers between disjoint accounts, as the transfers-only workload sends"
+    )]
+    LaneConflict { hash: TxHash, reason: &'static str },
+    /// A setup contract left no code at the address it was predicted at,
+    /// typically because the deployer nonce the prediction used was stale.
+    #[error(
+        "no {label} code at {address} after block {block}: its deployment transaction {hash} {outcome}"
+    )]
+    MissingContract {
+        label: &'static str,
+        address: Address,
+        block: u64,
+        hash: TxHash,
+        outcome: &'static str,
+    },
+    /// Sampled accounts a setup phase should have set up are not in the
+    /// state it left behind.
+    #[error(
+        "{phase} phase did not take effect: {failed} of {sampled} sampled accounts {failure}, first {first}"
+    )]
+    PhaseCheck {
+        phase: &'static str,
+        failed: usize,
+        sampled: usize,
+        failure: &'static str,
+        first: Address,
+    },
+    /// The output directory already holds files, most likely an earlier
+    /// run's.
+    #[error("{} already holds a run's output; pass --force to write into it anyway", path.display())]
+    OutputDirNotEmpty { path: PathBuf },
+    /// A transaction failed for a reason other than being invalid.
+    #[error("failed to execute transaction {hash} in block {block}: {source}")]
+    Execution {
+        block: u64,
+        hash: TxHash,
+        #[source]
+        source: BlockExecutionError,
+    },
+}
+
+impl SandboxError {
+    /// Wrap an I/O error with the path it happened on.
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
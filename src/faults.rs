@@ -0,0 +1,20 @@
+//! Deliberate faults for the integration tests that check a run notices, or
+//! recovers from, something going wrong. Only the `test-hooks` feature can
+//! set them; every other run uses [`FaultInjection::default`], which injects
+//! nothing.
+
+/// Faults the orchestrator builds into the transactions it generates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaultInjection {
+    /// Nonces to skip on the deployer before the Uniswap deployment, so its
+    /// transactions are rejected and the run must stop on the missing
+    /// contracts.
+    pub uniswap_nonce_skew: u64,
+    /// Run-wide index of a transfers-only load transaction made to send more
+    /// ether than its sender holds, so it is rejected and the sender's later
+    /// transactions wait for a nonce reconciliation.
+    pub fail_transfer_at: Option<u64>,
+    /// Send each mixed-load swap for ETH ahead of the approval it spends, so
+    /// it only succeeds on a retry.
+    pub swap_before_approve: bool,
+}
@@ -0,0 +1,125 @@
+//! Deliberately broken transactions used to check that the builder rejects
+//! exactly what it should and nothing else.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use alloy_primitives::TxHash;
+
+/// Ways an injected transaction is made invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidKind {
+    /// Nonce ahead of the account nonce.
+    NonceTooHigh,
+    /// Nonce already used by the account.
+    NonceTooLow,
+    /// Transfers more value than the account could ever hold.
+    InsufficientBalance,
+    /// Gas limit below the 21k intrinsic cost.
+    GasBelowIntrinsic,
+    /// Signed for a different chain id.
+    WrongChainId,
+}
+
+impl InvalidKind {
+    /// Every kind, in the order injection cycles through them.
+    pub const ALL: [Self; 5] = [
+        Self::NonceTooHigh,
+        Self::NonceTooLow,
+        Self::InsufficientBalance,
+        Self::GasBelowIntrinsic,
+        Self::WrongChainId,
+    ];
+}
+
+impl fmt::Display for InvalidKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonceTooHigh => f.write_str("nonce-too-high"),
+            Self::NonceTooLow => f.write_str("nonce-too-low"),
+            Self::InsufficientBalance => f.write_str("insufficient-balance"),
+            Self::GasBelowIntrinsic => f.write_str("gas-below-intrinsic"),
+            Self::WrongChainId => f.write_str("wrong-chain-id"),
+        }
+    }
+}
+
+/// Hashes the orchestrator injected, cross-checked against what the builder
+/// rejected. Shared between the two through an `Arc`.
+#[derive(Debug, Default)]
+pub struct InvalidTxRegistry {
+    injected: Mutex<HashSet<TxHash>>,
+    rejected_injected: AtomicU64,
+    accepted_injected: AtomicU64,
+    rejected_valid: AtomicU64,
+}
+
+impl InvalidTxRegistry {
+    /// Remember a transaction the orchestrator broke on purpose.
+    pub fn register(&self, hash: TxHash) {
+        self.injected.lock().unwrap().insert(hash);
+    }
+
+    /// Whether `hash` was registered as deliberately invalid.
+    pub fn is_injected(&self, hash: &TxHash) -> bool {
+        self.injected.lock().unwrap().contains(hash)
+    }
+
+    /// Record a transaction the builder refused to include; returns whether it
+    /// was one of the injected ones.
+    pub fn record_rejected(&self, hash: &TxHash) -> bool {
+        let injected = self.injected.lock().unwrap().contains(hash);
+        let counter = if injected {
+            &self.rejected_injected
+        } else {
+            &self.rejected_valid
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        injected
+    }
+
+    /// Record a transaction the builder included; returns whether it was
+    /// injected (and so should have been rejected).
+    pub fn record_accepted(&self, hash: &TxHash) -> bool {
+        let injected = self.injected.lock().unwrap().contains(hash);
+        if injected {
+            self.accepted_injected.fetch_add(1, Ordering::Relaxed);
+        }
+        injected
+    }
+
+    /// Counts collected so far.
+    pub fn report(&self) -> InjectionReport {
+        InjectionReport {
+            injected: self.injected.lock().unwrap().len() as u64,
+            rejected_injected: self.rejected_injected.load(Ordering::Relaxed),
+            accepted_injected: self.accepted_injected.load(Ordering::Relaxed),
+            rejected_valid: self.rejected_valid.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Injected vs rejected totals for the run summary.
+///
+/// `injected` can exceed `rejected_injected` because transactions still queued
+/// when the builder stops are never executed.
+#[derive(Debug, Clone, Copy)]
+pub struct InjectionReport {
+    pub injected: u64,
+    pub rejected_injected: u64,
+    pub accepted_injected: u64,
+    pub rejected_valid: u64,
+}
+
+impl InjectionReport {
+    /// No injected transaction made it into a block and no valid one was rejected.
+    pub fn is_consistent(&self) -> bool {
+        self.accepted_injected == 0 && self.rejected_valid == 0
+    }
+}
@@ -0,0 +1,59 @@
+//! Actor key derivation. Each key is drawn from its own ChaCha20 stream of a
+//! run seed, so it depends only on `(seed, index)`: chunks of keys can be
+//! derived in parallel, in any order, and a rerun with the same seed gets the
+//! same keys.
+
+use std::ops::Range;
+
+use alloy_primitives::B256;
+use alloy_signer_local::PrivateKeySigner;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Keys derived per unit of parallel work, and so between progress updates.
+pub const CHUNK_SIZE: u64 = 4_096;
+
+/// The key at `index` for `seed`, read from ChaCha20 stream `index`.
+pub fn derive_key(seed: B256, index: u64) -> PrivateKeySigner {
+    let mut rng = ChaCha20Rng::from_seed(seed.0);
+    rng.set_stream(index);
+    let mut key = B256::ZERO;
+    // Zero or anything past the secp256k1 order is not a valid scalar. That is
+    // astronomically unlikely, but draw again rather than panic.
+    loop {
+        rng.fill_bytes(key.as_mut_slice());
+        if let Ok(signer) = PrivateKeySigner::from_bytes(&key) {
+            return signer;
+        }
+    }
+}
+
+/// The keys at `indices` for `seed`, in index order. Chunks of
+/// [`CHUNK_SIZE`] are derived in parallel, and `on_chunk` is called with the
+/// number of keys each one produced as it finishes.
+pub fn derive_keys(
+    seed: B256,
+    indices: Range<u64>,
+    on_chunk: impl Fn(u64) + Sync,
+) -> Vec<PrivateKeySigner> {
+    let chunks = indices
+        .end
+        .saturating_sub(indices.start)
+        .div_ceil(CHUNK_SIZE);
+    (0..chunks)
+        .into_par_iter()
+        .map(|chunk| {
+            let start = indices.start + chunk * CHUNK_SIZE;
+            let end = (start + CHUNK_SIZE).min(indices.end);
+            let keys = (start..end)
+                .map(|index| derive_key(seed, index))
+                .collect::<Vec<_>>();
+            on_chunk(keys.len() as u64);
+            keys
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .collect()
+}
@@ -0,0 +1,400 @@
+//! Labels that travel with every transaction through the channel, so the
+//! builder can attribute gas and failures to the workload that produced them.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    iter,
+    path::Path,
+};
+
+use alloy_consensus::Transaction;
+use serde_json::{Value, json};
+
+use crate::{error::SandboxError, orchestrator::TX};
+
+/// CSV header line of a block labels file.
+const BLOCK_LABELS_CSV_HEADER: &str = "block,label,txs,gas_used,failed,rejected";
+
+/// Stages the simulation walks through before issuing steady-state load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationPhase {
+    /// Fund a deployer separate from the genesis allocation holder.
+    DeployerFunding,
+    /// Allocate ETH from deployer to the actor pool.
+    ActorFunding,
+    /// Deploy ERC20 bytecode.
+    TokenDeployment,
+    /// Deploy Uniswap contracts.
+    UniswapDeployment,
+    /// Seed each token with a pool and liquidity.
+    UniswapPoolCreation,
+    /// Deploy the batcher contract used by multicall load.
+    MulticallDeployment,
+    /// Deploy the CREATE2 factory tokens are deployed through.
+    Create2DeployerDeployment,
+    /// Send limitless user-style transactions. Mixes transaction types.
+    TransactionLoad,
+    /// Top actors running low back up from the deployer. Transient: its
+    /// batches are overlaid on the load rather than pausing it.
+    FundingTopUp,
+}
+
+impl SimulationPhase {
+    const ALL: [Self; 9] = [
+        Self::DeployerFunding,
+        Self::ActorFunding,
+        Self::TokenDeployment,
+        Self::UniswapDeployment,
+        Self::UniswapPoolCreation,
+        Self::MulticallDeployment,
+        Self::Create2DeployerDeployment,
+        Self::TransactionLoad,
+        Self::FundingTopUp,
+    ];
+
+    /// The phase whose [`Self::name`] is `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|phase| phase.name() == name)
+    }
+
+    /// Short label used in progress reporting.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DeployerFunding => "deployer-funding",
+            Self::ActorFunding => "actor-funding",
+            Self::TokenDeployment => "token-deployment",
+            Self::UniswapDeployment => "uniswap-deployment",
+            Self::UniswapPoolCreation => "uniswap-pool-creation",
+            Self::MulticallDeployment => "multicall-deployment",
+            Self::Create2DeployerDeployment => "create2-deployer-deployment",
+            Self::TransactionLoad => "transaction-load",
+            Self::FundingTopUp => "funding-top-up",
+        }
+    }
+}
+
+/// What a transaction does, as decided by the orchestrator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TxLabel {
+    /// ETH sent from the genesis allocation holder to a separate deployer.
+    DeployerFunding,
+    /// ETH sent from the deployer to a new actor.
+    ActorFunding,
+    /// ETH sent from the deployer to an actor running low during load.
+    FundingTopUp,
+    /// Setup `SandboxToken` deployment.
+    TokenDeployment,
+    /// WETH, factory, or router deployment.
+    UniswapDeployment,
+    /// Pair creation, approval, or initial liquidity.
+    UniswapPoolCreation,
+    /// Batcher deployment.
+    MulticallDeployment,
+    /// CREATE2 factory deployment.
+    Create2DeployerDeployment,
+    /// Simple ETH transfer between EOAs.
+    EthTransfer,
+    /// ERC20 transfer using the sandbox token.
+    TokenTransfer,
+    /// Spend token to receive WETH via the router.
+    UniswapSwapForEth,
+    /// Spend ETH to acquire a token via the router.
+    UniswapSwapForToken,
+    /// Wrap ETH by calling WETH9 `deposit()` directly.
+    WethDeposit,
+    /// Unwrap part of the actor's previously deposited WETH.
+    WethWithdraw,
+    /// Token transfers and approvals fanned out through the batcher contract.
+    Multicall,
+    /// A bundler actor's batcher transaction of token transfers to other
+    /// actors.
+    Bundle,
+    /// A random actor deploys its own `SandboxToken`.
+    ContractDeploy,
+    /// Liquidity removal authorized by an EIP-2612 permit.
+    PermitRemoval,
+    /// Part of a `Destructible` create / write / self-destruct lifecycle.
+    SelfDestruct,
+    /// A scenario's zero-value transfer carrying a block of calldata.
+    Calldata,
+    /// Deliberately invalid; the builder should reject it.
+    Invalid,
+}
+
+impl TxLabel {
+    const ALL: [Self; 21] = [
+        Self::DeployerFunding,
+        Self::ActorFunding,
+        Self::FundingTopUp,
+        Self::TokenDeployment,
+        Self::UniswapDeployment,
+        Self::UniswapPoolCreation,
+        Self::MulticallDeployment,
+        Self::Create2DeployerDeployment,
+        Self::EthTransfer,
+        Self::TokenTransfer,
+        Self::UniswapSwapForEth,
+        Self::UniswapSwapForToken,
+        Self::WethDeposit,
+        Self::WethWithdraw,
+        Self::Multicall,
+        Self::Bundle,
+        Self::ContractDeploy,
+        Self::PermitRemoval,
+        Self::SelfDestruct,
+        Self::Calldata,
+        Self::Invalid,
+    ];
+
+    /// The label whose [`Self::name`] is `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|label| label.name() == name)
+    }
+
+    /// Label used in stats, CSVs, and logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DeployerFunding => "deployer-funding",
+            Self::ActorFunding => "actor-funding",
+            Self::FundingTopUp => "funding-top-up",
+            Self::TokenDeployment => "token-deployment",
+            Self::UniswapDeployment => "uniswap-deployment",
+            Self::UniswapPoolCreation => "uniswap-pool-creation",
+            Self::MulticallDeployment => "multicall-deployment",
+            Self::Create2DeployerDeployment => "create2-deployer-deployment",
+            Self::EthTransfer => "eth-transfer",
+            Self::TokenTransfer => "token-transfer",
+            Self::UniswapSwapForEth => "uniswap-swap-for-eth",
+            Self::UniswapSwapForToken => "uniswap-swap-for-token",
+            Self::WethDeposit => "weth-deposit",
+            Self::WethWithdraw => "weth-withdraw",
+            Self::Multicall => "multicall",
+            Self::Bundle => "bundle",
+            Self::ContractDeploy => "contract-deploy",
+            Self::PermitRemoval => "permit-removal",
+            Self::SelfDestruct => "selfdestruct",
+            Self::Calldata => "calldata",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+/// A transaction plus the labels it is sent through the channel with.
+#[derive(Debug)]
+pub struct LabeledTx {
+    pub tx: TX,
+    pub label: TxLabel,
+    pub phase: SimulationPhase,
+    /// Transactions sent as one group with this one, in order. The builder
+    /// includes them directly behind it in the same block, or defers the
+    /// whole group to the next. Empty for a transaction sent on its own.
+    pub followers: Vec<LabeledTx>,
+}
+
+impl LabeledTx {
+    /// A transaction sent on its own.
+    pub fn new(tx: TX, label: TxLabel, phase: SimulationPhase) -> Self {
+        Self {
+            tx,
+            label,
+            phase,
+            followers: Vec::new(),
+        }
+    }
+
+    /// Tag every transaction in `txs` with the same label.
+    pub fn label_all(txs: Vec<TX>, label: TxLabel, phase: SimulationPhase) -> Vec<Self> {
+        txs.into_iter()
+            .map(|tx| Self::new(tx, label, phase))
+            .collect()
+    }
+
+    /// Tag `txs` with the same label and send them as one group led by the
+    /// first; `None` when there are none.
+    pub fn group(txs: Vec<TX>, label: TxLabel, phase: SimulationPhase) -> Option<Self> {
+        let mut members = Self::label_all(txs, label, phase).into_iter();
+        let mut lead = members.next()?;
+        lead.followers = members.collect();
+        Some(lead)
+    }
+
+    /// This transaction, then its followers.
+    pub fn members(&self) -> impl Iterator<Item = &Self> {
+        iter::once(self).chain(&self.followers)
+    }
+
+    /// This transaction, then its followers, each on its own.
+    pub fn into_members(mut self) -> impl Iterator<Item = Self> {
+        let followers = std::mem::take(&mut self.followers);
+        iter::once(self).chain(followers)
+    }
+
+    /// Every transaction in `batch`, with groups expanded.
+    pub fn flatten(batch: &[Self]) -> impl Iterator<Item = &Self> {
+        batch.iter().flat_map(Self::members)
+    }
+
+    /// Gas limit of this transaction and its followers together.
+    pub fn group_gas_limit(&self) -> u64 {
+        self.members().map(|member| member.tx.gas_limit()).sum()
+    }
+}
+
+/// What the builder did with the transactions carrying one label.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LabelStats {
+    /// Included in a block, successful or not.
+    pub txs: u64,
+    pub gas_used: u64,
+    /// Included but reverted or halted.
+    pub failed: u64,
+    /// Refused by the builder and left out of the block.
+    pub rejected: u64,
+}
+
+/// [`LabelStats`] per label.
+#[derive(Debug, Clone, Default)]
+pub struct LabelTotals(BTreeMap<TxLabel, LabelStats>);
+
+impl LabelTotals {
+    /// Account for an included transaction.
+    pub fn record_included(&mut self, label: TxLabel, gas_used: u64, failed: bool) {
+        let stats = self.0.entry(label).or_default();
+        stats.txs += 1;
+        stats.gas_used += gas_used;
+        stats.failed += failed as u64;
+    }
+
+    /// Account for a transaction the builder refused.
+    pub fn record_rejected(&mut self, label: TxLabel) {
+        self.0.entry(label).or_default().rejected += 1;
+    }
+
+    /// Counts for `label`, zero if none were recorded.
+    pub fn get(&self, label: TxLabel) -> LabelStats {
+        self.0.get(&label).copied().unwrap_or_default()
+    }
+
+    /// Add every count in `other`.
+    pub fn merge(&mut self, other: &Self) {
+        for (label, stats) in &other.0 {
+            let total = self.0.entry(*label).or_default();
+            total.txs += stats.txs;
+            total.gas_used += stats.gas_used;
+            total.failed += stats.failed;
+            total.rejected += stats.rejected;
+        }
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        self.0
+            .iter()
+            .map(|(label, stats)| {
+                (
+                    label.name().to_string(),
+                    json!({
+                        "txs": stats.txs,
+                        "gas_used": stats.gas_used,
+                        "failed": stats.failed,
+                        "rejected": stats.rejected,
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Print a label / txs / gas / avg gas / failed / rejected table.
+    pub fn print(&self) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        let name_w = self
+            .0
+            .keys()
+            .map(|label| label.name().len())
+            .max()
+            .unwrap_or_default()
+            .max("Label".len());
+
+        println!("\nExecuted by label:");
+        println!("{:-<1$}", "", name_w + 80);
+        println!(
+            "{:<name_w$}  {:>12}  {:>18}  {:>14}  {:>12}  {:>12}",
+            "Label", "Txs", "Gas used", "Avg gas", "Failed", "Rejected"
+        );
+        println!("{:-<1$}", "", name_w + 80);
+        for (label, stats) in &self.0 {
+            println!(
+                "{:<name_w$}  {:>12}  {:>18}  {:>14}  {:>12}  {:>12}",
+                label.name(),
+                stats.txs,
+                stats.gas_used,
+                stats.gas_used.checked_div(stats.txs).unwrap_or_default(),
+                stats.failed,
+                stats.rejected
+            );
+        }
+        println!("{:-<1$}", "", name_w + 80);
+    }
+
+    /// Print gas per bundled operation next to gas per direct token
+    /// transfer, the same operation sent as its own transaction. Nothing is
+    /// printed before any bundle is included.
+    pub fn print_bundle_efficiency(&self, bundle_size: u32) {
+        let bundles = self.get(TxLabel::Bundle);
+        let ops = bundles.txs * bundle_size as u64;
+        if ops == 0 {
+            return;
+        }
+        let direct = self.get(TxLabel::TokenTransfer);
+        println!(
+            "Bundles:  {} txs, {} gas per op; direct token transfers: {} txs, {} gas each",
+            bundles.txs,
+            bundles.gas_used / ops,
+            direct.txs,
+            direct.gas_used.checked_div(direct.txs).unwrap_or_default()
+        );
+    }
+}
+
+/// Appends one row per label per built block to a block labels file.
+pub struct BlockLabelsWriter {
+    writer: BufWriter<File>,
+}
+
+impl BlockLabelsWriter {
+    /// Create the file and write the CSV header.
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{BLOCK_LABELS_CSV_HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    /// Append the rows for block `number`.
+    pub fn record(&mut self, number: u64, totals: &LabelTotals) -> eyre::Result<()> {
+        for (label, stats) in &totals.0 {
+            writeln!(
+                self.writer,
+                "{number},{},{},{},{},{}",
+                label.name(),
+                stats.txs,
+                stats.gas_used,
+                stats.failed,
+                stats.rejected
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flush buffered rows to disk.
+    pub fn finish(mut self) -> eyre::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+//! Experimental parallel block building: a block's transactions are split by
+//! sender into lanes, each lane runs against its own `State` on its own
+//! thread, and the lane bundles are merged into one before the state root is
+//! computed.
+//!
+//! Lanes never see each other's writes, so this is only sound for blocks in
+//! which no account is touched by more than one lane. [`check_conflict_free`]
+//! enforces that for the one workload that guarantees it: plain ETH transfers
+//! to receivers nobody else sends from or to.
+
+use std::time::{Duration, Instant};
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, U256, map::HashSet};
+use reth_evm::{ConfigureEvm, Evm, EvmEnvFor};
+use reth_node_ethereum::EthEvmConfig;
+use reth_revm::{
+    State,
+    database::StateProviderDatabase,
+    db::{BundleState, states::bundle_state::BundleRetention},
+    revm::context::result::ExecutionResult,
+};
+use serde_json::{Value, json};
+
+use reth_chain_state::ExecutedBlock;
+
+use crate::{
+    block_builder::{self, PF, SandboxDatabase},
+    error::SandboxError,
+    labels::LabeledTx,
+};
+
+/// Lane that executes every transaction sent by `sender`.
+pub fn lane_for(sender: Address, lanes: usize) -> usize {
+    let low = u64::from_be_bytes(sender[12..].try_into().expect("8 bytes"));
+    (low % lanes as u64) as usize
+}
+
+/// Fail unless every transaction is a plain ETH transfer and no account is
+/// both a receiver and a sender, receives twice, or is the fee recipient.
+pub fn check_conflict_free(txs: &[LabeledTx], fee_recipient: Address) -> Result<(), SandboxError> {
+    let senders = txs
+        .iter()
+        .map(|labeled| labeled.tx.signer())
+        .collect::<HashSet<_>>();
+    let mut receivers = HashSet::<Address>::default();
+    for labeled in txs {
+        let hash = *labeled.tx.hash();
+        let conflict = |reason| SandboxError::LaneConflict { hash, reason };
+        let Some(to) = labeled.tx.to() else {
+            return Err(conflict("it creates a contract"));
+        };
+        if !labeled.tx.input().is_empty() {
+            return Err(conflict("it carries calldata"));
+        }
+        if senders.contains(&to) {
+            return Err(conflict("its receiver also sends in this block"));
+        }
+        if !receivers.insert(to) {
+            return Err(conflict("its receiver is paid twice in this block"));
+        }
+        if to == fee_recipient || labeled.tx.signer() == fee_recipient {
+            return Err(conflict("it touches the fee recipient"));
+        }
+    }
+    Ok(())
+}
+
+/// Split `txs` into `lanes` lanes by sender, keeping each sender's
+/// transactions in nonce order.
+pub fn partition(txs: Vec<LabeledTx>, lanes: usize) -> Vec<Vec<LabeledTx>> {
+    let mut partitioned = (0..lanes).map(|_| Vec::new()).collect::<Vec<_>>();
+    for labeled in txs {
+        partitioned[lane_for(labeled.tx.signer(), lanes)].push(labeled);
+    }
+    partitioned
+}
+
+/// What one lane produced.
+pub struct LaneOutput {
+    pub txs: Vec<LabeledTx>,
+    pub results: Vec<ExecutionResult>,
+    /// Changes made by the lane, with reverts for a single block.
+    pub bundle: BundleState,
+    /// Time spent executing, excluding thread start-up.
+    pub busy: Duration,
+}
+
+/// Execute `txs` in order against a fresh `State` over the latest database
+/// state with `pending` blocks overlaid.
+pub fn execute_lane<DB: SandboxDatabase>(
+    provider_factory: &PF<DB>,
+    pending: &[ExecutedBlock],
+    evm_config: &EthEvmConfig,
+    evm_env: EvmEnvFor<EthEvmConfig>,
+    txs: Vec<LabeledTx>,
+) -> eyre::Result<LaneOutput> {
+    let state_provider = block_builder::latest_state(provider_factory, pending)?;
+    let mut state_db = State::builder()
+        .with_database(StateProviderDatabase::new(&state_provider))
+        .with_bundle_update()
+        .build();
+
+    let started = Instant::now();
+    let mut results = Vec::with_capacity(txs.len());
+    {
+        let mut evm = evm_config.evm_with_env(&mut state_db, evm_env);
+        for labeled in &txs {
+            let result = evm
+                .transact_commit(labeled.tx.as_recovered_ref())
+                .map_err(|err| {
+                    eyre::eyre!("lane failed to execute {}: {err}", labeled.tx.hash())
+                })?;
+            results.push(result);
+        }
+    }
+    state_db.merge_transitions(BundleRetention::Reverts);
+    let busy = started.elapsed();
+
+    Ok(LaneOutput {
+        txs,
+        results,
+        bundle: state_db.take_bundle(),
+        busy,
+    })
+}
+
+/// Fold the lane bundles into `base`, the bundle holding the block's
+/// pre-execution changes.
+///
+/// Lanes touch disjoint accounts except the fee recipient, which every lane
+/// credits from the same starting balance; its balance is rebuilt from the
+/// sum of the lanes' credits. All reverts land in the single block `base`
+/// describes.
+pub fn merge_bundles(
+    mut base: BundleState,
+    lanes: Vec<BundleState>,
+    fee_recipient: Address,
+) -> BundleState {
+    let mut tips = U256::ZERO;
+    for lane in lanes {
+        for (address, account) in lane.state {
+            if address == fee_recipient {
+                let before = account.original_info.as_ref().map(|info| info.balance);
+                let after = account.info.as_ref().map(|info| info.balance);
+                tips += after.unwrap_or_default() - before.unwrap_or_default();
+                base.state.entry(address).or_insert(account);
+                continue;
+            }
+            base.state.insert(address, account);
+        }
+        base.contracts.extend(lane.contracts);
+
+        if base.reverts.is_empty() {
+            base.reverts.push(Vec::new());
+        }
+        for lane_reverts in lane.reverts.iter() {
+            for (address, revert) in lane_reverts {
+                // Every lane records the same pre-block fee recipient.
+                if *address == fee_recipient
+                    && base.reverts[0]
+                        .iter()
+                        .any(|(existing, _)| existing == address)
+                {
+                    continue;
+                }
+                base.reverts[0].push((*address, revert.clone()));
+            }
+        }
+    }
+
+    if let Some(account) = base.state.get_mut(&fee_recipient)
+        && let Some(info) = account.info.as_mut()
+    {
+        let before = account
+            .original_info
+            .as_ref()
+            .map(|info| info.balance)
+            .unwrap_or_default();
+        info.balance = before + tips;
+    }
+
+    base.state_size = base.state.len();
+    base.reverts_size = base.reverts.iter().map(Vec::len).sum();
+    base
+}
+
+/// Per-lane busy time and transactions over every lane-built block.
+#[derive(Debug, Clone, Default)]
+pub struct LaneReport {
+    lanes: Vec<LaneTotals>,
+    blocks: u64,
+    /// Wall time spent with lanes running.
+    wall: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LaneTotals {
+    txs: u64,
+    busy: Duration,
+}
+
+impl LaneReport {
+    /// Account for one block whose lanes ran for `wall` in total.
+    pub fn record_block(&mut self, outputs: &[LaneOutput], wall: Duration) {
+        if self.lanes.len() < outputs.len() {
+            self.lanes.resize(outputs.len(), LaneTotals::default());
+        }
+        for (totals, output) in self.lanes.iter_mut().zip(outputs) {
+            totals.txs += output.txs.len() as u64;
+            totals.busy += output.busy;
+        }
+        self.blocks += 1;
+        self.wall += wall;
+    }
+
+    /// Fraction of the parallel wall time `lane` spent executing.
+    fn utilization(&self, lane: &LaneTotals) -> f64 {
+        if self.wall.is_zero() {
+            return 0.0;
+        }
+        lane.busy.as_secs_f64() / self.wall.as_secs_f64()
+    }
+
+    /// Summed lane busy time over parallel wall time. This estimates the
+    /// speedup over running the same transactions serially, assuming each
+    /// costs the same on one thread as in a lane; no serial run is timed.
+    pub fn estimated_speedup(&self) -> f64 {
+        if self.wall.is_zero() {
+            return 0.0;
+        }
+        let busy: Duration = self.lanes.iter().map(|lane| lane.busy).sum();
+        busy.as_secs_f64() / self.wall.as_secs_f64()
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "blocks": self.blocks,
+            "wall_secs": self.wall.as_secs_f64(),
+            "estimated_speedup": self.estimated_speedup(),
+            "lanes": self
+                .lanes
+                .iter()
+                .map(|lane| json!({
+                    "txs": lane.txs,
+                    "busy_secs": lane.busy.as_secs_f64(),
+                    "utilization": self.utilization(lane),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Print a lane / txs / busy / utilization table.
+    pub fn print(&self) {
+        if self.blocks == 0 {
+            return;
+        }
+
+        println!(
+            "\nParallel lanes ({} blocks, {:.2}x estimated speedup from summed lane time):",
+            self.blocks,
+            self.estimated_speedup()
+        );
+        println!("{:-<1$}", "", 50);
+        println!(
+            "{:<6}  {:>12}  {:>12}  {:>12}",
+            "Lane", "Txs", "Busy (ms)", "Utilization"
+        );
+        println!("{:-<1$}", "", 50);
+        for (index, lane) in self.lanes.iter().enumerate() {
+            println!(
+                "{:<6}  {:>12}  {:>12}  {:>11.1}%",
+                index,
+                lane.txs,
+                lane.busy.as_millis(),
+                self.utilization(lane) * 100.0
+            );
+        }
+        println!("{:-<1$}", "", 50);
+    }
+}
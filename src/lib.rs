@@ -0,0 +1,52 @@
+//! Synthetic load generation for Reth: an orchestrator signs phased
+//! transaction workloads while a block builder executes them into blocks on a
+//! real database. [`simulation::Simulation`] runs one end to end; the
+//! `sandbox` binary wraps it in [`cli`].
+
+// `SimulationConfig::to_json` is one `json!` object with a key per setting.
+#![recursion_limit = "512"]
+
+mod actor;
+mod balances;
+mod block_builder;
+pub mod block_file;
+mod block_json;
+mod calibration;
+mod call_trace;
+mod chain;
+pub mod cli;
+pub mod config;
+mod create2;
+mod debug;
+mod deployments;
+pub mod error;
+mod faults;
+mod invalid;
+mod keygen;
+mod labels;
+mod lanes;
+pub mod logging;
+pub mod memory;
+mod metrics;
+mod multicall;
+mod orchestrator;
+mod ordering;
+mod payloads;
+mod permit;
+mod phase_checks;
+mod progress;
+mod retry;
+mod revert;
+mod roots;
+mod run_manifest;
+pub mod scenario;
+mod selfdestruct;
+mod senders;
+pub mod simulation;
+mod stages;
+mod stats;
+mod token;
+mod transaction;
+mod tx_stream;
+mod tx_writer;
+mod uniswap;
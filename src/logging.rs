@@ -0,0 +1,71 @@
+//! Log targets and the `tracing` subscriber the `sandbox` binary installs.
+//!
+//! Every event is logged under one of the targets below, so a
+//! `--log-filter` directive can pick them apart:
+//!
+//! | Target | Logged there |
+//! |---|---|
+//! | [`SIMULATION`] | Run-level checks and outcomes once both halves stop |
+//! | [`ORCHESTRATOR`] | Phases, batches, and setup as transactions are generated |
+//! | [`BUILDER`] | Block building, sealing, and database commits |
+//! | [`TX_FAILURES`] | Each reverted, halted, or wrongly rejected transaction, at `warn` |
+//! | [`WRITER`] | Artifacts written: manifests, traces, exported keys |
+//! | [`DEBUG`] | Debug reports and post-run state checks |
+//! | [`ACTOR`] | Actor nonce bookkeeping |
+//! | [`CHAIN`] | Chain spec and genesis |
+//! | [`PROGRESS`] | Progress heartbeats and phase transitions |
+//! | [`METRICS`] | Section timings and the Chrome trace |
+//! | [`CLI`] | Subcommands other than `run` |
+//!
+//! `info,sandbox::tx_failures=off`, for one, keeps block summaries while
+//! silencing per-transaction failures.
+
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+use crate::error::SandboxError;
+
+pub const SIMULATION: &str = "sandbox::simulation";
+pub const ORCHESTRATOR: &str = "sandbox::orchestrator";
+pub const BUILDER: &str = "sandbox::block_builder";
+pub const TX_FAILURES: &str = "sandbox::tx_failures";
+pub const WRITER: &str = "sandbox::writer";
+pub const DEBUG: &str = "sandbox::debug";
+pub const ACTOR: &str = "sandbox::actor";
+pub const CHAIN: &str = "sandbox::chain";
+pub const PROGRESS: &str = "sandbox::progress";
+pub const METRICS: &str = "sandbox::metrics";
+pub const CLI: &str = "sandbox::cli";
+
+/// How log lines are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// One line per event with its fields and spans.
+    #[default]
+    Full,
+    /// Multi-line, human-oriented output.
+    Pretty,
+    /// One shorter line per event.
+    Compact,
+    /// One JSON object per event, for log pipelines.
+    Json,
+}
+
+/// Install the global subscriber, rendering in `format` and filtering by
+/// `filter`, an `EnvFilter` directive; `RUST_LOG` is used when unset.
+pub fn init(format: LogFormat, filter: Option<&str>) -> Result<(), SandboxError> {
+    let filter = match filter {
+        Some(directive) => EnvFilter::try_new(directive).map_err(|err| {
+            SandboxError::Config(format!("invalid `--log-filter` {directive:?}: {err}"))
+        })?,
+        None => EnvFilter::from_default_env(),
+    };
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Full => subscriber.init(),
+        LogFormat::Pretty => subscriber.pretty().init(),
+        LogFormat::Compact => subscriber.compact().init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+    Ok(())
+}
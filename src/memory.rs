@@ -0,0 +1,62 @@
+//! Resident set size of the sandbox process, sampled by the progress reporter
+//! so a run that is heading for the OOM killer shows it in the log first.
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// Page size assumed when the kernel's cannot be read.
+const DEFAULT_PAGE_SIZE: u64 = 4096;
+
+/// Bytes resident in memory from a `/proc/<pid>/statm` line, whose second
+/// field is the resident page count.
+pub fn parse_statm(statm: &str, page_size: u64) -> Option<u64> {
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(resident_pages * page_size)
+}
+
+/// The process's current resident set size in bytes, or `None` when it cannot
+/// be read. Linux reads `/proc/self/statm`; elsewhere `ps` is asked.
+pub fn rss_bytes() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        let statm = fs::read_to_string("/proc/self/statm").ok()?;
+        return parse_statm(&statm, page_size());
+    }
+
+    // `ps` reports resident size in KiB.
+    let output = Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()?;
+    let kib: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// The kernel page size, from the first mapping in `/proc/self/smaps`. Read
+/// once; it cannot change while the process runs.
+fn page_size() -> u64 {
+    static PAGE_SIZE: OnceLock<u64> = OnceLock::new();
+    *PAGE_SIZE.get_or_init(|| {
+        File::open("/proc/self/smaps")
+            .ok()
+            .and_then(|smaps| {
+                BufReader::new(smaps)
+                    .lines()
+                    .map_while(Result::ok)
+                    .find_map(|line| {
+                        let kib = line
+                            .strip_prefix("KernelPageSize:")?
+                            .trim()
+                            .strip_suffix("kB")?;
+                        kib.trim().parse::<u64>().ok()
+                    })
+            })
+            .map_or(DEFAULT_PAGE_SIZE, |kib| kib * 1024)
+    })
+}
@@ -1,17 +1,127 @@
 // src/metrics.rs
+// With `metrics-off` the timing macros no longer reach the timers.
+#![cfg_attr(feature = "metrics-off", allow(dead_code))]
 use once_cell::sync::Lazy;
 use std::{
     borrow::Cow,
     collections::HashMap,
-    sync::Mutex,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread::{self, ThreadId},
     time::{Duration, Instant},
 };
+use tracing::warn;
+
+use crate::logging;
 
 #[derive(Default, Clone, Copy)]
 struct Accum {
     inclusive: Duration, // full span duration
     exclusive: Duration, // span minus time spent in child sections
     count: u64,
+    hist: Histogram, // distribution of inclusive durations
+}
+
+impl Accum {
+    fn record(&mut self, inclusive: Duration, exclusive: Duration) {
+        self.inclusive += inclusive;
+        self.exclusive += exclusive;
+        self.count += 1;
+        self.hist.record(inclusive);
+    }
+
+    fn merge(&mut self, other: &Accum) {
+        self.inclusive += other.inclusive;
+        self.exclusive += other.exclusive;
+        self.count += other.count;
+        self.hist.merge(&other.hist);
+    }
+}
+
+// -------- Latency histogram --------
+// Fixed log-linear buckets over nanoseconds: every power of two is split into
+// 2^SUB_BUCKET_BITS linear sub-buckets, so relative error stays under 25% and
+// memory is constant no matter how many samples are recorded.
+const SUB_BUCKET_BITS: u32 = 2;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const HIST_BUCKETS: usize = 64 << SUB_BUCKET_BITS;
+
+#[derive(Clone, Copy)]
+struct Histogram {
+    buckets: [u64; HIST_BUCKETS],
+    max: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; HIST_BUCKETS],
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl Histogram {
+    fn record(&mut self, sample: Duration) {
+        let nanos = u64::try_from(sample.as_nanos()).unwrap_or(u64::MAX);
+        self.buckets[Self::bucket_index(nanos)] += 1;
+        self.max = self.max.max(sample);
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.max = self.max.max(other.max);
+    }
+
+    fn bucket_index(nanos: u64) -> usize {
+        if nanos < SUB_BUCKETS {
+            return nanos as usize;
+        }
+        let msb = 63 - nanos.leading_zeros();
+        let shift = msb - SUB_BUCKET_BITS;
+        let sub = (nanos >> shift) & (SUB_BUCKETS - 1);
+        (((msb - SUB_BUCKET_BITS + 1) as u64) << SUB_BUCKET_BITS | sub) as usize
+    }
+
+    /// Largest value (in nanoseconds) that maps to bucket `index`.
+    fn bucket_upper_bound(index: usize) -> u64 {
+        let index = index as u64;
+        if index < SUB_BUCKETS {
+            return index;
+        }
+        let major = (index >> SUB_BUCKET_BITS) as u32;
+        let sub = index & (SUB_BUCKETS - 1);
+        let msb = major + SUB_BUCKET_BITS - 1;
+        let shift = msb - SUB_BUCKET_BITS;
+        let lower = (1u64 << msb) | (sub << shift);
+        lower.saturating_add((1u64 << shift) - 1)
+    }
+
+    /// Value at quantile `q` (0.0..=1.0), reported as the bucket's upper bound
+    /// clamped to the largest observed sample.
+    fn percentile(&self, q: f64) -> Duration {
+        let total: u64 = self.buckets.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let target = ((q * total as f64).ceil() as u64).clamp(1, total);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                let bound = Duration::from_nanos(Self::bucket_upper_bound(index));
+                return bound.min(self.max);
+            }
+        }
+        self.max
+    }
 }
 
 // We allow both 'static and owned names via Cow for flexibility.
@@ -42,9 +152,217 @@ impl Key {
     }
 }
 
-static SECTIONS: Lazy<Mutex<HashMap<Key, Accum>>> = Lazy::new(|| Mutex::new(HashMap::new()));
-static BLOCK_SECTIONS: Lazy<Mutex<HashMap<Key, HashMap<Key, Accum>>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+// -------- Kill switch --------
+// Timers check this once, as they start; one started while metrics were
+// enabled still records when it ends. Building with the `metrics-off` feature
+// turns the timing macros into no-ops and every check into a constant.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+const COMPILED_OUT: bool = cfg!(feature = "metrics-off");
+
+/// Turn timing on or off for every timer started from now on.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether new timers record anything.
+#[inline]
+pub fn enabled() -> bool {
+    !COMPILED_OUT && ENABLED.load(Ordering::Relaxed)
+}
+
+// -------- Section tables --------
+// Each thread records into its own tables, so timers on different threads
+// never contend on a lock. The tables are merged only when printed.
+#[derive(Default)]
+struct Sections {
+    totals: HashMap<Key, Accum>,
+    blocks: HashMap<Key, HashMap<Key, Accum>>,
+}
+
+impl Sections {
+    /// Apply `update` to the section's total and, when it is grouped under a
+    /// block, to its per-block entry.
+    fn update(&mut self, key: &Key, block: Option<&Key>, mut update: impl FnMut(&mut Accum)) {
+        update(self.totals.entry(key.clone()).or_default());
+        if let Some(block) = block {
+            let block_entry = self.blocks.entry(block.clone()).or_default();
+            update(block_entry.entry(key.clone()).or_default());
+        }
+    }
+
+    fn merge(&mut self, other: &Sections) {
+        for (key, accum) in &other.totals {
+            self.totals.entry(key.clone()).or_default().merge(accum);
+        }
+        for (block, sections) in &other.blocks {
+            let block_entry = self.blocks.entry(block.clone()).or_default();
+            for (key, accum) in sections {
+                block_entry.entry(key.clone()).or_default().merge(accum);
+            }
+        }
+    }
+}
+
+/// The tables of every live thread. Each is only locked by its own thread,
+/// except while a summary is being printed.
+static THREAD_SECTIONS: Lazy<Mutex<Vec<Arc<Mutex<Sections>>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+/// What exited threads recorded, folded in as each one exits.
+static EXITED_SECTIONS: Lazy<Mutex<Sections>> = Lazy::new(|| Mutex::new(Sections::default()));
+
+/// A thread's entry in [`THREAD_SECTIONS`], which it leaves on exit.
+struct LocalSections(Arc<Mutex<Sections>>);
+
+impl Drop for LocalSections {
+    fn drop(&mut self) {
+        // Hold the registry across the hand-over so a concurrent print sees
+        // these totals exactly once.
+        let mut registry = THREAD_SECTIONS.lock().unwrap();
+        registry.retain(|sections| !Arc::ptr_eq(sections, &self.0));
+        let local = self.0.lock().unwrap();
+        EXITED_SECTIONS.lock().unwrap().merge(&local);
+    }
+}
+
+thread_local! {
+    static LOCAL_SECTIONS: LocalSections = {
+        let sections = Arc::<Mutex<Sections>>::default();
+        THREAD_SECTIONS.lock().unwrap().push(Arc::clone(&sections));
+        LocalSections(sections)
+    };
+}
+
+/// Record into this thread's section tables.
+fn record_sections(record: impl FnOnce(&mut Sections)) {
+    let mut record = Some(record);
+    let recorded = LOCAL_SECTIONS.try_with(|local| {
+        (record.take().unwrap())(&mut local.0.lock().unwrap());
+    });
+    // A timer dropped while the thread's locals are being torn down records
+    // straight into the exited totals.
+    if recorded.is_err() {
+        (record.take().unwrap())(&mut EXITED_SECTIONS.lock().unwrap());
+    }
+}
+
+/// Every thread's section tables merged, exited threads included.
+fn collect_sections() -> Sections {
+    let registry = THREAD_SECTIONS.lock().unwrap();
+    let mut all = Sections::default();
+    for sections in registry.iter() {
+        all.merge(&sections.lock().unwrap());
+    }
+    all.merge(&EXITED_SECTIONS.lock().unwrap());
+    all
+}
+
+// -------- Counters --------
+static COUNTERS: Lazy<Mutex<HashMap<Key, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Handle to a named monotonically increasing counter.
+/// Use: `counter!("txs_skipped").increment(1);`
+pub struct Counter {
+    key: Key,
+}
+
+impl Counter {
+    /// Add `n` to the counter.
+    pub fn increment(&self, n: u64) {
+        let mut map = COUNTERS.lock().unwrap();
+        *map.entry(self.key.clone()).or_insert(0) += n;
+    }
+}
+
+pub fn counter(name: impl Into<Key>) -> Counter {
+    Counter { key: name.into() }
+}
+
+/// Every counter's value, by name. Counters are process-wide, so runs in the
+/// same process add to the same totals.
+pub fn counters_json() -> serde_json::Value {
+    let map = COUNTERS.lock().unwrap();
+    map.iter()
+        .map(|(key, value)| (key.as_str().to_string(), serde_json::Value::from(*value)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Write every counter to `path` as a `counter,value` CSV, sorted by name.
+pub fn write_counters_csv(path: &Path) -> eyre::Result<()> {
+    let map = COUNTERS.lock().unwrap();
+    let mut rows: Vec<_> = map.iter().collect();
+    rows.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "counter,value")?;
+    for (key, value) in rows {
+        writeln!(writer, "{},{value}", key.as_str())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// -------- Gauges --------
+// A gauge is a sampled point-in-time value (e.g. channel depth). We keep the
+// running min/max/sum so memory stays constant regardless of sample count.
+#[derive(Clone, Copy)]
+struct GaugeAccum {
+    last: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+    samples: u64,
+}
+
+impl Default for GaugeAccum {
+    fn default() -> Self {
+        Self {
+            last: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+            samples: 0,
+        }
+    }
+}
+
+impl GaugeAccum {
+    fn record(&mut self, value: u64) {
+        self.last = value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as u128;
+        self.samples += 1;
+    }
+
+    fn avg(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.samples as f64
+        }
+    }
+}
+
+static GAUGES: Lazy<Mutex<HashMap<Key, GaugeAccum>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Handle to a named gauge. Cheap to create; every `set` is one sample.
+/// Use: `gauge!("channel_depth").set(depth);`
+pub struct Gauge {
+    key: Key,
+}
+
+impl Gauge {
+    /// Record the gauge's current value.
+    pub fn set(&self, value: u64) {
+        let mut map = GAUGES.lock().unwrap();
+        map.entry(self.key.clone()).or_default().record(value);
+    }
+}
+
+pub fn gauge(name: impl Into<Key>) -> Gauge {
+    Gauge { key: name.into() }
+}
 
 // -------- Total run timer --------
 static RUN: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
@@ -73,6 +391,7 @@ thread_local! {
 }
 
 struct ActiveSpan {
+    id: u64,
     key: Key,
     block: Option<Key>,
     start: Instant,       // wall-clock start of this span (for inclusive)
@@ -81,9 +400,9 @@ struct ActiveSpan {
 }
 
 impl ActiveSpan {
-    fn new(key: Key, block: Option<Key>) -> Self {
-        let now = Instant::now();
+    fn new(id: u64, key: Key, block: Option<Key>, now: Instant) -> Self {
         Self {
+            id,
             key,
             block,
             start: now,
@@ -93,16 +412,88 @@ impl ActiveSpan {
     }
 }
 
-pub struct SectionTimer {
+// Unique span ids let Drop check it is closing *its own* span rather than
+// whatever happens to be on top of this thread's stack.
+static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Spans that were dropped on a thread whose stack didn't have them on top
+/// (typically a timer held across an `.await` that resumed elsewhere).
+const MISATTRIBUTED: &str = "misattributed";
+
+// A span dropped on another thread is still on its starting thread's stack.
+// The dropping thread leaves its id here, keyed by the starting thread, which
+// prunes it the next time it opens or closes a section.
+static ABANDONED_SPANS: Lazy<Mutex<HashMap<ThreadId, Vec<(u64, Instant)>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Spans in [`ABANDONED_SPANS`], so threads with none to prune skip the lock.
+static ABANDONED_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Mark span `id`, started on `thread`, as dropped at `now`.
+fn abandon_span(thread: ThreadId, id: u64, now: Instant) {
+    ABANDONED_SPANS
+        .lock()
+        .unwrap()
+        .entry(thread)
+        .or_default()
+        .push((id, now));
+    ABANDONED_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Remove the spans other threads dropped from this thread's `stack`. A live
+/// span uncovered by the pruning resumes from when the span above it ended.
+fn prune_abandoned(stack: &mut Vec<ActiveSpan>) {
+    if ABANDONED_COUNT.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let Some(abandoned) = ABANDONED_SPANS
+        .lock()
+        .unwrap()
+        .remove(&thread::current().id())
+    else {
+        return;
+    };
+    ABANDONED_COUNT.fetch_sub(abandoned.len() as u64, Ordering::Relaxed);
+    for (id, dropped_at) in abandoned {
+        let Some(index) = stack.iter().position(|span| span.id == id) else {
+            continue;
+        };
+        stack.remove(index);
+        if index == stack.len()
+            && let Some(parent) = stack.last_mut()
+        {
+            parent.last_resume = parent.last_resume.max(dropped_at);
+        }
+    }
+}
+
+/// `None` when metrics were disabled as the timer started.
+pub struct SectionTimer(Option<OpenSection>);
+
+struct OpenSection {
+    id: u64,
     key: Key,
     block: Option<Key>,
-    // field exists just so the type is not ZST; logic is in Drop + thread-local stack
-    _private: (),
+    start: Instant,
+    // Thread whose stack holds the span.
+    thread: ThreadId,
+    // Whether tracing captured the span's "B" event.
+    traced: bool,
 }
 
 /// Start a nested (exclusive) timing section.
 /// Use: `let _t = time_section!("execute_transaction");`
+///
+/// The exclusive-time stack is thread-local, so a `SectionTimer` must start and
+/// end on the same thread. Never hold one across an `.await` on a multi-thread
+/// runtime; use [`AsyncSectionTimer`] (or [`time_async_section`]) there instead.
+/// An unbalanced drop does not panic: it is logged and its inclusive time is
+/// recorded under a `misattributed` section.
 impl SectionTimer {
+    /// A timer that records nothing, for when metrics are disabled.
+    #[inline]
+    pub fn disabled() -> Self {
+        Self(None)
+    }
     #[inline]
     pub fn new_static(name: &'static str) -> Self {
         start_section(name.into(), None)
@@ -118,54 +509,77 @@ impl SectionTimer {
 }
 
 fn start_section(key: Key, block: Option<Key>) -> SectionTimer {
+    if !enabled() {
+        return SectionTimer::disabled();
+    }
+    let id = NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed);
+    let now = Instant::now();
     STACK.with(|stack| {
         let mut st = stack.borrow_mut();
-        let now = Instant::now();
+        prune_abandoned(&mut st);
         // Pause the current top (for exclusive accounting)
         if let Some(parent) = st.last_mut() {
             // Add parent's exclusive time up to now
             parent.paused_exclusive += now - parent.last_resume;
         }
         // Push this section
-        st.push(ActiveSpan::new(key.clone(), block.clone()));
+        st.push(ActiveSpan::new(id, key.clone(), block.clone(), now));
     });
-    SectionTimer {
+    let traced = trace_begin(id, &key, block.as_ref(), now);
+    SectionTimer(Some(OpenSection {
+        id,
         key,
         block,
-        _private: (),
-    }
+        start: now,
+        thread: thread::current().id(),
+        traced,
+    }))
 }
 
 impl Drop for SectionTimer {
     fn drop(&mut self) {
+        let Some(open) = self.0.as_ref().filter(|_| !COMPILED_OUT) else {
+            return;
+        };
+        if open.traced {
+            trace_end(open.id);
+        }
         STACK.with(|stack| {
             let mut st = stack.borrow_mut();
             let now = Instant::now();
-            let mut span = st.pop().expect("unbalanced SectionTimer");
+            prune_abandoned(&mut st);
+            let Some(span) = st.pop_if(|span| span.id == open.id) else {
+                // Our span isn't on top of this thread's stack (dropped on another
+                // thread or out of order). Take it off the stack it is on, and keep
+                // the wall-clock time so it doesn't silently vanish from the totals.
+                if open.thread == thread::current().id() {
+                    st.retain(|span| span.id != open.id);
+                } else {
+                    abandon_span(open.thread, open.id, now);
+                }
+                warn!(
+                    target: logging::METRICS,
+                    section = open.key.as_str(),
+                    "unbalanced SectionTimer drop, recording as misattributed"
+                );
+                let inclusive = now - open.start;
+                record_sections(|sections| {
+                    sections.update(&MISATTRIBUTED.into(), None, |accum| {
+                        accum.record(inclusive, inclusive)
+                    })
+                });
+                return;
+            };
 
             // Finalize this span's inclusive and exclusive times
             let inclusive = now - span.start;
             let exclusive = span.paused_exclusive + (now - span.last_resume);
 
-            // Record into global table
-            {
-                let mut map = SECTIONS.lock().unwrap();
-                let entry = map.entry(self.key.clone()).or_insert_with(Accum::default);
-                entry.inclusive += inclusive;
-                entry.exclusive += exclusive;
-                entry.count += 1;
-            }
-
-            if let Some(block_key) = self.block.clone() {
-                let mut map = BLOCK_SECTIONS.lock().unwrap();
-                let block_entry = map.entry(block_key).or_insert_with(HashMap::new);
-                let entry = block_entry
-                    .entry(self.key.clone())
-                    .or_insert_with(Accum::default);
-                entry.inclusive += inclusive;
-                entry.exclusive += exclusive;
-                entry.count += 1;
-            }
+            record_sections(|sections| {
+                sections.update(&open.key, open.block.as_ref(), |accum| {
+                    accum.record(inclusive, exclusive)
+                })
+            });
 
             // Resume parent (set its last_resume to now)
             if let Some(parent) = st.last_mut() {
@@ -175,7 +589,263 @@ impl Drop for SectionTimer {
     }
 }
 
+/// Timer that is safe to hold across `.await` points.
+///
+/// It captures its start on creation and records on drop without touching the
+/// thread-local stack, so it never affects (or is affected by) the exclusive
+/// accounting of nested [`SectionTimer`]s. Its exclusive time equals its
+/// inclusive time, and any `SectionTimer`s inside it are not subtracted.
+///
+/// `None` when metrics were disabled as the timer started.
+pub struct AsyncSectionTimer(Option<OpenAsyncSection>);
+
+struct OpenAsyncSection {
+    id: u64,
+    key: Key,
+    start: Instant,
+    traced: bool,
+}
+
+impl AsyncSectionTimer {
+    pub fn new(name: impl Into<Key>) -> Self {
+        if !enabled() {
+            return Self(None);
+        }
+        let id = NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed);
+        let key = name.into();
+        let start = Instant::now();
+        let traced = trace_begin(id, &key, None, start);
+        Self(Some(OpenAsyncSection {
+            id,
+            key,
+            start,
+            traced,
+        }))
+    }
+}
+
+impl Drop for AsyncSectionTimer {
+    fn drop(&mut self) {
+        let Some(open) = self.0.as_ref().filter(|_| !COMPILED_OUT) else {
+            return;
+        };
+        if open.traced {
+            trace_end(open.id);
+        }
+        let elapsed = open.start.elapsed();
+        record_sections(|sections| {
+            sections.update(&open.key, None, |accum| accum.record(elapsed, elapsed))
+        });
+    }
+}
+
+/// Samples of a hot section gathered locally and recorded in one go when the
+/// batch is dropped, so the hot path never takes the section map's lock.
+/// Use: `let mut exec = block_section_batch!(block, "execute_transaction");`
+/// then `exec.record(started.elapsed())` per sample.
+///
+/// Samples are timed by the caller and don't touch the exclusive-time stack,
+/// so a batch must not be recorded from inside another open section. They are
+/// not traced either.
+pub struct SectionBatch {
+    key: Key,
+    block: Option<Key>,
+    accum: Accum,
+    enabled: bool,
+}
+
+impl SectionBatch {
+    /// A batch that records nothing, for when metrics are disabled.
+    pub fn disabled() -> Self {
+        Self {
+            key: "".into(),
+            block: None,
+            accum: Accum::default(),
+            enabled: false,
+        }
+    }
+
+    pub fn new_grouped(block_label: impl Into<Key>, name: impl Into<Key>) -> Self {
+        Self {
+            key: name.into(),
+            block: Some(block_label.into()),
+            accum: Accum::default(),
+            enabled: enabled(),
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, elapsed: Duration) {
+        if self.enabled {
+            self.accum.record(elapsed, elapsed);
+        }
+    }
+}
+
+impl Drop for SectionBatch {
+    fn drop(&mut self) {
+        if COMPILED_OUT || self.accum.count == 0 {
+            return;
+        }
+        record_sections(|sections| {
+            sections.update(&self.key, self.block.as_ref(), |accum| {
+                accum.merge(&self.accum)
+            })
+        });
+    }
+}
+
+// ---------- Chrome trace export ----------
+// When enabled, every timer start/stop is buffered as a Chrome trace "B"/"E"
+// event (loadable in chrome://tracing or Perfetto). Disabled tracing costs a
+// single relaxed atomic load per timer.
+static TRACE_ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACE_EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+static TRACE_BUFFER: Lazy<Mutex<TraceBuffer>> = Lazy::new(Mutex::default);
+static NEXT_TRACE_TID: AtomicU64 = AtomicU64::new(1);
+
+/// Cap on buffered "B" events. Once reached, new spans are not traced (and
+/// counted as dropped), but spans already begun still get their "E" event, so
+/// the buffer holds at most twice this many entries and pairs always match.
+const TRACE_EVENT_CAP: usize = 1 << 20;
+
+thread_local! {
+    static TRACE_TID: u64 = NEXT_TRACE_TID.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Clone)]
+struct TraceEvent {
+    phase: char,
+    name: Key,
+    block: Option<Key>,
+    ts: Duration,
+    tid: u64,
+}
+
+/// Buffered events, and the "B" event of every span that has not ended yet.
+#[derive(Default)]
+struct TraceBuffer {
+    events: Vec<TraceEvent>,
+    /// Index in `events` of each open span's "B" event, by span id.
+    open: HashMap<u64, usize>,
+    begun: usize,
+    dropped: u64,
+}
+
+impl TraceBuffer {
+    /// Buffer the "B" event of span `id`, unless `cap` spans have begun.
+    fn begin(&mut self, cap: usize, id: u64, event: TraceEvent) -> bool {
+        if self.begun >= cap {
+            self.dropped += 1;
+            return false;
+        }
+        self.begun += 1;
+        self.open.insert(id, self.events.len());
+        self.events.push(event);
+        true
+    }
+
+    /// Buffer the "E" event of span `id`, on the thread and under the name
+    /// its "B" event was recorded with.
+    fn end(&mut self, id: u64, ts: Duration) {
+        let Some(index) = self.open.remove(&id) else {
+            return;
+        };
+        let begin = &self.events[index];
+        let end = TraceEvent {
+            phase: 'E',
+            ts: ts.max(begin.ts),
+            ..begin.clone()
+        };
+        self.events.push(end);
+    }
+
+    /// Every buffered event, with spans still open ended at `now` so no "B"
+    /// is left unmatched.
+    fn finished(&self, now: Duration) -> impl Iterator<Item = TraceEvent> + '_ {
+        let mut open: Vec<usize> = self.open.values().copied().collect();
+        open.sort_unstable();
+        let ends = open.into_iter().map(move |index| TraceEvent {
+            phase: 'E',
+            ts: now.max(self.events[index].ts),
+            ..self.events[index].clone()
+        });
+        self.events.iter().cloned().chain(ends)
+    }
+}
+
+/// Start buffering trace events for every timer created from now on.
+pub fn enable_trace() {
+    Lazy::force(&TRACE_EPOCH);
+    TRACE_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Buffer the start of span `id`; `false` if it is not traced.
+fn trace_begin(id: u64, key: &Key, block: Option<&Key>, now: Instant) -> bool {
+    if !TRACE_ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    let event = TraceEvent {
+        phase: 'B',
+        name: key.clone(),
+        block: block.cloned(),
+        ts: now.saturating_duration_since(*TRACE_EPOCH),
+        tid: TRACE_TID.with(|tid| *tid),
+    };
+    TRACE_BUFFER
+        .lock()
+        .unwrap()
+        .begin(TRACE_EVENT_CAP, id, event)
+}
+
+fn trace_end(id: u64) {
+    let ts = Instant::now().saturating_duration_since(*TRACE_EPOCH);
+    TRACE_BUFFER.lock().unwrap().end(id, ts);
+}
+
+/// Write every buffered event to `path` in Chrome trace JSON format. Spans
+/// still open are ended at the time of writing.
+pub fn write_chrome_trace(path: &Path) -> eyre::Result<()> {
+    let buffer = TRACE_BUFFER.lock().unwrap();
+    let now = Instant::now().saturating_duration_since(*TRACE_EPOCH);
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_all(b"{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+    for (i, event) in buffer.finished(now).enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let mut entry = serde_json::json!({
+            "name": event.name.as_str(),
+            "cat": "section",
+            "ph": event.phase.to_string(),
+            "ts": event.ts.as_secs_f64() * 1_000_000.0,
+            "pid": 1,
+            "tid": event.tid,
+        });
+        if let Some(block) = &event.block {
+            entry["args"] = serde_json::json!({ "block": block.as_str() });
+        }
+        serde_json::to_writer(&mut writer, &entry)?;
+    }
+    writer.write_all(b"]}")?;
+    writer.flush()?;
+
+    if buffer.dropped > 0 {
+        warn!(
+            target: logging::METRICS,
+            dropped = buffer.dropped,
+            cap = TRACE_EVENT_CAP,
+            "trace buffer full, some spans were not traced"
+        );
+    }
+    Ok(())
+}
+
 // ---------- Convenience macros ----------
+// Names are only formatted while metrics are enabled. With the `metrics-off`
+// feature the timing macros expand to `()` and nothing is timed at all.
+#[cfg(not(feature = "metrics-off"))]
 #[macro_export]
 macro_rules! time_section {
     // 1) Static string (no allocation)
@@ -184,36 +854,129 @@ macro_rules! time_section {
         };
     // 2) format!-style usage (allocates a String)
     ($fmt:literal, $($arg:tt)+) => {{
-        $crate::metrics::SectionTimer::new_owned(format!($fmt, $($arg)+))
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_owned(format!($fmt, $($arg)+))
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
     }};
     // 3) Any &str/String expr
     ($name:expr) => {{
-        $crate::metrics::SectionTimer::new_owned($name.to_string())
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_owned($name.to_string())
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
     }};
 }
+#[cfg(not(feature = "metrics-off"))]
 #[macro_export]
 macro_rules! time_section_owned {
     ($name_expr:expr) => {
-        $crate::metrics::SectionTimer::new_owned($name_expr.to_string())
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_owned($name_expr.to_string())
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
     };
 }
 
+#[cfg(not(feature = "metrics-off"))]
 #[macro_export]
 macro_rules! time_block_section {
     ($block:expr, $name:literal) => {{
-        $crate::metrics::SectionTimer::new_grouped(format!("block {}", $block), $name)
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_grouped(format!("block {}", $block), $name)
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
     }};
     ($block:expr, $fmt:literal, $($arg:tt)+) => {{
-        $crate::metrics::SectionTimer::new_grouped(
-            format!("block {}", $block),
-            format!($fmt, $($arg)+)
-        )
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_grouped(
+                format!("block {}", $block),
+                format!($fmt, $($arg)+)
+            )
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
     }};
     ($block:expr, $name:expr) => {{
-        $crate::metrics::SectionTimer::new_grouped(
-            format!("block {}", $block),
-            $name
-        )
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionTimer::new_grouped(
+                format!("block {}", $block),
+                $name
+            )
+        } else {
+            $crate::metrics::SectionTimer::disabled()
+        }
+    }};
+}
+
+#[cfg(not(feature = "metrics-off"))]
+#[macro_export]
+macro_rules! block_section_batch {
+    ($block:expr, $name:literal) => {{
+        if $crate::metrics::enabled() {
+            $crate::metrics::SectionBatch::new_grouped(format!("block {}", $block), $name)
+        } else {
+            $crate::metrics::SectionBatch::disabled()
+        }
+    }};
+}
+
+#[cfg(feature = "metrics-off")]
+#[macro_export]
+macro_rules! time_section {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+#[cfg(feature = "metrics-off")]
+#[macro_export]
+macro_rules! time_section_owned {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+#[cfg(feature = "metrics-off")]
+#[macro_export]
+macro_rules! time_block_section {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+#[cfg(feature = "metrics-off")]
+#[macro_export]
+macro_rules! block_section_batch {
+    ($($args:tt)*) => {
+        $crate::metrics::SectionBatch::disabled()
+    };
+}
+
+#[macro_export]
+macro_rules! counter {
+    ($name:literal) => {
+        $crate::metrics::counter($name)
+    };
+    ($fmt:literal, $($arg:tt)+) => {{
+        $crate::metrics::counter(format!($fmt, $($arg)+))
+    }};
+    ($name:expr) => {{
+        $crate::metrics::counter($name.to_string())
+    }};
+}
+
+#[macro_export]
+macro_rules! gauge {
+    ($name:literal) => {
+        $crate::metrics::gauge($name)
+    };
+    ($fmt:literal, $($arg:tt)+) => {{
+        $crate::metrics::gauge(format!($fmt, $($arg)+))
+    }};
+    ($name:expr) => {{
+        $crate::metrics::gauge($name.to_string())
     }};
 }
 
@@ -222,16 +985,71 @@ pub async fn time_async_section<F, T>(name: &'static str, fut: F) -> T
 where
     F: std::future::Future<Output = T>,
 {
-    let _t = SectionTimer::new_static(name);
+    let _t = AsyncSectionTimer::new(name);
     fut.await
 }
 
 // ---------- Printing ----------
+// Count + Incl + Excl + Avg Excl + p50/p90/p99/max columns, plus separators.
+const SECTION_TABLE_W: usize = 56 + 4 * 12;
+
+fn print_section_header(name_w: usize) {
+    println!("{:-<1$}", "", name_w + SECTION_TABLE_W);
+    println!(
+        "{:<name_w$}  {:>10}  {:>14}  {:>14}  {:>14}  {:>10}  {:>10}  {:>10}  {:>10}",
+        "Section",
+        "Count",
+        "Incl (ms)",
+        "Excl (ms)",
+        "Avg Excl (ms)",
+        "p50 (ms)",
+        "p90 (ms)",
+        "p99 (ms)",
+        "Max (ms)",
+        name_w = name_w
+    );
+    println!("{:-<1$}", "", name_w + SECTION_TABLE_W);
+}
+
+/// Print one section row and return its exclusive time in milliseconds.
+fn print_section_row(name: &str, acc: &Accum, name_w: usize) -> f64 {
+    let incl_ms = acc.inclusive.as_secs_f64() * 1000.0;
+    let excl_ms = acc.exclusive.as_secs_f64() * 1000.0;
+    let avg_excl = if acc.count > 0 {
+        excl_ms / acc.count as f64
+    } else {
+        0.0
+    };
+    let pct_ms = |q: f64| acc.hist.percentile(q).as_secs_f64() * 1000.0;
+    println!(
+        "{:<name_w$}  {:>10}  {:>14.3}  {:>14.3}  {:>14.3}  {:>10.3}  {:>10.3}  {:>10.3}  {:>10.3}",
+        name,
+        acc.count,
+        incl_ms,
+        excl_ms,
+        avg_excl,
+        pct_ms(0.50),
+        pct_ms(0.90),
+        pct_ms(0.99),
+        acc.hist.max.as_secs_f64() * 1000.0,
+        name_w = name_w
+    );
+    excl_ms
+}
+
 pub fn print_section_summary() {
     // Prefer to call run_end() before printing.
     let total = run_total();
 
-    let map = SECTIONS.lock().unwrap();
+    if !enabled() {
+        println!("\nSection timings: not recorded, metrics were disabled.");
+        print_counter_summary();
+        print_gauge_summary();
+        return;
+    }
+
+    let sections = collect_sections();
+    let map = &sections.totals;
 
     // width calc
     let mut name_w = "Section".len();
@@ -240,39 +1058,13 @@ pub fn print_section_summary() {
     }
 
     println!();
-    println!("{:-<1$}", "", name_w + 56);
-    println!(
-        "{:<name_w$}  {:>10}  {:>14}  {:>14}  {:>14}",
-        "Section",
-        "Count",
-        "Incl (ms)",
-        "Excl (ms)",
-        "Avg Excl (ms)",
-        name_w = name_w
-    );
-    println!("{:-<1$}", "", name_w + 56);
+    print_section_header(name_w);
 
     let mut sum_exclusive = 0.0f64;
     for (key, acc) in map.iter() {
-        let incl_ms = acc.inclusive.as_secs_f64() * 1000.0;
-        let excl_ms = acc.exclusive.as_secs_f64() * 1000.0;
-        sum_exclusive += excl_ms;
-        let avg_excl = if acc.count > 0 {
-            excl_ms / acc.count as f64
-        } else {
-            0.0
-        };
-        println!(
-            "{:<name_w$}  {:>10}  {:>14.3}  {:>14.3}  {:>14.3}",
-            &key.0,
-            acc.count,
-            incl_ms,
-            excl_ms,
-            avg_excl,
-            name_w = name_w
-        );
+        sum_exclusive += print_section_row(&key.0, acc, name_w);
     }
-    println!("{:-<1$}", "", name_w + 56);
+    println!("{:-<1$}", "", name_w + SECTION_TABLE_W);
 
     if total > Duration::ZERO {
         let total_ms = total.as_secs_f64() * 1000.0;
@@ -281,9 +1073,10 @@ pub fn print_section_summary() {
         println!("UNATTRIBUTED (ms) {:>10}  {:>14.3}", "", unattributed_ms);
     }
 
-    drop(map);
+    print_counter_summary();
+    print_gauge_summary();
 
-    let block_map = BLOCK_SECTIONS.lock().unwrap();
+    let block_map = &sections.blocks;
     if block_map.is_empty() {
         return;
     }
@@ -302,39 +1095,242 @@ pub fn print_section_summary() {
             name_w = name_w.max(name.as_str().len());
         }
 
-        println!("{:-<1$}", "", name_w + 56);
-        println!(
-            "{:<name_w$}  {:>10}  {:>14}  {:>14}  {:>14}",
-            "Section",
-            "Count",
-            "Incl (ms)",
-            "Excl (ms)",
-            "Avg Excl (ms)",
-            name_w = name_w
-        );
-        println!("{:-<1$}", "", name_w + 56);
+        print_section_header(name_w);
 
         let mut section_rows: Vec<_> = sections.iter().collect();
         section_rows.sort_by(|(_, a), (_, b)| b.exclusive.cmp(&a.exclusive));
 
         for (name, acc) in section_rows {
-            let incl_ms = acc.inclusive.as_secs_f64() * 1000.0;
-            let excl_ms = acc.exclusive.as_secs_f64() * 1000.0;
-            let avg_excl = if acc.count > 0 {
-                excl_ms / acc.count as f64
-            } else {
-                0.0
-            };
-            println!(
-                "{:<name_w$}  {:>10}  {:>14.3}  {:>14.3}  {:>14.3}",
-                name.as_str(),
-                acc.count,
-                incl_ms,
-                excl_ms,
-                avg_excl,
-                name_w = name_w
+            print_section_row(name.as_str(), acc, name_w);
+        }
+        println!("{:-<1$}", "", name_w + SECTION_TABLE_W);
+    }
+}
+
+fn print_counter_summary() {
+    let map = COUNTERS.lock().unwrap();
+    if map.is_empty() {
+        return;
+    }
+
+    let mut name_w = "Counter".len();
+    for k in map.keys() {
+        name_w = name_w.max(k.as_str().len());
+    }
+
+    println!("\nCounters:");
+    println!("{:-<1$}", "", name_w + 16);
+    println!("{:<name_w$}  {:>14}", "Counter", "Value", name_w = name_w);
+    println!("{:-<1$}", "", name_w + 16);
+
+    let mut rows: Vec<_> = map.iter().collect();
+    rows.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+    for (key, value) in rows {
+        println!("{:<name_w$}  {:>14}", key.as_str(), value, name_w = name_w);
+    }
+    println!("{:-<1$}", "", name_w + 16);
+}
+
+fn print_gauge_summary() {
+    let map = GAUGES.lock().unwrap();
+    if map.is_empty() {
+        return;
+    }
+
+    let mut name_w = "Gauge".len();
+    for k in map.keys() {
+        name_w = name_w.max(k.as_str().len());
+    }
+
+    println!("\nGauges:");
+    println!("{:-<1$}", "", name_w + 72);
+    println!(
+        "{:<name_w$}  {:>10}  {:>14}  {:>14}  {:>14}  {:>10}",
+        "Gauge",
+        "Samples",
+        "Min",
+        "Avg",
+        "Max",
+        "Last",
+        name_w = name_w
+    );
+    println!("{:-<1$}", "", name_w + 72);
+
+    let mut rows: Vec<_> = map.iter().collect();
+    rows.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+    for (key, acc) in rows {
+        println!(
+            "{:<name_w$}  {:>10}  {:>14}  {:>14.1}  {:>14}  {:>10}",
+            key.as_str(),
+            acc.samples,
+            if acc.samples > 0 { acc.min } else { 0 },
+            acc.avg(),
+            acc.max,
+            acc.last,
+            name_w = name_w
+        );
+    }
+    println!("{:-<1$}", "", name_w + 72);
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+
+    fn event(phase: char, name: &'static str, micros: u64) -> TraceEvent {
+        TraceEvent {
+            phase,
+            name: name.into(),
+            block: None,
+            ts: Duration::from_micros(micros),
+            tid: 1,
+        }
+    }
+
+    #[test]
+    fn capped_trace_leaves_no_begin_unmatched() {
+        let mut buffer = TraceBuffer::default();
+        assert!(buffer.begin(2, 10, event('B', "first", 1)));
+        assert!(buffer.begin(2, 11, event('B', "second", 2)));
+        // Past the cap a span is not traced, so its end adds nothing either.
+        assert!(!buffer.begin(2, 12, event('B', "third", 3)));
+        buffer.end(12, Duration::from_micros(4));
+        buffer.end(11, Duration::from_micros(5));
+        assert_eq!(buffer.dropped, 1);
+
+        // "first" is still open as the trace is written, so it is ended then.
+        let events: Vec<_> = buffer.finished(Duration::from_micros(9)).collect();
+        let phases: Vec<_> = events
+            .iter()
+            .map(|event| (event.phase, event.name.as_str()))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                ('B', "first"),
+                ('B', "second"),
+                ('E', "second"),
+                ('E', "first"),
+            ]
+        );
+        assert_eq!(events[3].ts, Duration::from_micros(9));
+    }
+
+    #[cfg(not(feature = "metrics-off"))]
+    #[test]
+    fn written_trace_pairs_every_begin_with_an_end() {
+        enable_trace();
+        let outer = SectionTimer::new_static("test_trace_outer");
+        drop(SectionTimer::new_static("test_trace_inner"));
+        drop(AsyncSectionTimer::new("test_trace_async"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.json");
+        write_chrome_trace(&path).unwrap();
+        drop(outer);
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let mut depth: HashMap<(u64, String), i64> = HashMap::new();
+        for event in trace["traceEvents"].as_array().unwrap() {
+            let key = (
+                event["tid"].as_u64().unwrap(),
+                event["name"].as_str().unwrap().to_string(),
             );
+            let open = depth.entry(key).or_default();
+            match event["ph"].as_str().unwrap() {
+                "B" => *open += 1,
+                "E" => *open -= 1,
+                phase => panic!("unexpected phase {phase}"),
+            }
+            assert!(*open >= 0, "E before its B");
         }
-        println!("{:-<1$}", "", name_w + 56);
+        assert!(depth.values().all(|&open| open == 0), "unmatched B events");
+        for name in ["test_trace_outer", "test_trace_inner", "test_trace_async"] {
+            assert!(
+                depth.keys().any(|(_, traced)| traced == name),
+                "{name} not traced"
+            );
+        }
+    }
+
+    fn open_spans() -> usize {
+        STACK.with(|stack| stack.borrow().len())
+    }
+
+    #[cfg(not(feature = "metrics-off"))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn span_dropped_on_a_worker_is_pruned_from_its_starting_thread() {
+        let outer = SectionTimer::new_static("test_outer");
+        let moved = SectionTimer::new_static("test_moved");
+        assert_eq!(open_spans(), 2);
+
+        // The test body runs outside the worker pool, so the spawned task
+        // drops the timer on another thread.
+        tokio::spawn(async move { drop(moved) }).await.unwrap();
+        assert_eq!(open_spans(), 2);
+
+        // The next section this thread opens finds the dead span and drops
+        // it, so the outer span is on top again and closes normally.
+        drop(SectionTimer::new_static("test_inner"));
+        assert_eq!(open_spans(), 1);
+        drop(outer);
+        assert_eq!(open_spans(), 0);
+        assert!(
+            collect_sections()
+                .totals
+                .contains_key(&Key::from("test_outer"))
+        );
+    }
+
+    #[test]
+    fn bimodal_percentiles_land_in_their_own_mode() {
+        let fast = Duration::from_micros(1);
+        let slow = Duration::from_millis(1);
+        let mut hist = Histogram::default();
+        for _ in 0..900 {
+            hist.record(fast);
+        }
+        for _ in 0..100 {
+            hist.record(slow);
+        }
+
+        // Up to the 900th sample the fast mode answers, within its bucket's
+        // 25% error; past it the slow one does, clamped to the true maximum.
+        for q in [0.0, 0.5, 0.9] {
+            let value = hist.percentile(q);
+            assert!(value >= fast && value < fast * 5 / 4, "p{q} was {value:?}");
+        }
+        assert_eq!(hist.percentile(0.91), slow);
+        assert_eq!(hist.percentile(0.99), slow);
+        assert_eq!(hist.percentile(1.0), slow);
+        assert_eq!(Histogram::default().percentile(0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn concurrent_increments_are_all_counted_and_exported() {
+        (0..64).into_par_iter().for_each(|_| {
+            for _ in 0..1_000 {
+                counter!("test_concurrent_increments").increment(1);
+            }
+        });
+
+        assert_eq!(
+            counters_json()["test_concurrent_increments"].as_u64(),
+            Some(64_000)
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counters.csv");
+        write_counters_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(path).unwrap();
+        assert_eq!(csv.lines().next(), Some("counter,value"));
+        assert!(
+            csv.lines()
+                .any(|row| row == "test_concurrent_increments,64000")
+        );
     }
 }
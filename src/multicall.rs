@@ -0,0 +1,48 @@
+//! Batching contract that forwards many calls from a single transaction, so
+//! one tx fans out into a deep call tree and many storage writes.
+//!
+//! `Batcher` is hand-assembled rather than compiled, so it takes a packed
+//! calldata format instead of an ABI-encoded `aggregate`: a sequence of
+//! `target (20 bytes) || length (4 bytes, big-endian) || data`. Calls run in
+//! order and the whole transaction reverts with the failing call's revert data.
+
+use alloy_primitives::{Address, Bytes};
+use alloy_sol_macro::sol;
+
+use crate::transaction::TRANSFER_GAS_LIMIT;
+
+sol!(
+    #[allow(missing_docs)]
+    Batcher,
+    "artifacts/Batcher.json"
+);
+
+/// Gas budgeted per forwarded call; covers a token transfer that mints into
+/// the batcher and writes a fresh recipient balance slot.
+pub const GAS_PER_BATCHED_CALL: u64 = 80_000;
+
+/// Encoders for deploying and calling [`Batcher`].
+pub struct BatcherHelper;
+
+impl BatcherHelper {
+    /// Creation bytecode.
+    pub fn deploy() -> Bytes {
+        Batcher::BYTECODE.clone()
+    }
+
+    /// Pack `(target, calldata)` pairs into the batcher's calldata format.
+    pub fn batch(calls: &[(Address, Bytes)]) -> Bytes {
+        let mut out = Vec::with_capacity(calls.iter().map(|(_, data)| 24 + data.len()).sum());
+        for (target, data) in calls {
+            out.extend_from_slice(target.as_slice());
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(data);
+        }
+        out.into()
+    }
+
+    /// Gas limit for a batch of `calls` forwarded calls.
+    pub fn gas_limit(calls: u64) -> u64 {
+        TRANSFER_GAS_LIMIT * 2 + calls * GAS_PER_BATCHED_CALL
+    }
+}
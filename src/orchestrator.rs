@@ -1,75 +1,240 @@
 //! Generates transaction load in distinct phases while the block builder ingests
 //! the resulting channel.
 
-use alloy_consensus::{EthereumTxEnvelope, TxEip4844};
-use alloy_primitives::{Address, TxKind, U256};
-use rand::Rng;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy_consensus::{EthereumTxEnvelope, Transaction, TxEip4844};
+use alloy_primitives::{Address, B256, Bytes, TxKind, U256, keccak256, map::HashMap};
+use eyre::WrapErr;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 use reth_primitives_traits::Recovered;
-use tokio::sync::mpsc::Sender;
-use tracing::{debug, info};
+use tokio::{
+    sync::{
+        broadcast,
+        mpsc::{Sender, error::TrySendError},
+        oneshot,
+    },
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
 
 use crate::{
-    actor::ActorPool,
-    config::SimulationConfig,
-    token::{SandboxTokenHelper, TokenPool},
-    transaction::tx,
-    uniswap::{Uniswap, UniswapV2FactoryHelper, UniswapV2Router02Helper},
+    actor::{ActorPool, NonceCorrections},
+    balances::BalanceEstimates,
+    config::{AmountRange, DeployVia, SimulationConfig, Stage, TokenTemplate, Workload},
+    counter,
+    create2::{Create2DeployerHelper, create2_address},
+    deployments::{DeployedToken, DeploymentManifest, ExpectedCode},
+    error::SandboxError,
+    faults::FaultInjection,
+    gauge,
+    invalid::{InvalidKind, InvalidTxRegistry},
+    labels::{LabeledTx, SimulationPhase, TxLabel},
+    logging,
+    metrics::AsyncSectionTimer,
+    multicall::BatcherHelper,
+    permit::{Permit, PermitNonces, sign_permit, uniswap_v2_domain},
+    phase_checks::{PhaseCheck, PhaseChecks},
+    progress::{PhaseEvent, PhaseSpan, RunProgress},
+    scenario::StepType,
+    selfdestruct::{DestroyedAccounts, DestructibleHelper},
+    senders::SenderPicker,
+    stats::GenerationStats,
+    token::{SandboxTokenHelper, TOKEN_DEPLOY_GAS_LIMIT, TokenPool, token_init_code},
+    transaction::{
+        DEFAULT_GAS_LIMIT, TRANSFER_GAS_LIMIT, TxTemplate, sign_batch, tx, tx_for_chain,
+        tx_with_gas_limit, tx_with_priority_fee, verify_sender,
+    },
+    tx_stream::{TxStreamReader, TxStreamWriter},
+    uniswap::{Uniswap, UniswapV2FactoryHelper, UniswapV2Router02Helper, WethHelper},
 };
 
 /// Convenience alias for recovered EIP-4844 envelopes sent across the channel.
 pub type TX = Recovered<EthereumTxEnvelope<TxEip4844>>;
 
-#[derive(Debug, Clone, Copy)]
-enum TransactionType {
-    /// Simple ETH transfer between EOAs.
-    EthTransfer,
-    /// ERC20 transfer using the sandbox token.
-    TokenTransfer,
-    /// Spend token to receive WETH via the router.
-    UniswapSwapForEth,
-    /// Spend ETH to acquire a token via the router.
-    UniswapSwapForToken,
-}
+/// Smallest batch adaptive sizing will shrink to (pool creation needs 3 txs per token).
+const MIN_ADAPTIVE_BATCH_SIZE: u64 = 30;
 
-/// Stages the simulation walks through before issuing steady-state load.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SimulationPhase {
-    /// Allocate ETH from deployer to the actor pool.
-    ActorFunding,
-    /// Deploy ERC20 bytecode.
-    TokenDeployment,
-    /// Deploy Uniswap contracts.
-    UniswapDeployment,
-    /// Seed each token with a pool and liquidity.
-    UniswapPoolCreation,
-    /// Send limitless user-style transactions. Mixes transaction types.
-    TransactionLoad,
-}
+/// Wei in one ETH, and base units in one whole 18-decimal token.
+const WEI_PER_ETH: u128 = 10u128.pow(18);
+
+/// ETH each setup pool is seeded with, in wei.
+const POOL_ETH_RESERVE: u128 = 10_000 * WEI_PER_ETH;
+
+/// Tokens each setup pool is seeded with, and the router is approved for.
+const POOL_TOKEN_RESERVE: u128 = 1_000_000 * WEI_PER_ETH;
+
+/// LP tokens burned by each permit-based liquidity removal.
+const PERMIT_REMOVAL_LIQUIDITY: u64 = 1_000_000_000_000;
+
+/// Storage writes each `Destructible` receives before it self-destructs.
+const DESTRUCTIBLE_WRITES: u8 = 3;
+
+/// First CREATE2 salt used for `Destructible`s; token salts count up from 0.
+const DESTRUCTIBLE_SALT_BASE: u64 = 1 << 32;
+
+/// Sends that wait at least this long for channel capacity are counted as
+/// blocked in `orchestrator_sends_blocked_slow`.
+const SLOW_SEND_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// Adaptive sizing never grows past `std_batch_size * MAX_BATCH_GROWTH`.
+const MAX_BATCH_GROWTH: u64 = 16;
+
+/// Progress phase reported while actor keys are derived, before any batch.
+const ACTOR_GENERATION: &str = "actor-generation";
 
 /// Drives high-level simulation phases and emits signed transactions onto the
 /// the block builder.
 pub struct TransactionOrchestrator {
-    sender: Sender<TX>,
+    sender: Sender<LabeledTx>,
     config: SimulationConfig,
     actor_pool: ActorPool,
     token_contract_pool: TokenPool,
     uniswap: Option<Uniswap>,
+    /// Whether the deployer holds its funds: from genesis, or once the faucet
+    /// has sent a separate deployer its share.
+    deployer_funded: bool,
     actors_funded: u64,
     tokens_deployed: u64,
     token_pools_created: u64,
+    /// Current batch size; starts at `std_batch_size` and only moves when
+    /// adaptive batching is enabled.
+    batch_size: u64,
+    /// Receives the deployment manifest once the setup phases are complete.
+    deployments_tx: Option<oneshot::Sender<DeploymentManifest>>,
+    /// Where the deployment manifest is written as the setup phases
+    /// complete, and the genesis it is recorded against.
+    deployments_out: Option<(PathBuf, B256)>,
+    /// Live counters shared with the progress reporter.
+    progress: Arc<RunProgress>,
+    /// Hashes of deliberately invalid transactions, checked by the builder.
+    invalid_txs: Arc<InvalidTxRegistry>,
+    /// WETH each actor (by index) has deposited and not yet withdrawn, so
+    /// withdrawals never exceed deposits.
+    weth_balances: HashMap<usize, U256>,
+    /// EIP-2612 nonces of permits signed so far.
+    permit_nonces: PermitNonces,
+    /// Address of the batcher contract once deployed.
+    batcher: Option<Address>,
+    /// Tokens deployed by actors during load. They have no Uniswap pools, so
+    /// only plain token transfers target them.
+    actor_tokens: TokenPool,
+    /// Address of the CREATE2 factory once deployed.
+    create2_deployer: Option<Address>,
+    /// Accounts whose self-destruct should delete them from state.
+    destroyed_accounts: Arc<DestroyedAccounts>,
+    /// Setup contracts the builder checks for once they are deployed.
+    expected_code: Arc<ExpectedCode>,
+    /// State the builder checks each setup phase left.
+    phase_checks: Arc<PhaseChecks>,
+    /// On-chain nonces of senders the builder skipped transactions from.
+    nonce_corrections: Arc<NonceCorrections>,
+    /// Transactions generated so far, for one-in-N sender verification.
+    senders_seen: u64,
+    /// Actor index round-robin sender selection resumes at.
+    next_sender: usize,
+    /// Receives an event every time a phase is entered or exited.
+    phase_events: broadcast::Sender<PhaseEvent>,
+    /// The phase currently generating batches.
+    active_phase: Option<ActivePhase>,
+    /// Everything generated so far, by type and phase.
+    stats: Arc<GenerationStats>,
+    /// Source of every random choice, seeded from `actor_seed` when set so a
+    /// seeded run generates the same transactions every time.
+    rng: StdRng,
+    /// Transient work overlaid on the phase from `current_phase`.
+    pending: VecDeque<PendingWork>,
+    /// Lower bounds on actor balances, kept only when top-ups are enabled.
+    balances: Option<BalanceEstimates>,
+    /// Blocks built at which balances are next checked for top-ups.
+    next_top_up_check: u64,
+    /// Scenario blocks generated so far.
+    scenario_blocks_generated: usize,
+    /// Transfers-only load transactions generated so far, for
+    /// `faults.fail_transfer_at`.
+    transfers_generated: u64,
+    /// Records every transaction sent into the channel, when set.
+    tx_stream: Option<TxStreamWriter>,
+    /// Recorded stream sent in place of generated transactions, when set.
+    replay: Option<TxStreamReader>,
+    /// Faults built into the generated transactions; none outside tests.
+    faults: FaultInjection,
+}
+
+/// Work queued on top of the steady phase, generated after each of its
+/// batches until the queue is empty.
+enum PendingWork {
+    /// Send `amount` wei from the deployer to the actor at index `actor`.
+    TopUp { actor: usize, amount: U256 },
+}
+
+impl PendingWork {
+    /// Phase the work's transactions are attributed to.
+    fn phase(&self) -> SimulationPhase {
+        match self {
+            Self::TopUp { .. } => SimulationPhase::FundingTopUp,
+        }
+    }
+}
+
+/// Bookkeeping for the phase the orchestrator is in.
+struct ActivePhase {
+    phase: SimulationPhase,
+    /// Time from the start of the run to entering the phase.
+    started: Duration,
+    txs_generated: u64,
+    /// Records the `phase::<Phase>` section when dropped.
+    _timer: AsyncSectionTimer,
 }
 
 impl TransactionOrchestrator {
     /// Wire together helper pools using the genesis deployer as the root signer.
-    pub fn new(sender: Sender<TX>, config: SimulationConfig) -> Self {
-        let actor_pool = ActorPool::new(
-            config.genesis_private_key,
-            config.genesis_address,
-            config.chain_id,
-        );
+    pub fn new(
+        sender: Sender<LabeledTx>,
+        config: SimulationConfig,
+        deployments_tx: oneshot::Sender<DeploymentManifest>,
+        progress: Arc<RunProgress>,
+        invalid_txs: Arc<InvalidTxRegistry>,
+        destroyed_accounts: Arc<DestroyedAccounts>,
+        expected_code: Arc<ExpectedCode>,
+        phase_checks: Arc<PhaseChecks>,
+        nonce_corrections: Arc<NonceCorrections>,
+        phase_events: broadcast::Sender<PhaseEvent>,
+        stats: Arc<GenerationStats>,
+    ) -> Self {
+        let actor_pool = ActorPool::for_config(&config);
+        let deployer_funded = !actor_pool.has_separate_faucet();
+        // Keyed apart from the actor keys derived from the same seed.
+        let rng = match config.actor_seed {
+            Some(seed) => StdRng::from_seed(keccak256([seed.as_slice(), b"generator"].concat()).0),
+            None => StdRng::from_os_rng(),
+        };
 
         let token_contract_pool = TokenPool::new();
+        let batch_size = config.std_batch_size;
+        // Genesis-funded actors need no funding transactions.
+        let actors_funded = if config.prefund_actors_in_genesis {
+            config.unique_accounts
+        } else {
+            0
+        };
+        let balances = config.top_up_every_blocks.map(|_| {
+            let initial = if config.prefund_actors_in_genesis {
+                config.actor_funding_amount
+            } else {
+                U256::ZERO
+            };
+            BalanceEstimates::new(config.unique_accounts as usize, initial)
+        });
+        let next_top_up_check = config.top_up_every_blocks.unwrap_or_default();
 
         Self {
             sender,
@@ -77,347 +242,1825 @@ impl TransactionOrchestrator {
             actor_pool,
             token_contract_pool,
             uniswap: None,
-            actors_funded: 0,
+            deployer_funded,
+            actors_funded,
             tokens_deployed: 0,
             token_pools_created: 0,
+            batch_size,
+            deployments_tx: Some(deployments_tx),
+            deployments_out: None,
+            progress,
+            invalid_txs,
+            weth_balances: HashMap::default(),
+            permit_nonces: PermitNonces::default(),
+            batcher: None,
+            actor_tokens: TokenPool::new(),
+            create2_deployer: None,
+            destroyed_accounts,
+            expected_code,
+            phase_checks,
+            nonce_corrections,
+            senders_seen: 0,
+            next_sender: 0,
+            phase_events,
+            active_phase: None,
+            stats,
+            rng,
+            pending: VecDeque::new(),
+            balances,
+            next_top_up_check,
+            scenario_blocks_generated: 0,
+            transfers_generated: 0,
+            tx_stream: None,
+            replay: None,
+            faults: FaultInjection::default(),
         }
     }
 
+    /// Record every transaction sent into the channel to `stream`.
+    pub fn with_tx_stream(mut self, stream: TxStreamWriter) -> Self {
+        self.tx_stream = Some(stream);
+        self
+    }
+
+    /// Write the deployment manifest for `genesis_hash` to `path` as soon as
+    /// the setup phases are complete, so it exists while the load runs.
+    pub fn with_deployments_out(mut self, path: PathBuf, genesis_hash: B256) -> Self {
+        self.deployments_out = Some((path, genesis_hash));
+        self
+    }
+
+    /// Build `faults` into the generated transactions.
+    #[cfg(feature = "test-hooks")]
+    pub fn with_faults(mut self, faults: FaultInjection) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Send the transactions recorded in `stream` instead of generating any.
+    pub fn replaying(mut self, stream: TxStreamReader) -> Self {
+        self.replay = Some(stream);
+        self
+    }
+
+    /// Start from what a setup stage left behind: the actors with their
+    /// nonces, and every contract the setup deployed. Every setup phase is
+    /// then complete, so the first batch is load.
+    pub fn with_setup(mut self, actor_pool: ActorPool, deployments: &DeploymentManifest) -> Self {
+        let initial_supply = self.config.token_initial_supply;
+        for &DeployedToken {
+            address,
+            deployer,
+            nonce,
+            template,
+        } in &deployments.tokens
+        {
+            self.token_contract_pool.add_token(
+                address,
+                nonce,
+                initial_supply,
+                actor_pool.actor_index(&deployer),
+                template,
+            );
+        }
+        self.uniswap = deployments
+            .uniswap
+            .map(|uniswap| Uniswap::new(uniswap.factory, uniswap.router, uniswap.weth));
+        self.batcher = deployments.batcher;
+        self.create2_deployer = deployments.create2_deployer;
+
+        self.deployer_funded = true;
+        self.actors_funded = actor_pool.len() as u64;
+        self.tokens_deployed = deployments.tokens.len() as u64;
+        self.token_pools_created = deployments.pairs.len() as u64;
+        // Setup spends little next to the funding, so actors are assumed to
+        // start the load with what they were funded.
+        if self.balances.is_some() {
+            self.balances = Some(BalanceEstimates::new(
+                actor_pool.len(),
+                self.config.actor_funding_amount,
+            ));
+        }
+        self.actor_pool = actor_pool;
+        self
+    }
+
     /// Spawn the orchestration loop and streams batches of transactions to the block builder.
-    pub async fn run(mut self) -> eyre::Result<()> {
-        tokio::spawn(async move {
+    ///
+    /// The returned handle resolves once the builder closes the channel, handing
+    /// back the actor pool (with the nonces of unsent transactions rewound) so
+    /// callers can reconcile it against on-chain state and export it. It
+    /// resolves to an error if a batch could not be generated; dropping the
+    /// sender then stops the builder as well.
+    pub async fn run(mut self) -> eyre::Result<JoinHandle<eyre::Result<ActorPool>>> {
+        let handle = tokio::spawn(async move {
+            if let Some(stream) = self.replay.take() {
+                return self.replay_stream(stream).await;
+            }
             info!(
-                target: "sandbox::orchestrator",
+                target: logging::ORCHESTRATOR,
                 accounts = self.config.unique_accounts,
                 tokens = self.config.unique_tokens,
                 gas_limit = self.config.gas_limit,
                 "starting transaction orchestration"
             );
-            //generate actors to use
-            self.actor_pool.generate_actors(self.config.unique_accounts);
+            // A load stage already has the setup's actors.
+            if self.actor_pool.is_empty() {
+                // Without a configured seed the keys are still derived from one,
+                // drawn once, so generation need not hit the OS RNG per key.
+                let seed = self.config.actor_seed.unwrap_or_else(B256::random);
+                self.progress.set_phase(ACTOR_GENERATION);
+                self.actor_pool.generate_actors_from_seed(
+                    seed,
+                    self.config.unique_accounts,
+                    &self.config.fee_strategy_mix,
+                    &self.progress,
+                );
+            }
+            self.nonce_corrections.set_actors(&self.actor_pool);
             debug!(
-                target: "sandbox::orchestrator",
+                target: logging::ORCHESTRATOR,
                 generated_actors = self.actor_pool.len(),
                 "actor pool ready"
             );
 
-            let mut last_phase: Option<SimulationPhase> = None;
-
             loop {
                 //run main loop
 
                 let phase = self.current_phase();
-                if last_phase != Some(phase) {
+                if phase == SimulationPhase::TransactionLoad && self.config.stage == Stage::Setup {
+                    info!(target: logging::ORCHESTRATOR, "setup complete, stopping before the load");
+                    self.publish_deployments()?;
+                    self.exit_phase();
+                    return Ok(self.actor_pool);
+                }
+                if phase == SimulationPhase::TransactionLoad && self.scenario_finished() {
+                    info!(target: logging::ORCHESTRATOR, "scenario complete, stopping orchestration");
+                    self.exit_phase();
+                    return Ok(self.actor_pool);
+                }
+                if self.active_phase.as_ref().map(|active| active.phase) != Some(phase) {
                     info!(
-                        target: "sandbox::orchestrator",
+                        target: logging::ORCHESTRATOR,
                         ?phase,
                         "entering simulation phase"
                     );
-                    last_phase = Some(phase);
+                    self.enter_phase(phase);
+
+                    if phase == SimulationPhase::TransactionLoad {
+                        self.publish_deployments()?;
+                    }
                 }
 
-                let batch = self.generate_batch();
+                self.adapt_batch_size();
+                let mut batch = self
+                    .generate_batch(phase)
+                    .wrap_err_with(|| format!("failed to generate a {} batch", phase.name()))?;
+                self.record_generated(phase, &batch)?;
+                if let Some(active) = self.active_phase.as_mut() {
+                    active.txs_generated += LabeledTx::flatten(&batch).count() as u64;
+                }
+                if batch.is_empty() {
+                    warn!(
+                        target: logging::ORCHESTRATOR,
+                        ?phase,
+                        "phase produced no transactions, stopping orchestration"
+                    );
+                    self.exit_phase();
+                    return Ok(self.actor_pool);
+                }
 
-                for tx in batch {
-                    if let Err(e) = self.sender.send(tx).await {
-                        // Channel closed - builder is done
-                        debug!(target: "sandbox::orchestrator", "channel closed, stopping orchestration");
-                        return;
-                    }
+                // Pending work rides behind the steady batch instead of
+                // pausing it.
+                self.schedule_top_ups(phase);
+                if let Some(overlay) = self.pending.front().map(PendingWork::phase) {
+                    let overlaid = self.generate_batch(overlay).wrap_err_with(|| {
+                        format!("failed to generate a {} batch", overlay.name())
+                    })?;
+                    self.record_generated(overlay, &overlaid)?;
+                    batch.extend(overlaid);
+                }
+                if let Some(balances) = self.balances.as_mut() {
+                    balances.record_batch(&batch, &self.actor_pool);
+                }
+
+                let records = self
+                    .tx_stream
+                    .as_ref()
+                    .map(|_| TxStreamWriter::encode(&batch));
+                let sent = self.send_batch(batch).await;
+                if let (Some(stream), Some(records)) = (self.tx_stream.as_mut(), records) {
+                    let undelivered = sent
+                        .as_ref()
+                        .err()
+                        .map_or(0, |undelivered| LabeledTx::flatten(undelivered).count());
+                    let delivered = records.len() - undelivered;
+                    stream.append(&records[..delivered])?;
+                }
+                if let Err(undelivered) = sent {
+                    // Channel closed - builder is done
+                    debug!(target: logging::ORCHESTRATOR, "channel closed, stopping orchestration");
+                    self.discard_undelivered(&undelivered);
+                    self.exit_phase();
+                    return Ok(self.actor_pool);
                 }
             }
         });
 
+        Ok(handle)
+    }
+
+    /// Send a recorded stream batch by batch, attributing each batch to the
+    /// phase it was recorded in. Injected invalid transactions are registered
+    /// again so the builder expects their rejection.
+    async fn replay_stream(mut self, stream: TxStreamReader) -> eyre::Result<ActorPool> {
+        info!(target: logging::ORCHESTRATOR, "replaying a recorded transaction stream");
+        let mut stream = stream.peekable();
+        while let Some(first) = stream.next() {
+            let first = first?;
+            let phase = first.phase;
+            let mut batch = vec![first];
+            while (batch.len() as u64) < self.batch_size
+                && let Some(Ok(next)) = stream.peek()
+                && next.phase == phase
+            {
+                batch.extend(stream.next().transpose()?);
+            }
+
+            if self.active_phase.as_ref().map(|active| active.phase) != Some(phase) {
+                self.enter_phase(phase);
+            }
+            for labeled in &batch {
+                if labeled.label == TxLabel::Invalid {
+                    self.invalid_txs.register(*labeled.tx.hash());
+                }
+            }
+            self.record_generated(phase, &batch)?;
+            if let Some(active) = self.active_phase.as_mut() {
+                active.txs_generated += batch.len() as u64;
+            }
+            if self.send_batch(batch).await.is_err() {
+                debug!(target: logging::ORCHESTRATOR, "channel closed, stopping replay");
+                break;
+            }
+        }
+        self.exit_phase();
+        Ok(self.actor_pool)
+    }
+
+    /// Verify and count a batch generated during `phase`.
+    fn record_generated(
+        &mut self,
+        phase: SimulationPhase,
+        batch: &[LabeledTx],
+    ) -> eyre::Result<()> {
+        self.verify_senders(batch)
+            .wrap_err_with(|| format!("bad signature in a {} batch", phase.name()))?;
+        let txs = LabeledTx::flatten(batch).count() as u64;
+        counter!("transactions_generated").increment(txs);
+        self.progress.record_generated(txs);
+        self.stats.record_batch(phase.name(), batch);
         Ok(())
     }
 
-    /// Dispatch to a specialized batch generator based on the current phase.
-    fn generate_batch(&mut self) -> Vec<TX> {
-        match self.current_phase() {
-            SimulationPhase::ActorFunding => self.generate_actor_funding_batch(),
-            SimulationPhase::TokenDeployment => self.generate_token_deployment_batch(),
-            SimulationPhase::UniswapDeployment => self.generate_uniswap_deployment_batch(),
-            SimulationPhase::UniswapPoolCreation => self.generate_uniswap_pool_creation_batch(),
-            SimulationPhase::TransactionLoad => self.generate_transaction_load_batch(),
+    /// Every `top_up_every_blocks` blocks of load, queue a top-up for each
+    /// actor estimated to hold less than `top_up_threshold`, bringing it back
+    /// to `actor_funding_amount`.
+    fn schedule_top_ups(&mut self, phase: SimulationPhase) {
+        let (Some(every), Some(balances)) =
+            (self.config.top_up_every_blocks, self.balances.as_ref())
+        else {
+            return;
+        };
+        if phase != SimulationPhase::TransactionLoad || !self.pending.is_empty() {
+            return;
+        }
+        let blocks_built = self.progress.snapshot().blocks_built;
+        if blocks_built < self.next_top_up_check {
+            return;
         }
+        self.next_top_up_check = blocks_built + every;
+
+        let low = balances.below(
+            self.config.top_up_threshold,
+            self.config.actor_funding_amount,
+        );
+        if low.is_empty() {
+            return;
+        }
+        info!(
+            target: logging::ORCHESTRATOR,
+            actors = low.len(),
+            blocks_built,
+            "topping up actors running low on ETH"
+        );
+        counter!("funding_top_ups").increment(low.len() as u64);
+        self.stats.record_top_up_round(low.len() as u64);
+        self.pending.extend(
+            low.into_iter()
+                .map(|(actor, amount)| PendingWork::TopUp { actor, amount }),
+        );
+    }
+
+    /// Enqueue `batch` in order, without waiting while the channel has room.
+    /// A group goes as one message, so nothing can come between its members.
+    ///
+    /// Waits for capacity are timed under the `orchestrator_send_blocked`
+    /// section, so generation blocked on the builder shows up separately from
+    /// the builder starving for transactions. If the builder closes the channel
+    /// the transactions that were not enqueued are returned.
+    async fn send_batch(&self, batch: Vec<LabeledTx>) -> Result<(), Vec<LabeledTx>> {
+        let mut batch = batch.into_iter();
+        while let Some(tx) = batch.next() {
+            let tx = match self.sender.try_send(tx) {
+                Ok(()) => continue,
+                Err(TrySendError::Full(tx)) => tx,
+                Err(TrySendError::Closed(tx)) => {
+                    return Err(std::iter::once(tx).chain(batch).collect());
+                }
+            };
+
+            let waited = Instant::now();
+            let permit = {
+                let _timer = AsyncSectionTimer::new("orchestrator_send_blocked");
+                self.sender.reserve().await
+            };
+            let Ok(permit) = permit else {
+                return Err(std::iter::once(tx).chain(batch).collect());
+            };
+            permit.send(tx);
+
+            counter!("orchestrator_sends_blocked").increment(1);
+            if waited.elapsed() >= SLOW_SEND_THRESHOLD {
+                counter!("orchestrator_sends_blocked_slow").increment(1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Roll back the nonces consumed by transactions the builder will never
+    /// see. Ones already queued in the channel are rewound by the caller once
+    /// the builder has been drained.
+    fn discard_undelivered(&mut self, undelivered: &[LabeledTx]) {
+        if undelivered.is_empty() {
+            return;
+        }
+
+        let undelivered = LabeledTx::flatten(undelivered).collect::<Vec<_>>();
+        self.stats.record_undelivered(undelivered.len() as u64);
+        warn!(
+            target: logging::ORCHESTRATOR,
+            undelivered = undelivered.len(),
+            "builder closed the channel mid-batch, rewinding nonces of unsent transactions"
+        );
+        self.actor_pool.rewind_unsent(
+            undelivered.iter().map(|labeled| &labeled.tx),
+            &self.invalid_txs,
+        );
+    }
+
+    /// Close out the previous phase and start timing `phase` under a
+    /// `phase::<Phase>` metrics section.
+    fn enter_phase(&mut self, phase: SimulationPhase) {
+        self.exit_phase();
+
+        let at = self.progress.snapshot().elapsed;
+        self.progress.set_phase(phase.name());
+        self.active_phase = Some(ActivePhase {
+            phase,
+            started: at,
+            txs_generated: 0,
+            _timer: AsyncSectionTimer::new(format!("phase::{phase:?}")),
+        });
+        // Nobody may be listening; the timeline is best effort.
+        let _ = self.phase_events.send(PhaseEvent::Entered {
+            phase: phase.name(),
+            at,
+        });
+    }
+
+    /// Record the end of the current phase, if any.
+    fn exit_phase(&mut self) {
+        let Some(active) = self.active_phase.take() else {
+            return;
+        };
+
+        let span = PhaseSpan {
+            phase: active.phase.name(),
+            started: active.started,
+            duration: self
+                .progress
+                .snapshot()
+                .elapsed
+                .saturating_sub(active.started),
+            txs_generated: active.txs_generated,
+        };
+        let _ = self.phase_events.send(PhaseEvent::Exited(span));
+    }
+
+    /// Write the addresses deployed during setup, then hand them back to
+    /// whoever is listening.
+    fn publish_deployments(&mut self) -> eyre::Result<()> {
+        let Some(deployments_tx) = self.deployments_tx.take() else {
+            return Ok(());
+        };
+
+        let mut manifest = DeploymentManifest::new(
+            self.config.chain_id,
+            &self.actor_pool,
+            &self.token_contract_pool,
+            self.uniswap.as_ref(),
+            self.batcher,
+            self.create2_deployer,
+        );
+        if let Some((path, genesis_hash)) = &self.deployments_out {
+            manifest.genesis_hash = Some(*genesis_hash);
+            manifest.write(path)?;
+            info!(target: logging::WRITER, path = %path.display(), "wrote deployment manifest");
+        }
+        // The receiver may already be gone if the run ended during setup.
+        let _ = deployments_tx.send(manifest);
+        Ok(())
+    }
+
+    /// Double the batch size while the channel is nearly empty (builder starved)
+    /// and halve it while nearly full (builder is the bottleneck).
+    fn adapt_batch_size(&mut self) {
+        if !self.config.adaptive_batch_size {
+            return;
+        }
+
+        let max = self.sender.max_capacity() as u64;
+        let depth = max - self.sender.capacity() as u64;
+        let max_batch_size = self.config.std_batch_size.saturating_mul(MAX_BATCH_GROWTH);
+
+        if depth * 10 <= max {
+            self.batch_size = (self.batch_size * 2).min(max_batch_size);
+        } else if depth * 10 >= max * 9 {
+            self.batch_size = (self.batch_size / 2).max(MIN_ADAPTIVE_BATCH_SIZE);
+        }
+
+        gauge!("orchestrator_batch_size").set(self.batch_size);
+    }
+
+    /// Recover the sender of one in `verify_senders_one_in` transactions of
+    /// `batch` and fail on the first that does not match its claimed signer.
+    fn verify_senders(&mut self, batch: &[LabeledTx]) -> Result<(), SandboxError> {
+        if !self.config.verify_senders {
+            return Ok(());
+        }
+
+        let one_in = self.config.verify_senders_one_in;
+        let offset = self.senders_seen;
+        let txs = LabeledTx::flatten(batch).collect::<Vec<_>>();
+        self.senders_seen += txs.len() as u64;
+        let sampled = txs
+            .par_iter()
+            .enumerate()
+            .filter(|(index, _)| (offset + *index as u64) % one_in == 0)
+            .map(|(_, labeled)| verify_sender(&labeled.tx).map(|()| 1u64))
+            .try_reduce(|| 0, |a, b| Ok(a + b))?;
+        counter!("senders_verified").increment(sampled);
+        Ok(())
+    }
+
+    /// Dispatch to a specialized batch generator for `phase` and label what
+    /// it produced.
+    fn generate_batch(&mut self, phase: SimulationPhase) -> eyre::Result<Vec<LabeledTx>> {
+        self.reconcile_nonces();
+        // Every setup phase emits a single kind of transaction.
+        let (txs, label) = match phase {
+            SimulationPhase::DeployerFunding => (
+                self.generate_deployer_funding_batch()?,
+                TxLabel::DeployerFunding,
+            ),
+            SimulationPhase::ActorFunding => {
+                (self.generate_actor_funding_batch()?, TxLabel::ActorFunding)
+            }
+            SimulationPhase::FundingTopUp => (self.generate_top_up_batch()?, TxLabel::FundingTopUp),
+            SimulationPhase::TokenDeployment => (
+                self.generate_token_deployment_batch()?,
+                TxLabel::TokenDeployment,
+            ),
+            SimulationPhase::UniswapDeployment => (
+                self.generate_uniswap_deployment_batch()?,
+                TxLabel::UniswapDeployment,
+            ),
+            SimulationPhase::UniswapPoolCreation => (
+                self.generate_uniswap_pool_creation_batch()?,
+                TxLabel::UniswapPoolCreation,
+            ),
+            SimulationPhase::MulticallDeployment => (
+                self.generate_multicall_deployment_batch()?,
+                TxLabel::MulticallDeployment,
+            ),
+            SimulationPhase::Create2DeployerDeployment => (
+                self.generate_create2_deployer_deployment_batch()?,
+                TxLabel::Create2DeployerDeployment,
+            ),
+            SimulationPhase::TransactionLoad => {
+                let mut batch = if self.config.scenario.is_some() {
+                    self.generate_scenario_block()?
+                } else {
+                    match self.config.workload {
+                        Workload::Mixed => self.generate_transaction_load_batch()?,
+                        Workload::TransfersOnly => LabeledTx::label_all(
+                            self.generate_transfer_load_batch()?,
+                            TxLabel::EthTransfer,
+                            phase,
+                        ),
+                    }
+                };
+                self.inject_invalid_txs(&mut batch)?;
+                return Ok(batch);
+            }
+        };
+        Ok(LabeledTx::label_all(txs, label, phase))
+    }
+
+    /// Reset the tracked nonce of every sender the builder skipped
+    /// transactions from to its on-chain nonce, so the next batch continues
+    /// from there instead of leaving a gap the builder would keep skipping.
+    fn reconcile_nonces(&mut self) {
+        let corrections = self.nonce_corrections.take();
+        if corrections.is_empty() {
+            return;
+        }
+        let corrected = self.actor_pool.reconcile_nonces(corrections);
+        counter!("nonce_corrections").increment(corrected as u64);
+    }
+
+    /// Append roughly `invalid_tx_rate * batch.len()` deliberately invalid
+    /// transactions and register their hashes.
+    ///
+    /// They go after every valid transaction in the batch, so each sender's
+    /// on-chain nonce equals its tracked nonce by the time they execute. That
+    /// keeps "too low" and "too high" nonces wrong without disturbing the
+    /// valid transactions.
+    fn inject_invalid_txs(&mut self, batch: &mut Vec<LabeledTx>) -> eyre::Result<()> {
+        let rate = self.config.invalid_tx_rate;
+        let num_actors = self.actor_pool.len();
+        if rate <= 0.0 || num_actors == 0 {
+            return Ok(());
+        }
+
+        let count = (0..LabeledTx::flatten(batch).count())
+            .filter(|_| self.rng.random_bool(rate))
+            .count();
+        for _ in 0..count {
+            let index = self.rng.random_range(0..num_actors);
+            let kind = InvalidKind::ALL[self.rng.random_range(0..InvalidKind::ALL.len())];
+            let Some((signer, nonce)) = self.actor_pool.actor_info(index) else {
+                continue;
+            };
+            let to = TxKind::Call(signer.address());
+            let value = Some(U256::from(1));
+
+            let tx = match kind {
+                InvalidKind::NonceTooLow if nonce > 0 => {
+                    tx_with_gas_limit(signer, nonce - 1, to, value, None, TRANSFER_GAS_LIMIT)?
+                }
+                InvalidKind::NonceTooHigh | InvalidKind::NonceTooLow => {
+                    tx_with_gas_limit(signer, nonce + 1, to, value, None, TRANSFER_GAS_LIMIT)?
+                }
+                InvalidKind::InsufficientBalance => tx_with_gas_limit(
+                    signer,
+                    nonce,
+                    to,
+                    Some(U256::from(u128::MAX)),
+                    None,
+                    TRANSFER_GAS_LIMIT,
+                )?,
+                InvalidKind::GasBelowIntrinsic => {
+                    tx_with_gas_limit(signer, nonce, to, value, None, TRANSFER_GAS_LIMIT - 1)?
+                }
+                InvalidKind::WrongChainId => tx_for_chain(
+                    signer,
+                    nonce,
+                    to,
+                    value,
+                    None,
+                    TRANSFER_GAS_LIMIT,
+                    self.config.chain_id + 1,
+                )?,
+            };
+
+            self.invalid_txs.register(*tx.hash());
+            counter!("invalid_transactions_injected").increment(1);
+            debug!(target: logging::ORCHESTRATOR, %kind, hash = %tx.hash(), "injected invalid transaction");
+            batch.push(LabeledTx::new(
+                tx,
+                TxLabel::Invalid,
+                SimulationPhase::TransactionLoad,
+            ));
+        }
+        Ok(())
     }
 
     /// Fund each synthetic actor from the genesis deployer so later phases can
     /// rely on independent nonces.
-    fn generate_actor_funding_batch(&mut self) -> Vec<TX> {
+    fn generate_deployer_funding_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        let deployer = self.actor_pool.deployer().address();
+        let faucet = self.actor_pool.faucet_mut();
+        let template = TxTemplate::new(
+            faucet.nonce(),
+            TxKind::Call(deployer),
+            Some(self.config.deployer_funding_amount),
+            None,
+        );
+        let txs = sign_batch(faucet.signer(), vec![template])?;
+        faucet.increment_nonce_by(1);
+
+        self.expect_after_phase(
+            SimulationPhase::DeployerFunding,
+            PhaseCheck::Funded,
+            [deployer],
+        );
+        self.deployer_funded = true;
+        Ok(txs)
+    }
+
+    fn generate_actor_funding_batch(&mut self) -> eyre::Result<Vec<TX>> {
         let batch_size = std::cmp::min(
-            self.config.std_batch_size,
+            self.batch_size,
             self.config.unique_accounts - self.actors_funded,
         );
 
+        let recipients = (self.actors_funded..self.actors_funded + batch_size)
+            .filter_map(|index| self.actor_pool.actor_address(index as usize))
+            .collect::<Vec<Address>>();
+        let batch_size = recipients.len() as u64;
+
         let (g_signer, g_nonce) = self.actor_pool.deployer_info();
 
-        let txs = (0..batch_size)
-            .into_par_iter()
-            .map(|i| {
-                tx(
-                    &g_signer,
-                    g_nonce + i,
-                    TxKind::Call(self.actor_pool.actor_address((g_nonce + i) as usize)),
-                    Some(U256::from(1_000_000e18)),
+        let templates = recipients
+            .iter()
+            .zip(g_nonce..)
+            .map(|(recipient, nonce)| {
+                TxTemplate::new(
+                    nonce,
+                    TxKind::Call(*recipient),
+                    Some(self.config.actor_funding_amount),
                     None,
                 )
             })
-            .collect::<Vec<TX>>();
+            .collect();
+        let txs = sign_batch(g_signer, templates)?;
 
+        self.expect_after_phase(
+            SimulationPhase::ActorFunding,
+            PhaseCheck::Funded,
+            recipients,
+        );
         self.actor_pool.increment_deployer_nonce_by(batch_size);
         self.actors_funded += batch_size;
 
-        txs
+        Ok(txs)
+    }
+
+    /// Send up to a batch of queued top-ups from the deployer.
+    fn generate_top_up_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        let count = std::cmp::min(self.batch_size as usize, self.pending.len());
+        let top_ups = self
+            .pending
+            .drain(..count)
+            .filter_map(|work| {
+                let PendingWork::TopUp { actor, amount } = work;
+                Some((self.actor_pool.actor_address(actor)?, amount))
+            })
+            .collect::<Vec<(Address, U256)>>();
+
+        let (g_signer, g_nonce) = self.actor_pool.deployer_info();
+        let templates = top_ups
+            .iter()
+            .zip(g_nonce..)
+            .map(|(&(recipient, amount), nonce)| {
+                TxTemplate::new(nonce, TxKind::Call(recipient), Some(amount), None)
+                    .with_gas_limit(TRANSFER_GAS_LIMIT)
+            })
+            .collect();
+        let txs = sign_batch(g_signer, templates)?;
+
+        self.actor_pool
+            .increment_deployer_nonce_by(txs.len() as u64);
+        Ok(txs)
     }
 
     /// Deploy simple ERC20 contracts and remember their deterministic addresses.
-    fn generate_token_deployment_batch(&mut self) -> Vec<TX> {
+    fn generate_token_deployment_batch(&mut self) -> eyre::Result<Vec<TX>> {
         let batch_size = std::cmp::min(
-            self.config.std_batch_size,
+            self.batch_size,
             self.config.unique_tokens - self.tokens_deployed,
         );
 
-        let (g_signer, g_nonce) = self.actor_pool.deployer_info();
+        let initial_supply = self.config.token_initial_supply;
 
-        for i in 0..batch_size {
-            let token_address: Address = g_signer.address().create(g_nonce + i);
-            self.token_contract_pool.add_token(token_address);
-        }
+        // With a CREATE2 factory, token `n` lands at the address derived from
+        // salt `n`, whatever nonce its deployer happens to be at.
+        let factory = self.create2_deployer;
+        let first = self.tokens_deployed;
 
-        let data = SandboxTokenHelper::deploy();
+        // Each deployer's templates stay in nonce order; deployers are signed
+        // one after another.
+        let mut templates: BTreeMap<Option<usize>, Vec<TxTemplate>> = BTreeMap::new();
+        for n in first..first + batch_size {
+            let deployer = self.token_deployer(n);
+            let owner = self
+                .actor_pool
+                .owner_mut(deployer)
+                .ok_or_else(|| eyre::eyre!("token deployer {deployer:?} is not an actor"))?;
+            let nonce = owner.nonce();
+            owner.increment_nonce_by(1);
 
-        let txs = (0..batch_size)
-            .into_par_iter()
-            .map(|i| {
-                tx(
-                    &g_signer,
-                    g_nonce + i,
-                    TxKind::Create,
-                    None,
-                    Some(data.clone()),
-                )
-            })
-            .collect::<Vec<TX>>();
+            let token_template = self.config.token_template(n);
+            let data = token_init_code(token_template, n, initial_supply);
+            let salt = B256::from(U256::from(n));
+            let (token_address, template) = match factory {
+                Some(factory) => (
+                    create2_address(factory, salt, keccak256(&data)),
+                    TxTemplate::new(
+                        nonce,
+                        TxKind::Call(factory),
+                        None,
+                        Some(Create2DeployerHelper::deploy_call(salt, &data)),
+                    ),
+                ),
+                None => (
+                    owner.contract_address(nonce),
+                    TxTemplate::new(nonce, TxKind::Create, None, Some(data)),
+                ),
+            };
+            self.token_contract_pool.add_token(
+                token_address,
+                nonce,
+                initial_supply,
+                deployer,
+                token_template,
+            );
+            templates.entry(deployer).or_default().push(template);
+        }
 
-        self.actor_pool.increment_deployer_nonce_by(batch_size);
+        let mut txs = Vec::with_capacity(batch_size as usize);
+        for (deployer, templates) in templates {
+            let Some(owner) = self.actor_pool.owner(deployer) else {
+                continue;
+            };
+            txs.extend(sign_batch(owner.signer(), templates)?);
+        }
+
+        self.expect_after_phase(
+            SimulationPhase::TokenDeployment,
+            PhaseCheck::HasCode,
+            (self.tokens_deployed..self.tokens_deployed + batch_size)
+                .filter_map(|index| self.token_contract_pool.get(index))
+                .map(|token| token.address()),
+        );
         self.tokens_deployed += batch_size;
 
-        txs
+        Ok(txs)
+    }
+
+    /// Have the builder check `addresses` against `check` once `phase` is in
+    /// blocks, unless phase checks are off.
+    fn expect_after_phase(
+        &self,
+        phase: SimulationPhase,
+        check: PhaseCheck,
+        addresses: impl IntoIterator<Item = Address>,
+    ) {
+        if self.config.phase_check_sample > 0 {
+            self.phase_checks.expect(phase, check, addresses);
+        }
+    }
+
+    /// Index of the actor that deploys setup token `n` and owns its pool, or
+    /// `None` when every token comes from the genesis deployer.
+    fn token_deployer(&self, n: u64) -> Option<usize> {
+        (self.config.token_deployers > 0).then(|| (n % self.config.token_deployers) as usize)
     }
 
     /// Deploy WETH, factory, and router contracts needed for subsequent swaps.
-    fn generate_uniswap_deployment_batch(&mut self) -> Vec<TX> {
-        let (uniswap, deployment_txs) = Uniswap::init(self.actor_pool.deployer());
-        self.uniswap = Some(uniswap);
+    fn generate_uniswap_deployment_batch(&mut self) -> eyre::Result<Vec<TX>> {
         self.actor_pool
-            .increment_deployer_nonce_by(deployment_txs.len() as u64);
-        deployment_txs
+            .increment_deployer_nonce_by(self.faults.uniswap_nonce_skew);
+        let fee_to_setter = self.actor_pool.uniswap_owner();
+        let (uniswap, deployment_txs) =
+            Uniswap::init(self.actor_pool.deployer_mut(), fee_to_setter)?;
+        for ((label, address), tx) in uniswap.contracts().into_iter().zip(&deployment_txs) {
+            self.expected_code.expect(*tx.hash(), label, address);
+        }
+        self.uniswap = Some(uniswap);
+        Ok(deployment_txs)
+    }
+
+    /// Deploy the CREATE2 factory from the deployer.
+    fn generate_create2_deployer_deployment_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        let deployer = self.actor_pool.deployer();
+        let nonce = deployer.nonce();
+        let factory = deployer.contract_address(nonce);
+        let deploy_tx = tx(
+            deployer.signer(),
+            nonce,
+            TxKind::Create,
+            None,
+            Some(Create2DeployerHelper::deploy()),
+        )?;
+        info!(target: logging::ORCHESTRATOR, %factory, "deploying create2 factory");
+
+        self.create2_deployer = Some(factory);
+        self.actor_pool.increment_deployer_nonce_by(1);
+        Ok(vec![deploy_tx])
+    }
+
+    /// Deploy the batcher contract from the deployer.
+    fn generate_multicall_deployment_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        let deployer = self.actor_pool.deployer();
+        let nonce = deployer.nonce();
+        let batcher = deployer.contract_address(nonce);
+        let deploy_tx = tx(
+            deployer.signer(),
+            nonce,
+            TxKind::Create,
+            None,
+            Some(BatcherHelper::deploy()),
+        )?;
+        info!(target: logging::ORCHESTRATOR, %batcher, "deploying batcher");
+
+        self.batcher = Some(batcher);
+        self.actor_pool.increment_deployer_nonce_by(1);
+        Ok(vec![deploy_tx])
     }
 
     /// Create Uniswap pools for each token, approve router spending, then add
     /// initial liquidity so price-impact transactions behave realistically.
-    fn generate_uniswap_pool_creation_batch(&mut self) -> Vec<TX> {
+    fn generate_uniswap_pool_creation_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        // Each pool takes three transactions, but a batch smaller than that
+        // must still create one, or the phase never finishes.
         let batch_size = std::cmp::min(
-            self.config.std_batch_size / 3,
+            (self.batch_size / 3).max(1),
             self.config.unique_tokens - self.token_pools_created,
         );
 
-        let mut txs = Vec::with_capacity(batch_size as usize);
+        let pool_created = self.token_pools_created;
+        let mut tokens = (pool_created..pool_created + batch_size)
+            .filter_map(|index| self.token_contract_pool.get(index))
+            .map(|token| (token.address(), token.deployer()))
+            .collect::<Vec<_>>();
+        let batch_size = tokens.len() as u64;
 
-        let uniswap = self.uniswap.as_ref().unwrap();
+        let Some(uniswap) = self.uniswap.as_ref() else {
+            return Ok(Vec::new());
+        };
 
-        let (g_signer, g_nonce) = self.actor_pool.deployer_info();
-        let pool_created = self.token_pools_created;
+        // Each pool is set up by the token's owner. Grouping by owner keeps
+        // every owner's three-transaction runs at consecutive nonces.
+        tokens.sort_by_key(|(_, deployer)| *deployer);
+        let mut pools = Vec::with_capacity(tokens.len());
+        for (token_address, deployer) in tokens {
+            let Some(owner) = self.actor_pool.owner_mut(deployer) else {
+                continue;
+            };
+            let nonce = owner.nonce();
+            owner.increment_nonce_by(3);
+            pools.push((token_address, deployer, nonce));
+        }
+        // Every owner's nonces are claimed; from here its signer is only borrowed.
+        let pools = pools
+            .into_iter()
+            .filter_map(|(token_address, deployer, nonce)| {
+                let owner = self.actor_pool.owner(deployer)?;
+                Some((token_address, owner.signer(), nonce))
+            })
+            .collect::<Vec<_>>();
+        self.expect_after_phase(
+            SimulationPhase::UniswapPoolCreation,
+            PhaseCheck::PoolReserves,
+            pools
+                .iter()
+                .map(|(token_address, ..)| uniswap.pair_address(*token_address)),
+        );
 
-        txs.extend(
-            (0..batch_size)
-                .into_par_iter()
-                .map(|i| {
+        let txs = pools
+            .into_par_iter()
+            .map(
+                |(token_address, signer, nonce)| -> Result<Vec<TX>, SandboxError> {
                     let mut txs = Vec::with_capacity(3);
-                    let nonce_offset = i * 3;
                     //create pair
                     txs.push(tx(
-                        &g_signer,
-                        g_nonce + nonce_offset,
+                        signer,
+                        nonce,
                         TxKind::Call(uniswap.factory()),
                         None,
                         Some(UniswapV2FactoryHelper::create_pair(
                             uniswap.weth(),
-                            self.token_contract_pool.token_address(pool_created + i),
+                            token_address,
                         )),
-                    ));
+                    )?);
 
                     //approve token
                     txs.push(tx(
-                        &g_signer,
-                        g_nonce + nonce_offset + 1,
-                        TxKind::Call(self.token_contract_pool.token_address(pool_created + i)),
+                        signer,
+                        nonce + 1,
+                        TxKind::Call(token_address),
                         None,
                         Some(SandboxTokenHelper::approve(
                             uniswap.router(),
-                            U256::from(1_000_000e18),
+                            U256::from(POOL_TOKEN_RESERVE),
                         )),
-                    ));
+                    )?);
 
                     //add liquidity
                     txs.push(tx(
-                        &g_signer,
-                        g_nonce + nonce_offset + 2,
+                        signer,
+                        nonce + 2,
                         TxKind::Call(uniswap.router()),
-                        Some(U256::from(10_000e18)),
+                        Some(U256::from(POOL_ETH_RESERVE)),
                         Some(UniswapV2Router02Helper::add_liquidity(
-                            self.token_contract_pool.token_address(pool_created + i),
-                            g_signer.address(),
-                            U256::from(1_000_000e18),
+                            token_address,
+                            signer.address(),
+                            U256::from(POOL_TOKEN_RESERVE),
                         )),
-                    ));
+                    )?);
 
-                    txs
-                })
-                .flatten()
-                .collect::<Vec<TX>>(),
-        );
+                    Ok(txs)
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<TX>>();
 
-        self.actor_pool.increment_deployer_nonce_by(batch_size * 3);
         self.token_pools_created += batch_size;
 
-        txs
+        Ok(txs)
     }
 
     /// Emit a mixed workload of transfers and swaps once necessary setup is complete.
-    fn generate_transaction_load_batch(&mut self) -> Vec<TX> {
-        let batch_size = self.config.std_batch_size;
-
-        let assignments: Vec<(usize, u64, usize, TransactionType)> = (0..batch_size)
-            .map(|_| {
-                let sending_actor_index = rand::rng().random_range(0..self.actor_pool.len() - 1);
-                let receiving_actor_index = rand::rng().random_range(0..self.actor_pool.len() - 1);
-
-                let transaction_type = match rand::rng().random_range(0..10) {
-                    0..=3 => TransactionType::TokenTransfer,
-                    4..=5 => TransactionType::UniswapSwapForEth,
-                    6..=7 => TransactionType::UniswapSwapForToken,
-                    _ => TransactionType::EthTransfer,
+    ///
+    /// Token and swap transactions are only assigned when the contracts they
+    /// touch exist, so a run with zero tokens degrades to plain ETH transfers.
+    fn generate_transaction_load_batch(&mut self) -> eyre::Result<Vec<LabeledTx>> {
+        let batch_size = self.batch_size;
+
+        let num_actors = self.actor_pool.len();
+        if num_actors == 0 {
+            warn!(target: logging::ORCHESTRATOR, "no actors available for transaction load");
+            return Ok(Vec::new());
+        }
+
+        let num_tokens = self.token_contract_pool.len() as u64;
+        // The setup tokens are fixed for the batch, so look them up once.
+        let token_addresses = self.token_contract_pool.addresses();
+        let fee_tokens = self.token_contract_pool.fee_token_addresses();
+        let hot_tokens = self.config.hot_tokens;
+        let has_uniswap = self.uniswap.is_some();
+        let batcher = self.batcher;
+        let multicall_calls = self.config.multicall_calls_per_tx;
+        let contract_deploy_rate = self.config.contract_deploy_rate;
+        let initial_supply = self.config.token_initial_supply;
+        let eth_transfer_amount = self.config.eth_transfer_amount;
+        let token_transfer_amount = self.config.token_transfer_amount;
+        let weth_deposit_amount = self.config.weth_deposit_amount;
+        // Keep each swap small next to the reserve it pays into. Reserves are
+        // not tracked, so the cap is taken against the seeded amounts.
+        let reserve_ratio = self.config.max_swap_reserve_ratio;
+        let swap_eth_amount = self
+            .config
+            .swap_eth_amount
+            .capped((POOL_ETH_RESERVE as f64 * reserve_ratio) as u64);
+        let swap_token_amount = self
+            .config
+            .swap_token_amount
+            .capped((POOL_TOKEN_RESERVE as f64 * reserve_ratio) as u64);
+
+        // Bundlers only send bundles; direct load comes from everyone else.
+        let bundlers = self
+            .config
+            .bundler_mode
+            .map_or(0, |mode| mode.bundlers as usize)
+            .min(num_actors);
+        let mut senders = SenderPicker::new(
+            self.config.sender_selection,
+            self.config.max_txs_per_sender_per_batch,
+            self.config.allow_self_transfer,
+            num_actors - bundlers,
+            self.next_sender,
+        );
+        let assignments: Vec<(usize, u64, usize, Option<Address>, TxLabel, U256)> = (0..batch_size)
+            .filter_map(|_| {
+                let sender = senders.sender(&mut self.rng)?;
+                let sending_actor_index = bundlers + sender;
+                let receiving_actor_index = bundlers + senders.receiver(sender, &mut self.rng);
+                let weth_balance = self
+                    .weth_balances
+                    .get(&sending_actor_index)
+                    .copied()
+                    .unwrap_or_default();
+
+                let transaction_type = if self.rng.random_bool(contract_deploy_rate) {
+                    TxLabel::ContractDeploy
+                } else {
+                    match self.rng.random_range(0..10) {
+                        0..=3 if num_tokens > 0 => TxLabel::TokenTransfer,
+                        4..=5 if num_tokens > 0 && has_uniswap => TxLabel::UniswapSwapForEth,
+                        6..=7 if num_tokens > 0 && has_uniswap => TxLabel::UniswapSwapForToken,
+                        8 if has_uniswap => {
+                            if !weth_balance.is_zero() && self.rng.random_bool(0.3) {
+                                TxLabel::WethWithdraw
+                            } else {
+                                TxLabel::WethDeposit
+                            }
+                        }
+                        9 if num_tokens > 0 && batcher.is_some() && multicall_calls > 0 => {
+                            TxLabel::Multicall
+                        }
+                        _ => TxLabel::EthTransfer,
+                    }
+                };
+
+                // Withdrawals never exceed what the actor has deposited.
+                let range = match transaction_type {
+                    TxLabel::EthTransfer => Some(eth_transfer_amount),
+                    TxLabel::TokenTransfer | TxLabel::Multicall => Some(token_transfer_amount),
+                    TxLabel::UniswapSwapForEth => Some(swap_token_amount),
+                    TxLabel::UniswapSwapForToken => Some(swap_eth_amount),
+                    TxLabel::WethDeposit => Some(weth_deposit_amount),
+                    TxLabel::WethWithdraw => {
+                        Some(AmountRange::new(1, weth_balance.saturating_to::<u64>()))
+                    }
+                    _ => None,
+                };
+                let amount = range
+                    .map(|range| range.sample(&mut self.rng))
+                    .unwrap_or_default();
+
+                let token_address = match transaction_type {
+                    TxLabel::EthTransfer
+                    | TxLabel::WethDeposit
+                    | TxLabel::WethWithdraw
+                    | TxLabel::ContractDeploy => None,
+                    TxLabel::TokenTransfer
+                        if !self.actor_tokens.is_empty() && self.rng.random_bool(0.5) =>
+                    {
+                        self.actor_tokens
+                            .token_address(self.rng.random_range(0..self.actor_tokens.len() as u64))
+                    }
+                    _ => {
+                        let index = match hot_tokens {
+                            Some(hot) => hot.pick(num_tokens, &mut self.rng),
+                            None => self.rng.random_range(0..num_tokens),
+                        };
+                        token_addresses.get(index as usize).copied()
+                    }
                 };
 
                 //We need to approve the token for the uniswap router
                 let increment_nonce_by = match transaction_type {
-                    TransactionType::UniswapSwapForEth => 2,
+                    TxLabel::UniswapSwapForEth => 2,
                     _ => 1,
                 };
 
                 let nonce = self
                     .actor_pool
-                    .get_and_increment_nonce_by(sending_actor_index, increment_nonce_by);
-                (
+                    .get_and_increment_nonce_by(sending_actor_index, increment_nonce_by)?;
+
+                // The new token's address is fixed by the sender and nonce, so it
+                // can be targeted as soon as the deploy is queued.
+                let token_address = match transaction_type {
+                    TxLabel::ContractDeploy => {
+                        let address = self
+                            .actor_pool
+                            .actor_address(sending_actor_index)?
+                            .create(nonce);
+                        self.actor_tokens.add_token(
+                            address,
+                            nonce,
+                            initial_supply,
+                            Some(sending_actor_index),
+                            TokenTemplate::Standard,
+                        );
+                        Some(address)
+                    }
+                    _ => token_address,
+                };
+
+                match transaction_type {
+                    TxLabel::WethDeposit => {
+                        *self.weth_balances.entry(sending_actor_index).or_default() += amount;
+                    }
+                    TxLabel::WethWithdraw => {
+                        *self.weth_balances.entry(sending_actor_index).or_default() -= amount;
+                    }
+                    _ => {}
+                }
+
+                Some((
                     sending_actor_index,
                     nonce,
                     receiving_actor_index,
+                    token_address,
                     transaction_type,
-                )
+                    amount,
+                ))
             })
             .collect();
+        self.next_sender = senders.cursor();
 
-        let payloads = (0..batch_size)
+        // Multicalls alternate transfer and approve calls; only transfers
+        // move tokens.
+        let multicall_transfers = U256::from(multicall_calls.div_ceil(2));
+        self.stats
+            .record_values(assignments.iter().map(|&(.., label, amount)| {
+                let moved = match label {
+                    TxLabel::Multicall => amount * multicall_transfers,
+                    _ => amount,
+                };
+                (label.name(), moved)
+            }));
+        self.stats
+            .record_token_traffic(assignments.iter().filter_map(
+                |&(_, _, _, token_address, label, amount)| match label {
+                    TxLabel::ContractDeploy => None,
+                    _ => Some((token_address?, label, amount)),
+                },
+            ));
+
+        // Multicall recipients are drawn on rayon workers, each from its own
+        // generator derived from this seed and the assignment index.
+        let multicall_seed: u64 = self.rng.random();
+        let per_assignment = (0..assignments.len())
             .into_par_iter()
-            .flat_map(|i| {
-                let (sending_actor_index, nonce, receiving_actor_index, transaction_type) =
-                    assignments[i as usize];
+            .map(|i| {
+                let (
+                    sending_actor_index,
+                    nonce,
+                    receiving_actor_index,
+                    token_address,
+                    transaction_type,
+                    amount,
+                ) = assignments[i];
 
-                let (signer, _) = self.actor_pool.actor_info(sending_actor_index);
-                let receiving_address = self.actor_pool.actor_address(receiving_actor_index);
+                let (Some((signer, _)), Some(receiving_address)) = (
+                    self.actor_pool.actor_info(sending_actor_index),
+                    self.actor_pool.actor_address(receiving_actor_index),
+                ) else {
+                    return Ok(Vec::new());
+                };
 
-                let token_address = self
-                    .token_contract_pool
-                    .token_address(rand::rng().random_range(0..self.tokens_deployed));
+                // Calibrated limits from an earlier run replace the defaults.
+                let gas_limit = |default: u64| {
+                    self.config
+                        .calibrated_gas_limits
+                        .get(transaction_type.name())
+                        .copied()
+                        .unwrap_or(default)
+                };
+                let fee = self.priority_fee(sending_actor_index);
+                let build = || -> Result<Vec<TX>, SandboxError> {
+                    let txs = match (transaction_type, token_address, self.uniswap.as_ref()) {
+                        (TxLabel::TokenTransfer, Some(token_address), _) => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(token_address),
+                                None,
+                                Some(SandboxTokenHelper::transfer(receiving_address, amount)),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                        (TxLabel::UniswapSwapForEth, Some(token_address), Some(uniswap)) => {
+                            //create two transactions
+                            //approve the token for the uniswap router
 
-                let uniswap = self.uniswap.as_ref().unwrap();
+                            let approve_tx = tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(token_address),
+                                None,
+                                Some(SandboxTokenHelper::approve(uniswap.router(), amount)),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?;
 
-                let txs = match transaction_type {
-                    TransactionType::EthTransfer => {
-                        vec![tx(
-                            &signer,
-                            nonce,
-                            TxKind::Call(receiving_address),
-                            Some(U256::from(100)),
-                            None,
-                        )]
-                    }
+                            let swap_tx = tx_with_priority_fee(
+                                &signer,
+                                nonce + 1,
+                                TxKind::Call(uniswap.router()),
+                                None,
+                                Some(UniswapV2Router02Helper::swap_token_for_eth(
+                                    token_address,
+                                    uniswap.weth(),
+                                    amount,
+                                    signer.address(),
+                                    fee_tokens.contains(&token_address),
+                                )),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?;
+
+                            if self.faults.swap_before_approve {
+                                vec![swap_tx, approve_tx]
+                            } else {
+                                vec![approve_tx, swap_tx]
+                            }
+                        }
+                        (TxLabel::UniswapSwapForToken, Some(token_address), Some(uniswap)) => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(uniswap.router()),
+                                Some(amount),
+                                Some(UniswapV2Router02Helper::swap_eth_for_token(
+                                    uniswap.weth(),
+                                    token_address,
+                                    signer.address(),
+                                    fee_tokens.contains(&token_address),
+                                )),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                        (TxLabel::ContractDeploy, Some(_), _) => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Create,
+                                None,
+                                Some(SandboxTokenHelper::deploy(initial_supply)),
+                                gas_limit(TOKEN_DEPLOY_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                        (TxLabel::Multicall, Some(token_address), _) => {
+                            let Some(batcher) = batcher else {
+                                return Ok(Vec::new());
+                            };
+                            let mut rng = StdRng::seed_from_u64(multicall_seed ^ i as u64);
+                            let calls = (0..multicall_calls)
+                                .map(|call| {
+                                    let recipient = self
+                                        .actor_pool
+                                        .actor_address(rng.random_range(0..num_actors))
+                                        .unwrap_or(receiving_address);
+                                    let data = if call % 2 == 0 {
+                                        SandboxTokenHelper::transfer(recipient, amount)
+                                    } else {
+                                        SandboxTokenHelper::approve(recipient, amount)
+                                    };
+                                    (token_address, data)
+                                })
+                                .collect::<Vec<_>>();
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(batcher),
+                                None,
+                                Some(BatcherHelper::batch(&calls)),
+                                gas_limit(BatcherHelper::gas_limit(multicall_calls)),
+                                fee,
+                            )?]
+                        }
+                        (TxLabel::WethDeposit, _, Some(uniswap)) => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(uniswap.weth()),
+                                Some(amount),
+                                Some(WethHelper::deposit()),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                        (TxLabel::WethWithdraw, _, Some(uniswap)) => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(uniswap.weth()),
+                                None,
+                                Some(WethHelper::withdraw(amount)),
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                        // Everything else is a plain transfer; token and swap types are
+                        // never assigned without the contracts they need.
+                        _ => {
+                            vec![tx_with_priority_fee(
+                                &signer,
+                                nonce,
+                                TxKind::Call(receiving_address),
+                                Some(amount),
+                                None,
+                                gas_limit(DEFAULT_GAS_LIMIT),
+                                fee,
+                            )?]
+                        }
+                    };
+                    Ok(txs)
+                };
+                build().wrap_err_with(|| {
+                    format!(
+                        "failed to build {:?} from actor {} at nonce {}",
+                        transaction_type, sending_actor_index, nonce
+                    )
+                })
+            })
+            .collect::<eyre::Result<Vec<Vec<TX>>>>()?;
+
+        // Each assignment's transactions, such as an approval and the swap
+        // spending it, go to the builder as one group.
+        let phase = SimulationPhase::TransactionLoad;
+        let mut payloads = assignments
+            .iter()
+            .zip(per_assignment)
+            .filter_map(|((.., label, _), txs)| LabeledTx::group(txs, *label, phase))
+            .collect::<Vec<LabeledTx>>();
+
+        payloads.extend(LabeledTx::label_all(
+            self.generate_bundles()?,
+            TxLabel::Bundle,
+            phase,
+        ));
+        payloads.extend(LabeledTx::label_all(
+            self.generate_permit_removals()?,
+            TxLabel::PermitRemoval,
+            phase,
+        ));
+        payloads.extend(LabeledTx::label_all(
+            self.generate_selfdestruct_lifecycles()?,
+            TxLabel::SelfDestruct,
+            phase,
+        ));
+        Ok(payloads)
+    }
+
+    /// Have the deployer create `Destructible`s, write a few slots in each, and
+    /// destroy them again, all within this batch.
+    ///
+    /// Before Cancun `die()` deletes the account, so when the CREATE2 factory
+    /// is deployed the same salts are reused and every batch redeploys at the
+    /// addresses the previous one destroyed. From Cancun on (EIP-6780) `die()`
+    /// only sweeps the balance, so each lifecycle is paired with an ephemeral
+    /// contract that self-destructs inside its own creation transaction.
+    fn generate_selfdestruct_lifecycles(&mut self) -> eyre::Result<Vec<TX>> {
+        let count = self.config.selfdestructs_per_batch;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cancun = self.config.hardfork.is_cancun_active();
+        let factory = self.create2_deployer.filter(|_| !cancun);
+        let (signer, first_nonce) = self.actor_pool.deployer_info();
+        let init_code = DestructibleHelper::deploy();
+        let init_code_hash = keccak256(&init_code);
 
-                    TransactionType::TokenTransfer => {
-                        vec![tx(
-                            &signer,
+        let mut nonce = first_nonce;
+        let mut txs = Vec::new();
+        for i in 0..count {
+            let (address, deploy_tx) = match factory {
+                Some(factory) => {
+                    let salt = B256::from(U256::from(DESTRUCTIBLE_SALT_BASE + i));
+                    (
+                        create2_address(factory, salt, init_code_hash),
+                        tx(
+                            signer,
                             nonce,
-                            TxKind::Call(token_address),
+                            TxKind::Call(factory),
                             None,
-                            Some(SandboxTokenHelper::transfer(
-                                receiving_address,
-                                U256::from(100),
-                            )),
-                        )]
-                    }
-                    TransactionType::UniswapSwapForEth => {
-                        //create two transactions
-                        //approve the token for the uniswap router
+                            Some(Create2DeployerHelper::deploy_call(salt, &init_code)),
+                        )?,
+                    )
+                }
+                None => (
+                    signer.address().create(nonce),
+                    tx(signer, nonce, TxKind::Create, None, Some(init_code.clone()))?,
+                ),
+            };
+            txs.push(deploy_tx);
+            nonce += 1;
 
-                        let approve_tx = tx(
-                            &signer,
+            for slot in 0..DESTRUCTIBLE_WRITES {
+                txs.push(tx(
+                    signer,
+                    nonce,
+                    TxKind::Call(address),
+                    None,
+                    Some(DestructibleHelper::store(
+                        U256::from(slot),
+                        U256::from(nonce),
+                    )),
+                )?);
+                nonce += 1;
+            }
+
+            txs.push(tx(
+                signer,
+                nonce,
+                TxKind::Call(address),
+                None,
+                Some(DestructibleHelper::die()),
+            )?);
+            nonce += 1;
+
+            if cancun {
+                let ephemeral = signer.address().create(nonce);
+                txs.push(tx(
+                    signer,
+                    nonce,
+                    TxKind::Create,
+                    None,
+                    Some(DestructibleHelper::ephemeral(DESTRUCTIBLE_WRITES)),
+                )?);
+                nonce += 1;
+                self.destroyed_accounts.record(ephemeral);
+            } else {
+                self.destroyed_accounts.record(address);
+            }
+        }
+
+        self.actor_pool
+            .increment_deployer_nonce_by(nonce - first_nonce);
+        Ok(txs)
+    }
+
+    /// Have each bundler send one batcher transaction of `bundle_size` token
+    /// transfers to random non-bundler actors, standing in for user
+    /// operations an ERC-4337 bundler executes on their behalf.
+    ///
+    /// The batcher is the token sender, so the transfers are paid from
+    /// tokens it holds, which `SandboxToken` mints on demand.
+    fn generate_bundles(&mut self) -> eyre::Result<Vec<TX>> {
+        let (Some(mode), Some(batcher)) = (self.config.bundler_mode, self.batcher) else {
+            return Ok(Vec::new());
+        };
+        let num_actors = self.actor_pool.len();
+        let num_tokens = self.token_contract_pool.len() as u64;
+        let bundlers = (mode.bundlers as usize).min(num_actors);
+        if bundlers == num_actors || num_tokens == 0 {
+            return Ok(Vec::new());
+        }
+
+        let bundle_size = mode.bundle_size as u64;
+        let mut txs = Vec::with_capacity(bundlers);
+        let mut moved = U256::ZERO;
+        for bundler in 0..bundlers {
+            let calls = (0..bundle_size)
+                .filter_map(|_| {
+                    let token = self
+                        .token_contract_pool
+                        .token_address(self.rng.random_range(0..num_tokens))?;
+                    let recipient = self
+                        .actor_pool
+                        .actor_address(self.rng.random_range(bundlers..num_actors))?;
+                    let amount = self.config.token_transfer_amount.sample(&mut self.rng);
+                    moved += amount;
+                    Some((token, SandboxTokenHelper::transfer(recipient, amount)))
+                })
+                .collect::<Vec<_>>();
+            let Some(nonce) = self.actor_pool.get_and_increment_nonce_by(bundler, 1) else {
+                continue;
+            };
+            let Some((signer, _)) = self.actor_pool.actor_info(bundler) else {
+                continue;
+            };
+            txs.push(tx_with_gas_limit(
+                signer,
+                nonce,
+                TxKind::Call(batcher),
+                None,
+                Some(BatcherHelper::batch(&calls)),
+                self.config
+                    .calibrated_gas_limits
+                    .get(TxLabel::Bundle.name())
+                    .copied()
+                    .unwrap_or_else(|| BatcherHelper::gas_limit(calls.len() as u64)),
+            )?);
+        }
+        self.stats.record_values([(TxLabel::Bundle.name(), moved)]);
+        Ok(txs)
+    }
+
+    /// Have pool owners burn a sliver of LP tokens through
+    /// `removeLiquidityETHWithPermit`, authorizing the router with a signed
+    /// EIP-2612 permit instead of an `approve` transaction.
+    ///
+    /// `SandboxToken` has no `permit`, so the pair's LP token (which does) is
+    /// the permit target; whoever seeded a pool is its only LP holder.
+    fn generate_permit_removals(&mut self) -> eyre::Result<Vec<TX>> {
+        let count = self.config.permit_removals_per_batch;
+        let num_tokens = self.token_contract_pool.len() as u64;
+        let Some(uniswap) = self.uniswap.as_ref() else {
+            return Ok(Vec::new());
+        };
+        if count == 0 || num_tokens == 0 {
+            return Ok(Vec::new());
+        }
+
+        let liquidity = U256::from(PERMIT_REMOVAL_LIQUIDITY);
+        let mut txs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let Some(token) = self
+                .token_contract_pool
+                .get(self.rng.random_range(0..num_tokens))
+            else {
+                continue;
+            };
+            // The LP tokens belong to whoever seeded the pool.
+            let deployer = token.deployer();
+            let token = token.address();
+            let Some(owner) = self.actor_pool.owner(deployer) else {
+                continue;
+            };
+            let (signer, nonce) = (owner.signer(), owner.nonce());
+            let pair = uniswap.pair_address(token);
+            let permit = Permit {
+                owner: owner.address(),
+                spender: uniswap.router(),
+                value: liquidity,
+                nonce: self.permit_nonces.next(owner.address(), pair),
+                deadline: UniswapV2Router02Helper::get_deadline(),
+            };
+            let signature = match sign_permit(
+                signer,
+                &uniswap_v2_domain(self.config.chain_id, pair),
+                &permit,
+            ) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    warn!(target: logging::ORCHESTRATOR, %err, "failed to sign permit");
+                    break;
+                }
+            };
+            txs.push(tx(
+                signer,
+                nonce,
+                TxKind::Call(uniswap.router()),
+                None,
+                Some(UniswapV2Router02Helper::remove_liquidity_eth_with_permit(
+                    token,
+                    liquidity,
+                    owner.address(),
+                    signature,
+                )),
+            )?);
+            // The signer is only borrowed above; claim the nonce once it is used.
+            if let Some(owner) = self.actor_pool.owner_mut(deployer) {
+                owner.increment_nonce_by(1);
+            }
+        }
+
+        Ok(txs)
+    }
+
+    /// Emit 21k-gas ETH transfers between random actors, ignoring the mixed
+    /// workload weights entirely.
+    ///
+    /// With parallel lanes, each transfer instead pays a fresh address derived
+    /// from its sender and nonce, so no account is touched by two lanes.
+    fn generate_transfer_load_batch(&mut self) -> eyre::Result<Vec<TX>> {
+        let disjoint_receivers = self.config.parallel_lanes > 1;
+        let batch_size = self.batch_size;
+
+        let num_actors = self.actor_pool.len();
+        if num_actors == 0 {
+            warn!(target: logging::ORCHESTRATOR, "no actors available for transaction load");
+            return Ok(Vec::new());
+        }
+
+        let eth_transfer_amount = self.config.eth_transfer_amount;
+        let fail_transfer_at = self.faults.fail_transfer_at;
+        let mut transfer_index = self.transfers_generated;
+        let mut senders = SenderPicker::new(
+            self.config.sender_selection,
+            self.config.max_txs_per_sender_per_batch,
+            self.config.allow_self_transfer,
+            num_actors,
+            self.next_sender,
+        );
+        let assignments: Vec<(usize, u64, usize, U256)> = (0..batch_size)
+            .filter_map(|_| {
+                let sending_actor_index = senders.sender(&mut self.rng)?;
+                let receiving_actor_index = senders.receiver(sending_actor_index, &mut self.rng);
+                let nonce = self
+                    .actor_pool
+                    .get_and_increment_nonce_by(sending_actor_index, 1)?;
+                let mut amount = eth_transfer_amount.sample(&mut self.rng);
+                // More than any account holds, so the builder rejects it.
+                if fail_transfer_at == Some(transfer_index) {
+                    amount = U256::from(u128::MAX);
+                }
+                transfer_index += 1;
+                Some((sending_actor_index, nonce, receiving_actor_index, amount))
+            })
+            .collect();
+        self.transfers_generated = transfer_index;
+        self.next_sender = senders.cursor();
+        self.stats.record_values(
+            assignments
+                .iter()
+                .map(|&(.., amount)| (TxLabel::EthTransfer.name(), amount)),
+        );
+
+        let txs = (0..assignments.len())
+            .into_par_iter()
+            .filter_map(|i| {
+                let (sending_actor_index, nonce, receiving_actor_index, amount) = assignments[i];
+                let (signer, _) = self.actor_pool.actor_info(sending_actor_index)?;
+                let receiving_address = if disjoint_receivers {
+                    Address::from_word(keccak256(
+                        [signer.address().as_slice(), &nonce.to_be_bytes()].concat(),
+                    ))
+                } else {
+                    self.actor_pool.actor_address(receiving_actor_index)?
+                };
+
+                Some(tx_with_priority_fee(
+                    &signer,
+                    nonce,
+                    TxKind::Call(receiving_address),
+                    Some(amount),
+                    None,
+                    TRANSFER_GAS_LIMIT,
+                    self.priority_fee(sending_actor_index),
+                ))
+            })
+            .collect::<Result<Vec<TX>, _>>()?;
+        Ok(txs)
+    }
+
+    /// Priority fee per gas the actor at `index` offers on its load, set by
+    /// its fee strategy.
+    fn priority_fee(&self, index: usize) -> u128 {
+        self.actor_pool
+            .fee_strategy(index)
+            .unwrap_or_default()
+            .priority_fee(self.config.priority_fee)
+    }
+
+    /// Whether every block of the configured scenario has been generated.
+    fn scenario_finished(&self) -> bool {
+        self.config
+            .scenario
+            .as_ref()
+            .is_some_and(|scenario| self.scenario_blocks_generated >= scenario.blocks.len())
+    }
+
+    /// Generate the scenario's next block, step by step. The builder seals it
+    /// once every one of its transactions is included.
+    fn generate_scenario_block(&mut self) -> eyre::Result<Vec<LabeledTx>> {
+        let Some(steps) = self
+            .config
+            .scenario
+            .as_ref()
+            .and_then(|scenario| scenario.blocks.get(self.scenario_blocks_generated))
+            .cloned()
+        else {
+            return Ok(Vec::new());
+        };
+        self.scenario_blocks_generated += 1;
+
+        let num_actors = self.actor_pool.len();
+        let phase = SimulationPhase::TransactionLoad;
+        let mut batch = Vec::new();
+        for step in &steps {
+            let params = &step.params;
+            let label = step.tx_type.label();
+            let gas_limit = step.gas_limit(&self.config);
+            let amount = params.amount.unwrap_or_else(|| {
+                U256::from(match step.tx_type {
+                    StepType::EthTransfer => self.config.eth_transfer_amount.min,
+                    StepType::TokenTransfer => self.config.token_transfer_amount.min,
+                    StepType::SwapForToken => self.config.swap_eth_amount.min,
+                    StepType::SwapForEth => self.config.swap_token_amount.min,
+                    StepType::WethDeposit => self.config.weth_deposit_amount.min,
+                    StepType::Calldata => 0,
+                })
+            });
+            let token = self.token_contract_pool.token_address(params.token as u64);
+            let charges_fee = self
+                .token_contract_pool
+                .get(params.token as u64)
+                .is_some_and(|token| token.template().charges_fee());
+            let data = Bytes::from(vec![0xff; params.bytes]);
+
+            for _ in 0..step.count {
+                let sender = params.from.unwrap_or_else(|| {
+                    let sender = self.next_sender;
+                    self.next_sender = (sender + 1) % num_actors;
+                    sender
+                });
+                let receiver = params.to.unwrap_or((sender + 1) % num_actors);
+                let nonce = self
+                    .actor_pool
+                    .get_and_increment_nonce_by(sender, step.tx_type.txs_each())
+                    .ok_or_else(|| eyre::eyre!("scenario actor {sender} does not exist"))?;
+                let (Some((signer, _)), Some(receiving_address)) = (
+                    self.actor_pool.actor_info(sender),
+                    self.actor_pool.actor_address(receiver),
+                ) else {
+                    eyre::bail!("scenario actor {receiver} does not exist");
+                };
+                let fee = self.priority_fee(sender);
+                let call = |nonce: u64, to: Address, value: Option<U256>, data: Option<Bytes>| {
+                    tx_with_priority_fee(
+                        signer,
+                        nonce,
+                        TxKind::Call(to),
+                        value,
+                        data,
+                        gas_limit,
+                        fee,
+                    )
+                };
+
+                let txs = match (step.tx_type, token, self.uniswap.as_ref()) {
+                    (StepType::EthTransfer, ..) => {
+                        vec![call(nonce, receiving_address, Some(amount), None)?]
+                    }
+                    (StepType::Calldata, ..) => {
+                        vec![call(nonce, receiving_address, None, Some(data.clone()))?]
+                    }
+                    (StepType::TokenTransfer, Some(token), _) => vec![call(
+                        nonce,
+                        token,
+                        None,
+                        Some(SandboxTokenHelper::transfer(receiving_address, amount)),
+                    )?],
+                    (StepType::SwapForToken, Some(token), Some(uniswap)) => vec![call(
+                        nonce,
+                        uniswap.router(),
+                        Some(amount),
+                        Some(UniswapV2Router02Helper::swap_eth_for_token(
+                            uniswap.weth(),
+                            token,
+                            signer.address(),
+                            charges_fee,
+                        )),
+                    )?],
+                    (StepType::SwapForEth, Some(token), Some(uniswap)) => vec![
+                        call(
                             nonce,
-                            TxKind::Call(token_address),
+                            token,
                             None,
-                            Some(SandboxTokenHelper::approve(
-                                uniswap.router(),
-                                U256::from(1e18),
-                            )),
-                        );
-
-                        let swap_tx = tx(
-                            &signer,
+                            Some(SandboxTokenHelper::approve(uniswap.router(), amount)),
+                        )?,
+                        call(
                             nonce + 1,
-                            TxKind::Call(uniswap.router()),
+                            uniswap.router(),
                             None,
                             Some(UniswapV2Router02Helper::swap_token_for_eth(
-                                token_address,
-                                uniswap.weth(),
-                                U256::from(1e18),
-                                signer.address(),
-                            )),
-                        );
-
-                        vec![approve_tx, swap_tx]
-                    }
-                    TransactionType::UniswapSwapForToken => {
-                        vec![tx(
-                            &signer,
-                            nonce,
-                            TxKind::Call(uniswap.router()),
-                            Some(U256::from(100)),
-                            Some(UniswapV2Router02Helper::swap_eth_for_token(
+                                token,
                                 uniswap.weth(),
-                                token_address,
+                                amount,
                                 signer.address(),
+                                charges_fee,
                             )),
-                        )]
-                    }
+                        )?,
+                    ],
+                    (StepType::WethDeposit, _, Some(uniswap)) => vec![call(
+                        nonce,
+                        uniswap.weth(),
+                        Some(amount),
+                        Some(WethHelper::deposit()),
+                    )?],
+                    // `check_scenario` rejects steps the run has no contracts for.
+                    (ty, ..) => eyre::bail!(
+                        "scenario step {} has no contract to call",
+                        ty.label().name()
+                    ),
                 };
-                txs
-            })
-            .collect::<Vec<TX>>();
-
-        payloads
+                batch.extend(LabeledTx::group(txs, label, phase));
+            }
+            self.stats.record_values(std::iter::repeat_n(
+                (label.name(), amount),
+                step.count as usize,
+            ));
+        }
+        Ok(batch)
     }
 
-    /// Decide which phase of the simulation should run next.
+    /// Decide which steady phase of the simulation should run next. Transient
+    /// phases are queued in `pending` instead.
     fn current_phase(&self) -> SimulationPhase {
-        if self.actors_funded < self.config.unique_accounts {
+        if !self.deployer_funded {
+            SimulationPhase::DeployerFunding
+        } else if self.actors_funded < self.config.unique_accounts {
             SimulationPhase::ActorFunding
+        } else if !self.config.deploys_contracts() {
+            SimulationPhase::TransactionLoad
+        } else if self.config.deploy_via == DeployVia::Create2 && self.create2_deployer.is_none() {
+            SimulationPhase::Create2DeployerDeployment
         } else if self.tokens_deployed < self.config.unique_tokens {
             SimulationPhase::TokenDeployment
         } else if self.uniswap.is_none() {
             SimulationPhase::UniswapDeployment
         } else if self.token_pools_created < self.config.unique_tokens {
             SimulationPhase::UniswapPoolCreation
+        } else if (self.config.multicall_calls_per_tx > 0 || self.config.bundler_mode.is_some())
+            && self.batcher.is_none()
+        {
+            SimulationPhase::MulticallDeployment
         } else {
             SimulationPhase::TransactionLoad
         }
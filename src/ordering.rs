@@ -0,0 +1,94 @@
+//! Priority fee ordering: the builder's candidate set sorted the way a
+//! fee-maximizing block builder would include it, and the per-block file
+//! that shows the ordering took effect.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, map::HashMap};
+
+use crate::{error::SandboxError, labels::LabeledTx};
+
+/// Header of the block fees file.
+const BLOCK_FEES_CSV_HEADER: &str = "block,txs,first_tip,last_tip";
+
+/// Reorder `candidates` by effective priority fee at `base_fee`, highest
+/// first. Each sender's transactions stay in arrival order, which is their
+/// nonce order, so a sender's next transaction only competes once the one
+/// before it is placed. Ties go to the transaction that arrived first.
+pub fn order_by_priority_fee(candidates: &mut VecDeque<LabeledTx>, base_fee: u64) {
+    let mut by_sender: HashMap<Address, VecDeque<(usize, LabeledTx)>> = HashMap::default();
+    for (arrival, labeled) in candidates.drain(..).enumerate() {
+        by_sender
+            .entry(labeled.tx.signer())
+            .or_default()
+            .push_back((arrival, labeled));
+    }
+
+    let tip = |labeled: &LabeledTx| {
+        labeled
+            .tx
+            .effective_tip_per_gas(base_fee)
+            .unwrap_or_default()
+    };
+    let mut heads = BinaryHeap::with_capacity(by_sender.len());
+    for (sender, queue) in &by_sender {
+        if let Some((arrival, labeled)) = queue.front() {
+            heads.push((tip(labeled), Reverse(*arrival), *sender));
+        }
+    }
+    while let Some((_, _, sender)) = heads.pop() {
+        let Some(queue) = by_sender.get_mut(&sender) else {
+            continue;
+        };
+        if let Some((_, labeled)) = queue.pop_front() {
+            candidates.push_back(labeled);
+        }
+        if let Some((arrival, labeled)) = queue.front() {
+            heads.push((tip(labeled), Reverse(*arrival), sender));
+        }
+    }
+}
+
+/// Appends one row per built block with the effective tip, in wei per gas,
+/// of its first and last included transaction.
+pub struct BlockFeesWriter {
+    writer: BufWriter<File>,
+}
+
+impl BlockFeesWriter {
+    /// Create the file and write the CSV header.
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{BLOCK_FEES_CSV_HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    /// Append the row for block `number`; the tips are empty when it holds
+    /// no transactions.
+    pub fn record(
+        &mut self,
+        number: u64,
+        txs: u64,
+        tips: Option<(u128, u128)>,
+    ) -> eyre::Result<()> {
+        match tips {
+            Some((first, last)) => writeln!(self.writer, "{number},{txs},{first},{last}")?,
+            None => writeln!(self.writer, "{number},{txs},,")?,
+        }
+        Ok(())
+    }
+
+    /// Flush buffered rows to disk.
+    pub fn finish(mut self) -> eyre::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
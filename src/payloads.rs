@@ -0,0 +1,98 @@
+//! Engine API `newPayload` requests for every built block, written as JSONL so
+//! the chain can be replayed against a real node (see
+//! `scripts/replay_payloads.py`).
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alloy_eips::eip7685::Requests;
+use alloy_primitives::B256;
+use alloy_rpc_types_engine::ExecutionPayload;
+use reth_ethereum::Block;
+use serde_json::json;
+
+use crate::{config::Hardfork, error::SandboxError};
+
+/// Appends one JSON-RPC `engine_newPayloadV*` request per built block, after
+/// a first line identifying the chain the payloads build on.
+pub struct PayloadWriter {
+    writer: BufWriter<File>,
+    hardfork: Hardfork,
+    next_id: u64,
+}
+
+impl PayloadWriter {
+    /// Create the file and write the line carrying `genesis_hash` and
+    /// `chain_id`.
+    pub fn new(
+        path: &Path,
+        genesis_hash: B256,
+        chain_id: u64,
+        hardfork: Hardfork,
+    ) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        let chain = json!({
+            "genesis_hash": genesis_hash,
+            "chain_id": chain_id,
+            "hardfork": hardfork.to_string(),
+            "new_payload_method": Self::method(hardfork),
+        });
+        writeln!(writer, "{chain}")?;
+        Ok(Self {
+            writer,
+            hardfork,
+            next_id: 1,
+        })
+    }
+
+    /// `newPayload` version the hardfork calls for.
+    fn method(hardfork: Hardfork) -> &'static str {
+        match hardfork {
+            Hardfork::Shanghai => "engine_newPayloadV2",
+            Hardfork::Cancun => "engine_newPayloadV3",
+            Hardfork::Prague => "engine_newPayloadV4",
+        }
+    }
+
+    /// Append the request for sealed block `hash`. `requests` are the
+    /// execution requests Prague payloads carry alongside the block.
+    pub fn record(&mut self, hash: B256, block: &Block, requests: &Requests) -> eyre::Result<()> {
+        let (payload, _) = ExecutionPayload::from_block_unchecked(hash, block);
+        let versioned_hashes = block
+            .body
+            .blob_versioned_hashes_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let parent_beacon_block_root = block.header.parent_beacon_block_root;
+
+        let params = match self.hardfork {
+            Hardfork::Shanghai => json!([payload]),
+            Hardfork::Cancun => json!([payload, versioned_hashes, parent_beacon_block_root]),
+            Hardfork::Prague => json!([
+                payload,
+                versioned_hashes,
+                parent_beacon_block_root,
+                requests.iter().collect::<Vec<_>>(),
+            ]),
+        };
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": Self::method(self.hardfork),
+            "params": params,
+        });
+        self.next_id += 1;
+        writeln!(self.writer, "{request}")?;
+        Ok(())
+    }
+
+    /// Flush buffered requests to disk.
+    pub fn finish(mut self) -> eyre::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
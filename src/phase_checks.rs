@@ -0,0 +1,122 @@
+//! State checks run once a setup phase's transactions are all in sealed
+//! blocks, so a phase that silently failed stops the run before later phases
+//! build on it.
+
+use std::sync::Mutex;
+
+use alloy_primitives::Address;
+
+use crate::{debug::StateCaller, error::SandboxError, labels::SimulationPhase};
+
+/// What every address a phase touched should hold afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseCheck {
+    /// A nonzero balance, for funded actors.
+    Funded,
+    /// Deployed code, for setup tokens.
+    HasCode,
+    /// Nonzero reserves on both sides, for seeded pairs.
+    PoolReserves,
+}
+
+impl PhaseCheck {
+    /// How an address failing the check is described in the error.
+    fn failure(&self) -> &'static str {
+        match self {
+            Self::Funded => "have no balance",
+            Self::HasCode => "have no code",
+            Self::PoolReserves => "have an empty reserve",
+        }
+    }
+
+    /// Whether `address` passes the check in the state `caller` reads.
+    fn holds(&self, caller: &StateCaller<'_>, address: Address) -> eyre::Result<bool> {
+        let state_provider = caller.state_provider();
+        Ok(match self {
+            Self::Funded => !state_provider
+                .account_balance(&address)?
+                .unwrap_or_default()
+                .is_zero(),
+            Self::HasCode => state_provider
+                .basic_account(&address)?
+                .is_some_and(|account| account.has_bytecode()),
+            Self::PoolReserves => {
+                let (reserve0, reserve1) = caller.pair_get_reserves(address)?;
+                !reserve0.is_zero() && !reserve1.is_zero()
+            }
+        })
+    }
+}
+
+/// Addresses the orchestrator expects each setup phase to leave in a given
+/// state. Shared with the builder through an `Arc`; it runs a phase's checks
+/// once a later phase's transactions start landing in blocks.
+#[derive(Debug, Default)]
+pub struct PhaseChecks {
+    pending: Mutex<Vec<(SimulationPhase, PhaseCheck, Vec<Address>)>>,
+}
+
+impl PhaseChecks {
+    /// Expect every address in `addresses` to pass `check` once `phase` is
+    /// done.
+    pub fn expect(
+        &self,
+        phase: SimulationPhase,
+        check: PhaseCheck,
+        addresses: impl IntoIterator<Item = Address>,
+    ) {
+        let mut pending = self.pending.lock().unwrap();
+        match pending
+            .iter_mut()
+            .find(|(pending_phase, pending_check, _)| {
+                *pending_phase == phase && *pending_check == check
+            }) {
+            Some((.., expected)) => expected.extend(addresses),
+            None => pending.push((phase, check, addresses.into_iter().collect())),
+        }
+    }
+
+    /// Run the checks registered for `phase` against up to `sample`
+    /// addresses each, spread evenly over the ones recorded, and forget
+    /// them. Fails on the first check any sampled address misses.
+    pub fn verify(
+        &self,
+        phase: SimulationPhase,
+        caller: &StateCaller<'_>,
+        sample: usize,
+    ) -> eyre::Result<()> {
+        let checks = {
+            let mut pending = self.pending.lock().unwrap();
+            let (due, rest) = pending
+                .drain(..)
+                .partition::<Vec<_>, _>(|(pending_phase, ..)| *pending_phase == phase);
+            *pending = rest;
+            due
+        };
+        for (_, check, addresses) in checks {
+            if addresses.is_empty() || sample == 0 {
+                continue;
+            }
+            let step = addresses.len().div_ceil(sample);
+            let mut sampled = 0;
+            let mut failed = Vec::new();
+            for &address in addresses.iter().step_by(step) {
+                sampled += 1;
+                if !check.holds(caller, address)? {
+                    failed.push(address);
+                }
+            }
+            if let Some(&first) = failed.first() {
+                return Err(SandboxError::PhaseCheck {
+                    phase: phase.name(),
+                    failed: failed.len(),
+                    sampled,
+                    failure: check.failure(),
+                    first,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
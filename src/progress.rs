@@ -0,0 +1,620 @@
+//! Shared run counters plus a periodic heartbeat (or a terminal progress bar)
+//! that reports them while a long simulation is in flight, and a sampler that
+//! turns them into throughput over sliding windows.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, IsTerminal, Write},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde_json::{Value, json};
+use tokio::{
+    sync::{
+        broadcast::{self, error::TryRecvError},
+        mpsc::WeakSender,
+        oneshot,
+    },
+    task::JoinHandle,
+};
+use tracing::{info, warn};
+
+use crate::{
+    config::SimulationConfig, error::SandboxError, gauge, labels::LabeledTx, logging, memory,
+};
+
+const MIB: u64 = 1024 * 1024;
+
+/// How often the throughput sampler records the run's counters.
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the progress bar is redrawn.
+const PROGRESS_BAR_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shorter window throughput is reported over.
+const SHORT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Longer window throughput is reported over, and the span of samples kept.
+const LONG_WINDOW: Duration = Duration::from_secs(60);
+
+/// Capacity of the phase event channel; a run only has a handful of phases.
+pub const PHASE_EVENT_CAPACITY: usize = 64;
+
+/// A simulation phase transition, broadcast by the orchestrator.
+#[derive(Debug, Clone, Copy)]
+pub enum PhaseEvent {
+    /// The orchestrator started generating `phase` batches `at` into the run.
+    Entered { phase: &'static str, at: Duration },
+    /// The orchestrator left a phase, either for the next one or because the
+    /// run ended.
+    Exited(PhaseSpan),
+}
+
+/// One completed phase of the run.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseSpan {
+    pub phase: &'static str,
+    /// Time from the start of the run to entering the phase.
+    pub started: Duration,
+    pub duration: Duration,
+    pub txs_generated: u64,
+}
+
+impl PhaseSpan {
+    /// Transactions generated per second of the phase's wall time.
+    pub fn txs_per_sec(&self) -> f64 {
+        self.txs_generated as f64 / self.duration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Completed phases in the order they ran, collected from [`PhaseEvent`]s.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseTimeline {
+    spans: Vec<PhaseSpan>,
+}
+
+impl PhaseTimeline {
+    /// Collect every event already sent on `events` without waiting for more.
+    pub fn drain(events: &mut broadcast::Receiver<PhaseEvent>) -> Self {
+        let mut timeline = Self::default();
+        loop {
+            match events.try_recv() {
+                Ok(PhaseEvent::Exited(span)) => timeline.spans.push(span),
+                Ok(PhaseEvent::Entered { .. }) => {}
+                Err(TryRecvError::Lagged(missed)) => {
+                    warn!(target: logging::PROGRESS, missed, "phase timeline is missing events");
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return timeline,
+            }
+        }
+    }
+
+    /// Render the timeline for the run manifest.
+    pub fn to_json(&self) -> Value {
+        self.spans
+            .iter()
+            .map(|span| {
+                json!({
+                    "phase": span.phase,
+                    "started_secs": span.started.as_secs_f64(),
+                    "duration_secs": span.duration.as_secs_f64(),
+                    "txs_generated": span.txs_generated,
+                })
+            })
+            .collect()
+    }
+
+    /// Print a phase / duration / generated / throughput table.
+    pub fn print(&self) {
+        if self.spans.is_empty() {
+            return;
+        }
+
+        let name_w = self
+            .spans
+            .iter()
+            .map(|span| span.phase.len())
+            .max()
+            .unwrap_or_default()
+            .max("Phase".len());
+
+        println!("\nPhases:");
+        println!("{:-<1$}", "", name_w + 48);
+        println!(
+            "{:<name_w$}  {:>14}  {:>14}  {:>14}",
+            "Phase", "Duration (s)", "Generated", "Txs/sec"
+        );
+        println!("{:-<1$}", "", name_w + 48);
+        for span in &self.spans {
+            println!(
+                "{:<name_w$}  {:>14.3}  {:>14}  {:>14.1}",
+                span.phase,
+                span.duration.as_secs_f64(),
+                span.txs_generated,
+                span.txs_per_sec()
+            );
+        }
+        println!("{:-<1$}", "", name_w + 48);
+    }
+}
+
+/// Cumulative block, transaction, and gas counts at one instant of the run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThroughputSample {
+    /// Time from the start of the run.
+    pub at: Duration,
+    pub blocks: u64,
+    pub txs: u64,
+    pub gas_used: u64,
+}
+
+impl ThroughputSample {
+    /// Rates between `earlier` and this sample, if any time passed between
+    /// them.
+    fn rates_since(&self, earlier: &Self) -> Option<Rates> {
+        let secs = self.at.checked_sub(earlier.at)?.as_secs_f64();
+        (secs > 0.0).then(|| Rates {
+            gas_per_sec: (self.gas_used - earlier.gas_used) as f64 / secs,
+            txs_per_sec: (self.txs - earlier.txs) as f64 / secs,
+        })
+    }
+}
+
+/// Gas and transactions executed per second over some span of the run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rates {
+    pub gas_per_sec: f64,
+    pub txs_per_sec: f64,
+}
+
+impl Rates {
+    fn to_json(self) -> Value {
+        json!({
+            "gas_per_sec": self.gas_per_sec,
+            "txs_per_sec": self.txs_per_sec,
+        })
+    }
+}
+
+impl std::fmt::Display for Rates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} gas/s, {:.1} tx/s",
+            self.gas_per_sec, self.txs_per_sec
+        )
+    }
+}
+
+/// Ring buffer of the samples covering the last [`LONG_WINDOW`], plus the
+/// rates over the run's first [`LONG_WINDOW`] once it is that old.
+#[derive(Debug, Default)]
+struct ThroughputWindow {
+    recent: VecDeque<ThroughputSample>,
+    first_window: Option<Rates>,
+}
+
+impl ThroughputWindow {
+    fn push(&mut self, sample: ThroughputSample) {
+        if self.first_window.is_none() && sample.at >= LONG_WINDOW {
+            self.first_window = sample.rates_since(&ThroughputSample::default());
+        }
+        self.recent.push_back(sample);
+        // Keep the newest sample at least a window old, so the full window
+        // is always covered.
+        while self
+            .recent
+            .get(1)
+            .is_some_and(|next| sample.at.saturating_sub(next.at) >= LONG_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Rates over the last `window`, measured from the newest sample at
+    /// least that old, or the oldest kept while the run is younger.
+    fn rates_over(&self, window: Duration) -> Option<Rates> {
+        let latest = self.recent.back()?;
+        let start = self
+            .recent
+            .iter()
+            .rev()
+            .find(|sample| latest.at.saturating_sub(sample.at) >= window)
+            .or(self.recent.front())?;
+        latest.rates_since(start)
+    }
+}
+
+/// Throughput over the whole run and over its sliding windows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThroughputReport {
+    /// Time from the start of the run to the last sample.
+    pub elapsed: Duration,
+    pub overall: Option<Rates>,
+    pub last_10s: Option<Rates>,
+    pub last_60s: Option<Rates>,
+    pub first_60s: Option<Rates>,
+}
+
+impl ThroughputReport {
+    /// Whether gas per second over the last minute fell below half of the
+    /// first minute's, the usual sign that state growth is slowing execution.
+    /// Only judged once the two minutes no longer overlap.
+    pub fn degraded(&self) -> bool {
+        match (self.first_60s, self.last_60s) {
+            (Some(first), Some(last)) if self.elapsed >= 2 * LONG_WINDOW => {
+                last.gas_per_sec < first.gas_per_sec / 2.0
+            }
+            _ => false,
+        }
+    }
+
+    /// Render the rates for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "overall": self.overall.map(Rates::to_json),
+            "last_10s": self.last_10s.map(Rates::to_json),
+            "last_60s": self.last_60s.map(Rates::to_json),
+            "first_60s": self.first_60s.map(Rates::to_json),
+            "degraded": self.degraded(),
+        })
+    }
+
+    /// Print the overall and windowed rates, flagging a degraded run.
+    pub fn print(&self) {
+        let Some(overall) = self.overall else {
+            return;
+        };
+        println!("Throughput: {overall} overall");
+        for (window, rates) in [
+            ("first 60s", self.first_60s),
+            ("last 60s", self.last_60s),
+            ("last 10s", self.last_10s),
+        ] {
+            if let Some(rates) = rates {
+                println!("            {rates} {window}");
+            }
+        }
+        if self.degraded() {
+            println!(
+                "            last 60s gas/s is under half of the first 60s; state growth may be slowing execution"
+            );
+        }
+    }
+}
+
+/// Live counters updated by the builder and orchestrator and read by the
+/// progress reporter. Everything is relaxed atomics; readers only need a
+/// roughly consistent snapshot.
+#[derive(Debug)]
+pub struct RunProgress {
+    started: Instant,
+    blocks_built: AtomicU64,
+    txs_processed: AtomicU64,
+    gas_used: AtomicU64,
+    txs_generated: AtomicU64,
+    actors_generated: AtomicU64,
+    peak_rss: AtomicU64,
+    phase: Mutex<&'static str>,
+    throughput: Mutex<ThroughputWindow>,
+}
+
+impl Default for RunProgress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            blocks_built: AtomicU64::new(0),
+            txs_processed: AtomicU64::new(0),
+            gas_used: AtomicU64::new(0),
+            txs_generated: AtomicU64::new(0),
+            actors_generated: AtomicU64::new(0),
+            peak_rss: AtomicU64::new(0),
+            phase: Mutex::new("starting"),
+            throughput: Mutex::new(ThroughputWindow::default()),
+        }
+    }
+}
+
+impl RunProgress {
+    /// Account for a block that has been sealed and persisted.
+    pub fn record_block(&self, txs: u64, gas_used: u64) {
+        self.blocks_built.fetch_add(1, Ordering::Relaxed);
+        self.txs_processed.fetch_add(txs, Ordering::Relaxed);
+        self.gas_used.fetch_add(gas_used, Ordering::Relaxed);
+    }
+
+    /// Account for transactions the orchestrator has produced.
+    pub fn record_generated(&self, txs: u64) {
+        self.txs_generated.fetch_add(txs, Ordering::Relaxed);
+    }
+
+    /// Account for actor keys derived so far.
+    pub fn record_actors_generated(&self, actors: u64) {
+        self.actors_generated.fetch_add(actors, Ordering::Relaxed);
+    }
+
+    /// Sample the process's resident set size into the `rss_bytes` and
+    /// `peak_rss_bytes` gauges, returning it if it could be read.
+    pub fn sample_memory(&self) -> Option<u64> {
+        let rss = memory::rss_bytes()?;
+        let peak = self.peak_rss.fetch_max(rss, Ordering::Relaxed).max(rss);
+        gauge!("rss_bytes").set(rss);
+        gauge!("peak_rss_bytes").set(peak);
+        Some(rss)
+    }
+
+    /// Record the simulation phase the orchestrator is currently in.
+    pub fn set_phase(&self, phase: &'static str) {
+        *self.phase.lock().unwrap() = phase;
+    }
+
+    /// Record the block, transaction, and gas counters as a throughput
+    /// sample, and return it.
+    pub fn sample_throughput(&self) -> ThroughputSample {
+        let now = self.snapshot();
+        let sample = ThroughputSample {
+            at: now.elapsed,
+            blocks: now.blocks_built,
+            txs: now.txs_processed,
+            gas_used: now.gas_used,
+        };
+        self.throughput.lock().unwrap().push(sample);
+        sample
+    }
+
+    /// Rates up to the latest throughput sample.
+    pub fn throughput(&self) -> ThroughputReport {
+        let window = self.throughput.lock().unwrap();
+        let latest = window.recent.back().copied().unwrap_or_default();
+        ThroughputReport {
+            elapsed: latest.at,
+            overall: latest.rates_since(&ThroughputSample::default()),
+            last_10s: window.rates_over(SHORT_WINDOW),
+            last_60s: window.rates_over(LONG_WINDOW),
+            first_60s: window.first_window,
+        }
+    }
+
+    /// Take a point-in-time copy of every counter.
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            elapsed: self.started.elapsed(),
+            blocks_built: self.blocks_built.load(Ordering::Relaxed),
+            txs_processed: self.txs_processed.load(Ordering::Relaxed),
+            gas_used: self.gas_used.load(Ordering::Relaxed),
+            txs_generated: self.txs_generated.load(Ordering::Relaxed),
+            actors_generated: self.actors_generated.load(Ordering::Relaxed),
+            peak_rss: self.peak_rss.load(Ordering::Relaxed),
+            phase: *self.phase.lock().unwrap(),
+        }
+    }
+}
+
+/// Copy of [`RunProgress`] at a single instant.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressSnapshot {
+    pub elapsed: Duration,
+    pub blocks_built: u64,
+    pub txs_processed: u64,
+    pub gas_used: u64,
+    pub txs_generated: u64,
+    pub actors_generated: u64,
+    /// Largest resident set size sampled so far, in bytes; `0` if none was.
+    pub peak_rss: u64,
+    pub phase: &'static str,
+}
+
+impl ProgressSnapshot {
+    /// Estimated time left until the first configured limit is hit, based on the
+    /// rates observed since the start of the run.
+    fn eta(&self, config: &SimulationConfig) -> Option<Duration> {
+        let secs = self.elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+
+        let remaining = |done: u64, target: u64| {
+            let rate = done as f64 / secs;
+            (rate > 0.0).then(|| target.saturating_sub(done) as f64 / rate)
+        };
+
+        [
+            config
+                .max_blocks()
+                .and_then(|max| remaining(self.blocks_built, max)),
+            config
+                .max_transactions()
+                .and_then(|max| remaining(self.txs_processed, max)),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by(f64::total_cmp)
+        .map(Duration::from_secs_f64)
+    }
+}
+
+/// Spawn a task that logs a heartbeat line every `config.progress_interval_secs`
+/// seconds, plus a line per completed phase, until the transaction channel
+/// closes. `0` disables reporting. Each heartbeat samples memory and warns the
+/// first time it crosses `config.rss_warn_mib`.
+pub fn spawn_progress_reporter(
+    progress: Arc<RunProgress>,
+    sender: WeakSender<LabeledTx>,
+    mut phase_events: broadcast::Receiver<PhaseEvent>,
+    config: SimulationConfig,
+) {
+    let interval_secs = config.progress_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // The first tick fires immediately; skip it so the first report covers a full interval.
+        interval.tick().await;
+        let mut rss_warned = false;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                event = phase_events.recv() => {
+                    match event {
+                        Ok(PhaseEvent::Exited(span)) => info!(
+                            target: logging::PROGRESS,
+                            phase = span.phase,
+                            duration = ?span.duration,
+                            generated = span.txs_generated,
+                            txs_per_sec = span.txs_per_sec().round() as u64,
+                            "phase complete"
+                        ),
+                        // The orchestrator (and with it the channel) is gone.
+                        Err(broadcast::error::RecvError::Closed) => return,
+                        Ok(PhaseEvent::Entered { .. }) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    }
+                    continue;
+                }
+            }
+            let Some(channel_depth) = sender
+                .upgrade()
+                .filter(|sender| !sender.is_closed())
+                .map(|sender| sender.max_capacity() - sender.capacity())
+            else {
+                return;
+            };
+
+            let rss = progress.sample_memory();
+            if let (Some(rss), Some(threshold_mib)) = (rss, config.rss_warn_mib)
+                && !rss_warned
+                && rss >= threshold_mib * MIB
+            {
+                rss_warned = true;
+                warn!(
+                    target: logging::PROGRESS,
+                    rss_mib = rss / MIB,
+                    threshold_mib,
+                    "resident memory crossed the warning threshold; lower the batch size or db commit interval if the run gets killed"
+                );
+            }
+
+            let now = progress.snapshot();
+            let throughput = progress.throughput();
+            let rate = |rates: Option<Rates>, pick: fn(Rates) -> f64| {
+                rates.map(|rates| pick(rates).round() as u64)
+            };
+
+            info!(
+                target: logging::PROGRESS,
+                phase = now.phase,
+                blocks = now.blocks_built,
+                txs = now.txs_processed,
+                generated = now.txs_generated,
+                actors = now.actors_generated,
+                tps_10s = rate(throughput.last_10s, |rates| rates.txs_per_sec),
+                tps_60s = rate(throughput.last_60s, |rates| rates.txs_per_sec),
+                gas_per_sec_10s = rate(throughput.last_10s, |rates| rates.gas_per_sec),
+                gas_per_sec_60s = rate(throughput.last_60s, |rates| rates.gas_per_sec),
+                channel_depth,
+                rss_mib = rss.map(|rss| rss / MIB),
+                elapsed = ?Duration::from_secs(now.elapsed.as_secs()),
+                eta = ?now.eta(&config).map(|eta| Duration::from_secs(eta.as_secs())),
+                "progress"
+            );
+        }
+    });
+}
+
+/// Spawn a task that draws a progress bar on stdout from `progress` until
+/// `stop` fires (or its sender is dropped). Returns `None` without drawing
+/// anything unless `config.progress_bar` is set and stdout is a terminal.
+///
+/// The bar counts blocks against `max_blocks`, or transactions against
+/// `max_transactions` when only that limit is set; an unlimited run gets a
+/// spinner instead.
+pub fn spawn_progress_bar(
+    progress: Arc<RunProgress>,
+    mut stop: oneshot::Receiver<()>,
+    config: &SimulationConfig,
+) -> Option<JoinHandle<()>> {
+    if !config.progress_bar || !std::io::stdout().is_terminal() {
+        return None;
+    }
+
+    let (len, unit, position): (_, _, fn(&ProgressSnapshot) -> u64) =
+        match (config.max_blocks(), config.max_transactions()) {
+            (Some(max), _) => (Some(max), "blocks", |now| now.blocks_built),
+            (None, Some(max)) => (Some(max), "txs", |now| now.txs_processed),
+            (None, None) => (None, "blocks", |now| now.blocks_built),
+        };
+    let template = if len.is_some() {
+        format!("[{{elapsed_precise}}] {{wide_bar}} {{pos}}/{{len}} {unit} {{msg}}")
+    } else {
+        format!("{{spinner}} [{{elapsed_precise}}] {{pos}} {unit} {{msg}}")
+    };
+    let bar = ProgressBar::with_draw_target(len, ProgressDrawTarget::stdout());
+    bar.set_style(ProgressStyle::with_template(&template).expect("progress bar template is valid"));
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PROGRESS_BAR_INTERVAL);
+        loop {
+            let stopping = tokio::select! {
+                _ = interval.tick() => false,
+                _ = &mut stop => true,
+            };
+            let now = progress.snapshot();
+            bar.set_position(position(&now));
+            bar.set_message(format!(
+                "({}, {} txs, {} generated)",
+                now.phase, now.txs_processed, now.txs_generated
+            ));
+            if stopping {
+                bar.finish();
+                return;
+            }
+        }
+    }))
+}
+
+/// Spawn a task that samples `progress` every [`THROUGHPUT_SAMPLE_INTERVAL`]
+/// for the windowed rates, appending each sample to `path` as CSV. Once
+/// `stop` fires (or its sender is dropped) it takes one last sample and
+/// flushes the file.
+pub fn spawn_throughput_sampler(
+    progress: Arc<RunProgress>,
+    path: PathBuf,
+    mut stop: oneshot::Receiver<()>,
+) -> JoinHandle<eyre::Result<()>> {
+    tokio::spawn(async move {
+        let file = File::create(&path).map_err(|err| SandboxError::io(&path, err))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "elapsed_secs,blocks,txs,gas_used")?;
+
+        let mut interval = tokio::time::interval(THROUGHPUT_SAMPLE_INTERVAL);
+        loop {
+            let stopping = tokio::select! {
+                _ = interval.tick() => false,
+                _ = &mut stop => true,
+            };
+            let sample = progress.sample_throughput();
+            writeln!(
+                writer,
+                "{:.3},{},{},{}",
+                sample.at.as_secs_f64(),
+                sample.blocks,
+                sample.txs,
+                sample.gas_used
+            )?;
+            if stopping {
+                break;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    })
+}
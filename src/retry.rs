@@ -0,0 +1,78 @@
+//! Transactions that failed only because they ran ahead of one they depend
+//! on: the builder holds them for another attempt in a later block rather
+//! than counting them as failed.
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, map::HashMap};
+use alloy_sol_types::{SolError, decode_revert_reason};
+use serde_json::{Value, json};
+
+use crate::{labels::LabeledTx, token::SandboxToken};
+
+/// Reason the Uniswap router reverts with when a token `transferFrom` fails.
+const TRANSFER_FROM_FAILED: &str = "TRANSFER_FROM_FAILED";
+
+/// Whether revert `output` says a spender lacked allowance, which an approval
+/// still to come from the same sender may grant.
+pub fn is_allowance_revert(output: &[u8]) -> bool {
+    SandboxToken::ERC20InsufficientAllowance::abi_decode(output).is_ok()
+        || decode_revert_reason(output).is_some_and(|reason| reason.contains(TRANSFER_FROM_FAILED))
+}
+
+/// Sort each sender's transactions in `txs` into nonce order, leaving every
+/// sender in the positions its transactions held.
+pub fn order_by_nonce(txs: Vec<LabeledTx>) -> Vec<LabeledTx> {
+    let mut positions: HashMap<Address, Vec<usize>> = HashMap::default();
+    for (position, labeled) in txs.iter().enumerate() {
+        positions
+            .entry(labeled.tx.signer())
+            .or_default()
+            .push(position);
+    }
+    let mut slots: Vec<Option<LabeledTx>> = txs.into_iter().map(Some).collect();
+    for positions in positions.into_values() {
+        let mut sender_txs: Vec<LabeledTx> = positions
+            .iter()
+            .filter_map(|&position| slots[position].take())
+            .collect();
+        sender_txs.sort_by_key(|labeled| labeled.tx.nonce());
+        for (position, labeled) in positions.into_iter().zip(sender_txs) {
+            slots[position] = Some(labeled);
+        }
+    }
+    slots.into_iter().flatten().collect()
+}
+
+/// Outcome of every transaction the builder queued for a retry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryStats {
+    /// Distinct transactions queued at least once.
+    pub retried: u64,
+    /// Retried transactions that were later included and succeeded.
+    pub succeeded: u64,
+    /// Retried transactions rejected, dropped, or reverted on their last
+    /// attempt.
+    pub failed: u64,
+}
+
+impl RetryStats {
+    /// Render the counts for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "retried": self.retried,
+            "succeeded": self.succeeded,
+            "failed": self.failed,
+        })
+    }
+
+    /// Print a one-line summary, if anything was retried.
+    pub fn print(&self) {
+        if self.retried == 0 {
+            return;
+        }
+        println!(
+            "Retries:  {} retried, {} succeeded, {} permanently failed",
+            self.retried, self.succeeded, self.failed
+        );
+    }
+}
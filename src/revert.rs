@@ -0,0 +1,117 @@
+//! Turns revert output bytes into something a human can read.
+
+use alloy_primitives::hex;
+use alloy_sol_types::{SolError, decode_revert_reason};
+
+use crate::token::SandboxToken;
+
+/// Decode revert output into a readable reason.
+///
+/// Tries, in order: empty output, the custom errors declared by the embedded `SandboxToken`
+/// artifact, the standard `Error(string)` / `Panic(uint256)` encodings (which is
+/// what the Uniswap v2 `require` messages use), and finally raw hex.
+pub fn revert_reason(output: &[u8]) -> String {
+    // Checked first: empty output is valid UTF-8, which the generic decoder
+    // would return as an empty reason.
+    if output.is_empty() {
+        return "empty revert data".to_string();
+    }
+
+    if let Some(reason) = sandbox_token_error(output) {
+        return reason;
+    }
+
+    if let Some(reason) = decode_revert_reason(output) {
+        return reason;
+    }
+
+    hex::encode_prefixed(output)
+}
+
+/// Decode the OpenZeppelin ERC-6093 errors `SandboxToken` can revert with.
+fn sandbox_token_error(output: &[u8]) -> Option<String> {
+    if let Ok(err) = SandboxToken::ERC20InsufficientBalance::abi_decode(output) {
+        return Some(format!(
+            "ERC20InsufficientBalance: sender {} has {} but needs {}",
+            err.sender, err.balance, err.needed
+        ));
+    }
+    if let Ok(err) = SandboxToken::ERC20InsufficientAllowance::abi_decode(output) {
+        return Some(format!(
+            "ERC20InsufficientAllowance: spender {} has {} but needs {}",
+            err.spender, err.allowance, err.needed
+        ));
+    }
+    if let Ok(err) = SandboxToken::ERC20InvalidApprover::abi_decode(output) {
+        return Some(format!("ERC20InvalidApprover: {}", err.approver));
+    }
+    if let Ok(err) = SandboxToken::ERC20InvalidReceiver::abi_decode(output) {
+        return Some(format!("ERC20InvalidReceiver: {}", err.receiver));
+    }
+    if let Ok(err) = SandboxToken::ERC20InvalidSender::abi_decode(output) {
+        return Some(format!("ERC20InvalidSender: {}", err.sender));
+    }
+    if let Ok(err) = SandboxToken::ERC20InvalidSpender::abi_decode(output) {
+        return Some(format!("ERC20InvalidSpender: {}", err.spender));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, U256};
+    use alloy_sol_types::{Panic, PanicKind, Revert};
+
+    use super::*;
+
+    #[test]
+    fn error_string_reverts_keep_their_message() {
+        let output = Revert::from("UniswapV2: K").abi_encode();
+        assert!(revert_reason(&output).contains("UniswapV2: K"));
+    }
+
+    #[test]
+    fn panics_name_their_kind() {
+        let output = Panic::from(PanicKind::UnderOverflow).abi_encode();
+        assert!(revert_reason(&output).contains("overflow"));
+    }
+
+    #[test]
+    fn sandbox_token_errors_are_decoded_with_their_fields() {
+        let sender = Address::repeat_byte(0x11);
+        let output = SandboxToken::ERC20InsufficientBalance {
+            sender,
+            balance: U256::from(5),
+            needed: U256::from(7),
+        }
+        .abi_encode();
+        assert_eq!(
+            revert_reason(&output),
+            format!("ERC20InsufficientBalance: sender {sender} has 5 but needs 7")
+        );
+
+        let output = SandboxToken::ERC20InvalidReceiver {
+            receiver: Address::ZERO,
+        }
+        .abi_encode();
+        assert_eq!(
+            revert_reason(&output),
+            format!("ERC20InvalidReceiver: {}", Address::ZERO)
+        );
+    }
+
+    #[test]
+    fn unknown_selectors_fall_back_to_hex() {
+        assert_eq!(revert_reason(&[0xde, 0xad, 0xbe, 0xef]), "0xdeadbeef");
+    }
+
+    #[test]
+    fn empty_and_short_output() {
+        assert_eq!(revert_reason(&[]), "empty revert data");
+        // A truncated `Error(string)` selector decodes as nothing.
+        assert_eq!(revert_reason(&[0x08, 0xc3, 0x79, 0xa0]), "0x08c379a0");
+        let mut truncated = Revert::from("UniswapV2: K").abi_encode();
+        truncated.truncate(40);
+        assert_eq!(revert_reason(&truncated), hex::encode_prefixed(&truncated));
+    }
+}
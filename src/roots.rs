@@ -0,0 +1,175 @@
+//! Per-block hashes and roots written to `roots.csv`, so two runs can be
+//! diffed for any observable change in the blocks they built.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use alloy_consensus::Header;
+use alloy_primitives::B256;
+use reth_primitives_traits::SealedHeader;
+
+use crate::error::SandboxError;
+
+/// CSV header line of a roots file.
+const ROOTS_CSV_HEADER: &str = "block,hash,state_root,receipts_root,gas_used";
+
+/// One row of a roots file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRoots {
+    pub number: u64,
+    pub hash: B256,
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub gas_used: u64,
+}
+
+impl BlockRoots {
+    /// Row for a sealed block header.
+    pub fn from_header(header: &SealedHeader<Header>) -> Self {
+        Self {
+            number: header.number,
+            hash: header.hash(),
+            state_root: header.state_root,
+            receipts_root: header.receipts_root,
+            gas_used: header.gas_used,
+        }
+    }
+
+    /// Parse one CSV row.
+    fn parse(line: &str) -> eyre::Result<Self> {
+        let fields = line.split(',').collect::<Vec<_>>();
+        let [number, hash, state_root, receipts_root, gas_used] = fields.as_slice() else {
+            eyre::bail!("expected 5 fields, found {}", fields.len());
+        };
+        Ok(Self {
+            number: number.parse()?,
+            hash: hash.parse()?,
+            state_root: state_root.parse()?,
+            receipts_root: receipts_root.parse()?,
+            gas_used: gas_used.parse()?,
+        })
+    }
+
+    /// Names and values of the fields that differ from `other`.
+    fn differences(&self, other: &Self) -> Vec<(&'static str, String, String)> {
+        let mut diffs = Vec::new();
+        if self.hash != other.hash {
+            diffs.push(("hash", self.hash.to_string(), other.hash.to_string()));
+        }
+        if self.state_root != other.state_root {
+            diffs.push((
+                "state_root",
+                self.state_root.to_string(),
+                other.state_root.to_string(),
+            ));
+        }
+        if self.receipts_root != other.receipts_root {
+            diffs.push((
+                "receipts_root",
+                self.receipts_root.to_string(),
+                other.receipts_root.to_string(),
+            ));
+        }
+        if self.gas_used != other.gas_used {
+            diffs.push((
+                "gas_used",
+                self.gas_used.to_string(),
+                other.gas_used.to_string(),
+            ));
+        }
+        diffs
+    }
+}
+
+/// Appends one row per built block to a roots file.
+pub struct RootsWriter {
+    writer: BufWriter<File>,
+}
+
+impl RootsWriter {
+    /// Create the file and write the CSV header.
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{ROOTS_CSV_HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    /// Append the row for `header`.
+    pub fn record(&mut self, header: &SealedHeader<Header>) -> eyre::Result<()> {
+        let roots = BlockRoots::from_header(header);
+        writeln!(
+            self.writer,
+            "{},{},{},{},{}",
+            roots.number, roots.hash, roots.state_root, roots.receipts_root, roots.gas_used
+        )?;
+        Ok(())
+    }
+
+    /// Flush buffered rows to disk.
+    pub fn finish(mut self) -> eyre::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Read every row of the roots file at `path`.
+pub fn read(path: &Path) -> eyre::Result<Vec<BlockRoots>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("failed to read roots file {}: {err}", path.display()))?;
+    let mut lines = contents.lines();
+    eyre::ensure!(
+        lines.next() == Some(ROOTS_CSV_HEADER),
+        "{} is not a roots file",
+        path.display()
+    );
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            BlockRoots::parse(line)
+                .map_err(|err| eyre::eyre!("{}:{}: {err}", path.display(), index + 2))
+        })
+        .collect()
+}
+
+/// Check that `current` starts with every row of `baseline`. Extra blocks in
+/// `current` are fine; the first differing or missing block is an error
+/// describing each field that changed.
+pub fn compare(baseline: &[BlockRoots], current: &[BlockRoots]) -> eyre::Result<()> {
+    for (expected, actual) in baseline.iter().zip(current) {
+        eyre::ensure!(
+            expected.number == actual.number,
+            "block numbers diverge: baseline has block {}, this run has block {}",
+            expected.number,
+            actual.number
+        );
+
+        let diffs = expected.differences(actual);
+        if !diffs.is_empty() {
+            let report = diffs
+                .iter()
+                .map(|(field, before, after)| {
+                    format!("  {field}:\n    baseline: {before}\n    this run: {after}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            eyre::bail!(
+                "block {} differs from the baseline:\n{report}",
+                expected.number
+            );
+        }
+    }
+
+    eyre::ensure!(
+        current.len() >= baseline.len(),
+        "this run built {} blocks, but the baseline has {}; block {} is missing",
+        current.len(),
+        baseline.len(),
+        baseline[current.len()].number
+    );
+    Ok(())
+}
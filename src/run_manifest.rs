@@ -0,0 +1,218 @@
+//! Provenance record written at the end of every run so emitted artifacts can
+//! be traced back to the config that produced them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::B256;
+use serde_json::{Value, json};
+
+use crate::{
+    block_builder::{BaseFeeDrift, DbCommitStats},
+    calibration::{GasCalibration, TipCalibration},
+    config::{SimulationConfig, StopReason},
+    labels::LabelTotals,
+    lanes::LaneReport,
+    progress::{PhaseTimeline, ProgressSnapshot, ThroughputReport},
+    retry::RetryStats,
+    senders::SenderDiversity,
+    stats::GenerationReport,
+};
+
+/// `git describe` of the source tree, exported by `build.rs` when the binary
+/// was built from a git checkout.
+const GIT_DESCRIBE: Option<&str> = option_env!("SANDBOX_GIT_DESCRIBE");
+
+/// Everything needed to tell which config, seed, and binary produced a run.
+pub struct RunManifest {
+    started_at: u64,
+    finished_at: Option<u64>,
+    genesis_hash: Option<B256>,
+    totals: Option<ProgressSnapshot>,
+    stop_reason: Option<StopReason>,
+    phases: PhaseTimeline,
+    generated: Option<GenerationReport>,
+    labels: Option<LabelTotals>,
+    gas_calibration: Option<GasCalibration>,
+    tip_calibration: Option<TipCalibration>,
+    senders: Option<SenderDiversity>,
+    retries: Option<RetryStats>,
+    lanes: Option<LaneReport>,
+    db_commits: Option<DbCommitStats>,
+    base_fee_drift: Option<BaseFeeDrift>,
+    throughput: Option<ThroughputReport>,
+    /// Every `counter!` value as the run ended.
+    counters: Option<Value>,
+    /// Directory artifact paths are recorded relative to.
+    output_dir: PathBuf,
+    artifacts: Vec<PathBuf>,
+}
+
+impl RunManifest {
+    /// Start a manifest stamped with the current time, for a run writing
+    /// into `output_dir`.
+    pub fn start(output_dir: &Path) -> Self {
+        Self {
+            started_at: unix_now(),
+            finished_at: None,
+            genesis_hash: None,
+            totals: None,
+            stop_reason: None,
+            phases: PhaseTimeline::default(),
+            generated: None,
+            labels: None,
+            gas_calibration: None,
+            tip_calibration: None,
+            senders: None,
+            retries: None,
+            lanes: None,
+            db_commits: None,
+            base_fee_drift: None,
+            throughput: None,
+            counters: None,
+            output_dir: output_dir.to_path_buf(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Record the hash of the genesis block the run built on.
+    pub fn set_genesis_hash(&mut self, hash: B256) {
+        self.genesis_hash = Some(hash);
+    }
+
+    /// Record how long each simulation phase took.
+    pub fn set_phases(&mut self, phases: PhaseTimeline) {
+        self.phases = phases;
+    }
+
+    /// Record what the orchestrator generated.
+    pub fn set_generated(&mut self, generated: GenerationReport) {
+        self.generated = Some(generated);
+    }
+
+    /// Record what the builder executed per transaction label.
+    pub fn set_labels(&mut self, labels: LabelTotals) {
+        self.labels = Some(labels);
+    }
+
+    /// Record gas used against gas limits per transaction label.
+    pub fn set_gas_calibration(&mut self, calibration: GasCalibration) {
+        self.gas_calibration = Some(calibration);
+    }
+
+    /// Record the tip each fee strategy achieved.
+    pub fn set_tip_calibration(&mut self, calibration: TipCalibration) {
+        self.tip_calibration = Some(calibration);
+    }
+
+    /// Record how many distinct senders the built blocks held.
+    pub fn set_sender_diversity(&mut self, senders: SenderDiversity) {
+        self.senders = Some(senders);
+    }
+
+    /// Record how the transactions queued for a retry turned out.
+    pub fn set_retries(&mut self, retries: RetryStats) {
+        self.retries = Some(retries);
+    }
+
+    /// Record lane utilization for a run built with parallel lanes.
+    pub fn set_lanes(&mut self, lanes: LaneReport) {
+        self.lanes = Some(lanes);
+    }
+
+    /// Record how long database commits took.
+    pub fn set_db_commits(&mut self, db_commits: DbCommitStats) {
+        self.db_commits = Some(db_commits);
+    }
+
+    /// Record how block base fees moved.
+    pub fn set_base_fee_drift(&mut self, drift: BaseFeeDrift) {
+        self.base_fee_drift = Some(drift);
+    }
+
+    /// Record throughput over the run and its sliding windows.
+    pub fn set_throughput(&mut self, throughput: ThroughputReport) {
+        self.throughput = Some(throughput);
+    }
+
+    /// Record the process-wide counters, as `metrics::counters_json` renders
+    /// them.
+    pub fn set_counters(&mut self, counters: Value) {
+        self.counters = Some(counters);
+    }
+
+    /// Record an emitted file. Paths under the output directory are stored
+    /// relative to it.
+    pub fn add_artifact(&mut self, path: &Path) {
+        let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+        self.artifacts.push(relative.to_path_buf());
+    }
+
+    /// Paths added with [`Self::add_artifact`], relative to the output
+    /// directory where possible.
+    pub fn artifacts(&self) -> &[PathBuf] {
+        &self.artifacts
+    }
+
+    /// Stamp the end time, final block/tx/gas totals, and the limit that ended the run.
+    pub fn finish(&mut self, totals: ProgressSnapshot, stop_reason: StopReason) {
+        self.finished_at = Some(unix_now());
+        self.totals = Some(totals);
+        self.stop_reason = Some(stop_reason);
+    }
+
+    /// Render the manifest, embedding `config` in full.
+    pub fn to_json(&self, config: &SimulationConfig) -> Value {
+        json!({
+            "tag": config.tag,
+            "git_describe": GIT_DESCRIBE,
+            "seed": config.actor_seed.map(|seed| seed.to_string()),
+            "started_at": self.started_at,
+            "finished_at": self.finished_at,
+            "genesis_hash": self.genesis_hash.map(|hash| hash.to_string()),
+            "totals": self.totals.map(|totals| json!({
+                "blocks": totals.blocks_built,
+                "transactions": totals.txs_processed,
+                "gas_used": totals.gas_used,
+                "elapsed_secs": totals.elapsed.as_secs_f64(),
+                "peak_rss_bytes": totals.peak_rss,
+            })),
+            "stop_reason": self.stop_reason.map(|reason| reason.to_string()),
+            "phases": self.phases.to_json(),
+            "generated": self.generated.as_ref().map(GenerationReport::to_json),
+            "labels": self.labels.as_ref().map(LabelTotals::to_json),
+            "gas_calibration": self.gas_calibration.as_ref().map(GasCalibration::to_json),
+            "tip_calibration": self.tip_calibration.as_ref().map(TipCalibration::to_json),
+            "senders": self.senders.as_ref().map(SenderDiversity::to_json),
+            "retries": self.retries.as_ref().map(RetryStats::to_json),
+            "lanes": self.lanes.as_ref().map(LaneReport::to_json),
+            "db_commits": self.db_commits.as_ref().map(DbCommitStats::to_json),
+            "base_fee": self.base_fee_drift.as_ref().map(BaseFeeDrift::to_json),
+            "throughput": self.throughput.as_ref().map(ThroughputReport::to_json),
+            "counters": self.counters,
+            "artifacts": self
+                .artifacts
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>(),
+            "config": config.to_json(),
+        })
+    }
+
+    /// Write the manifest to `path` as pretty-printed JSON.
+    pub fn write(&self, path: &Path, config: &SimulationConfig) -> eyre::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_json(config))?)?;
+        Ok(())
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,359 @@
+//! Scripted load: a scenario file lists every load block to build and the
+//! steps that fill it, in place of the weighted mixed generator.
+//!
+//! A scenario is JSON with one array of steps per block:
+//!
+//! ```json
+//! { "blocks": [
+//!     [{ "type": "eth-transfer", "count": 100 }],
+//!     [{ "type": "uniswap-swap-for-token", "count": 50, "token": 3 }],
+//!     [{ "type": "calldata", "count": 1, "bytes": 100000 }]
+//! ] }
+//! ```
+//!
+//! Each step sends `count` transactions of its `type`. Optional parameters:
+//! `from` and `to` pick actors by index, `token` picks a setup token by
+//! index, `amount` (wei or token base units, as a number or decimal string)
+//! replaces the low end of the configured range, and `bytes` sizes a
+//! calldata transaction. Without `from`, senders go round-robin over every
+//! actor; without `to`, each sender pays the next actor.
+
+use std::{fs, path::Path};
+
+use alloy_primitives::U256;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    config::SimulationConfig,
+    error::SandboxError,
+    labels::TxLabel,
+    transaction::{DEFAULT_GAS_LIMIT, TRANSFER_GAS_LIMIT},
+};
+
+/// Gas a nonzero calldata byte costs at most: the EIP-7623 floor price,
+/// which is above the standard one.
+const CALLDATA_GAS_PER_BYTE: u64 = 40;
+
+/// Kind of transaction a step sends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepType {
+    /// Plain ETH transfer to an actor.
+    EthTransfer,
+    /// `SandboxToken` transfer to an actor.
+    TokenTransfer,
+    /// Spend ETH on a token via the router.
+    SwapForToken,
+    /// Approve the router, then spend a token on ETH: two transactions.
+    SwapForEth,
+    /// Wrap ETH by calling WETH9 `deposit()`.
+    WethDeposit,
+    /// Zero-value transfer to an actor carrying `bytes` of calldata.
+    Calldata,
+}
+
+impl StepType {
+    const ALL: [Self; 6] = [
+        Self::EthTransfer,
+        Self::TokenTransfer,
+        Self::SwapForToken,
+        Self::SwapForEth,
+        Self::WethDeposit,
+        Self::Calldata,
+    ];
+
+    /// Label the step's transactions are sent with; its name is the step's
+    /// `type` in a scenario file.
+    pub fn label(&self) -> TxLabel {
+        match self {
+            Self::EthTransfer => TxLabel::EthTransfer,
+            Self::TokenTransfer => TxLabel::TokenTransfer,
+            Self::SwapForToken => TxLabel::UniswapSwapForToken,
+            Self::SwapForEth => TxLabel::UniswapSwapForEth,
+            Self::WethDeposit => TxLabel::WethDeposit,
+            Self::Calldata => TxLabel::Calldata,
+        }
+    }
+
+    /// Transactions sent per repetition of the step.
+    pub fn txs_each(&self) -> u64 {
+        match self {
+            Self::SwapForEth => 2,
+            _ => 1,
+        }
+    }
+
+    /// Whether the step calls the setup's tokens or Uniswap contracts.
+    pub fn needs_contracts(&self) -> bool {
+        matches!(
+            self,
+            Self::TokenTransfer | Self::SwapForToken | Self::SwapForEth | Self::WethDeposit
+        )
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| ty.label().name() == name)
+    }
+}
+
+/// Optional parameters of a step.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepParams {
+    /// Sending actor; round-robin over every actor when unset.
+    pub from: Option<usize>,
+    /// Receiving actor of a transfer; the sender's successor when unset.
+    pub to: Option<usize>,
+    /// Setup token a token step uses.
+    pub token: usize,
+    /// Amount sent; the low end of the configured range when unset.
+    pub amount: Option<U256>,
+    /// Calldata length of a calldata step.
+    pub bytes: usize,
+}
+
+/// `count` transactions of one kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub count: u64,
+    pub tx_type: StepType,
+    pub params: StepParams,
+}
+
+impl Step {
+    /// Transactions the step sends.
+    pub fn tx_count(&self) -> u64 {
+        self.count * self.tx_type.txs_each()
+    }
+
+    /// Gas limit each of the step's transactions is signed with. Contract
+    /// calls take a calibrated limit for their label when there is one.
+    pub fn gas_limit(&self, config: &SimulationConfig) -> u64 {
+        match self.tx_type {
+            StepType::EthTransfer => TRANSFER_GAS_LIMIT,
+            StepType::Calldata => {
+                TRANSFER_GAS_LIMIT + CALLDATA_GAS_PER_BYTE * self.params.bytes as u64
+            }
+            ty => config
+                .calibrated_gas_limits
+                .get(ty.label().name())
+                .copied()
+                .unwrap_or(DEFAULT_GAS_LIMIT),
+        }
+    }
+
+    fn parse(step: &Value) -> Result<Self, String> {
+        let step = step.as_object().ok_or("a step is not an object")?;
+        let name = step
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or("missing the `type` string")?;
+        let tx_type =
+            StepType::from_name(name).ok_or_else(|| format!("unknown step type `{name}`"))?;
+        let count = step
+            .get("count")
+            .and_then(Value::as_u64)
+            .ok_or("missing the `count` number")?;
+        if count == 0 {
+            return Err("`count` is 0".to_string());
+        }
+
+        let mut params = StepParams::default();
+        for (key, value) in step {
+            let applies = match key.as_str() {
+                "type" | "count" | "from" => true,
+                "to" => matches!(
+                    tx_type,
+                    StepType::EthTransfer | StepType::TokenTransfer | StepType::Calldata
+                ),
+                "token" => matches!(
+                    tx_type,
+                    StepType::TokenTransfer | StepType::SwapForToken | StepType::SwapForEth
+                ),
+                "amount" => tx_type != StepType::Calldata,
+                "bytes" => tx_type == StepType::Calldata,
+                _ => return Err(format!("unknown parameter `{key}`")),
+            };
+            if !applies {
+                return Err(format!("a {name} step takes no `{key}`"));
+            }
+            let index = || {
+                value
+                    .as_u64()
+                    .map(|index| index as usize)
+                    .ok_or_else(|| format!("`{key}` is not an index"))
+            };
+            match key.as_str() {
+                "from" => params.from = Some(index()?),
+                "to" => params.to = Some(index()?),
+                "token" => params.token = index()?,
+                "bytes" => params.bytes = index()?,
+                "amount" => params.amount = Some(parse_amount(value)?),
+                _ => {}
+            }
+        }
+        if tx_type == StepType::Calldata && params.bytes == 0 {
+            return Err("a calldata step needs a nonzero `bytes`".to_string());
+        }
+        Ok(Self {
+            count,
+            tx_type,
+            params,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let mut step = Map::new();
+        step.insert("type".into(), json!(self.tx_type.label().name()));
+        step.insert("count".into(), json!(self.count));
+        let params = &self.params;
+        if let Some(from) = params.from {
+            step.insert("from".into(), json!(from));
+        }
+        if let Some(to) = params.to {
+            step.insert("to".into(), json!(to));
+        }
+        if matches!(
+            self.tx_type,
+            StepType::TokenTransfer | StepType::SwapForToken | StepType::SwapForEth
+        ) {
+            step.insert("token".into(), json!(params.token));
+        }
+        if let Some(amount) = params.amount {
+            step.insert("amount".into(), json!(amount.to_string()));
+        }
+        if self.tx_type == StepType::Calldata {
+            step.insert("bytes".into(), json!(params.bytes));
+        }
+        Value::Object(step)
+    }
+}
+
+/// A wei or token amount, as a JSON number or a decimal or `0x` string.
+fn parse_amount(value: &Value) -> Result<U256, String> {
+    match value {
+        Value::Number(number) => number.as_u64().map(U256::from),
+        Value::String(amount) => amount.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("`amount` {value} is not a whole number"))
+}
+
+/// The load blocks of a scripted run, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scenario {
+    pub blocks: Vec<Vec<Step>>,
+}
+
+impl Scenario {
+    /// Read and parse a scenario file.
+    pub fn read(path: &Path) -> Result<Self, SandboxError> {
+        let contents = fs::read_to_string(path).map_err(|err| SandboxError::io(path, err))?;
+        Self::parse(&contents).map_err(|err| match err {
+            SandboxError::Config(reason) => {
+                SandboxError::Config(format!("{}: {reason}", path.display()))
+            }
+            err => err,
+        })
+    }
+
+    /// Parse a scenario from JSON. Unknown step types and parameters are
+    /// rejected here; indices are checked against the run by [`Self::check`].
+    pub fn parse(json: &str) -> Result<Self, SandboxError> {
+        let scenario: Value = serde_json::from_str(json)
+            .map_err(|err| SandboxError::Config(format!("scenario is not JSON: {err}")))?;
+        let blocks = scenario["blocks"]
+            .as_array()
+            .filter(|blocks| !blocks.is_empty())
+            .ok_or_else(|| SandboxError::Config("scenario has no `blocks`".to_string()))?;
+        let blocks = blocks
+            .iter()
+            .enumerate()
+            .map(|(b, block)| {
+                let steps = block
+                    .as_array()
+                    .filter(|steps| !steps.is_empty())
+                    .ok_or_else(|| format!("block {b} is not a nonempty array of steps"))?;
+                steps
+                    .iter()
+                    .enumerate()
+                    .map(|(s, step)| {
+                        Step::parse(step).map_err(|reason| format!("block {b}, step {s}: {reason}"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|reason| SandboxError::Config(format!("scenario {reason}")))?;
+        Ok(Self { blocks })
+    }
+
+    /// Transactions in each block, in order.
+    pub fn block_tx_counts(&self) -> Vec<u64> {
+        self.blocks
+            .iter()
+            .map(|steps| steps.iter().map(Step::tx_count).sum())
+            .collect()
+    }
+
+    /// Reject steps `config` cannot satisfy: an actor or token index past
+    /// the end of the pool, a contract call on a run that deploys no
+    /// contracts, or a block whose transactions' gas limits add up past the
+    /// block gas limit, which would split it.
+    pub fn check(&self, config: &SimulationConfig) -> Result<(), SandboxError> {
+        for (b, steps) in self.blocks.iter().enumerate() {
+            let mut block_gas = 0u64;
+            for (s, step) in steps.iter().enumerate() {
+                let params = &step.params;
+                let actor = [params.from, params.to]
+                    .into_iter()
+                    .flatten()
+                    .find(|&actor| actor as u64 >= config.unique_accounts);
+                let problem = if let Some(actor) = actor {
+                    Some(format!(
+                        "uses actor {actor}, but the run has {} actors",
+                        config.unique_accounts
+                    ))
+                } else if step.tx_type.needs_contracts() && !config.deploys_contracts() {
+                    Some(format!(
+                        "sends {}, which needs the mixed workload with at least one token",
+                        step.tx_type.label().name()
+                    ))
+                } else if step.tx_type.needs_contracts()
+                    && step.tx_type != StepType::WethDeposit
+                    && params.token as u64 >= config.unique_tokens
+                {
+                    Some(format!(
+                        "uses token {}, but the run deploys {} tokens",
+                        params.token, config.unique_tokens
+                    ))
+                } else {
+                    None
+                };
+                if let Some(problem) = problem {
+                    return Err(SandboxError::Config(format!(
+                        "scenario block {b}, step {s} {problem}"
+                    )));
+                }
+                block_gas = block_gas
+                    .saturating_add(step.tx_count().saturating_mul(step.gas_limit(config)));
+            }
+            if block_gas > config.gas_limit {
+                return Err(SandboxError::Config(format!(
+                    "scenario block {b} sends transactions with gas limits totalling {block_gas}, \
+                     past the block gas limit of {}; split it or raise `gas_limit`",
+                    config.gas_limit
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The scenario in the format [`Self::parse`] reads, for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "blocks": self
+                .blocks
+                .iter()
+                .map(|steps| steps.iter().map(Step::to_json).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Contracts that are created, written to, and destroyed within a run, to
+//! exercise account deletion in the execution and commit paths.
+//!
+//! `Destructible` is hand-assembled: 64 bytes of calldata (`slot || value`)
+//! store `value` at `slot`, and empty calldata `SELFDESTRUCT`s to the caller.
+//! Since Cancun (EIP-6780) that only sends the balance unless the contract was
+//! created in the same transaction, so [`DestructibleHelper::ephemeral`] builds
+//! init code that writes storage and self-destructs during creation.
+
+use std::{collections::BTreeSet, sync::Mutex};
+
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_sol_macro::sol;
+
+sol!(
+    #[allow(missing_docs)]
+    Destructible,
+    "artifacts/Destructible.json"
+);
+
+/// Encoders for deploying and calling [`Destructible`].
+pub struct DestructibleHelper;
+
+impl DestructibleHelper {
+    /// Creation bytecode.
+    pub fn deploy() -> Bytes {
+        Destructible::BYTECODE.clone()
+    }
+
+    /// Calldata that writes `value` to `slot`.
+    pub fn store(slot: U256, value: U256) -> Bytes {
+        let mut out = Vec::with_capacity(64);
+        out.extend_from_slice(&slot.to_be_bytes::<32>());
+        out.extend_from_slice(&value.to_be_bytes::<32>());
+        out.into()
+    }
+
+    /// Calldata for `die()`: self-destruct to the caller.
+    pub fn die() -> Bytes {
+        Bytes::new()
+    }
+
+    /// Init code that sets slots `0..slots` to 1, then self-destructs to the
+    /// caller before returning any code, so the account is created and
+    /// deleted within one transaction.
+    pub fn ephemeral(slots: u8) -> Bytes {
+        let mut code = Vec::with_capacity(slots as usize * 5 + 2);
+        for slot in 0..slots {
+            // PUSH1 1, PUSH1 slot, SSTORE
+            code.extend_from_slice(&[0x60, 0x01, 0x60, slot, 0x55]);
+        }
+        // CALLER, SELFDESTRUCT
+        code.extend_from_slice(&[0x33, 0xff]);
+        code.into()
+    }
+}
+
+/// Accounts the orchestrator expects to be gone from state once their
+/// destructing transaction executes. Shared with the run through an `Arc`.
+#[derive(Debug, Default)]
+pub struct DestroyedAccounts {
+    addresses: Mutex<BTreeSet<Address>>,
+}
+
+impl DestroyedAccounts {
+    /// Remember that `address` should be deleted.
+    pub fn record(&self, address: Address) {
+        self.addresses.lock().unwrap().insert(address);
+    }
+
+    /// Every address recorded so far.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.addresses.lock().unwrap().iter().copied().collect()
+    }
+}
@@ -0,0 +1,159 @@
+//! Which actors send load transactions, and how many distinct senders the
+//! builder's blocks end up with.
+
+use alloy_primitives::map::HashMap;
+use rand::Rng;
+use serde_json::{Value, json};
+
+use crate::config::SenderSelection;
+
+/// Hands out sender and receiver indices for one load batch.
+pub struct SenderPicker {
+    selection: SenderSelection,
+    /// `0` places no limit on transactions per sender.
+    max_per_sender: u32,
+    allow_self_transfer: bool,
+    num_actors: usize,
+    /// Next index round-robin selection hands out.
+    cursor: usize,
+    picked: HashMap<usize, u32>,
+    /// Senders that reached `max_per_sender`.
+    saturated: usize,
+}
+
+impl SenderPicker {
+    /// Pick among `num_actors` actors, resuming round-robin selection at
+    /// `cursor`.
+    pub fn new(
+        selection: SenderSelection,
+        max_per_sender: u32,
+        allow_self_transfer: bool,
+        num_actors: usize,
+        cursor: usize,
+    ) -> Self {
+        Self {
+            selection,
+            max_per_sender,
+            allow_self_transfer,
+            num_actors,
+            cursor,
+            picked: HashMap::default(),
+            saturated: 0,
+        }
+    }
+
+    /// Next sender, or `None` once every actor has sent `max_per_sender`
+    /// transactions in this batch.
+    ///
+    /// Random selection resamples senders that are already at the cap, so
+    /// the batch draws senders without replacement once the cap is 1.
+    pub fn sender(&mut self, rng: &mut impl Rng) -> Option<usize> {
+        if self.num_actors == 0 || (self.max_per_sender > 0 && self.saturated >= self.num_actors) {
+            return None;
+        }
+        loop {
+            let index = match self.selection {
+                SenderSelection::Random => rng.random_range(0..self.num_actors),
+                SenderSelection::RoundRobin => {
+                    let index = self.cursor % self.num_actors;
+                    self.cursor = index + 1;
+                    index
+                }
+            };
+            let picked = self.picked.entry(index).or_default();
+            if self.max_per_sender == 0 || *picked < self.max_per_sender {
+                *picked += 1;
+                if *picked == self.max_per_sender {
+                    self.saturated += 1;
+                }
+                return Some(index);
+            }
+        }
+    }
+
+    /// Random receiver for `sender`, never `sender` itself unless self
+    /// transfers are allowed or it is the only actor.
+    pub fn receiver(&self, sender: usize, rng: &mut impl Rng) -> usize {
+        if self.allow_self_transfer || self.num_actors < 2 {
+            return rng.random_range(0..self.num_actors);
+        }
+        // Draw from the other actors and skip over the sender.
+        let index = rng.random_range(0..self.num_actors - 1);
+        if index >= sender { index + 1 } else { index }
+    }
+
+    /// Where round-robin selection should resume in the next batch.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
+
+/// Distinct senders per sealed block, over a whole run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SenderDiversity {
+    blocks: u64,
+    txs: u64,
+    /// Sum over blocks of each block's distinct senders.
+    senders: u64,
+    min: Option<u64>,
+    max: u64,
+    /// Most transactions any one sender had in a single block.
+    max_txs_from_one_sender: u64,
+}
+
+impl SenderDiversity {
+    /// Account for a sealed block with `txs` transactions from `senders`
+    /// distinct accounts, the busiest of which sent `max_from_one`.
+    pub fn record_block(&mut self, txs: u64, senders: u64, max_from_one: u64) {
+        self.blocks += 1;
+        self.txs += txs;
+        self.senders += senders;
+        self.min = Some(self.min.map_or(senders, |min| min.min(senders)));
+        self.max = self.max.max(senders);
+        self.max_txs_from_one_sender = self.max_txs_from_one_sender.max(max_from_one);
+    }
+
+    /// Mean distinct senders per block.
+    pub fn avg_senders_per_block(&self) -> f64 {
+        if self.blocks == 0 {
+            return 0.0;
+        }
+        self.senders as f64 / self.blocks as f64
+    }
+
+    /// Mean transactions each sender has in a block; `1.0` means no block
+    /// holds two transactions from the same account.
+    pub fn txs_per_sender(&self) -> f64 {
+        if self.senders == 0 {
+            return 0.0;
+        }
+        self.txs as f64 / self.senders as f64
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "blocks": self.blocks,
+            "avg_senders_per_block": self.avg_senders_per_block(),
+            "min_senders_per_block": self.min.unwrap_or_default(),
+            "max_senders_per_block": self.max,
+            "txs_per_sender": self.txs_per_sender(),
+            "max_txs_from_one_sender": self.max_txs_from_one_sender,
+        })
+    }
+
+    /// Print a one-line summary.
+    pub fn print(&self) {
+        if self.blocks == 0 {
+            return;
+        }
+        println!(
+            "Senders:  {:.1} distinct per block (min {}, max {}), {:.2} txs per sender (at most {} from one)",
+            self.avg_senders_per_block(),
+            self.min.unwrap_or_default(),
+            self.max,
+            self.txs_per_sender(),
+            self.max_txs_from_one_sender
+        );
+    }
+}
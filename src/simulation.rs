@@ -0,0 +1,1109 @@
+//! One complete simulation as a library call. [`Simulation::new`] builds the
+//! chain, boots the database, and wires the orchestrator to the builder;
+//! [`Simulation::run`] drives both halves, writes the run's artifacts, and
+//! hands back the database for inspection. `sandbox run` only assembles the
+//! config around it.
+
+use alloy_primitives::B256;
+use reth_chainspec::ChainSpec;
+use reth_db::{DatabaseEnv, test_utils::TempDatabase};
+use reth_db_common::init::init_genesis;
+use reth_node_ethereum::{EthEvmConfig, EthereumNode};
+use reth_primitives_traits::SealedHeader;
+use reth_provider::{
+    BlockNumReader, ChainSpecProvider, HeaderProvider, ProviderFactory, StateProviderBox,
+    test_utils::create_test_provider_factory_with_node_types,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::sync::{
+    broadcast,
+    mpsc::{self, WeakSender},
+    oneshot,
+};
+use tracing::{info, warn};
+
+use crate::{
+    actor::{ActorPool, NonceCorrections},
+    block_builder::{BaseFeeDrift, DbCommitStats, PF, SandboxBlockBuilder, SandboxDatabase},
+    calibration::{GasCalibration, TipCalibration},
+    chain,
+    config::{BundlerMode, SimulationConfig, Stage, StopReason, Workload},
+    debug::{self, TableStat},
+    deployments::{DeploymentManifest, ExpectedCode},
+    error::SandboxError,
+    gauge,
+    invalid::{InjectionReport, InvalidTxRegistry},
+    labels::{LabelTotals, LabeledTx},
+    lanes::LaneReport,
+    logging, metrics,
+    orchestrator::TransactionOrchestrator,
+    phase_checks::PhaseChecks,
+    progress::{
+        self, PHASE_EVENT_CAPACITY, PhaseEvent, PhaseTimeline, RunProgress, ThroughputReport,
+    },
+    retry::RetryStats,
+    run_manifest::RunManifest,
+    selfdestruct::DestroyedAccounts,
+    senders::SenderDiversity,
+    stages::{self, SetupDir},
+    stats::{GenerationReport, GenerationStats},
+    tx_stream::{TxStreamReader, TxStreamWriter},
+};
+
+#[cfg(feature = "test-hooks")]
+pub use crate::faults::FaultInjection;
+
+/// Database behind `in_memory` runs: reth's throwaway test database.
+pub type InMemoryDb = Arc<TempDatabase<DatabaseEnv>>;
+
+/// Directory (under the output directory) that receives failed transaction traces.
+const FAILED_TRACES_DIR: &str = "failed_traces";
+
+/// Directory (under the output directory) that receives per-block state diffs.
+const STATE_DIFFS_DIR: &str = "state_diffs";
+
+/// Directory (under the output directory) that receives per-token holdings.
+const TOKEN_HOLDINGS_DIR: &str = "token_holdings";
+
+/// Where a simulation writes its artifacts: the files it names itself
+/// (`run_manifest.json`, `deployments.json`, `throughput.csv`, `counters.csv`, the balance
+/// and pool reports, token holdings, state diffs, and failed transaction traces), and every relative output
+/// path in the config. Inputs such as `genesis_path` and `setup_dir` are
+/// used as given.
+#[derive(Debug, Clone)]
+pub struct SimulationPaths {
+    pub output_dir: PathBuf,
+}
+
+impl SimulationPaths {
+    /// Write into `output_dir`, which must exist.
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+        }
+    }
+
+    /// Create `output_dir` and write into it. A directory that already holds
+    /// files is refused unless `force` is set, so one run cannot overwrite
+    /// another's artifacts.
+    pub fn create(output_dir: impl Into<PathBuf>, force: bool) -> Result<Self, SandboxError> {
+        let output_dir = output_dir.into();
+        let occupied = match fs::read_dir(&output_dir) {
+            Ok(mut entries) => entries.next().is_some(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => false,
+            Err(err) => return Err(SandboxError::io(output_dir, err)),
+        };
+        if occupied && !force {
+            return Err(SandboxError::OutputDirNotEmpty { path: output_dir });
+        }
+        fs::create_dir_all(&output_dir).map_err(|err| SandboxError::io(&output_dir, err))?;
+        Ok(Self::new(output_dir))
+    }
+
+    /// The directory under `root` for a run started at `started_at` (Unix
+    /// seconds): `<started_at>-<tag>`, or just `<started_at>` untagged.
+    /// Characters of the tag that do not belong in a file name become `_`.
+    pub fn run_dir(root: &Path, started_at: u64, tag: Option<&str>) -> PathBuf {
+        let name = match tag {
+            Some(tag) => {
+                let tag: String = tag
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                format!("{started_at}-{tag}")
+            }
+            None => started_at.to_string(),
+        };
+        root.join(name)
+    }
+
+    /// `path` under the output directory if it is relative, as given if not.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.output_dir.join(path)
+    }
+
+    /// Directory per-block state diffs are written to.
+    pub fn state_diffs_dir(&self) -> PathBuf {
+        self.join(STATE_DIFFS_DIR)
+    }
+
+    /// Directory the balance report writes each setup token's holdings to.
+    pub fn token_holdings_dir(&self) -> PathBuf {
+        self.join(TOKEN_HOLDINGS_DIR)
+    }
+
+    /// Directory failed transaction traces are written to.
+    pub fn failed_traces_dir(&self) -> PathBuf {
+        self.join(FAILED_TRACES_DIR)
+    }
+
+    fn join(&self, file: &str) -> PathBuf {
+        self.output_dir.join(file)
+    }
+
+    /// Point every output path in `config` into the output directory.
+    fn resolve_outputs(&self, config: &mut SimulationConfig) {
+        for path in [
+            &mut config.blocks_out,
+            &mut config.blocks_jsonl_out,
+            &mut config.roots_out,
+        ] {
+            *path = self.resolve(path);
+        }
+        for path in [
+            &mut config.genesis_out,
+            &mut config.actors_export_path,
+            &mut config.trace_out,
+            &mut config.block_labels_out,
+            &mut config.txs_out,
+            &mut config.payloads_out,
+            &mut config.block_fees_out,
+            &mut config.tx_stream_out,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = self.resolve(path);
+        }
+    }
+}
+
+/// A simulation that is set up and ready to run.
+pub struct Simulation {
+    setup: Setup,
+    backend: Backend,
+}
+
+/// Everything [`Simulation::new`] creates besides the builder and database.
+struct Setup {
+    config: SimulationConfig,
+    paths: SimulationPaths,
+    run_manifest: RunManifest,
+    orchestrator: TransactionOrchestrator,
+    /// Lets the progress reporter and channel sampler watch the channel
+    /// without keeping it open.
+    weak_sender: WeakSender<LabeledTx>,
+    progress: Arc<RunProgress>,
+    invalid_txs: Arc<InvalidTxRegistry>,
+    destroyed_accounts: Arc<DestroyedAccounts>,
+    nonce_corrections: Arc<NonceCorrections>,
+    generation_stats: Arc<GenerationStats>,
+    deployments_rx: oneshot::Receiver<DeploymentManifest>,
+    /// Where the orchestrator writes `deployments.json` once setup completes.
+    deployments_path: PathBuf,
+    /// Phase events for the progress reporter.
+    progress_events: broadcast::Receiver<PhaseEvent>,
+    /// Phase events collected into the timeline once the run ends.
+    timeline_events: broadcast::Receiver<PhaseEvent>,
+}
+
+/// The builder together with the database it builds on.
+enum Backend {
+    Mdbx {
+        builder: SandboxBlockBuilder,
+        provider_factory: PF,
+        db: Arc<DatabaseEnv>,
+        datadir: PathBuf,
+        /// Deletes a temporary datadir when the database is dropped.
+        temp_dir: Option<TempDir>,
+    },
+    InMemory {
+        builder: SandboxBlockBuilder<InMemoryDb>,
+        provider_factory: PF<InMemoryDb>,
+    },
+}
+
+impl Simulation {
+    /// Build the chain, boot a Reth data directory (or a test database for
+    /// `in_memory` runs) with its genesis, and create the orchestrator and
+    /// builder connected by the transaction channel.
+    ///
+    /// With `actor_seed` set, two simulations of the same config build the
+    /// same blocks.
+    ///
+    /// A setup stage points its outputs into `setup_dir`; a load stage reads
+    /// its actors, contracts, and genesis from there and builds on a copy of
+    /// the setup's datadir.
+    pub fn new(mut config: SimulationConfig, paths: SimulationPaths) -> eyre::Result<Self> {
+        config.validate()?;
+        // Before a setup stage points its own outputs into `setup_dir`.
+        paths.resolve_outputs(&mut config);
+        let setup_artifacts = match config.stage {
+            Stage::Full => None,
+            Stage::Setup => {
+                stages::prepare_setup(&mut config)?;
+                None
+            }
+            Stage::Load => {
+                let setup = stages::read_setup(&mut config)?;
+                // The token count now comes from the setup.
+                config.check_bundler_mode()?;
+                Some(setup)
+            }
+        };
+        metrics::set_enabled(config.metrics);
+        metrics::run_start();
+        let mut run_manifest = RunManifest::start(&paths.output_dir);
+
+        if config.trace_out.is_some() {
+            metrics::enable_trace();
+        }
+
+        let genesis_path = match &setup_artifacts {
+            Some(setup) => Some(setup.genesis.clone()),
+            None => config.genesis_path.clone(),
+        };
+        let chain = match genesis_path {
+            Some(path) => chain::chain_from_file(&path, &mut config)?,
+            None => {
+                // Pre-funded actors must be known before genesis, so their keys have
+                // to be derived from a seed the orchestrator will reuse.
+                let actor_addresses = if config.prefund_actors_in_genesis {
+                    let seed = *config.actor_seed.get_or_insert_with(B256::random);
+                    ActorPool::seeded_addresses(seed, config.unique_accounts)
+                } else {
+                    Vec::new()
+                };
+
+                chain::custom_chain(
+                    config.gas_limit,
+                    config.chain_id,
+                    &config.genesis_alloc(&actor_addresses),
+                    config.hardfork,
+                    config.genesis_timestamp,
+                    config.genesis_out.as_deref(),
+                )?
+            }
+        };
+        // The hardfork and gas limit may come from a genesis file, and a load
+        // stage's token count from its setup, so check once they are known.
+        config.check_parallel_lanes()?;
+        config.check_gas_limit()?;
+        config.check_scenario()?;
+        let genesis_balance = chain
+            .genesis()
+            .alloc
+            .get(&config.genesis_address())
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        config.check_deployer_funding(genesis_balance)?;
+        let genesis_hash = chain.genesis_hash();
+        run_manifest.set_genesis_hash(genesis_hash);
+        if let Some(setup_hash) = setup_artifacts
+            .as_ref()
+            .and_then(|setup| setup.deployments.genesis_hash)
+        {
+            eyre::ensure!(
+                setup_hash == genesis_hash,
+                "the setup's contracts were deployed on genesis {setup_hash}, not {genesis_hash}"
+            );
+        }
+
+        let (sender, receiver) = mpsc::channel::<LabeledTx>(config.channel_buffer_size);
+        let weak_sender = sender.downgrade();
+        let progress = Arc::new(RunProgress::default());
+        let (phase_events, timeline_events) = broadcast::channel(PHASE_EVENT_CAPACITY);
+        let progress_events = phase_events.subscribe();
+        let invalid_txs = Arc::new(InvalidTxRegistry::default());
+        let destroyed_accounts = Arc::new(DestroyedAccounts::default());
+        let expected_code = Arc::new(ExpectedCode::default());
+        let phase_checks = Arc::new(PhaseChecks::default());
+        let nonce_corrections = Arc::new(NonceCorrections::default());
+        let generation_stats = Arc::new(GenerationStats::default());
+
+        let backend = if config.in_memory {
+            if config.datadir.is_some() {
+                return Err(SandboxError::Config(
+                    "`in_memory` runs keep no datadir; unset `datadir`".to_string(),
+                )
+                .into());
+            }
+            let provider_factory =
+                create_test_provider_factory_with_node_types::<EthereumNode>(chain.clone());
+            init_genesis(&provider_factory)?;
+            let builder = SandboxBlockBuilder::new(
+                provider_factory.clone(),
+                chain,
+                receiver,
+                config.clone(),
+                progress.clone(),
+                invalid_txs.clone(),
+                expected_code.clone(),
+                phase_checks.clone(),
+                nonce_corrections.clone(),
+                paths.clone(),
+            )?;
+            Backend::InMemory {
+                builder,
+                provider_factory,
+            }
+        } else {
+            let (datadir, temp_dir) = match config.datadir.clone() {
+                Some(datadir) => {
+                    fs::create_dir_all(&datadir)?;
+                    (datadir, None)
+                }
+                None => {
+                    let temp_dir = TempDir::new()?;
+                    (temp_dir.path().to_path_buf(), Some(temp_dir))
+                }
+            };
+            if config.stage == Stage::Load {
+                stages::copy_setup_chain(&config, &datadir)?;
+            }
+            let (provider_factory, db) = init_provider_factory(chain.clone(), &datadir)?;
+            let builder = SandboxBlockBuilder::new(
+                provider_factory.clone(),
+                chain,
+                receiver,
+                config.clone(),
+                progress.clone(),
+                invalid_txs.clone(),
+                expected_code.clone(),
+                phase_checks.clone(),
+                nonce_corrections.clone(),
+                paths.clone(),
+            )?;
+            Backend::Mdbx {
+                builder,
+                provider_factory,
+                db,
+                datadir,
+                temp_dir,
+            }
+        };
+
+        let (deployments_tx, deployments_rx) = oneshot::channel();
+        let mut orchestrator = TransactionOrchestrator::new(
+            sender,
+            config.clone(),
+            deployments_tx,
+            progress.clone(),
+            invalid_txs.clone(),
+            destroyed_accounts.clone(),
+            expected_code.clone(),
+            phase_checks.clone(),
+            nonce_corrections.clone(),
+            phase_events,
+            generation_stats.clone(),
+        );
+        if let Some(setup) = setup_artifacts {
+            orchestrator = orchestrator.with_setup(setup.actors, &setup.deployments);
+        }
+        let deployments_path = match config.stage {
+            Stage::Setup => SetupDir::of(&config)?.deployments(),
+            Stage::Full | Stage::Load => paths.join("deployments.json"),
+        };
+        orchestrator = orchestrator.with_deployments_out(deployments_path.clone(), genesis_hash);
+        if let Some(path) = &config.tx_stream_out {
+            orchestrator = orchestrator.with_tx_stream(TxStreamWriter::create(path, genesis_hash)?);
+        }
+        if let Some(path) = &config.replay_tx_stream {
+            let stream = TxStreamReader::open(path)?;
+            eyre::ensure!(
+                stream.genesis_hash() == genesis_hash,
+                "{} was recorded on genesis {}, but this run's genesis is {genesis_hash}",
+                path.display(),
+                stream.genesis_hash()
+            );
+            orchestrator = orchestrator.replaying(stream);
+        }
+
+        let setup = Setup {
+            config,
+            paths,
+            run_manifest,
+            orchestrator,
+            weak_sender,
+            progress,
+            invalid_txs,
+            destroyed_accounts,
+            nonce_corrections,
+            generation_stats,
+            deployments_rx,
+            deployments_path,
+            progress_events,
+            timeline_events,
+        };
+        Ok(Self { setup, backend })
+    }
+
+    /// The config as the run will use it, with settings taken from a genesis
+    /// file and a generated seed filled in.
+    pub fn config(&self) -> &SimulationConfig {
+        &self.setup.config
+    }
+
+    /// Build `faults` into the orchestrator's transactions, so a test can
+    /// check the run catches or recovers from them.
+    #[cfg(feature = "test-hooks")]
+    pub fn with_faults(mut self, faults: FaultInjection) -> Self {
+        self.setup.orchestrator = self.setup.orchestrator.with_faults(faults);
+        self
+    }
+
+    /// Generate load and build blocks until the configured limits are hit,
+    /// then check the final state and write the run's artifacts.
+    ///
+    /// If the builder fails, the channel is closed and the orchestrator is
+    /// waited for before the builder's error is returned; if the orchestrator
+    /// fails, its error is returned once the builder has drained the channel.
+    pub async fn run(self) -> eyre::Result<RunResult> {
+        let Self { setup, backend } = self;
+        match backend {
+            Backend::Mdbx {
+                builder,
+                provider_factory,
+                db,
+                datadir,
+                temp_dir,
+            } => {
+                let (totals, summary) = setup
+                    .drive(
+                        builder,
+                        &provider_factory,
+                        Some((db.as_ref(), datadir.as_path())),
+                    )
+                    .await?;
+                RunResult::new(
+                    totals,
+                    summary,
+                    SimulationDb(DbInner::Mdbx {
+                        provider_factory,
+                        db,
+                        datadir,
+                        _temp_dir: temp_dir,
+                    }),
+                )
+            }
+            Backend::InMemory {
+                builder,
+                provider_factory,
+            } => {
+                let (totals, summary) = setup.drive(builder, &provider_factory, None).await?;
+                RunResult::new(
+                    totals,
+                    summary,
+                    SimulationDb(DbInner::InMemory { provider_factory }),
+                )
+            }
+        }
+    }
+}
+
+impl Setup {
+    /// Run the orchestrator and `block_builder` against each other on
+    /// `provider_factory`. `mdbx` is the environment and datadir behind it,
+    /// absent for `in_memory` runs.
+    async fn drive<DB: SandboxDatabase>(
+        self,
+        mut block_builder: SandboxBlockBuilder<DB>,
+        provider_factory: &PF<DB>,
+        mdbx: Option<(&DatabaseEnv, &Path)>,
+    ) -> eyre::Result<(RunTotals, RunSummary)> {
+        let Self {
+            config,
+            paths,
+            mut run_manifest,
+            orchestrator,
+            weak_sender,
+            progress,
+            invalid_txs,
+            destroyed_accounts,
+            nonce_corrections,
+            generation_stats,
+            deployments_rx,
+            deployments_path,
+            progress_events,
+            mut timeline_events,
+        } = self;
+
+        spawn_channel_depth_sampler(weak_sender.clone(), config.channel_sample_interval_ms);
+        progress::spawn_progress_reporter(
+            progress.clone(),
+            weak_sender,
+            progress_events,
+            config.clone(),
+        );
+
+        let (stop_throughput, throughput_stop) = oneshot::channel();
+        let throughput_path = paths.join("throughput.csv");
+        let throughput_sampler = progress::spawn_throughput_sampler(
+            progress.clone(),
+            throughput_path.clone(),
+            throughput_stop,
+        );
+
+        let (stop_progress_bar, progress_bar_stop) = oneshot::channel();
+        let progress_bar =
+            progress::spawn_progress_bar(progress.clone(), progress_bar_stop, &config);
+
+        let orchestrator_handle = orchestrator.run().await?;
+        let stop_reason = match block_builder.start_building().await {
+            Ok(stop_reason) => stop_reason,
+            Err(err) => {
+                // Dropping the builder closes the channel, so the
+                // orchestrator stops at its next send.
+                drop(block_builder);
+                if let Err(orchestrator_err) = orchestrator_handle.await? {
+                    warn!(target: logging::SIMULATION, %orchestrator_err, "orchestrator also failed");
+                }
+                return Err(err);
+            }
+        };
+        // The builder's counters are final: sample them one last time.
+        let _ = stop_progress_bar.send(());
+        if let Some(progress_bar) = progress_bar {
+            progress_bar.await?;
+        }
+        let _ = stop_throughput.send(());
+        throughput_sampler.await??;
+        run_manifest.add_artifact(&throughput_path);
+        let throughput = progress.throughput();
+
+        let mut actor_pool = orchestrator_handle.await??;
+        // Skips in the last blocks leave corrections the orchestrator never
+        // got to apply.
+        actor_pool.reconcile_nonces(nonce_corrections.take());
+        // Transactions still queued, or executed into a block that was never
+        // sealed, never reached the chain, so their nonces are still free.
+        let unexecuted = block_builder.drain_unexecuted();
+        actor_pool.rewind_unsent(&unexecuted, &invalid_txs);
+        if let Some(path) = &config.actors_export_path {
+            export_actors(&actor_pool, path);
+        }
+        let phases = PhaseTimeline::drain(&mut timeline_events);
+
+        // The orchestrator wrote the manifest as setup completed; it is only
+        // missing when the run ended first.
+        let manifest = deployments_rx.await.ok();
+        if manifest.is_some() {
+            run_manifest.add_artifact(&deployments_path);
+        }
+
+        if config.balance_report {
+            let contracts = manifest
+                .as_ref()
+                .map(|manifest| manifest.labeled_contracts())
+                .unwrap_or_default();
+            let path = paths.join("balances.csv");
+            let state_provider = provider_factory.latest()?;
+            debug::dump_actor_balances(state_provider.as_ref(), &actor_pool, &contracts, &path)?;
+            run_manifest.add_artifact(&path);
+
+            // Token balances are read straight from storage, which only
+            // the setup tokens' shared layout allows.
+            let tokens = manifest
+                .as_ref()
+                .map_or(&[][..], |manifest| &manifest.tokens);
+            if !tokens.is_empty() {
+                let dir = paths.token_holdings_dir();
+                fs::create_dir_all(&dir)?;
+                for (index, token) in tokens.iter().enumerate() {
+                    let path = dir.join(format!("token_{index}.csv"));
+                    debug::dump_token_holdings(
+                        state_provider.as_ref(),
+                        token.address,
+                        &actor_pool,
+                        &path,
+                    )?;
+                    run_manifest.add_artifact(&path);
+                }
+            }
+        }
+
+        if config.pool_report
+            && let Some(manifest) = manifest.as_ref()
+        {
+            let path = paths.join("pools.csv");
+            let state_provider = provider_factory.latest()?;
+            let caller = debug::StateCaller::new(
+                &EthEvmConfig::new(provider_factory.chain_spec()),
+                &head(provider_factory)?,
+                state_provider.as_ref(),
+            )?;
+            debug::pool_report(
+                &caller,
+                manifest,
+                &generation_stats.report().per_token,
+                &path,
+            )?;
+            run_manifest.add_artifact(&path);
+        }
+
+        let shortfalls = debug::check_fee_recipients(
+            provider_factory.latest()?.as_ref(),
+            block_builder.expected_tips(),
+        )?;
+        if shortfalls > 0 {
+            warn!(target: logging::SIMULATION, shortfalls, "fee recipients hold less than their expected tips");
+        }
+
+        if let Some(manifest) = manifest.as_ref() {
+            let missing =
+                debug::check_contract_code(provider_factory.latest()?.as_ref(), manifest)?;
+            if missing > 0 {
+                warn!(target: logging::SIMULATION, missing, "precomputed contract addresses hold no code");
+            }
+        }
+
+        let destroyed = destroyed_accounts.addresses();
+        let survivors = debug::check_destroyed_accounts(&provider_factory.provider()?, &destroyed)?;
+        if !survivors.is_empty() {
+            warn!(
+                target: logging::SIMULATION,
+                survivors = survivors.len(),
+                "self-destructed accounts are still in PlainAccountState"
+            );
+        }
+
+        let label_totals = block_builder.label_totals().clone();
+        let gas_calibration = block_builder.gas_calibration().clone();
+        let tip_calibration = TipCalibration::new(
+            block_builder.sender_tips(),
+            &actor_pool,
+            config.priority_fee,
+        );
+        let sender_diversity = block_builder.sender_diversity();
+        let retry_stats = block_builder.retry_stats();
+        let lane_report = block_builder.lane_report().clone();
+        let db_commits = block_builder.db_commits();
+        let base_fee_drift = block_builder.base_fee_drift();
+        let block_files = block_builder.finish_file_writer()?;
+        metrics::run_end();
+
+        // The datadir is only guaranteed to exist until the caller drops the
+        // result, so measure it now.
+        let db_stats = match (config.db_stats, mdbx) {
+            (false, _) => None,
+            (true, Some((db, datadir))) => Some((debug::db_stats(db)?, debug::dir_size(datadir)?)),
+            (true, None) => {
+                warn!(target: logging::SIMULATION, "`db_stats` has no datadir to report on in memory");
+                None
+            }
+        };
+
+        if let Some(path) = &config.trace_out {
+            metrics::write_chrome_trace(path)?;
+            run_manifest.add_artifact(path);
+            info!(target: logging::WRITER, path = %path.display(), "wrote chrome trace");
+        }
+        let counters_path = paths.join("counters.csv");
+        metrics::write_counters_csv(&counters_path)?;
+        run_manifest.add_artifact(&counters_path);
+        run_manifest.set_counters(metrics::counters_json());
+
+        for path in &block_files {
+            run_manifest.add_artifact(path);
+        }
+        if config.output_format.writes_jsonl() {
+            run_manifest.add_artifact(&config.blocks_jsonl_out);
+        }
+        run_manifest.add_artifact(&config.roots_out);
+        for path in [
+            &config.genesis_out,
+            &config.actors_export_path,
+            &config.block_labels_out,
+            &config.txs_out,
+            &config.payloads_out,
+            &config.block_fees_out,
+            &config.tx_stream_out,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            run_manifest.add_artifact(path);
+        }
+
+        let injection = invalid_txs.report();
+        let generated = generation_stats.report();
+        run_manifest.set_phases(phases.clone());
+        run_manifest.set_generated(generated.clone());
+        run_manifest.set_labels(label_totals.clone());
+        run_manifest.set_gas_calibration(gas_calibration.clone());
+        run_manifest.set_tip_calibration(tip_calibration.clone());
+        run_manifest.set_sender_diversity(sender_diversity);
+        run_manifest.set_retries(retry_stats);
+        run_manifest.set_db_commits(db_commits);
+        run_manifest.set_base_fee_drift(base_fee_drift);
+        run_manifest.set_throughput(throughput);
+        if config.parallel_lanes > 1 {
+            run_manifest.set_lanes(lane_report.clone());
+        }
+        // Sample once more so the peak is known even without heartbeats.
+        progress.sample_memory();
+        let totals = progress.snapshot();
+        run_manifest.finish(totals, stop_reason);
+        let path = paths.join("run_manifest.json");
+        run_manifest.write(&path, &config)?;
+        info!(target: logging::WRITER, path = %path.display(), "wrote run manifest");
+
+        eyre::ensure!(
+            injection.is_consistent(),
+            "builder rejections diverged from injected invalid transactions: {injection:?}"
+        );
+
+        let mut artifact_paths: Vec<_> = run_manifest
+            .artifacts()
+            .iter()
+            .map(|artifact| paths.resolve(artifact))
+            .collect();
+        artifact_paths.push(path);
+        let run_totals = RunTotals {
+            stop_reason,
+            blocks: totals.blocks_built,
+            txs: totals.txs_processed,
+            gas: totals.gas_used,
+            unexecuted: unexecuted.len() as u64,
+            artifact_paths,
+        };
+        let summary = RunSummary {
+            workload: config.workload,
+            stop_reason,
+            generated,
+            included: totals.txs_processed,
+            injection,
+            show_injection: config.invalid_tx_rate > 0.0 || injection.rejected_valid > 0,
+            destroyed: destroyed.len(),
+            survivors: survivors.len(),
+            sender_diversity,
+            retry_stats,
+            db_commits,
+            base_fee_drift,
+            throughput,
+            block_files,
+            blocks_dir: config
+                .blocks_out
+                .parent()
+                .unwrap_or(Path::new("."))
+                .to_path_buf(),
+            blocks_jsonl_out: config
+                .output_format
+                .writes_jsonl()
+                .then(|| config.blocks_jsonl_out.clone()),
+            roots_out: config.roots_out.clone(),
+            genesis_out: config.genesis_out.clone(),
+            setup_dir: config
+                .setup_dir
+                .clone()
+                .filter(|_| config.stage == Stage::Setup),
+            phases,
+            label_totals,
+            gas_calibration,
+            tip_calibration,
+            bundler_mode: config.bundler_mode,
+            lane_report,
+            peak_rss: totals.peak_rss,
+            db_stats,
+        };
+        Ok((run_totals, summary))
+    }
+}
+
+/// Totals of a run, before the database is attached.
+struct RunTotals {
+    stop_reason: StopReason,
+    blocks: u64,
+    txs: u64,
+    gas: u64,
+    unexecuted: u64,
+    artifact_paths: Vec<PathBuf>,
+}
+
+/// The database a simulation built on, kept open (and, for a temporary
+/// datadir, on disk) for as long as this value lives.
+pub struct SimulationDb(DbInner);
+
+enum DbInner {
+    Mdbx {
+        provider_factory: PF,
+        db: Arc<DatabaseEnv>,
+        datadir: PathBuf,
+        _temp_dir: Option<TempDir>,
+    },
+    InMemory {
+        provider_factory: PF<InMemoryDb>,
+    },
+}
+
+impl SimulationDb {
+    /// Provider factory over the MDBX datadir; `None` for `in_memory` runs.
+    pub fn provider_factory(&self) -> Option<&PF> {
+        match &self.0 {
+            DbInner::Mdbx {
+                provider_factory, ..
+            } => Some(provider_factory),
+            DbInner::InMemory { .. } => None,
+        }
+    }
+
+    /// Provider factory over the test database of an `in_memory` run.
+    pub fn in_memory_provider_factory(&self) -> Option<&PF<InMemoryDb>> {
+        match &self.0 {
+            DbInner::Mdbx { .. } => None,
+            DbInner::InMemory { provider_factory } => Some(provider_factory),
+        }
+    }
+
+    /// MDBX environment and datadir; `None` for `in_memory` runs.
+    pub fn mdbx(&self) -> Option<(&DatabaseEnv, &Path)> {
+        match &self.0 {
+            DbInner::Mdbx { db, datadir, .. } => Some((db.as_ref(), datadir.as_path())),
+            DbInner::InMemory { .. } => None,
+        }
+    }
+
+    /// State as of the last block written to the database.
+    pub fn latest(&self) -> eyre::Result<StateProviderBox> {
+        Ok(match &self.0 {
+            DbInner::Mdbx {
+                provider_factory, ..
+            } => provider_factory.latest()?,
+            DbInner::InMemory { provider_factory } => provider_factory.latest()?,
+        })
+    }
+
+    /// Header of the last block written to the database (genesis if none
+    /// were built).
+    pub fn head(&self) -> eyre::Result<SealedHeader> {
+        match &self.0 {
+            DbInner::Mdbx {
+                provider_factory, ..
+            } => head(provider_factory),
+            DbInner::InMemory { provider_factory } => head(provider_factory),
+        }
+    }
+}
+
+/// Sealed header of `provider_factory`'s best block.
+fn head<DB: SandboxDatabase>(provider_factory: &PF<DB>) -> eyre::Result<SealedHeader> {
+    let provider = provider_factory.provider()?;
+    let best_block = provider.best_block_number()?;
+    provider
+        .sealed_header(best_block)?
+        .ok_or_else(|| eyre::eyre!("database has no header for its best block {best_block}"))
+}
+
+/// Totals and outputs of a finished run.
+pub struct RunResult {
+    pub stop_reason: StopReason,
+    pub blocks: u64,
+    pub txs: u64,
+    pub gas: u64,
+    /// Transactions received but never sealed into a block; their nonces were
+    /// rewound.
+    pub unexecuted: u64,
+    /// Hash of the last block in the database (genesis if none were built).
+    pub head_hash: B256,
+    /// State root of that block.
+    pub final_state_root: B256,
+    /// Every file the run wrote, ending with `run_manifest.json`.
+    pub artifact_paths: Vec<PathBuf>,
+    /// The database the run built on, still open for inspection.
+    pub database: SimulationDb,
+    summary: RunSummary,
+}
+
+impl RunResult {
+    fn new(totals: RunTotals, summary: RunSummary, database: SimulationDb) -> eyre::Result<Self> {
+        let head = database.head()?;
+        Ok(Self {
+            stop_reason: totals.stop_reason,
+            blocks: totals.blocks,
+            txs: totals.txs,
+            gas: totals.gas,
+            unexecuted: totals.unexecuted,
+            head_hash: head.hash(),
+            final_state_root: head.state_root,
+            artifact_paths: totals.artifact_paths,
+            database,
+            summary,
+        })
+    }
+
+    /// Print the end-of-run summary: totals, output paths, and the phase,
+    /// generation, label, lane, timing, and database tables.
+    pub fn print_summary(&self) {
+        self.summary.print();
+    }
+}
+
+/// Everything the end-of-run summary prints.
+struct RunSummary {
+    workload: Workload,
+    stop_reason: StopReason,
+    generated: GenerationReport,
+    included: u64,
+    injection: InjectionReport,
+    show_injection: bool,
+    destroyed: usize,
+    survivors: usize,
+    sender_diversity: SenderDiversity,
+    retry_stats: RetryStats,
+    db_commits: DbCommitStats,
+    base_fee_drift: BaseFeeDrift,
+    throughput: ThroughputReport,
+    block_files: Vec<PathBuf>,
+    blocks_dir: PathBuf,
+    blocks_jsonl_out: Option<PathBuf>,
+    roots_out: PathBuf,
+    genesis_out: Option<PathBuf>,
+    /// Directory a setup stage left its artifacts in.
+    setup_dir: Option<PathBuf>,
+    phases: PhaseTimeline,
+    label_totals: LabelTotals,
+    gas_calibration: GasCalibration,
+    tip_calibration: TipCalibration,
+    bundler_mode: Option<BundlerMode>,
+    lane_report: LaneReport,
+    /// Largest resident set size sampled, in bytes.
+    peak_rss: u64,
+    /// Table statistics and datadir size, when `db_stats` is set.
+    db_stats: Option<(Vec<TableStat>, u64)>,
+}
+
+impl RunSummary {
+    fn print(&self) {
+        println!();
+        println!("Workload: {}", self.workload);
+        println!("Stopped:  {}", self.stop_reason);
+        let injection = &self.injection;
+        let rejected = injection.rejected_injected + injection.rejected_valid;
+        println!(
+            "Generated: {} ({} included, {} rejected, {} not executed)",
+            self.generated.total,
+            self.included,
+            rejected,
+            self.generated.not_executed(self.included, rejected)
+        );
+        if self.show_injection {
+            println!(
+                "Invalid:  {} injected, {} rejected, {} included, {} valid rejected",
+                injection.injected,
+                injection.rejected_injected,
+                injection.accepted_injected,
+                injection.rejected_valid
+            );
+        }
+        if self.destroyed > 0 {
+            println!(
+                "Destroyed: {} expected, {} still present",
+                self.destroyed, self.survivors
+            );
+        }
+        if self.generated.top_up_rounds > 0 {
+            println!(
+                "Top-ups:  {} rounds, {} actors topped up",
+                self.generated.top_up_rounds, self.generated.actors_topped_up
+            );
+        }
+        self.sender_diversity.print();
+        self.retry_stats.print();
+        self.db_commits.print();
+        self.base_fee_drift.print();
+        self.throughput.print();
+        if self.peak_rss > 0 {
+            println!("Peak RSS: {} MiB", self.peak_rss / (1024 * 1024));
+        }
+        match self.block_files.as_slice() {
+            [] if self.blocks_jsonl_out.is_none() => println!("Blocks:   not written"),
+            [] => {}
+            [file] => println!("Blocks:   {}", file.display()),
+            files => println!(
+                "Blocks:   {} segments in {}",
+                files.len(),
+                self.blocks_dir.display()
+            ),
+        }
+        if let Some(path) = &self.blocks_jsonl_out {
+            println!("JSONL:    {}", path.display());
+        }
+        println!("Roots:    {}", self.roots_out.display());
+        if let Some(path) = &self.genesis_out {
+            println!("Genesis:  {}", path.display());
+        }
+        if let Some(dir) = &self.setup_dir {
+            println!("Setup:    {} (run `sandbox load` on it)", dir.display());
+        }
+        self.phases.print();
+        self.generated.print();
+        self.label_totals.print();
+        if let Some(mode) = self.bundler_mode {
+            self.label_totals.print_bundle_efficiency(mode.bundle_size);
+        }
+        self.gas_calibration.print();
+        self.tip_calibration.print();
+        self.lane_report.print();
+        metrics::print_section_summary();
+        if let Some((stats, datadir_size)) = &self.db_stats {
+            debug::print_db_stats(stats, *datadir_size);
+        }
+    }
+}
+
+/// Write the actor key file, warning instead of failing the run.
+fn export_actors(actor_pool: &ActorPool, path: &Path) {
+    match actor_pool.export(path) {
+        Ok(()) => info!(
+            target: logging::WRITER,
+            path = %path.display(),
+            actors = actor_pool.len(),
+            "exported actor keys"
+        ),
+        Err(err) => warn!(target: logging::WRITER, %err, "failed to export actor keys"),
+    }
+}
+
+/// Periodically record how many transactions are waiting in the channel. Holds
+/// only a weak sender so it never keeps the channel open on its own.
+fn spawn_channel_depth_sampler(weak_sender: WeakSender<LabeledTx>, interval_ms: u64) {
+    if interval_ms == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+        loop {
+            interval.tick().await;
+            let Some(sender) = weak_sender.upgrade() else {
+                return;
+            };
+            if sender.is_closed() {
+                return;
+            }
+            let depth = sender.max_capacity() - sender.capacity();
+            gauge!("channel_depth").set(depth as u64);
+        }
+    });
+}
+
+/// Create the database and static files under `datadir` and write `chain`'s
+/// genesis into them.
+pub(crate) fn init_provider_factory(
+    chain: Arc<ChainSpec>,
+    datadir: &Path,
+) -> eyre::Result<(PF, Arc<DatabaseEnv>)> {
+    let db_args = reth_node_core::args::DatabaseArgs::default().database_args();
+    let db = Arc::new(reth_db::init_db(datadir.join("db"), db_args)?);
+
+    let provider_factory = ProviderFactory::new(
+        db.clone(),
+        chain,
+        reth_provider::providers::StaticFileProvider::read_write(datadir.join("static_files"))?,
+    )?;
+
+    init_genesis(&provider_factory)?;
+    Ok((provider_factory, db))
+}
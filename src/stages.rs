@@ -0,0 +1,151 @@
+//! Splitting a run into one setup stage and any number of load stages: the
+//! directory a setup stage leaves behind, and how a load stage starts from it.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    actor::ActorPool, config::SimulationConfig, deployments::DeploymentManifest,
+    error::SandboxError,
+};
+
+/// Layout of a setup stage's directory.
+#[derive(Debug, Clone)]
+pub struct SetupDir {
+    dir: PathBuf,
+}
+
+impl SetupDir {
+    /// Layout rooted at `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The config's `setup_dir`, which validation requires for every stage
+    /// but a full run.
+    pub fn of(config: &SimulationConfig) -> Result<Self, SandboxError> {
+        config.setup_dir.as_deref().map(Self::new).ok_or_else(|| {
+            SandboxError::Config(format!("a {} stage needs a `setup_dir`", config.stage))
+        })
+    }
+
+    /// Reth datadir holding the setup blocks.
+    pub fn datadir(&self) -> PathBuf {
+        self.dir.join("datadir")
+    }
+
+    /// Genesis the setup blocks were built on.
+    pub fn genesis(&self) -> PathBuf {
+        self.dir.join("genesis.json")
+    }
+
+    /// Actor keys and nonces as of the end of setup.
+    pub fn actors(&self) -> PathBuf {
+        self.dir.join("actors.json")
+    }
+
+    /// Contracts the setup deployed.
+    pub fn deployments(&self) -> PathBuf {
+        self.dir.join("deployments.json")
+    }
+
+    /// RLP block file of the setup blocks, which each load continues.
+    pub fn blocks(&self) -> PathBuf {
+        self.dir.join("blocks.bin")
+    }
+}
+
+/// Point a setup stage's datadir, genesis, actor export, and block file into
+/// its directory.
+pub fn prepare_setup(config: &mut SimulationConfig) -> eyre::Result<()> {
+    let setup = SetupDir::of(config)?;
+    // A datadir that already holds blocks would be resumed, not set up.
+    eyre::ensure!(
+        !setup.datadir().exists(),
+        "{} already holds a setup; remove it or pick another `setup_dir`",
+        setup.dir.display()
+    );
+    fs::create_dir_all(&setup.dir).map_err(|err| SandboxError::io(&setup.dir, err))?;
+
+    config.datadir = Some(setup.datadir());
+    config.genesis_out = Some(setup.genesis());
+    config.actors_export_path = Some(setup.actors());
+    config.blocks_out = setup.blocks();
+    Ok(())
+}
+
+/// What a load stage starts from.
+pub struct SetupArtifacts {
+    /// Every actor with its nonce as of the end of setup, and the deployer.
+    pub actors: ActorPool,
+    pub deployments: DeploymentManifest,
+    /// Genesis file the load's chain is built from.
+    pub genesis: PathBuf,
+}
+
+/// Read the setup a load stage runs on. The actor and token counts are taken
+/// from it, whatever the config says.
+pub fn read_setup(config: &mut SimulationConfig) -> eyre::Result<SetupArtifacts> {
+    let setup = SetupDir::of(config)?;
+    let deployments = DeploymentManifest::read(&setup.deployments())?;
+    eyre::ensure!(
+        deployments.chain_id == config.chain_id,
+        "the setup in {} was built for chain id {}, but the load is configured for {}",
+        setup.dir.display(),
+        deployments.chain_id,
+        config.chain_id
+    );
+
+    let mut actors = ActorPool::for_config(config);
+    actors.import(&setup.actors())?;
+    eyre::ensure!(
+        actors.deployer().address() == deployments.deployer,
+        "the setup in {} was deployed by {}, not the configured deployer key",
+        setup.dir.display(),
+        deployments.deployer
+    );
+
+    config.unique_accounts = actors.len() as u64;
+    config.unique_tokens = deployments.tokens.len() as u64;
+    Ok(SetupArtifacts {
+        actors,
+        deployments,
+        genesis: setup.genesis(),
+    })
+}
+
+/// Copy the setup's datadir to `datadir`, and its block file to `blocks_out`
+/// when the load writes one, so every load builds on its own copy.
+pub fn copy_setup_chain(config: &SimulationConfig, datadir: &Path) -> eyre::Result<()> {
+    let setup = SetupDir::of(config)?;
+    eyre::ensure!(
+        !datadir.join("db").exists(),
+        "{} already holds a chain; a load stage starts from a copy of its setup",
+        datadir.display()
+    );
+    copy_dir(&setup.datadir(), datadir)?;
+    if config.output_format.writes_rlp() {
+        let blocks = setup.blocks();
+        fs::copy(&blocks, &config.blocks_out).map_err(|err| SandboxError::io(&blocks, err))?;
+    }
+    Ok(())
+}
+
+/// Recursively copy the contents of `from` into `to`.
+fn copy_dir(from: &Path, to: &Path) -> eyre::Result<()> {
+    fs::create_dir_all(to).map_err(|err| SandboxError::io(to, err))?;
+    for entry in fs::read_dir(from).map_err(|err| SandboxError::io(from, err))? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target).map_err(|err| SandboxError::io(entry.path(), err))?;
+        }
+    }
+    Ok(())
+}
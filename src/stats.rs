@@ -0,0 +1,213 @@
+//! What the orchestrator generated, by transaction type and phase, so it can be
+//! reconciled against what the builder executed.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, U256};
+use serde_json::{Value, json};
+
+use crate::labels::{LabeledTx, TxLabel};
+
+/// Generation counters shared between the orchestrator and the caller through
+/// an `Arc`.
+#[derive(Debug, Default)]
+pub struct GenerationStats {
+    inner: Mutex<GenerationReport>,
+}
+
+impl GenerationStats {
+    /// Account for a whole batch generated during `phase`.
+    pub fn record_batch(&self, phase: &'static str, batch: &[LabeledTx]) {
+        let calldata_bytes: usize = LabeledTx::flatten(batch)
+            .map(|labeled| labeled.tx.input().len())
+            .sum();
+        let mut report = self.inner.lock().unwrap();
+        let mut txs = 0;
+        for labeled in LabeledTx::flatten(batch) {
+            *report.per_type.entry(labeled.label.name()).or_default() += 1;
+            txs += 1;
+        }
+        *report.per_phase.entry(phase).or_default() += txs;
+        report.total += txs;
+        report.calldata_bytes += calldata_bytes as u64;
+    }
+
+    /// Account for `txs` generated transactions the builder closed the
+    /// channel before receiving.
+    pub fn record_undelivered(&self, txs: u64) {
+        self.inner.lock().unwrap().undelivered += txs;
+    }
+
+    /// Add the value each load transaction moves, keyed by type. Units are
+    /// the type's own: wei for ETH, token units for tokens.
+    pub fn record_values(&self, values: impl IntoIterator<Item = (&'static str, U256)>) {
+        let mut report = self.inner.lock().unwrap();
+        for (kind, value) in values {
+            *report.value_per_type.entry(kind).or_default() += value;
+        }
+    }
+
+    /// Count the load transactions sent to each token, telling swaps, which
+    /// go through the token's pair, from the rest, and add up what each swap
+    /// paid into the pair.
+    pub fn record_token_traffic(
+        &self,
+        traffic: impl IntoIterator<Item = (Address, TxLabel, U256)>,
+    ) {
+        let mut report = self.inner.lock().unwrap();
+        for (token, label, amount) in traffic {
+            let counts = report.per_token.entry(token).or_default();
+            counts.txs += 1;
+            match label {
+                TxLabel::UniswapSwapForToken => {
+                    counts.swaps += 1;
+                    counts.weth_in += amount;
+                }
+                TxLabel::UniswapSwapForEth => {
+                    counts.swaps += 1;
+                    counts.token_in += amount;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Account for a funding top-up round that queued `actors` top-ups.
+    pub fn record_top_up_round(&self, actors: u64) {
+        let mut report = self.inner.lock().unwrap();
+        report.top_up_rounds += 1;
+        report.actors_topped_up += actors;
+    }
+
+    /// Counts collected so far.
+    pub fn report(&self) -> GenerationReport {
+        self.inner.lock().unwrap().clone()
+    }
+}
+
+/// Totals of everything the orchestrator generated.
+#[derive(Debug, Clone, Default)]
+pub struct GenerationReport {
+    pub per_type: BTreeMap<&'static str, u64>,
+    /// Total value moved by each type, in that type's units.
+    pub value_per_type: BTreeMap<&'static str, U256>,
+    pub per_phase: BTreeMap<&'static str, u64>,
+    pub total: u64,
+    /// Generated transactions never handed to the builder because it had
+    /// already stopped; their nonces were rewound.
+    pub undelivered: u64,
+    pub calldata_bytes: u64,
+    /// Balance checks during load that found actors to top up.
+    pub top_up_rounds: u64,
+    /// Top-ups queued across every round; an actor may count more than once.
+    pub actors_topped_up: u64,
+    /// Load transactions sent to each token.
+    pub per_token: BTreeMap<Address, TokenTraffic>,
+}
+
+/// Load transactions sent to one token.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenTraffic {
+    /// Transfers, swaps, and multicalls that use the token.
+    pub txs: u64,
+    /// The swaps among them, which trade through the token's pair.
+    pub swaps: u64,
+    /// WETH the swaps for the token paid into the pair, in wei.
+    pub weth_in: U256,
+    /// Tokens the swaps for ETH sold, in token units, before any transfer
+    /// fee.
+    pub token_in: U256,
+}
+
+impl GenerationReport {
+    /// Generated transactions the builder neither included nor rejected: still
+    /// queued, executed in a block that was never sealed, or never delivered
+    /// when it stopped.
+    pub fn not_executed(&self, included: u64, rejected: u64) -> u64 {
+        self.total.saturating_sub(included + rejected)
+    }
+
+    /// Render the totals for the run manifest.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "total": self.total,
+            "undelivered": self.undelivered,
+            "calldata_bytes": self.calldata_bytes,
+            "per_type": self.per_type,
+            "value_per_type": self
+                .value_per_type
+                .iter()
+                .map(|(kind, value)| (kind.to_string(), Value::from(value.to_string())))
+                .collect::<serde_json::Map<_, _>>(),
+            "per_phase": self.per_phase,
+            "top_up_rounds": self.top_up_rounds,
+            "actors_topped_up": self.actors_topped_up,
+            "per_token": self
+                .per_token
+                .iter()
+                .map(|(token, traffic)| {
+                    (
+                        token.to_string(),
+                        json!({
+                            "txs": traffic.txs,
+                            "swaps": traffic.swaps,
+                            "weth_in": traffic.weth_in.to_string(),
+                            "token_in": traffic.token_in.to_string(),
+                        }),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }
+
+    /// Print a per-type table.
+    pub fn print(&self) {
+        if self.per_type.is_empty() {
+            return;
+        }
+
+        let name_w = self
+            .per_type
+            .keys()
+            .map(|kind| kind.len())
+            .max()
+            .unwrap_or_default()
+            .max("Type".len());
+
+        println!("\nGenerated by type:");
+        println!("{:-<1$}", "", name_w + 46);
+        println!(
+            "{:<name_w$}  {:>14}  {:>28}",
+            "Type", "Transactions", "Value moved"
+        );
+        println!("{:-<1$}", "", name_w + 46);
+        for (kind, txs) in &self.per_type {
+            let value = self
+                .value_per_type
+                .get(kind)
+                .map(U256::to_string)
+                .unwrap_or_default();
+            println!("{kind:<name_w$}  {txs:>14}  {value:>28}");
+        }
+        println!("{:-<1$}", "", name_w + 46);
+        self.print_token_concentration();
+    }
+
+    /// Share of token traffic the busiest tokens took.
+    fn print_token_concentration(&self) {
+        let mut txs: Vec<u64> = self.per_token.values().map(|traffic| traffic.txs).collect();
+        let total: u64 = txs.iter().sum();
+        if total == 0 {
+            return;
+        }
+        txs.sort_unstable_by(|a, b| b.cmp(a));
+        let top = txs.len().min(3);
+        let top_txs: u64 = txs[..top].iter().sum();
+        println!(
+            "Token traffic: {} tokens used, busiest {top} took {:.1}% of {total} txs",
+            txs.len(),
+            top_txs as f64 * 100.0 / total as f64
+        );
+    }
+}
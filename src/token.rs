@@ -1,11 +1,22 @@
-//! Helpers for the synthetic ERC20 used within the sandbox.
+//! Helpers for the synthetic ERC20s used within the sandbox.
+//!
+//! Tokens come from one of two templates. `SandboxToken` is a compiled
+//! OpenZeppelin ERC20. `FeeToken` is hand-assembled to the same storage
+//! layout, errors, and auto-mint, but burns a fee out of every transfer, the
+//! way fee-on-transfer tokens break DEX integrations that assume the amount
+//! sent is the amount received. Its constructor takes
+//! `(initialSupply, feeBps, name, symbol)`, with each string packed into a
+//! `bytes32`: up to 31 bytes, left-aligned, with the length in the low byte.
 
-use alloy_primitives::{Address, Bytes, U256};
+use alloy_primitives::{Address, B256, Bytes, U256, keccak256, map::HashSet};
 
 use alloy_sol_macro::sol;
 use alloy_sol_types::{SolCall, SolConstructor, SolEvent};
+use serde_json::{Value, json};
 use tracing::info;
 
+use crate::config::TokenTemplate;
+
 //We use a custom ERC20 token for the sandbox which auto-mints tokens that actors try to send
 sol!(
     #[allow(missing_docs)]
@@ -13,15 +24,88 @@ sol!(
     "artifacts/SandboxToken.json"
 );
 
+sol!(
+    #[allow(missing_docs)]
+    FeeToken,
+    "artifacts/FeeToken.json"
+);
+
+/// Storage slot of the `_balances` mapping in `SandboxToken`. The token
+/// inherits OpenZeppelin v5 `ERC20`, whose first state variable is
+/// `mapping(address => uint256) _balances`, and declares no storage before it.
+pub const SANDBOX_TOKEN_BALANCES_SLOT: U256 = U256::ZERO;
+
+/// Name, symbol, and decimals `SandboxToken` is compiled with.
+pub const SANDBOX_TOKEN_NAME: &str = "SandboxToken";
+pub const SANDBOX_TOKEN_SYMBOL: &str = "SANDX";
+pub const TOKEN_DECIMALS: u8 = 18;
+
+/// Basis points a fee is charged in; a fee of this many takes the whole
+/// transfer.
+pub const FEE_BPS_DENOMINATOR: u32 = 10_000;
+
+/// Gas limit for a single token deployment: creation, the ~2KB code deposit,
+/// and the constructor's storage writes, with headroom.
+pub const TOKEN_DEPLOY_GAS_LIMIT: u64 = 1_000_000;
+
+/// ERC20 metadata a token reports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenMetadata {
+    /// Name, symbol, and decimals of the setup token at `index`, deployed
+    /// from `template`. `SandboxToken` has its own compiled in; fee tokens
+    /// are numbered.
+    pub fn new(template: TokenTemplate, index: u64) -> Self {
+        match template {
+            TokenTemplate::Standard => Self {
+                name: SANDBOX_TOKEN_NAME.to_string(),
+                symbol: SANDBOX_TOKEN_SYMBOL.to_string(),
+                decimals: TOKEN_DECIMALS,
+            },
+            TokenTemplate::FeeOnTransfer { .. } => Self {
+                name: format!("Fee Token {index}"),
+                symbol: format!("FEE{index}"),
+                decimals: TOKEN_DECIMALS,
+            },
+        }
+    }
+
+    /// Render the metadata for `deployments.json`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "symbol": self.symbol,
+            "decimals": self.decimals,
+        })
+    }
+}
+
+/// Creation payload for the setup token at `index`, deployed from
+/// `template` and minting `initial_supply` to the deployer.
+pub fn token_init_code(template: TokenTemplate, index: u64, initial_supply: U256) -> Bytes {
+    match template {
+        TokenTemplate::Standard => SandboxTokenHelper::deploy(initial_supply),
+        TokenTemplate::FeeOnTransfer { fee_bps } => {
+            let metadata = TokenMetadata::new(template, index);
+            FeeTokenHelper::deploy(initial_supply, fee_bps, &metadata.name, &metadata.symbol)
+        }
+    }
+}
+
 /// Static helpers for constructing calls against the sandbox ERC20.
 pub struct SandboxTokenHelper;
 
 impl SandboxTokenHelper {
     /// Combine bytecode and constructor args into deployable payload.
-    pub fn deploy() -> Bytes {
+    pub fn deploy(initial_supply: U256) -> Bytes {
         [
             SandboxToken::BYTECODE.as_ref(),
-            &SandboxToken::constructorCall::new((U256::from(1e18),)).abi_encode(),
+            &SandboxToken::constructorCall::new((initial_supply,)).abi_encode(),
         ]
         .concat()
         .into()
@@ -33,6 +117,17 @@ impl SandboxTokenHelper {
         call_data.into()
     }
 
+    /// Storage key holding `holder`'s balance: `keccak256(abi.encode(holder, slot))`.
+    pub fn balance_slot(holder: Address) -> B256 {
+        keccak256(
+            [
+                holder.into_word().as_slice(),
+                B256::from(SANDBOX_TOKEN_BALANCES_SLOT).as_slice(),
+            ]
+            .concat(),
+        )
+    }
+
     /// ABI-encode an approval call for the Uniswap router.
     pub fn approve(spender: Address, value: U256) -> Bytes {
         let call_data = SandboxToken::approveCall::new((spender, value)).abi_encode();
@@ -40,6 +135,39 @@ impl SandboxTokenHelper {
     }
 }
 
+/// Encoders for deploying [`FeeToken`]. Calls go through
+/// [`SandboxTokenHelper`], since both templates share the ERC20 interface.
+pub struct FeeTokenHelper;
+
+impl FeeTokenHelper {
+    /// Combine bytecode and constructor args into a deployable payload.
+    /// `name` and `symbol` are cut to the 31 bytes the contract stores.
+    pub fn deploy(initial_supply: U256, fee_bps: u32, name: &str, symbol: &str) -> Bytes {
+        [
+            FeeToken::BYTECODE.as_ref(),
+            &FeeToken::constructorCall::new((
+                initial_supply,
+                U256::from(fee_bps),
+                Self::short_string(name),
+                Self::short_string(symbol),
+            ))
+            .abi_encode(),
+        ]
+        .concat()
+        .into()
+    }
+
+    /// Pack up to 31 bytes of `value` left-aligned, with the length in the
+    /// low byte.
+    pub fn short_string(value: &str) -> B256 {
+        let bytes = &value.as_bytes()[..value.len().min(31)];
+        let mut word = B256::ZERO;
+        word[..bytes.len()].copy_from_slice(bytes);
+        word[31] = bytes.len() as u8;
+        word
+    }
+}
+
 /// Tracks deterministic ERC20 addresses so the orchestrator can reuse them.
 pub struct TokenPool {
     tokens: Vec<Token>,
@@ -51,26 +179,113 @@ impl TokenPool {
         Self { tokens: Vec::new() }
     }
 
-    /// Record a new token deployment
-    pub fn add_token(&mut self, token_address: Address) {
-        self.tokens.push(Token::new(token_address));
+    /// Record a new token deployment from `template` by the actor at
+    /// `deployer`, or by the genesis deployer when `None`.
+    pub fn add_token(
+        &mut self,
+        token_address: Address,
+        deployment_nonce: u64,
+        initial_supply: U256,
+        deployer: Option<usize>,
+        template: TokenTemplate,
+    ) {
+        self.tokens.push(Token::new(
+            token_address,
+            deployment_nonce,
+            initial_supply,
+            deployer,
+            template,
+        ));
+    }
+
+    /// Iterate over every recorded token in deployment order.
+    pub fn iter(&self) -> impl Iterator<Item = &Token> {
+        self.tokens.iter()
+    }
+
+    /// Addresses of every recorded token, in index order.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.tokens.iter().map(Token::address).collect()
+    }
+
+    /// Addresses of the recorded tokens that charge a transfer fee.
+    pub fn fee_token_addresses(&self) -> HashSet<Address> {
+        self.tokens
+            .iter()
+            .filter(|token| token.template().charges_fee())
+            .map(Token::address)
+            .collect()
     }
 
-    /// Get the address for the provided index.
-    pub fn token_address(&self, index: u64) -> Address {
-        self.tokens[index as usize].address()
+    /// Get the address for the provided index, if a token was recorded there.
+    pub fn token_address(&self, index: u64) -> Option<Address> {
+        self.tokens.get(index as usize).map(Token::address)
+    }
+
+    /// Get the token recorded at the provided index, if any.
+    pub fn get(&self, index: u64) -> Option<&Token> {
+        self.tokens.get(index as usize)
+    }
+
+    /// Number of recorded tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Whether no tokens have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
     }
 }
 
 /// Lightweight token handle stored in [`TokenPool`].
 pub struct Token {
     address: Address,
+    deployment_nonce: u64,
+    initial_supply: U256,
+    deployer: Option<usize>,
+    template: TokenTemplate,
 }
 
 impl Token {
-    /// Remember the deployed address.
-    pub fn new(address: Address) -> Self {
-        Self { address }
+    /// Remember the deployed address, the deployer nonce that created it, the
+    /// supply minted to the deployer, which actor deployed it, and from which
+    /// template.
+    pub fn new(
+        address: Address,
+        deployment_nonce: u64,
+        initial_supply: U256,
+        deployer: Option<usize>,
+        template: TokenTemplate,
+    ) -> Self {
+        Self {
+            address,
+            deployment_nonce,
+            initial_supply,
+            deployer,
+            template,
+        }
+    }
+
+    /// Template the token was deployed from.
+    pub fn template(&self) -> TokenTemplate {
+        self.template
+    }
+
+    /// Index of the actor that deployed the token and owns its pool, or
+    /// `None` for the genesis deployer.
+    pub fn deployer(&self) -> Option<usize> {
+        self.deployer
+    }
+
+    /// Deployer nonce used by the creating transaction.
+    pub fn deployment_nonce(&self) -> u64 {
+        self.deployment_nonce
+    }
+
+    /// Supply passed to the constructor when the token was deployed.
+    pub fn initial_supply(&self) -> U256 {
+        self.initial_supply
     }
 
     /// Returns the token address.
@@ -78,3 +293,35 @@ impl Token {
         self.address.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_strings_are_left_aligned_with_their_length_last() {
+        let word = FeeTokenHelper::short_string("FEE0");
+        assert_eq!(&word[..4], b"FEE0");
+        assert!(word[4..31].iter().all(|&byte| byte == 0));
+        assert_eq!(word[31], 4);
+
+        let long = "x".repeat(40);
+        let word = FeeTokenHelper::short_string(&long);
+        assert_eq!(&word[..31], &long.as_bytes()[..31]);
+        assert_eq!(word[31], 31);
+    }
+
+    #[test]
+    fn fee_tokens_are_numbered_and_standard_tokens_are_not() {
+        let fee = TokenTemplate::FeeOnTransfer { fee_bps: 100 };
+        assert_eq!(TokenMetadata::new(fee, 3).symbol, "FEE3");
+        assert_eq!(
+            TokenMetadata::new(TokenTemplate::Standard, 3),
+            TokenMetadata::new(TokenTemplate::Standard, 0)
+        );
+        // The constructor arguments follow the shared bytecode prefix.
+        let code = token_init_code(fee, 3, U256::from(1));
+        assert!(code.starts_with(FeeToken::BYTECODE.as_ref()));
+        assert_eq!(code.len(), FeeToken::BYTECODE.len() + 4 * 32);
+    }
+}
@@ -1,18 +1,86 @@
 //! Utilities for constructing and signing sandbox transactions.
 
-use alloy_consensus::{EthereumTxEnvelope, TxEip4844};
-use alloy_network::TxSignerSync;
-use alloy_primitives::{Bytes, TxKind, U256};
-use alloy_rpc_types_eth::{TransactionInput, TransactionRequest};
+use std::cell::RefCell;
 
+use alloy_consensus::{
+    EthereumTxEnvelope, SignableTransaction, Transaction, TxEip1559, TxEip4844,
+    transaction::SignerRecoverable,
+};
+use alloy_primitives::{Bytes, TxKind, U256, keccak256};
+use alloy_signer::SignerSync;
 use alloy_signer_local::LocalSigner;
 use k256::ecdsa::SigningKey;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use reth_ethereum::TransactionSigned;
 use reth_primitives_traits::Recovered;
 
+use crate::error::SandboxError;
+
 /// Gas limit assigned to every synthetic transaction (high at the moment, no reason not to be).
 pub const DEFAULT_GAS_LIMIT: u64 = 5_000_000;
 
+/// Intrinsic gas of a plain ETH transfer, used when the payload is known to be empty.
+pub const TRANSFER_GAS_LIMIT: u64 = 21_000;
+
+/// Chain id transactions are signed for unless a caller asks for another.
+pub const DEFAULT_CHAIN_ID: u64 = 2600;
+
+/// Max fee of every sandbox transaction, and its priority fee unless the
+/// template sets another.
+pub const FEE_PER_GAS: u128 = 20_000_000_000;
+
+thread_local! {
+    /// Per-thread scratch buffer for the signing preimage, so signing inside
+    /// rayon workers does not allocate per transaction.
+    static SIGNING_BUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(1024));
+}
+
+/// The fields that vary between sandbox transactions. The max fee and type
+/// are fixed, so the EIP-1559 transaction is built directly from these.
+#[derive(Debug, Clone)]
+pub struct TxTemplate {
+    pub nonce: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub input: Bytes,
+    pub gas_limit: u64,
+    pub chain_id: u64,
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl TxTemplate {
+    /// Template signed for [`DEFAULT_CHAIN_ID`] with [`DEFAULT_GAS_LIMIT`].
+    pub fn new(nonce: u64, to: TxKind, value: Option<U256>, data: Option<Bytes>) -> Self {
+        Self {
+            nonce,
+            to,
+            value: value.unwrap_or_default(),
+            input: data.unwrap_or_default(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            chain_id: DEFAULT_CHAIN_ID,
+            max_priority_fee_per_gas: FEE_PER_GAS,
+        }
+    }
+
+    /// Use `gas_limit` instead of [`DEFAULT_GAS_LIMIT`].
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Sign for `chain_id` instead of [`DEFAULT_CHAIN_ID`].
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Offer `fee` per gas as the priority fee instead of [`FEE_PER_GAS`].
+    pub fn with_priority_fee(mut self, fee: u128) -> Self {
+        self.max_priority_fee_per_gas = fee;
+        self
+    }
+}
+
 /// Construct and sign a recovered EIP-4844 transaction using the provided
 /// signer, nonce, and payload.
 pub fn tx(
@@ -21,33 +89,164 @@ pub fn tx(
     to: TxKind,
     value: Option<U256>,
     data: Option<Bytes>,
-) -> Recovered<EthereumTxEnvelope<TxEip4844>> {
-    let tx = TransactionRequest {
-        nonce: Some(nonce),
-        value: value,
-        to: Some(to),
-        gas: Some(DEFAULT_GAS_LIMIT),
-        max_fee_per_gas: Some(20e9 as u128),
-        max_priority_fee_per_gas: Some(20e9 as u128),
-        chain_id: Some(2600u64), // make this dynamic
-        input: TransactionInput {
-            input: None,
-            data: data,
-        },
-        authorization_list: None,
-        ..Default::default()
-    };
-    sign_tx(sender, tx)
+) -> Result<Recovered<EthereumTxEnvelope<TxEip4844>>, SandboxError> {
+    sign(sender, TxTemplate::new(nonce, to, value, data))
+}
+
+/// Same as [`tx`] but with an explicit gas limit instead of [`DEFAULT_GAS_LIMIT`].
+pub fn tx_with_gas_limit(
+    sender: &LocalSigner<SigningKey>,
+    nonce: u64,
+    to: TxKind,
+    value: Option<U256>,
+    data: Option<Bytes>,
+    gas_limit: u64,
+) -> Result<Recovered<EthereumTxEnvelope<TxEip4844>>, SandboxError> {
+    sign(
+        sender,
+        TxTemplate::new(nonce, to, value, data).with_gas_limit(gas_limit),
+    )
 }
 
-/// Signs an arbitrary [`TransactionRequest`] using the provided wallet.
-fn sign_tx(
+/// Same as [`tx_with_gas_limit`] but signed for `chain_id`.
+pub fn tx_for_chain(
+    sender: &LocalSigner<SigningKey>,
+    nonce: u64,
+    to: TxKind,
+    value: Option<U256>,
+    data: Option<Bytes>,
+    gas_limit: u64,
+    chain_id: u64,
+) -> Result<Recovered<EthereumTxEnvelope<TxEip4844>>, SandboxError> {
+    sign(
+        sender,
+        TxTemplate::new(nonce, to, value, data)
+            .with_gas_limit(gas_limit)
+            .with_chain_id(chain_id),
+    )
+}
+
+/// Same as [`tx_with_gas_limit`] but offering `priority_fee` per gas instead
+/// of [`FEE_PER_GAS`].
+pub fn tx_with_priority_fee(
+    sender: &LocalSigner<SigningKey>,
+    nonce: u64,
+    to: TxKind,
+    value: Option<U256>,
+    data: Option<Bytes>,
+    gas_limit: u64,
+    priority_fee: u128,
+) -> Result<Recovered<EthereumTxEnvelope<TxEip4844>>, SandboxError> {
+    sign(
+        sender,
+        TxTemplate::new(nonce, to, value, data)
+            .with_gas_limit(gas_limit)
+            .with_priority_fee(priority_fee),
+    )
+}
+
+/// Sign every template with `signer` in parallel, preserving order.
+pub fn sign_batch(
+    signer: &LocalSigner<SigningKey>,
+    templates: Vec<TxTemplate>,
+) -> Result<Vec<Recovered<EthereumTxEnvelope<TxEip4844>>>, SandboxError> {
+    templates
+        .into_par_iter()
+        .map(|template| sign(signer, template))
+        .collect()
+}
+
+/// Recover the sender of `tx` from its signature and check it matches the
+/// signer recorded alongside it, which `sign` sets without checking.
+pub fn verify_sender(tx: &Recovered<EthereumTxEnvelope<TxEip4844>>) -> Result<(), SandboxError> {
+    let recovered = tx.inner().recover_signer().ok();
+    if recovered == Some(tx.signer()) {
+        return Ok(());
+    }
+    Err(SandboxError::SenderMismatch {
+        hash: *tx.hash(),
+        nonce: tx.nonce(),
+        chain_id: tx.chain_id(),
+        claimed: tx.signer(),
+        recovered,
+    })
+}
+
+/// Build the EIP-1559 transaction for `template` and sign its hash directly,
+/// skipping `TransactionRequest` and its validation since every field is
+/// set here.
+pub fn sign(
     signer: &LocalSigner<SigningKey>,
-    tx: TransactionRequest,
-) -> Recovered<EthereumTxEnvelope<TxEip4844>> {
-    let mut typed_tx = tx.build_typed_tx().unwrap();
-    let signature = signer.sign_transaction_sync(&mut typed_tx).unwrap();
-    let signed_tx = typed_tx.into_envelope(signature);
-    let reth_tx: TransactionSigned = signed_tx.into();
-    Recovered::new_unchecked(reth_tx, signer.address())
+    template: TxTemplate,
+) -> Result<Recovered<EthereumTxEnvelope<TxEip4844>>, SandboxError> {
+    let tx = TxEip1559 {
+        chain_id: template.chain_id,
+        nonce: template.nonce,
+        gas_limit: template.gas_limit,
+        max_fee_per_gas: FEE_PER_GAS,
+        max_priority_fee_per_gas: template.max_priority_fee_per_gas,
+        to: template.to,
+        value: template.value,
+        access_list: Default::default(),
+        input: template.input,
+    };
+
+    let signature_hash = SIGNING_BUF.with_borrow_mut(|buf| {
+        buf.clear();
+        tx.encode_for_signing(buf);
+        keccak256(&buf[..])
+    });
+    let signature =
+        signer
+            .sign_hash_sync(&signature_hash)
+            .map_err(|err| SandboxError::Signing {
+                sender: signer.address(),
+                nonce: template.nonce,
+                reason: err.to_string(),
+            })?;
+
+    let signed_tx: TransactionSigned = tx.into_signed(signature).into();
+    Ok(Recovered::new_unchecked(signed_tx, signer.address()))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, B256};
+
+    use super::*;
+
+    fn signed(nonce: u64) -> Recovered<EthereumTxEnvelope<TxEip4844>> {
+        let signer = LocalSigner::from_bytes(&B256::repeat_byte(0x42)).unwrap();
+        let template =
+            TxTemplate::new(nonce, TxKind::Call(Address::ZERO), None, None).with_chain_id(7);
+        sign(&signer, template).unwrap()
+    }
+
+    #[test]
+    fn signed_transactions_recover_to_their_signer() {
+        verify_sender(&signed(3)).unwrap();
+    }
+
+    #[test]
+    fn wrong_claimed_sender_is_a_mismatch() {
+        let (tx, signer) = signed(3).into_parts();
+        let hash = *tx.hash();
+        let claimed = Address::repeat_byte(0xee);
+        match verify_sender(&Recovered::new_unchecked(tx, claimed)) {
+            Err(SandboxError::SenderMismatch {
+                hash: reported,
+                nonce,
+                chain_id,
+                claimed: reported_claimed,
+                recovered,
+            }) => {
+                assert_eq!(reported, hash);
+                assert_eq!(nonce, 3);
+                assert_eq!(chain_id, Some(7));
+                assert_eq!(reported_claimed, claimed);
+                assert_eq!(recovered, Some(signer));
+            }
+            other => panic!("expected a sender mismatch, got {other:?}"),
+        }
+    }
 }
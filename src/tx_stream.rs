@@ -0,0 +1,161 @@
+//! Transaction stream file: every transaction the orchestrator sent into the
+//! channel, setup included, in send order, so a later run can feed its
+//! builder the identical stream without generating anything.
+//!
+//! Framing follows the transaction file: magic, a version byte, and the hash
+//! of the genesis the stream was generated on, then one record per
+//! transaction: a little-endian `u32` length and that many EIP-2718 bytes,
+//! then the transaction's label and phase names, each behind a one-byte
+//! length.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use alloy_consensus::{EthereumTxEnvelope, TxEip4844, transaction::SignerRecoverable};
+use alloy_eips::eip2718::{Decodable2718, Encodable2718};
+use alloy_primitives::B256;
+
+use crate::{
+    error::SandboxError,
+    labels::{LabeledTx, SimulationPhase, TxLabel},
+};
+
+/// Magic bytes identifying a transaction stream file.
+const MAGIC_BYTES: &[u8] = b"RTXQ";
+
+/// Transaction stream format version.
+const FILE_FORMAT_VERSION: u8 = 1;
+
+/// Appends the transactions sent into the channel to a stream file.
+pub struct TxStreamWriter {
+    writer: BufWriter<File>,
+}
+
+impl TxStreamWriter {
+    /// Create the file and write the header for a run on `genesis_hash`.
+    pub fn create(path: &Path, genesis_hash: B256) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC_BYTES)?;
+        writer.write_all(&[FILE_FORMAT_VERSION])?;
+        writer.write_all(genesis_hash.as_slice())?;
+        Ok(Self { writer })
+    }
+
+    /// Encode one record per transaction of `batch`, before sending moves it
+    /// into the channel. Groups are recorded member by member, so a replay
+    /// sends each on its own.
+    pub fn encode(batch: &[LabeledTx]) -> Vec<Vec<u8>> {
+        LabeledTx::flatten(batch)
+            .map(|labeled| {
+                let mut record = vec![0; 4];
+                labeled.tx.inner().encode_2718(&mut record);
+                let len = (record.len() - 4) as u32;
+                record[..4].copy_from_slice(&len.to_le_bytes());
+                for name in [labeled.label.name(), labeled.phase.name()] {
+                    record.push(name.len() as u8);
+                    record.extend_from_slice(name.as_bytes());
+                }
+                record
+            })
+            .collect()
+    }
+
+    /// Append `records` and flush, so the file is whole whenever the
+    /// orchestrator stops.
+    pub fn append(&mut self, records: &[Vec<u8>]) -> eyre::Result<()> {
+        for record in records {
+            self.writer.write_all(record)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads back a file produced by [`TxStreamWriter`].
+pub struct TxStreamReader {
+    reader: BufReader<File>,
+    path: PathBuf,
+    genesis_hash: B256,
+}
+
+impl TxStreamReader {
+    /// Open the file and check its magic and version.
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let file = File::open(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        eyre::ensure!(
+            magic == MAGIC_BYTES,
+            "{} is not a transaction stream file",
+            path.display()
+        );
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        eyre::ensure!(
+            version[0] == FILE_FORMAT_VERSION,
+            "unsupported transaction stream version: {}",
+            version[0]
+        );
+        let mut genesis_hash = B256::ZERO;
+        reader.read_exact(genesis_hash.as_mut_slice())?;
+        Ok(Self {
+            reader,
+            path: path.to_path_buf(),
+            genesis_hash,
+        })
+    }
+
+    /// Hash of the genesis the stream was generated on.
+    pub fn genesis_hash(&self) -> B256 {
+        self.genesis_hash
+    }
+
+    /// Read the next transaction with its sender recovered from its
+    /// signature, or `None` at a clean end of file.
+    pub fn next_tx(&mut self) -> eyre::Result<Option<LabeledTx>> {
+        let mut len = [0u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let mut raw = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader
+            .read_exact(&mut raw)
+            .map_err(|err| eyre::eyre!("{}: truncated transaction: {err}", self.path.display()))?;
+        let tx = EthereumTxEnvelope::<TxEip4844>::decode_2718(&mut raw.as_slice())
+            .map_err(|err| eyre::eyre!("{}: undecodable transaction: {err}", self.path.display()))?
+            .try_into_recovered()
+            .map_err(|err| eyre::eyre!("{}: unrecoverable sender: {err}", self.path.display()))?;
+
+        let label = self.read_name()?;
+        let label = TxLabel::from_name(&label)
+            .ok_or_else(|| eyre::eyre!("{}: unknown label `{label}`", self.path.display()))?;
+        let phase = self.read_name()?;
+        let phase = SimulationPhase::from_name(&phase)
+            .ok_or_else(|| eyre::eyre!("{}: unknown phase `{phase}`", self.path.display()))?;
+        Ok(Some(LabeledTx::new(tx, label, phase)))
+    }
+
+    fn read_name(&mut self) -> eyre::Result<String> {
+        let mut len = [0u8; 1];
+        self.reader.read_exact(&mut len)?;
+        let mut name = vec![0u8; len[0] as usize];
+        self.reader.read_exact(&mut name)?;
+        Ok(String::from_utf8(name)?)
+    }
+}
+
+impl Iterator for TxStreamReader {
+    type Item = eyre::Result<LabeledTx>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_tx().transpose()
+    }
+}
@@ -0,0 +1,213 @@
+//! Raw transaction file: every transaction the builder included, as
+//! EIP-2718 bytes in execution order, for benchmarks that feed a node's
+//! txpool instead of importing blocks.
+//!
+//! Framing follows the block file: magic, a version byte, then records of a
+//! little-endian `u32` length and that many bytes. A zero-length record
+//! closes each block.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use alloy_eips::eip2718::Encodable2718;
+
+use crate::error::SandboxError;
+
+/// Magic bytes identifying a transaction file.
+const MAGIC_BYTES: &[u8] = b"RTXS";
+
+/// Transaction file format version.
+const FILE_FORMAT_VERSION: u8 = 1;
+
+/// One record of a transaction file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TxRecord {
+    /// EIP-2718 encoded signed transaction.
+    Tx(Vec<u8>),
+    /// The previous transactions made up one block.
+    BlockEnd,
+}
+
+/// Streams included transactions to a transaction file.
+pub struct TxFileWriter {
+    writer: BufWriter<File>,
+    txs_written: u64,
+}
+
+impl TxFileWriter {
+    /// Create the file and write the magic and version.
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let file = File::create(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MAGIC_BYTES)?;
+        writer.write_all(&[FILE_FORMAT_VERSION])?;
+        Ok(Self {
+            writer,
+            txs_written: 0,
+        })
+    }
+
+    /// Append a block's transactions in order, then the block sentinel.
+    pub fn write_block<'a, T: Encodable2718 + 'a>(
+        &mut self,
+        txs: impl IntoIterator<Item = &'a T>,
+    ) -> eyre::Result<()> {
+        let mut buf = Vec::new();
+        for tx in txs {
+            buf.clear();
+            tx.encode_2718(&mut buf);
+            self.writer.write_all(&(buf.len() as u32).to_le_bytes())?;
+            self.writer.write_all(&buf)?;
+            self.txs_written += 1;
+        }
+        self.writer.write_all(&0u32.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Flush the writer and return how many transactions were written.
+    pub fn finish(mut self) -> eyre::Result<u64> {
+        self.writer.flush()?;
+        Ok(self.txs_written)
+    }
+}
+
+/// Reads back a file produced by [`TxFileWriter`].
+pub struct TxFileReader {
+    reader: BufReader<File>,
+}
+
+impl TxFileReader {
+    /// Open the file and check its magic and version.
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let file = File::open(path).map_err(|err| SandboxError::io(path, err))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        eyre::ensure!(
+            magic == MAGIC_BYTES,
+            "{} is not a transaction file",
+            path.display()
+        );
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        eyre::ensure!(
+            version[0] == FILE_FORMAT_VERSION,
+            "unsupported transaction file version: {}",
+            version[0]
+        );
+        Ok(Self { reader })
+    }
+
+    /// Read the next record, or `None` at a clean end of file.
+    pub fn next_record(&mut self) -> eyre::Result<Option<TxRecord>> {
+        let mut len = [0u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if len == 0 {
+            return Ok(Some(TxRecord::BlockEnd));
+        }
+        let mut tx = vec![0u8; len];
+        self.reader
+            .read_exact(&mut tx)
+            .map_err(|err| eyre::eyre!("truncated transaction in file: {err}"))?;
+        Ok(Some(TxRecord::Tx(tx)))
+    }
+}
+
+impl Iterator for TxFileReader {
+    type Item = eyre::Result<TxRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{EthereumTxEnvelope, TxEip4844, transaction::SignerRecoverable};
+    use alloy_eips::eip2718::Decodable2718;
+    use alloy_primitives::{Address, B256, TxKind};
+    use alloy_signer_local::PrivateKeySigner;
+
+    use super::*;
+    use crate::transaction::{TxTemplate, sign};
+
+    #[test]
+    fn written_transactions_decode_and_recover_their_senders() {
+        let signers = [0x01, 0x02]
+            .map(|byte| PrivateKeySigner::from_bytes(&B256::repeat_byte(byte)).unwrap());
+        let blocks = [vec![(0, 0), (1, 0), (0, 1)], vec![], vec![(1, 1)]].map(|block| {
+            block
+                .into_iter()
+                .map(|(signer, nonce)| {
+                    let template = TxTemplate::new(nonce, TxKind::Call(Address::ZERO), None, None);
+                    sign(&signers[signer], template).unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("txs.bin");
+        let mut writer = TxFileWriter::new(&path).unwrap();
+        for block in &blocks {
+            writer
+                .write_block(block.iter().map(|tx| tx.inner()))
+                .unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 4);
+
+        let mut read = vec![Vec::new()];
+        for record in TxFileReader::open(&path).unwrap() {
+            match record.unwrap() {
+                TxRecord::Tx(bytes) => {
+                    let tx = EthereumTxEnvelope::<TxEip4844>::decode_2718(&mut bytes.as_slice())
+                        .unwrap();
+                    let sender = tx.recover_signer().unwrap();
+                    read.last_mut().unwrap().push((tx, sender));
+                }
+                TxRecord::BlockEnd => read.push(Vec::new()),
+            }
+        }
+        // Every block is closed, leaving the one opened after the last empty.
+        assert_eq!(read.pop(), Some(Vec::new()));
+        assert_eq!(read.len(), blocks.len());
+        for (written, read) in blocks.iter().zip(&read) {
+            assert_eq!(written.len(), read.len());
+            for (written, (tx, sender)) in written.iter().zip(read) {
+                assert_eq!(tx, written.inner());
+                assert_eq!(*sender, written.signer());
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_transaction_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("txs.bin");
+        let mut bytes = MAGIC_BYTES.to_vec();
+        bytes.push(FILE_FORMAT_VERSION);
+        bytes.extend_from_slice(&10u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut reader = TxFileReader::open(&path).unwrap();
+        assert!(reader.next_record().is_err());
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.bin");
+        std::fs::write(&path, b"RBLK\x01").unwrap();
+        assert!(TxFileReader::open(&path).is_err());
+    }
+}
@@ -1,14 +1,15 @@
 //! Helpers that deploy Uniswap v2 artifacts and craft router interactions.
 
-use std::time::{SystemTime, UNIX_EPOCH};
-
-use alloy_primitives::{Address, Bytes, TxKind, U256};
+use alloy_primitives::{Address, Bytes, TxKind, U256, keccak256};
 use alloy_sol_macro::sol;
 use alloy_sol_types::{SolCall, SolConstructor};
 use tracing::info;
 
 use crate::actor::Actor;
+use crate::error::SandboxError;
+use crate::logging;
 use crate::orchestrator::TX;
+use crate::permit::PermitSignature;
 use crate::transaction::tx;
 
 sol!(
@@ -47,7 +48,7 @@ pub struct Uniswap {
 impl Uniswap {
     /// Record the deployed addresses.
     pub fn new(factory_address: Address, router_address: Address, weth_address: Address) -> Self {
-        info!(target: "sandbox", "Uniswap created: factory_address: {:?}, router_address: {:?}, weth_address: {:?}", factory_address, router_address, weth_address);
+        info!(target: logging::ORCHESTRATOR, "Uniswap created: factory_address: {:?}, router_address: {:?}, weth_address: {:?}", factory_address, router_address, weth_address);
         Self {
             factory_address,
             router_address,
@@ -55,51 +56,45 @@ impl Uniswap {
         }
     }
 
-    /// Deploy WETH, factory, and router contracts using the provided deployer.
-    pub fn init(deployer: &Actor) -> (Uniswap, Vec<TX>) {
+    /// Deploy WETH, factory, and router contracts from `deployer`, with the
+    /// factory's `feeToSetter` set to `fee_to_setter`. Each address is
+    /// predicted from the nonce its transaction is signed at, and the
+    /// deployer's nonce is advanced past every one, so callers need not know
+    /// how many transactions the deployment takes.
+    pub fn init(
+        deployer: &mut Actor,
+        fee_to_setter: Address,
+    ) -> Result<(Uniswap, Vec<TX>), SandboxError> {
         let mut txs = Vec::new();
+        let mut deploy = |data: Bytes| -> Result<Address, SandboxError> {
+            let nonce = deployer.nonce();
+            txs.push(tx(
+                deployer.signer(),
+                nonce,
+                TxKind::Create,
+                None,
+                Some(data),
+            )?);
+            deployer.increment_nonce_by(1);
+            Ok(deployer.contract_address(nonce))
+        };
 
-        let signer = deployer.signer().clone();
-
-        let weth9_addr = deployer.contract_address(deployer.nonce());
-
-        let weth9_tx = tx(
-            &signer,
-            deployer.nonce(),
-            TxKind::Create,
-            None,
-            Some(WETH9::BYTECODE.clone()),
-        );
-
-        txs.push(weth9_tx);
-
-        let factory_addr = deployer.contract_address(deployer.nonce() + 1);
-
-        let factory_tx = tx(
-            &signer,
-            deployer.nonce() + 1,
-            TxKind::Create,
-            None,
-            Some(UniswapV2FactoryHelper::deploy(deployer.address())),
-        );
-
-        txs.push(factory_tx);
-
-        let router_addr = deployer.contract_address(deployer.nonce() + 2);
-
-        let router_tx = tx(
-            &signer,
-            deployer.nonce() + 2,
-            TxKind::Create,
-            None,
-            Some(UniswapV2Router02Helper::deploy(factory_addr, weth9_addr)),
-        );
-
-        txs.push(router_tx);
+        let weth9_addr = deploy(WETH9::BYTECODE.clone())?;
+        let factory_addr = deploy(UniswapV2FactoryHelper::deploy(fee_to_setter))?;
+        let router_addr = deploy(UniswapV2Router02Helper::deploy(factory_addr, weth9_addr))?;
 
         let uniswap = Self::new(factory_addr, router_addr, weth9_addr);
 
-        (uniswap, txs)
+        Ok((uniswap, txs))
+    }
+
+    /// Each contract with the name it is reported under, in deployment order.
+    pub fn contracts(&self) -> [(&'static str, Address); 3] {
+        [
+            ("uniswap weth", self.weth_address),
+            ("uniswap factory", self.factory_address),
+            ("uniswap router", self.router_address),
+        ]
     }
 
     /// Factory address accessor.
@@ -114,6 +109,33 @@ impl Uniswap {
     pub fn weth(&self) -> Address {
         self.weth_address
     }
+
+    /// Predict the WETH/`token` pair address the factory creates with CREATE2.
+    pub fn pair_address(&self, token: Address) -> Address {
+        let (token0, token1) = if self.weth_address < token {
+            (self.weth_address, token)
+        } else {
+            (token, self.weth_address)
+        };
+        let salt = keccak256([token0.as_slice(), token1.as_slice()].concat());
+        self.factory_address
+            .create2(salt, keccak256(&UniswapV2Pair::BYTECODE))
+    }
+}
+
+/// Encode direct WETH9 calls.
+pub struct WethHelper;
+
+impl WethHelper {
+    /// Encode `deposit()`; the amount to wrap is the transaction value.
+    pub fn deposit() -> Bytes {
+        WETH9::depositCall::new(()).abi_encode().into()
+    }
+
+    /// Encode `withdraw(amount)`.
+    pub fn withdraw(amount: U256) -> Bytes {
+        WETH9::withdrawCall::new((amount,)).abi_encode().into()
+    }
 }
 
 /// Encode commonly used factory contract calls.
@@ -169,48 +191,81 @@ impl UniswapV2Router02Helper {
         call_data.into()
     }
 
-    /// Build calldata for `swapExactETHForTokens`.
-    pub fn swap_eth_for_token(weth: Address, token_out: Address, to: Address) -> Bytes {
+    /// Build calldata for `swapExactETHForTokens`, or its
+    /// `SupportingFeeOnTransferTokens` variant when `token_out` takes a fee.
+    pub fn swap_eth_for_token(
+        weth: Address,
+        token_out: Address,
+        to: Address,
+        fee_on_transfer: bool,
+    ) -> Bytes {
         let amount_out_min = U256::from(0);
 
         let path = vec![weth, token_out];
-        UniswapV2Router02::swapExactETHForTokensCall::new((
-            amount_out_min,
-            path,
-            to,
-            Self::get_deadline(),
-        ))
-        .abi_encode()
-        .into()
+        let args = (amount_out_min, path, to, Self::get_deadline());
+        if fee_on_transfer {
+            UniswapV2Router02::swapExactETHForTokensSupportingFeeOnTransferTokensCall::new(args)
+                .abi_encode()
+                .into()
+        } else {
+            UniswapV2Router02::swapExactETHForTokensCall::new(args)
+                .abi_encode()
+                .into()
+        }
     }
 
-    /// Build calldata for `swapExactTokensForETH`.
+    /// Build calldata for `swapExactTokensForETH`, or its
+    /// `SupportingFeeOnTransferTokens` variant when `token_in` takes a fee:
+    /// the plain path quotes the pre-fee amount and the pair rejects the swap.
     pub fn swap_token_for_eth(
         token_in: Address,
         weth: Address,
         amount_in: U256,
         to: Address,
+        fee_on_transfer: bool,
     ) -> Bytes {
         let amount_out_min = U256::from(0);
         let path = vec![token_in, weth];
-        UniswapV2Router02::swapExactTokensForETHCall::new((
-            amount_in,
-            amount_out_min,
-            path,
+        let args = (amount_in, amount_out_min, path, to, Self::get_deadline());
+        if fee_on_transfer {
+            UniswapV2Router02::swapExactTokensForETHSupportingFeeOnTransferTokensCall::new(args)
+                .abi_encode()
+                .into()
+        } else {
+            UniswapV2Router02::swapExactTokensForETHCall::new(args)
+                .abi_encode()
+                .into()
+        }
+    }
+
+    /// Build calldata for `removeLiquidityETHWithPermit`, which submits the
+    /// LP token permit and burns `liquidity` in the same call.
+    pub fn remove_liquidity_eth_with_permit(
+        token: Address,
+        liquidity: U256,
+        to: Address,
+        signature: PermitSignature,
+    ) -> Bytes {
+        UniswapV2Router02::removeLiquidityETHWithPermitCall::new((
+            token,
+            liquidity,
+            U256::ZERO,
+            U256::ZERO,
             to,
             Self::get_deadline(),
+            false,
+            signature.v,
+            signature.r,
+            signature.s,
         ))
         .abi_encode()
         .into()
     }
 
-    /// Timestamps for simulation start at 0 and increment by 1 so this should be good
-    fn get_deadline() -> U256 {
-        U256::from(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        )
+    /// Router deadline for every call. The simulated clock starts at
+    /// `genesis_timestamp` and advances by `block_time_secs` per block, so it
+    /// can run arbitrarily far ahead of wall time; never expire instead.
+    pub fn get_deadline() -> U256 {
+        U256::from(u64::MAX)
     }
 }
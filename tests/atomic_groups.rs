@@ -0,0 +1,46 @@
+//! An approve and the swap spending it go out as one group, which the builder
+//! includes in the same block or defers as a whole.
+
+mod common;
+
+use std::{collections::BTreeMap, fs};
+
+use common::{run_in, small_config};
+use reth_sandbox::config::SimulationConfig;
+
+/// Gas limit the approve and the swap are each signed with.
+const DEFAULT_GAS_LIMIT: u64 = 5_000_000;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn group_at_the_gas_boundary_stays_in_one_block() {
+    let dir = tempfile::tempdir().unwrap();
+    // An approve+swap group's gas limits add up to exactly the block's gas
+    // target: it fits an empty block and nothing else.
+    let config = SimulationConfig {
+        num_of_blocks: Some(60),
+        unique_accounts: 10,
+        unique_tokens: 1,
+        gas_limit: 2 * DEFAULT_GAS_LIMIT,
+        std_batch_size: 20,
+        ..small_config(0x4b)
+    }
+    .with_block_labels_out(Some(dir.path().join("labels.csv")));
+    run_in(dir.path(), config).await;
+
+    // Block -> swap-for-eth transactions, approvals included.
+    let mut swaps = BTreeMap::<u64, u64>::new();
+    let labels = fs::read_to_string(dir.path().join("labels.csv")).unwrap();
+    for row in labels.lines().skip(1) {
+        let fields = row.split(',').collect::<Vec<_>>();
+        if fields[1] != "uniswap-swap-for-eth" {
+            continue;
+        }
+        assert_eq!(fields[4], "0", "failed swap: {row}");
+        assert_eq!(fields[5], "0", "rejected swap: {row}");
+        swaps.insert(fields[0].parse().unwrap(), fields[2].parse().unwrap());
+    }
+    assert!(!swaps.is_empty(), "no swap was included");
+    for (block, txs) in swaps {
+        assert_eq!(txs, 2, "block {block} split an approve+swap group");
+    }
+}
@@ -0,0 +1,46 @@
+//! The balance report reads every setup token's holdings from storage.
+
+mod common;
+
+use std::fs;
+
+use common::{run_in, small_config};
+use reth_sandbox::{config::SimulationConfig, scenario::Scenario};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_holdings_show_a_scripted_transfer() {
+    let dir = tempfile::tempdir().unwrap();
+    // The only load is actor 0 sending 12345 units of token 0 to actor 9,
+    // which holds none of it after setup.
+    let scenario = Scenario::parse(
+        r#"{ "blocks": [[{
+            "type": "token-transfer", "count": 1,
+            "from": 0, "to": 9, "token": 0, "amount": 12345
+        }]] }"#,
+    )
+    .unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: None,
+        unique_accounts: 10,
+        ..small_config(0x4a)
+    }
+    .with_scenario(Some(scenario))
+    .with_balance_report(true);
+    run_in(dir.path(), config).await;
+
+    let holdings = fs::read_to_string(dir.path().join("token_holdings/token_0.csv")).unwrap();
+    let mut rows = holdings.lines();
+    assert_eq!(rows.next(), Some("label,address,balance"));
+    let balance = |label: &str| {
+        holdings
+            .lines()
+            .find_map(|row| {
+                let fields: Vec<_> = row.split(',').collect();
+                (fields[0] == label).then(|| fields[2].to_string())
+            })
+            .unwrap()
+    };
+    assert_eq!(balance("actor_9"), "12345");
+    assert_eq!(rows.count(), 11, "deployer and 10 actors");
+    assert!(dir.path().join("token_holdings/token_1.csv").is_file());
+}
@@ -0,0 +1,26 @@
+//! Batches smaller than one unit of setup work still make progress.
+
+mod common;
+
+use std::fs;
+
+use common::{run_in, small_config};
+use reth_sandbox::config::SimulationConfig;
+use serde_json::Value;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pools_are_created_with_a_two_transaction_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        std_batch_size: 2,
+        ..small_config(0x3b)
+    };
+    run_in(dir.path(), config).await;
+
+    // Setup only completes, and writes the manifest, once every token has
+    // its pool.
+    let deployments: Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("deployments.json")).unwrap())
+            .unwrap();
+    assert_eq!(deployments["pairs"].as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,89 @@
+//! The block file writer and reader agree on the format for any sequence of
+//! blocks, and the reader turns damaged input into errors rather than panics
+//! or wrong blocks.
+
+use std::io::Cursor;
+
+use proptest::prelude::*;
+use reth_sandbox::block_file::{
+    BlockFileReader, BlockFileWriter,
+    format::{BlockFileHeader, HEADER_LEN},
+};
+
+/// Encode `blocks` as a block file starting at `from_block`.
+fn write_file(is_optimism: bool, from_block: u64, blocks: &[Vec<u8>]) -> Vec<u8> {
+    let header = BlockFileHeader::new(is_optimism, from_block, from_block);
+    let mut writer = BlockFileWriter::from_writer(Cursor::new(Vec::new()), header).unwrap();
+    for block in blocks {
+        writer.write_block(block).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Every block the reader yields before its first error, and whether it hit
+/// one.
+fn read_file(bytes: &[u8]) -> (Vec<Vec<u8>>, bool) {
+    let Ok(reader) = BlockFileReader::from_reader(bytes) else {
+        return (Vec::new(), true);
+    };
+    let mut blocks = Vec::new();
+    for block in reader {
+        match block {
+            Ok(block) => blocks.push(block),
+            Err(_) => return (blocks, true),
+        }
+    }
+    (blocks, false)
+}
+
+fn blocks() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    prop::collection::vec(prop::collection::vec(any::<u8>(), 0..512), 0..32)
+}
+
+proptest! {
+    #[test]
+    fn header_round_trips(is_optimism: bool, from_block: u64, to_block: u64) {
+        let header = BlockFileHeader::new(is_optimism, from_block, to_block);
+        prop_assert_eq!(BlockFileHeader::decode(&header.encode()).unwrap(), header);
+    }
+
+    #[test]
+    fn blocks_round_trip(is_optimism: bool, from_block in 0u64..1 << 48, blocks in blocks()) {
+        let bytes = write_file(is_optimism, from_block, &blocks);
+        let reader = BlockFileReader::from_reader(bytes.as_slice()).unwrap();
+        let header = *reader.header();
+        let last = from_block + (blocks.len() as u64).saturating_sub(1);
+        prop_assert_eq!(header, BlockFileHeader::new(is_optimism, from_block, last));
+
+        let read = reader.collect::<eyre::Result<Vec<_>>>().unwrap();
+        prop_assert_eq!(read, blocks);
+    }
+
+    #[test]
+    fn truncated_file_reads_a_prefix_then_fails(blocks in blocks(), cut in any::<prop::sample::Index>()) {
+        let bytes = write_file(false, 1, &blocks);
+        let cut = cut.index(bytes.len());
+        let (read, failed) = read_file(&bytes[..cut]);
+
+        // Only the header and whole records make a file that ends cleanly.
+        let mut boundaries = vec![HEADER_LEN];
+        for block in &blocks {
+            boundaries.push(boundaries.last().unwrap() + 4 + block.len());
+        }
+        prop_assert_eq!(failed, !boundaries.contains(&cut));
+        prop_assert!(blocks.starts_with(&read));
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..1024)) {
+        read_file(&bytes);
+    }
+
+    #[test]
+    fn arbitrary_records_never_panic(records in prop::collection::vec(any::<u8>(), 0..1024)) {
+        // A valid header, so the reader gets as far as the records.
+        let mut bytes = BlockFileHeader::new(false, 1, 1).encode().to_vec();
+        bytes.extend(records);
+        read_file(&bytes);
+    }
+}
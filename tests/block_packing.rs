@@ -0,0 +1,38 @@
+//! Blocks stop short of their gas target: a transaction whose gas limit could
+//! overshoot it opens the next block instead, and none is lost on the way.
+
+mod common;
+
+use std::fs;
+
+use common::{run_in, small_config};
+use reth_sandbox::config::SimulationConfig;
+
+const GAS_LIMIT: u64 = 100_000_000;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocks_never_overshoot_the_gas_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(10),
+        gas_limit: GAS_LIMIT,
+        ..small_config(0x67)
+    };
+    let target = config.block_gas_target();
+
+    // A held-over transaction that never made it into a block would leave a
+    // nonce gap, and the run fails once a later transaction from the same
+    // sender is rejected for it.
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 10);
+    assert!(result.txs > result.blocks);
+
+    let roots = fs::read_to_string(dir.path().join("roots.csv")).unwrap();
+    for row in roots.lines().skip(1) {
+        let gas_used: u64 = row.rsplit(',').next().unwrap().parse().unwrap();
+        assert!(
+            gas_used <= target,
+            "block used {gas_used} gas, target {target}: {row}"
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Block timestamps start at the configured genesis timestamp and advance by
+//! the block time, never by less than a second.
+
+mod common;
+
+use common::{run_in, small_config};
+use reth_provider::HeaderProvider;
+use reth_sandbox::{config::SimulationConfig, simulation::RunResult};
+
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+async fn run(block_time_secs: u64, seed: u8) -> RunResult {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(3),
+        ..small_config(seed)
+    }
+    .with_genesis_timestamp(GENESIS_TIMESTAMP)
+    .with_block_time_secs(block_time_secs);
+    run_in(dir.path(), config).await
+}
+
+/// Timestamps of blocks `0..=last`, genesis included.
+fn timestamps(result: &RunResult, last: u64) -> Vec<u64> {
+    let factory = result.database.in_memory_provider_factory().unwrap();
+    (0..=last)
+        .map(|number| factory.header_by_number(number).unwrap().unwrap().timestamp)
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timestamps_advance_by_the_block_time() {
+    let result = run(12, 0x6b).await;
+    assert_eq!(result.blocks, 3);
+    assert_eq!(
+        timestamps(&result, 3),
+        [0, 1, 2, 3].map(|n| GENESIS_TIMESTAMP + 12 * n)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn zero_block_time_still_advances_a_second_per_block() {
+    let result = run(0, 0x6c).await;
+    assert_eq!(result.blocks, 3);
+    assert_eq!(
+        timestamps(&result, 3),
+        [0, 1, 2, 3].map(|n| GENESIS_TIMESTAMP + n)
+    );
+}
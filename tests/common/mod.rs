@@ -0,0 +1,64 @@
+//! Fixtures shared by the integration tests.
+
+// Each test binary uses its own subset.
+#![allow(dead_code)]
+
+use std::{fs, path::Path};
+
+use alloy_primitives::B256;
+use reth_sandbox::{
+    config::{GENESIS_PRIVATE_KEY, SimulationConfig, parse_genesis_key},
+    simulation::{FaultInjection, RunResult, Simulation, SimulationPaths},
+};
+use serde_json::Value;
+
+/// A seeded, in-memory run of 3 blocks with 20 actors and 2 tokens on a 30M
+/// gas chain, with the progress heartbeat off.
+///
+/// Tests that need a different size override the public fields with struct
+/// update syntax; everything else goes through the `with_*` builders.
+pub fn small_config(seed: u8) -> SimulationConfig {
+    SimulationConfig::new(
+        2600,
+        Some(3),
+        None,
+        20,
+        2,
+        30_000_000,
+        parse_genesis_key(GENESIS_PRIVATE_KEY).unwrap(),
+        100,
+    )
+    .with_actor_seed(Some(B256::repeat_byte(seed)))
+    .with_progress_interval_secs(0)
+    .with_in_memory(true)
+}
+
+/// Run `config` to completion with every artifact written under `dir`.
+pub async fn run_in(dir: &Path, config: SimulationConfig) -> RunResult {
+    Simulation::new(config, SimulationPaths::new(dir))
+        .unwrap()
+        .run()
+        .await
+        .unwrap()
+}
+
+/// Run `config` to completion like [`run_in`], with `faults` built into the
+/// generated transactions.
+pub async fn run_with_faults(
+    dir: &Path,
+    config: SimulationConfig,
+    faults: FaultInjection,
+) -> RunResult {
+    Simulation::new(config, SimulationPaths::new(dir))
+        .unwrap()
+        .with_faults(faults)
+        .run()
+        .await
+        .unwrap()
+}
+
+/// The run manifest, always the last artifact written.
+pub fn manifest(result: &RunResult) -> Value {
+    let path = result.artifact_paths.last().unwrap();
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
@@ -0,0 +1,121 @@
+//! Config combinations that could never produce a useful run are rejected by
+//! `SimulationConfig::validate` before any chain is built.
+
+use std::time::Duration;
+
+use reth_sandbox::{
+    config::{
+        FeeOnTransfer, GENESIS_PRIVATE_KEY, OutputFormat, SimulationConfig, Workload,
+        parse_genesis_key,
+    },
+    error::SandboxError,
+};
+
+fn config(unique_accounts: u64, gas_limit: u64) -> SimulationConfig {
+    SimulationConfig::new(
+        2600,
+        Some(3),
+        None,
+        unique_accounts,
+        2,
+        gas_limit,
+        parse_genesis_key(GENESIS_PRIVATE_KEY).unwrap(),
+        100,
+    )
+}
+
+fn assert_rejected(config: SimulationConfig, field: &str) {
+    match config.validate() {
+        Err(SandboxError::Config(message)) => {
+            assert!(message.contains(field), "{field} not named in: {message}")
+        }
+        other => panic!("expected {field} to be rejected, got {other:?}"),
+    }
+}
+
+#[test]
+fn default_config_is_valid() {
+    config(20, 30_000_000).validate().unwrap();
+}
+
+#[test]
+fn zero_sizes_are_rejected() {
+    assert_rejected(config(0, 30_000_000), "unique_accounts");
+    assert_rejected(
+        config(20, 30_000_000).with_channel_buffer_size(0),
+        "channel_buffer_size",
+    );
+    assert_rejected(
+        SimulationConfig::new(
+            2600,
+            Some(3),
+            None,
+            20,
+            2,
+            30_000_000,
+            parse_genesis_key(GENESIS_PRIVATE_KEY).unwrap(),
+            0,
+        ),
+        "std_batch_size",
+    );
+}
+
+#[test]
+fn limits_that_stop_before_the_first_block_are_rejected() {
+    assert_rejected(
+        SimulationConfig::new(
+            2600,
+            Some(0),
+            None,
+            20,
+            2,
+            30_000_000,
+            parse_genesis_key(GENESIS_PRIVATE_KEY).unwrap(),
+            100,
+        ),
+        "num_of_blocks",
+    );
+    assert_rejected(
+        config(20, 30_000_000).with_max_duration(Some(Duration::ZERO)),
+        "max_duration",
+    );
+}
+
+#[test]
+fn gas_limit_must_fit_the_largest_transaction() {
+    assert_rejected(config(20, 1_000_000), "gas_limit");
+
+    // A transfers-only run with pre-funded actors sends nothing but transfers.
+    config(20, 21_000)
+        .with_workload(Workload::TransfersOnly)
+        .with_prefund_actors_in_genesis(true)
+        .validate()
+        .unwrap();
+    assert_rejected(
+        config(20, 21_000).with_workload(Workload::TransfersOnly),
+        "gas_limit",
+    );
+}
+
+#[test]
+fn output_limit_needs_a_block_file() {
+    assert_rejected(
+        config(20, 30_000_000)
+            .with_max_output_bytes(Some(1 << 20))
+            .with_output_format(OutputFormat::Off, "blocks.jsonl".into()),
+        "max_output_bytes",
+    );
+}
+
+#[test]
+fn fee_on_transfer_needs_a_share_and_a_partial_fee() {
+    let fee = |token_percent, fee_bps| {
+        config(20, 30_000_000).with_fee_on_transfer(Some(FeeOnTransfer {
+            token_percent,
+            fee_bps,
+        }))
+    };
+    fee(50, 100).validate().unwrap();
+    assert_rejected(fee(101, 100), "token_percent");
+    assert_rejected(fee(50, 10_000), "fee_bps");
+}
@@ -0,0 +1,50 @@
+//! With `deploy_via = create2`, setup tokens land at the factory's
+//! salt-derived addresses rather than the ones their deployers' nonces give.
+
+mod common;
+
+use std::fs;
+
+use alloy_primitives::Address;
+use common::{run_in, small_config};
+use reth_provider::StateProvider;
+use reth_sandbox::config::{DeployVia, SimulationConfig};
+use serde_json::Value;
+
+fn address(value: &Value) -> Address {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tokens_are_deployed_at_their_predicted_addresses() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(4),
+        ..small_config(0x6d)
+    }
+    .with_deploy_via(DeployVia::Create2);
+    let result = run_in(dir.path(), config).await;
+
+    let deployments: Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("deployments.json")).unwrap())
+            .unwrap();
+    let factory = address(&deployments["create2_deployer"]);
+    let tokens = deployments["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 2);
+
+    let state = result.database.latest().unwrap();
+    assert!(state.account_code(&factory).unwrap().is_some());
+    for token in tokens {
+        let token_address = address(&token["address"]);
+        // Code at the address recorded before the deployment was sent means
+        // the prediction was right.
+        let code = state.account_code(&token_address).unwrap();
+        assert!(
+            code.is_some_and(|code| !code.is_empty()),
+            "no code at {token_address}"
+        );
+        let deployer = address(&token["deployer"]);
+        let nonce = token["nonce"].as_u64().unwrap();
+        assert_ne!(token_address, deployer.create(nonce));
+    }
+}
@@ -0,0 +1,68 @@
+//! Setup that did not take effect stops the run: a Uniswap deployment signed
+//! at the wrong deployer nonce, or actors left without a balance.
+
+mod common;
+
+use alloy_primitives::U256;
+use common::small_config;
+use reth_sandbox::{
+    config::{SimulationConfig, Workload},
+    error::SandboxError,
+    simulation::{FaultInjection, Simulation, SimulationPaths},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn desynced_deployer_nonce_is_caught() {
+    let dir = tempfile::tempdir().unwrap();
+    let faults = FaultInjection {
+        uniswap_nonce_skew: 1,
+        ..FaultInjection::default()
+    };
+    let err = Simulation::new(small_config(0x38), SimulationPaths::new(dir.path()))
+        .unwrap()
+        .with_faults(faults)
+        .run()
+        .await
+        .unwrap_err();
+
+    match err.downcast_ref::<SandboxError>() {
+        Some(SandboxError::MissingContract { label, outcome, .. }) => {
+            assert_eq!(*label, "uniswap weth");
+            assert_eq!(*outcome, "was rejected");
+        }
+        _ => panic!("expected a missing contract, got {err:?}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unfunded_actors_fail_the_phase_check() {
+    let dir = tempfile::tempdir().unwrap();
+    // Funding transfers of zero wei all succeed, yet leave every actor
+    // without a balance.
+    let config = SimulationConfig {
+        num_of_blocks: Some(4),
+        unique_tokens: 0,
+        ..small_config(0x39)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_actor_funding_amount(U256::ZERO)
+    .with_phase_check_sample(5);
+    let err = Simulation::new(config, SimulationPaths::new(dir.path()))
+        .unwrap()
+        .run()
+        .await
+        .unwrap_err();
+
+    match err.downcast_ref::<SandboxError>() {
+        Some(SandboxError::PhaseCheck {
+            phase,
+            failed,
+            sampled,
+            ..
+        }) => {
+            assert_eq!(*phase, "actor-funding");
+            assert_eq!((*failed, *sampled), (5, 5));
+        }
+        _ => panic!("expected the actor funding check to fail, got {err:?}"),
+    }
+}
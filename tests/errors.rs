@@ -0,0 +1,74 @@
+//! Bad user input and an unusable environment surface as `SandboxError`s that
+//! name what was wrong, not as panics.
+
+mod common;
+
+use std::fs;
+
+use common::small_config;
+use reth_sandbox::{
+    config::{GENESIS_PRIVATE_KEY, SimulationConfig, parse_genesis_key},
+    error::SandboxError,
+    simulation::{Simulation, SimulationPaths},
+};
+
+#[test]
+fn malformed_private_keys_are_config_errors() {
+    for key in [
+        "",
+        "0x",
+        "not a key",
+        "0x1234",
+        &format!("{GENESIS_PRIVATE_KEY}00"),
+    ] {
+        match parse_genesis_key(key) {
+            Err(SandboxError::Config(message)) => {
+                assert!(message.contains("invalid genesis private key"), "{message}")
+            }
+            other => panic!("expected {key:?} to be rejected, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn private_keys_parse_with_or_without_a_prefix() {
+    let key = GENESIS_PRIVATE_KEY.trim_start_matches("0x");
+    let address = parse_genesis_key(key).unwrap().address();
+    assert_eq!(
+        parse_genesis_key(&format!(" 0x{key}\n")).unwrap().address(),
+        address
+    );
+}
+
+#[test]
+fn output_dir_under_a_file_is_an_io_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, "").unwrap();
+    let output_dir = file.join("out");
+
+    match SimulationPaths::create(&output_dir, false) {
+        Err(SandboxError::Io { path, .. }) => assert_eq!(path, output_dir),
+        other => panic!("expected an I/O error, got {other:?}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unwritable_block_file_fails_the_run_with_its_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    fs::write(&file, "").unwrap();
+    let blocks_out = file.join("blocks.bin");
+    let config = SimulationConfig {
+        num_of_blocks: Some(1),
+        ..small_config(0x6e)
+    }
+    .with_blocks_out(blocks_out.clone());
+
+    let err = match Simulation::new(config, SimulationPaths::new(dir.path())) {
+        Ok(simulation) => simulation.run().await.err().unwrap(),
+        Err(err) => err,
+    };
+    let err = format!("{err:?}");
+    assert!(err.contains(&blocks_out.display().to_string()), "{err}");
+}
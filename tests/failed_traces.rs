@@ -0,0 +1,70 @@
+//! With `trace_failed_txs`, every failed transaction is replayed under a call
+//! tracer and its record carries the call tree, reverts decoded through the
+//! token's errors.
+
+mod common;
+
+use std::fs;
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::AmountRange;
+use serde_json::Value;
+
+/// Whether `call` or any call under it reverted with `reason`.
+fn reverted_with(call: &Value, reason: &str) -> bool {
+    call["revert_reason"]
+        .as_str()
+        .is_some_and(|revert_reason| revert_reason.starts_with(reason))
+        || call["calls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|inner| reverted_with(inner, reason))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reverted_token_transfers_are_traced() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = small_config(0x7b);
+    let eth_amount = config.eth_transfer_amount;
+    // More than a token's whole supply, so every token transfer reverts.
+    let too_much = AmountRange::new(u64::MAX, u64::MAX);
+    let config = config
+        .with_transfer_amounts(eth_amount, too_much)
+        .with_trace_failed_txs(true);
+    let result = run_in(dir.path(), config).await;
+
+    let labels = &manifest(&result)["labels"];
+    assert!(
+        labels["token-transfer"]["failed"].as_u64().unwrap() > 0,
+        "no token transfer failed"
+    );
+    let failed: u64 = labels
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|stats| stats["failed"].as_u64().unwrap())
+        .sum();
+
+    let mut traced = 0;
+    for entry in fs::read_dir(dir.path().join("failed_traces")).unwrap() {
+        let path = entry.unwrap().path();
+        let record: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            path.file_stem().unwrap().to_str().unwrap(),
+            format!("{}_{}", record["block"], record["index"])
+        );
+
+        let call = &record["call_tree"];
+        assert_eq!(call["to"], record["to"]);
+        assert_eq!(call["selector"], record["selector"]);
+        assert!(call["error"].is_string(), "{call}");
+        assert!(
+            reverted_with(call, "ERC20InsufficientBalance"),
+            "{} has no decoded revert: {call}",
+            path.display()
+        );
+        traced += 1;
+    }
+    assert_eq!(traced, failed);
+}
@@ -0,0 +1,62 @@
+//! Fee-on-transfer tokens deploy from their own template and are swapped
+//! through the router paths that tolerate the short transfer.
+
+mod common;
+
+use std::fs;
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::{FeeOnTransfer, SimulationConfig, TokenTemplate};
+use serde_json::Value;
+
+#[test]
+fn fee_tokens_are_spread_through_the_deployment_order() {
+    let fee = FeeOnTransfer {
+        token_percent: 25,
+        fee_bps: 300,
+    };
+    let charged: Vec<u64> = (0..8)
+        .filter(|&index| fee.template(index).charges_fee())
+        .collect();
+    assert_eq!(charged, [3, 7]);
+    assert_eq!(
+        fee.template(3),
+        TokenTemplate::FeeOnTransfer { fee_bps: 300 }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fee_token_swaps_succeed_in_both_directions() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(6),
+        ..small_config(0x7e)
+    }
+    .with_fee_on_transfer(Some(FeeOnTransfer {
+        token_percent: 50,
+        fee_bps: 100,
+    }));
+    let result = run_in(dir.path(), config).await;
+
+    let deployments: Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("deployments.json")).unwrap())
+            .unwrap();
+    let tokens = deployments["tokens"].as_array().unwrap();
+    let templates: Vec<&str> = tokens
+        .iter()
+        .map(|token| token["template"].as_str().unwrap())
+        .collect();
+    assert_eq!(templates, ["standard", "fee-on-transfer"]);
+    assert_eq!(tokens[1]["fee_bps"].as_u64(), Some(100));
+    assert_eq!(tokens[1]["metadata"]["symbol"].as_str(), Some("FEE1"));
+    assert_eq!(tokens[0]["metadata"]["decimals"].as_u64(), Some(18));
+
+    // Plain swaps out of the fee token would revert on the pair's K check.
+    let manifest = manifest(&result);
+    for label in ["uniswap-swap-for-eth", "uniswap-swap-for-token"] {
+        let swaps = &manifest["labels"][label];
+        assert!(swaps["txs"].as_u64().unwrap() > 0, "no {label} txs");
+        assert_eq!(swaps["failed"].as_u64(), Some(0), "{label} failed");
+        assert_eq!(swaps["rejected"].as_u64(), Some(0), "{label} rejected");
+    }
+}
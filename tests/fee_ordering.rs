@@ -0,0 +1,61 @@
+//! Priority fee ordering includes the best-paying candidates first.
+
+mod common;
+
+use std::fs;
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::{AmountRange, FeeStrategyMix, SimulationConfig, TxOrdering, Workload};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocks_open_with_their_highest_tip() {
+    let dir = tempfile::tempdir().unwrap();
+    let fees_out = dir.path().join("block_fees.csv");
+    let config = SimulationConfig {
+        num_of_blocks: Some(8),
+        unique_accounts: 50,
+        unique_tokens: 0,
+        ..small_config(0x7a)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_prefund_actors_in_genesis(true)
+    .with_tx_ordering(TxOrdering::PriorityFee { window: None })
+    .with_priority_fee(AmountRange::new(1_000_000_000, 10_000_000_000))
+    .with_fee_strategy_mix(FeeStrategyMix {
+        cheapskate: 1,
+        median: 1,
+        aggressive: 1,
+    })
+    .with_block_fees_out(Some(fees_out.clone()));
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 8);
+
+    let fees = fs::read_to_string(&fees_out).unwrap();
+    let mut spread = false;
+    for row in fees.lines().skip(1) {
+        let fields: Vec<&str> = row.split(',').collect();
+        let first: u128 = fields[2].parse().unwrap();
+        let last: u128 = fields[3].parse().unwrap();
+        assert!(
+            first >= last,
+            "block {} opens below its last tip",
+            fields[0]
+        );
+        spread |= first > last;
+    }
+    assert!(spread, "no block mixed priority fees");
+
+    // Every strategy earns what it offers while the base fee stays below the
+    // max fee, and aggressive actors offer the most.
+    let manifest = manifest(&result);
+    let avg_tip = |strategy: &str| -> u128 {
+        manifest["tip_calibration"][strategy]["avg_tip"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    };
+    assert_eq!(avg_tip("cheapskate"), 1_000_000_000);
+    assert_eq!(avg_tip("aggressive"), 10_000_000_000);
+    assert!(avg_tip("median") > avg_tip("cheapskate"));
+}
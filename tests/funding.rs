@@ -0,0 +1,119 @@
+//! Actors are funded with exactly the configured amount, whatever nonce the
+//! deployer starts from, and a deployer that cannot cover the funding is
+//! rejected up front.
+
+mod common;
+
+use std::fs;
+
+use alloy_primitives::{Address, U256, utils::Unit};
+use common::{run_in, small_config};
+use reth_provider::StateProvider;
+use reth_sandbox::{
+    config::{FillStrategy, SimulationConfig, Workload},
+    simulation::{Simulation, SimulationPaths},
+};
+use serde_json::Value;
+
+const ACCOUNTS: u64 = 20;
+
+/// A one-block transfer run whose block holds exactly the `ACCOUNTS` funding
+/// transfers.
+fn config(seed: u8) -> SimulationConfig {
+    SimulationConfig {
+        num_of_blocks: Some(1),
+        unique_accounts: ACCOUNTS,
+        unique_tokens: 0,
+        ..small_config(seed)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_fill_strategy(FillStrategy::TxCount(ACCOUNTS))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn actors_hold_exactly_the_funding_amount() {
+    // One wei over a round amount: an f64 on the way would lose it.
+    let funding = U256::from(1_000_000) * Unit::ETHER.wei() + U256::from(1);
+    let dir = tempfile::tempdir().unwrap();
+    let actors_path = dir.path().join("actors.json");
+    // Funding comes first, so a single block of `ACCOUNTS` transactions holds
+    // every funding transfer and nothing an actor sent.
+    let config = config(0x73)
+        .with_actor_funding_amount(funding)
+        .with_actors_export_path(Some(actors_path.clone()));
+
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 1);
+
+    let actors: Value = serde_json::from_str(&fs::read_to_string(actors_path).unwrap()).unwrap();
+    let actors = actors["actors"].as_array().unwrap();
+    assert_eq!(actors.len() as u64, ACCOUNTS);
+
+    let state = result.database.latest().unwrap();
+    for actor in actors {
+        let address: Address = actor["address"].as_str().unwrap().parse().unwrap();
+        assert_eq!(
+            state.account_balance(&address).unwrap(),
+            Some(funding),
+            "actor {address}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn funding_does_not_follow_the_deployer_nonce() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // A genesis whose deployer has already sent a transaction.
+    let genesis_path = dir.path().join("genesis.json");
+    let generated = config(0x74).with_genesis_out(Some(genesis_path.clone()));
+    let deployer = generated.genesis_address();
+    drop(Simulation::new(generated, SimulationPaths::new(dir.path())).unwrap());
+    let mut genesis: Value =
+        serde_json::from_str(&fs::read_to_string(&genesis_path).unwrap()).unwrap();
+    let (_, account) = genesis["alloc"]
+        .as_object_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|(address, _)| address.parse::<Address>().unwrap() == deployer)
+        .unwrap();
+    account["nonce"] = "0x1".into();
+    fs::write(&genesis_path, genesis.to_string()).unwrap();
+
+    let actors_path = dir.path().join("actors.json");
+    let config = config(0x74)
+        .with_genesis_path(Some(genesis_path))
+        .with_actors_export_path(Some(actors_path.clone()));
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 1);
+
+    let actors: Value = serde_json::from_str(&fs::read_to_string(actors_path).unwrap()).unwrap();
+    let actors = actors["actors"].as_array().unwrap();
+    assert_eq!(actors.len() as u64, ACCOUNTS);
+    let state = result.database.latest().unwrap();
+    // One funding transfer per actor, each at the amount configured.
+    assert_eq!(state.account_nonce(&deployer).unwrap(), Some(1 + ACCOUNTS));
+    let funding = config(0x74).actor_funding_amount;
+    for actor in actors {
+        let address: Address = actor["address"].as_str().unwrap().parse().unwrap();
+        assert_eq!(
+            state.account_balance(&address).unwrap(),
+            Some(funding),
+            "actor {address}"
+        );
+    }
+}
+
+#[test]
+fn deployer_short_of_funding_is_rejected() {
+    let config = config(0x73);
+    let needed = config.actor_funding_amount * U256::from(ACCOUNTS);
+    assert!(config.check_deployer_funding(needed).is_err());
+    assert!(config.check_deployer_funding(U256::MAX).is_ok());
+    assert!(
+        config
+            .with_prefund_actors_in_genesis(true)
+            .check_deployer_funding(U256::ZERO)
+            .is_ok()
+    );
+}
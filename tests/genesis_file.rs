@@ -0,0 +1,66 @@
+//! A hand-written genesis file is adopted as the chain the sandbox builds on,
+//! and one that schedules a fork after genesis is refused.
+
+mod common;
+
+use std::fs;
+
+use common::{run_in, small_config};
+use reth_sandbox::{
+    config::{Hardfork, SimulationConfig},
+    simulation::{Simulation, SimulationPaths},
+};
+use serde_json::Value;
+
+const GENESIS: &str = include_str!("fixtures/genesis.json");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocks_are_built_on_a_genesis_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let genesis_path = dir.path().join("genesis.json");
+    fs::write(&genesis_path, GENESIS).unwrap();
+
+    let config = SimulationConfig {
+        num_of_blocks: Some(1),
+        ..small_config(0x61)
+    }
+    .with_genesis_path(Some(genesis_path));
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 1);
+
+    // The file activates Cancun at genesis and sets its own gas limit.
+    let header = result.database.head().unwrap().header().clone();
+    assert_eq!(header.number, 1);
+    assert_eq!(header.gas_limit, 30_000_000);
+    assert!(header.parent_beacon_block_root.is_some());
+    assert_eq!(header.requests_hash, None);
+}
+
+#[test]
+fn fork_scheduled_after_genesis_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut genesis: Value = serde_json::from_str(GENESIS).unwrap();
+    genesis["config"]["pragueTime"] = 1_700_000_000u64.into();
+    let genesis_path = dir.path().join("genesis.json");
+    fs::write(&genesis_path, genesis.to_string()).unwrap();
+
+    let config = small_config(0x62).with_genesis_path(Some(genesis_path));
+    let err = Simulation::new(config, SimulationPaths::new(dir.path()))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("activates Prague at 1700000000"), "{err}");
+}
+
+#[test]
+fn forks_active_at_genesis_are_adopted() {
+    let dir = tempfile::tempdir().unwrap();
+    let genesis_path = dir.path().join("genesis.json");
+    fs::write(&genesis_path, GENESIS).unwrap();
+
+    let config = small_config(0x63)
+        .with_hardfork(Hardfork::Shanghai)
+        .with_genesis_path(Some(genesis_path));
+    let simulation = Simulation::new(config, SimulationPaths::new(dir.path())).unwrap();
+    assert_eq!(simulation.config().hardfork, Hardfork::Cancun);
+}
@@ -0,0 +1,53 @@
+//! Every supported fork builds blocks whose headers carry exactly the fields
+//! that fork introduced.
+
+mod common;
+
+use alloy_consensus::constants::EMPTY_ROOT_HASH;
+use alloy_eips::eip7685::EMPTY_REQUESTS_HASH;
+use common::{run_in, small_config};
+use reth_sandbox::config::{Hardfork, SimulationConfig};
+
+async fn head_under(hardfork: Hardfork, seed: u8) -> alloy_consensus::Header {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(1),
+        ..small_config(seed)
+    }
+    .with_hardfork(hardfork);
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 1);
+    result.database.head().unwrap().header().clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shanghai_headers_stop_at_withdrawals() {
+    let header = head_under(Hardfork::Shanghai, 0x51).await;
+    assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT_HASH));
+    assert_eq!(header.blob_gas_used, None);
+    assert_eq!(header.excess_blob_gas, None);
+    assert_eq!(header.parent_beacon_block_root, None);
+    assert_eq!(header.requests_hash, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cancun_headers_carry_blob_gas_and_a_beacon_root() {
+    let header = head_under(Hardfork::Cancun, 0x52).await;
+    assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT_HASH));
+    // The load sends no blobs, so blob gas never builds up.
+    assert_eq!(header.blob_gas_used, Some(0));
+    assert_eq!(header.excess_blob_gas, Some(0));
+    assert!(header.parent_beacon_block_root.is_some());
+    assert_eq!(header.requests_hash, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prague_headers_commit_to_empty_requests() {
+    let header = head_under(Hardfork::Prague, 0x53).await;
+    assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT_HASH));
+    assert_eq!(header.blob_gas_used, Some(0));
+    assert_eq!(header.excess_blob_gas, Some(0));
+    assert!(header.parent_beacon_block_root.is_some());
+    // No withdrawal or consolidation requests are sent.
+    assert_eq!(header.requests_hash, Some(EMPTY_REQUESTS_HASH));
+}
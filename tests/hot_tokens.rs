@@ -0,0 +1,51 @@
+//! A hot-token set concentrates token traffic on the first setup tokens.
+
+mod common;
+
+use std::fs;
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::{HotTokens, SimulationConfig};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hot_tokens_take_most_token_traffic() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(4),
+        unique_accounts: 40,
+        unique_tokens: 6,
+        ..small_config(0x5c)
+    }
+    .with_hot_tokens(Some(HotTokens {
+        tokens: 2,
+        traffic_percent: 90,
+    }))
+    .with_pool_report(true);
+    let result = run_in(dir.path(), config).await;
+
+    // The pool report lists setup tokens in order, so its first two rows
+    // are the hot set. Tokens actors deploy during load are not in it.
+    let pools = fs::read_to_string(dir.path().join("pools.csv")).unwrap();
+    let txs: Vec<u64> = pools
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').nth(6).unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(txs.len(), 6);
+    let total: u64 = txs.iter().sum();
+    let hot: u64 = txs[..2].iter().sum();
+    assert!(total > 0, "no token traffic generated");
+
+    // The manifest counts the same traffic per token.
+    let manifest = manifest(&result);
+    let per_token = manifest["generated"]["per_token"].as_object().unwrap();
+    let counted: u64 = per_token
+        .values()
+        .map(|traffic| traffic["txs"].as_u64().unwrap())
+        .sum();
+    assert!(counted >= total);
+    assert!(
+        hot * 100 >= total * 75,
+        "hot tokens took {hot} of {total} token txs"
+    );
+}
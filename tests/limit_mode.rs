@@ -0,0 +1,90 @@
+//! What happens to queued transactions when a run limit is hit: a hard limit
+//! stops on the spot and rewinds them, a soft one builds them into final
+//! blocks so nothing already generated is thrown away. A transaction limit
+//! is exact, even when it falls part-way through a block.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::{
+    config::{FillStrategy, LimitMode, SimulationConfig, StopReason, Workload},
+    simulation::RunResult,
+};
+
+const BLOCKS: u64 = 3;
+
+async fn run(dir: &Path, limit_mode: LimitMode) -> RunResult {
+    let config = SimulationConfig {
+        num_of_blocks: Some(BLOCKS),
+        ..small_config(0x68)
+    };
+    run_in(dir, config.with_limit_mode(limit_mode)).await
+}
+
+/// Whether every generated transaction is accounted for: included,
+/// rejected, left unexecuted, or never delivered to the builder.
+fn reconciles(result: &RunResult) -> bool {
+    let manifest = manifest(result);
+    let generated = &manifest["generated"];
+    let rejected: u64 = manifest["labels"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|label| label["rejected"].as_u64().unwrap())
+        .sum();
+    generated["total"].as_u64().unwrap()
+        == result.txs + rejected + result.unexecuted + generated["undelivered"].as_u64().unwrap()
+}
+
+/// Rows in `roots.csv`, one per sealed block.
+fn sealed_blocks(dir: &Path) -> u64 {
+    let roots = fs::read_to_string(dir.join("roots.csv")).unwrap();
+    roots.lines().skip(1).count() as u64
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hard_limit_stops_at_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = run(dir.path(), LimitMode::Hard).await;
+
+    assert_eq!(result.stop_reason, StopReason::Blocks);
+    assert_eq!(result.blocks, BLOCKS);
+    assert_eq!(sealed_blocks(dir.path()), BLOCKS);
+    assert!(reconciles(&result));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn soft_limit_builds_every_queued_transaction() {
+    let dir = tempfile::tempdir().unwrap();
+    let result = run(dir.path(), LimitMode::Soft).await;
+
+    assert_eq!(result.stop_reason, StopReason::Blocks);
+    assert!(result.blocks >= BLOCKS);
+    assert_eq!(result.unexecuted, 0, "queued transactions were discarded");
+    assert_eq!(sealed_blocks(dir.path()), result.blocks);
+    assert!(reconciles(&result));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transaction_limit_is_exact_mid_block() {
+    const MAX_TXS: u64 = 1_500;
+    let dir = tempfile::tempdir().unwrap();
+    // Blocks of 1000 transfers fit the gas target, so only the transaction
+    // count seals them: the limit lands half-way through the second.
+    let config = SimulationConfig {
+        num_of_blocks: None,
+        num_of_transactions: Some(MAX_TXS),
+        unique_tokens: 0,
+        ..small_config(0x69)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_fill_strategy(FillStrategy::TxCount(1_000));
+    let result = run_in(dir.path(), config).await;
+
+    assert_eq!(result.stop_reason, StopReason::Transactions);
+    assert_eq!(result.txs, MAX_TXS);
+    assert_eq!(result.blocks, 2);
+    assert_eq!(sealed_blocks(dir.path()), 2);
+}
@@ -0,0 +1,16 @@
+//! `--log-filter` directives are checked before the subscriber is installed.
+
+use reth_sandbox::{
+    error::SandboxError,
+    logging::{self, LogFormat},
+};
+
+#[test]
+fn malformed_filter_is_rejected() {
+    match logging::init(LogFormat::Json, Some("sandbox::tx_failures=loud")) {
+        Err(SandboxError::Config(message)) => {
+            assert!(message.contains("--log-filter"), "{message}")
+        }
+        other => panic!("expected a config error, got {other:?}"),
+    }
+}
@@ -0,0 +1,20 @@
+//! The `/proc/<pid>/statm` parser behind the memory sampler, against a line
+//! captured from a running process.
+
+use reth_sandbox::memory::parse_statm;
+
+/// `size resident shared text lib data dt`, all in pages.
+const STATM: &str = include_str!("fixtures/statm");
+
+#[test]
+fn resident_pages_scale_by_page_size() {
+    assert_eq!(parse_statm(STATM, 4096), Some(2272 * 4096));
+    assert_eq!(parse_statm(STATM, 65536), Some(2272 * 65536));
+}
+
+#[test]
+fn malformed_statm_is_rejected() {
+    assert_eq!(parse_statm("", 4096), None);
+    assert_eq!(parse_statm("5969", 4096), None);
+    assert_eq!(parse_statm("5969 lots 1570", 4096), None);
+}
@@ -0,0 +1,97 @@
+//! A rejected transfer leaves its sender's tracked nonce ahead of the chain;
+//! once reconciled, the sender's later transfers execute again. Transactions
+//! cut off when the builder stops mid-batch give their nonces back.
+
+mod common;
+
+use std::fs;
+
+use alloy_primitives::Address;
+use common::{manifest, run_in, run_with_faults, small_config};
+use reth_provider::StateProvider;
+use reth_sandbox::{
+    config::{FillStrategy, SimulationConfig, Workload},
+    simulation::FaultInjection,
+};
+use serde_json::Value;
+
+const ACCOUNTS: u64 = 10;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sender_resumes_after_a_rejected_transfer() {
+    let dir = tempfile::tempdir().unwrap();
+    let actors_path = dir.path().join("actors.json");
+    let config = SimulationConfig {
+        num_of_blocks: Some(6),
+        unique_accounts: ACCOUNTS,
+        unique_tokens: 0,
+        ..small_config(0x4e)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_prefund_actors_in_genesis(true)
+    .with_actors_export_path(Some(actors_path.clone()));
+    let faults = FaultInjection {
+        fail_transfer_at: Some(3),
+        ..FaultInjection::default()
+    };
+    let result = run_with_faults(dir.path(), config, faults).await;
+    assert_eq!(result.blocks, 6);
+    assert!(result.txs >= 1_000, "only {} txs included", result.txs);
+
+    // A sender left stuck at the gap would stop at a nonce from the first
+    // batch, far below its share of the load.
+    let actors: Value = serde_json::from_str(&fs::read_to_string(actors_path).unwrap()).unwrap();
+    let state = result.database.latest().unwrap();
+    for actor in actors["actors"].as_array().unwrap() {
+        let address: Address = actor["address"].as_str().unwrap().parse().unwrap();
+        let chain_nonce = state.account_nonce(&address).unwrap().unwrap_or_default();
+        assert!(
+            chain_nonce * ACCOUNTS * 2 >= result.txs,
+            "actor {address} stopped at nonce {chain_nonce}"
+        );
+        assert_eq!(
+            actor["nonce"].as_u64(),
+            Some(chain_nonce),
+            "actor {address} tracked nonce"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stopping_mid_batch_rewinds_the_unsent_nonces() {
+    let dir = tempfile::tempdir().unwrap();
+    let actors_path = dir.path().join("actors.json");
+    // Batches far larger than a block, through a one-slot channel, so the
+    // orchestrator is part-way through a batch when the builder stops.
+    let config = SimulationConfig {
+        num_of_blocks: Some(3),
+        unique_accounts: ACCOUNTS,
+        unique_tokens: 0,
+        std_batch_size: 500,
+        ..small_config(0x6f)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_prefund_actors_in_genesis(true)
+    .with_fill_strategy(FillStrategy::TxCount(100))
+    .with_channel_buffer_size(1)
+    .with_actors_export_path(Some(actors_path.clone()));
+    let result = run_in(dir.path(), config).await;
+    assert_eq!(result.blocks, 3);
+
+    let manifest = manifest(&result);
+    assert!(manifest["generated"]["undelivered"].as_u64().unwrap() > 0);
+
+    // Every nonce handed out but never used is free again, so the tracked
+    // nonce is exactly the next one the chain expects.
+    let actors: Value = serde_json::from_str(&fs::read_to_string(actors_path).unwrap()).unwrap();
+    let state = result.database.latest().unwrap();
+    for actor in actors["actors"].as_array().unwrap() {
+        let address: Address = actor["address"].as_str().unwrap().parse().unwrap();
+        let chain_nonce = state.account_nonce(&address).unwrap().unwrap_or_default();
+        assert_eq!(
+            actor["nonce"].as_u64(),
+            Some(chain_nonce),
+            "actor {address} tracked nonce"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Every artifact lands in the run's output directory, and a second run into
+//! the same directory is refused unless forced. A run can also skip the
+//! block files altogether.
+
+mod common;
+
+use common::{run_in, small_config};
+use reth_sandbox::{
+    config::{OutputFormat, SimulationConfig},
+    error::SandboxError,
+    simulation::{Simulation, SimulationPaths},
+};
+
+const TAG: &str = "repeat";
+const STARTED_AT: u64 = 1_700_000_000;
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        unique_accounts: 5,
+        unique_tokens: 1,
+        std_batch_size: 20,
+        ..small_config(0x0d)
+    }
+    .with_tag(Some(TAG.to_string()))
+}
+
+async fn run(paths: SimulationPaths) {
+    Simulation::new(config(), paths)
+        .unwrap()
+        .run()
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn second_run_with_the_same_tag_needs_force() {
+    let root = tempfile::tempdir().unwrap();
+    let out_dir = SimulationPaths::run_dir(root.path(), STARTED_AT, Some(TAG));
+    assert_eq!(out_dir, root.path().join("1700000000-repeat"));
+
+    run(SimulationPaths::create(&out_dir, false).unwrap()).await;
+    for file in ["blocks.bin", "roots.csv", "run_manifest.json"] {
+        assert!(out_dir.join(file).is_file(), "{file} not in the output dir");
+    }
+
+    let err = SimulationPaths::create(&out_dir, false).unwrap_err();
+    assert!(
+        matches!(&err, SandboxError::OutputDirNotEmpty { path } if *path == out_dir),
+        "{err}"
+    );
+
+    run(SimulationPaths::create(&out_dir, true).unwrap()).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn no_output_format_writes_no_block_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config().with_output_format(OutputFormat::Off, "blocks.jsonl".into());
+    let result = run_in(dir.path(), config).await;
+
+    assert_eq!(result.blocks, 3);
+    for file in ["blocks.bin", "blocks.jsonl"] {
+        assert!(!dir.path().join(file).exists(), "{file} was written");
+        assert!(
+            !result
+                .artifact_paths
+                .iter()
+                .any(|path| path.ends_with(file)),
+            "{file} listed as an artifact"
+        );
+    }
+    assert!(dir.path().join("roots.csv").is_file());
+}
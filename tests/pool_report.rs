@@ -0,0 +1,83 @@
+//! The pool report reads reserves from each pair's packed reserves slot, so
+//! right after setup every pool holds exactly the liquidity its owner added,
+//! and it adds up what the load's swaps paid into each pool.
+
+mod common;
+
+use std::fs;
+
+use alloy_primitives::U256;
+use common::{run_in, small_config};
+use reth_sandbox::config::{SimulationConfig, Stage};
+
+/// WETH and tokens the setup adds to each pool.
+const POOL_ETH_RESERVE: u128 = 10_000 * 10u128.pow(18);
+const POOL_TOKEN_RESERVE: u128 = 1_000_000 * 10u128.pow(18);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn setup_pools_hold_the_added_liquidity() {
+    let dir = tempfile::tempdir().unwrap();
+    // The setup stage keeps its chain on disk for the loads.
+    let config = SimulationConfig {
+        num_of_blocks: None,
+        ..small_config(0x3b)
+    }
+    .with_in_memory(false)
+    .with_stage(Stage::Setup, Some(dir.path().join("setup")))
+    .with_pool_report(true);
+    run_in(dir.path(), config).await;
+
+    let pools = fs::read_to_string(dir.path().join("pools.csv")).unwrap();
+    let rows: Vec<Vec<&str>> = pools
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').collect())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    for row in rows {
+        let reserve_weth: U256 = row[2].parse().unwrap();
+        let reserve_token: U256 = row[3].parse().unwrap();
+        assert_eq!(
+            reserve_weth,
+            U256::from(POOL_ETH_RESERVE),
+            "pair {}",
+            row[1]
+        );
+        assert_eq!(
+            reserve_token,
+            U256::from(POOL_TOKEN_RESERVE),
+            "pair {}",
+            row[1]
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_volume_is_counted_per_pool() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(6),
+        ..small_config(0x3c)
+    }
+    .with_pool_report(true);
+    run_in(dir.path(), config).await;
+
+    let pools = fs::read_to_string(dir.path().join("pools.csv")).unwrap();
+    let mut lines = pools.lines();
+    assert!(lines.next().unwrap().ends_with(",swaps,weth_in,token_in"));
+    let mut volume = U256::ZERO;
+    for row in lines {
+        let fields: Vec<&str> = row.split(',').collect();
+        let swaps: u64 = fields[7].parse().unwrap();
+        let weth_in: U256 = fields[8].parse().unwrap();
+        let token_in: U256 = fields[9].parse().unwrap();
+        assert_eq!(
+            swaps == 0,
+            (weth_in + token_in).is_zero(),
+            "pair {}",
+            fields[1]
+        );
+        volume += weth_in + token_in;
+    }
+    assert!(!volume.is_zero(), "no swap volume");
+}
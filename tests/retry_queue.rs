@@ -0,0 +1,32 @@
+//! A swap sent ahead of the approval it spends is rejected for the nonce gap,
+//! queued, and included once the approval has run.
+
+mod common;
+
+use common::{manifest, run_with_faults, small_config};
+use reth_sandbox::{config::SimulationConfig, simulation::FaultInjection};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_ahead_of_its_approval_succeeds_on_retry() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(8),
+        ..small_config(0x6d)
+    }
+    .with_max_tx_retries(3);
+    let faults = FaultInjection {
+        swap_before_approve: true,
+        ..FaultInjection::default()
+    };
+    let result = run_with_faults(dir.path(), config, faults).await;
+
+    let manifest = manifest(&result);
+    let retries = &manifest["retries"];
+    let retried = retries["retried"].as_u64().unwrap();
+    assert!(retried > 0, "no swap was retried");
+    assert!(retries["succeeded"].as_u64().unwrap() > 0);
+
+    let swaps = &manifest["labels"]["uniswap-swap-for-eth"];
+    assert!(swaps["txs"].as_u64().unwrap() > 0);
+    assert_eq!(swaps["rejected"].as_u64(), Some(0));
+}
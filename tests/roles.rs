@@ -0,0 +1,70 @@
+//! The genesis key can act as a faucet only: it funds a separate deployer,
+//! which funds the actors and deploys the contracts, and the Uniswap factory
+//! can answer to yet another owner.
+
+mod common;
+
+use std::fs;
+
+use alloy_primitives::{Address, B256, U256};
+use alloy_signer_local::PrivateKeySigner;
+use common::{manifest, run_in, small_config};
+use reth_provider::StateProvider;
+use reth_sandbox::config::SimulationConfig;
+use serde_json::Value;
+
+/// Storage slot of `feeToSetter` in the Uniswap V2 factory, after `feeTo`.
+const FEE_TO_SETTER_SLOT: u64 = 1;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn faucet_funds_a_separate_deployer() {
+    let dir = tempfile::tempdir().unwrap();
+    let deployer = PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap();
+    let owner = PrivateKeySigner::from_bytes(&B256::repeat_byte(0x22)).unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(4),
+        unique_accounts: 10,
+        std_batch_size: 20,
+        ..small_config(0x5a)
+    }
+    .with_deployer_signer(Some(deployer.clone()))
+    .with_uniswap_owner(Some(owner.clone()));
+    let faucet = config.genesis_address();
+    let result = run_in(dir.path(), config).await;
+
+    let manifest = manifest(&result);
+    let funding = &manifest["labels"]["deployer-funding"];
+    assert_eq!(funding["txs"].as_u64(), Some(1));
+    assert_eq!(funding["rejected"].as_u64(), Some(0));
+
+    let deployments: Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("deployments.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        deployments["deployer"]
+            .as_str()
+            .unwrap()
+            .parse::<Address>()
+            .unwrap(),
+        deployer.address()
+    );
+    let factory: Address = deployments["uniswap"]["factory"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    let state = result.database.latest().unwrap();
+    // The faucet sent only the deployer's funding; the deployer sent the rest
+    // of the setup.
+    assert_eq!(state.account_nonce(&faucet).unwrap(), Some(1));
+    assert!(state.account_nonce(&deployer.address()).unwrap() > Some(10));
+    let fee_to_setter = state
+        .storage(factory, B256::from(U256::from(FEE_TO_SETTER_SLOT)))
+        .unwrap()
+        .unwrap_or_default();
+    assert_eq!(
+        Address::from_word(B256::from(fee_to_setter)),
+        owner.address()
+    );
+}
@@ -0,0 +1,135 @@
+//! The per-block gas target the builder seals at, the gas budget derived from
+//! it, and which limit `limit_hit` reports when several are reached at once.
+
+mod common;
+
+use std::time::Duration;
+
+use common::small_config;
+use reth_sandbox::config::{FillStrategy, SimulationConfig, StopReason};
+
+const GAS_LIMIT: u64 = 30_000_000;
+
+fn filled_by(strategy: FillStrategy) -> SimulationConfig {
+    small_config(0x6a).with_fill_strategy(strategy)
+}
+
+/// 3 blocks, 100 transactions, 60 seconds and 1000 bytes of block file.
+fn bounded() -> SimulationConfig {
+    SimulationConfig {
+        num_of_transactions: Some(100),
+        ..small_config(0x6a)
+    }
+    .with_max_duration(Some(Duration::from_secs(60)))
+    .with_max_output_bytes(Some(1_000))
+}
+
+#[test]
+fn gas_target_is_a_share_of_the_gas_limit() {
+    assert_eq!(small_config(0x6a).block_gas_target(), GAS_LIMIT / 2);
+    assert_eq!(
+        filled_by(FillStrategy::GasTarget(80)).block_gas_target(),
+        24_000_000
+    );
+    // Out-of-range percentages are clamped to 1..=100.
+    assert_eq!(
+        filled_by(FillStrategy::GasTarget(0)).block_gas_target(),
+        GAS_LIMIT / 100
+    );
+    assert_eq!(
+        filled_by(FillStrategy::GasTarget(250)).block_gas_target(),
+        GAS_LIMIT
+    );
+}
+
+#[test]
+fn strategies_without_a_gas_target_fill_the_whole_block() {
+    assert_eq!(
+        filled_by(FillStrategy::TxCount(100)).block_gas_target(),
+        GAS_LIMIT
+    );
+    assert_eq!(
+        filled_by(FillStrategy::ByteSize(1 << 20)).block_gas_target(),
+        GAS_LIMIT
+    );
+    // A composite seals at its smallest gas target.
+    let composite = FillStrategy::Composite(vec![
+        FillStrategy::GasTarget(80),
+        FillStrategy::TxCount(100),
+        FillStrategy::GasTarget(40),
+    ]);
+    assert_eq!(filled_by(composite).block_gas_target(), 12_000_000);
+}
+
+#[test]
+fn gas_budget_is_the_target_times_the_block_count() {
+    let config = small_config(0x6a);
+    assert_eq!(config.gas_budget(), Some(3 * GAS_LIMIT / 2));
+    let unbounded = SimulationConfig {
+        num_of_blocks: None,
+        ..small_config(0x6a)
+    };
+    assert_eq!(unbounded.gas_budget(), None);
+}
+
+#[test]
+fn no_limit_is_hit_below_every_limit() {
+    let config = bounded();
+    let budget = config.gas_budget().unwrap();
+    assert_eq!(
+        config.limit_hit(2, 99, budget - 1, Duration::from_secs(59), 999),
+        None
+    );
+}
+
+#[test]
+fn each_limit_is_reported_when_reached() {
+    let config = bounded();
+    let budget = config.gas_budget().unwrap();
+    let second = Duration::from_secs(1);
+
+    assert_eq!(
+        config.limit_hit(3, 0, 0, second, 0),
+        Some(StopReason::Blocks)
+    );
+    assert_eq!(
+        config.limit_hit(0, 100, 0, second, 0),
+        Some(StopReason::Transactions)
+    );
+    assert_eq!(
+        config.limit_hit(0, 0, budget, second, 0),
+        Some(StopReason::Gas)
+    );
+    assert_eq!(
+        config.limit_hit(0, 0, 0, Duration::from_secs(60), 0),
+        Some(StopReason::Time)
+    );
+    assert_eq!(
+        config.limit_hit(0, 0, 0, second, 1_000),
+        Some(StopReason::OutputSize)
+    );
+}
+
+#[test]
+fn limits_reached_together_report_blocks_then_volume_then_time_then_size() {
+    let config = bounded();
+    let budget = config.gas_budget().unwrap();
+    let late = Duration::from_secs(60);
+
+    assert_eq!(
+        config.limit_hit(3, 100, budget, late, 1_000),
+        Some(StopReason::Blocks)
+    );
+    assert_eq!(
+        config.limit_hit(2, 100, budget, late, 1_000),
+        Some(StopReason::Transactions)
+    );
+    assert_eq!(
+        config.limit_hit(2, 99, budget, late, 1_000),
+        Some(StopReason::Gas)
+    );
+    assert_eq!(
+        config.limit_hit(2, 99, budget - 1, late, 1_000),
+        Some(StopReason::Time)
+    );
+}
@@ -0,0 +1,107 @@
+//! A scenario file replaces the generated load: every block it lists is built
+//! as its own block holding exactly its transactions, and steps the run
+//! cannot satisfy are rejected before anything is built.
+
+mod common;
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use common::{run_in, small_config};
+use reth_sandbox::{config::SimulationConfig, scenario::Scenario};
+
+fn config(dir: &Path, scenario: Scenario) -> SimulationConfig {
+    SimulationConfig {
+        num_of_blocks: None,
+        unique_accounts: 10,
+        unique_tokens: 5,
+        gas_limit: 1_000_000_000,
+        ..small_config(0x77)
+    }
+    .with_scenario(Some(scenario))
+    .with_block_labels_out(Some(dir.join("labels.csv")))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn example_scenario_builds_one_block_per_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/example.json");
+    let scenario = Scenario::read(&example).unwrap();
+
+    let result = run_in(dir.path(), config(dir.path(), scenario)).await;
+
+    // label -> txs, by block, for every block holding a scripted transaction.
+    let scripted = ["eth-transfer", "uniswap-swap-for-token", "calldata"];
+    let mut blocks = BTreeMap::<u64, BTreeMap<String, u64>>::new();
+    let labels = fs::read_to_string(dir.path().join("labels.csv")).unwrap();
+    for row in labels.lines().skip(1) {
+        let fields = row.split(',').collect::<Vec<_>>();
+        let (block, label, txs) = (fields[0], fields[1], fields[2]);
+        assert_eq!(fields[4], "0", "failed transactions: {row}");
+        assert_eq!(fields[5], "0", "rejected transactions: {row}");
+        blocks
+            .entry(block.parse().unwrap())
+            .or_default()
+            .insert(label.to_string(), txs.parse().unwrap());
+    }
+    let load = blocks
+        .into_iter()
+        .filter(|(_, labels)| {
+            labels
+                .keys()
+                .any(|label| scripted.contains(&label.as_str()))
+        })
+        .map(|(block, labels)| (block, labels.into_iter().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+
+    let expected = [
+        ("eth-transfer", 100),
+        ("uniswap-swap-for-token", 50),
+        ("calldata", 1),
+    ];
+    assert_eq!(load.len(), expected.len(), "{load:?}");
+    for ((block, labels), (label, txs)) in load.iter().zip(expected) {
+        assert_eq!(labels, &[(label.to_string(), txs)], "block {block}");
+    }
+    // The scenario's last block is the run's last.
+    assert_eq!(load.last().unwrap().0, result.blocks);
+}
+
+#[test]
+fn unsatisfiable_steps_are_rejected_up_front() {
+    let dir = tempfile::tempdir().unwrap();
+    let scenario = Scenario::parse(
+        r#"{ "blocks": [[{ "type": "token-transfer", "count": 1, "token": 9 }]] }"#,
+    )
+    .unwrap();
+    let err = config(dir.path(), scenario).validate().unwrap_err();
+    assert!(err.to_string().contains("token 9"), "{err}");
+
+    let scenario =
+        Scenario::parse(r#"{ "blocks": [[{ "type": "eth-transfer", "count": 1, "from": 10 }]] }"#)
+            .unwrap();
+    let err = config(dir.path(), scenario).validate().unwrap_err();
+    assert!(err.to_string().contains("actor 10"), "{err}");
+
+    for (json, reason) in [
+        (
+            r#"{ "blocks": [[{ "type": "teleport", "count": 1 }]] }"#,
+            "unknown step type",
+        ),
+        (
+            r#"{ "blocks": [[{ "type": "eth-transfer", "count": 0 }]] }"#,
+            "`count` is 0",
+        ),
+        (
+            r#"{ "blocks": [[{ "type": "eth-transfer", "count": 1, "token": 1 }]] }"#,
+            "takes no `token`",
+        ),
+        (
+            r#"{ "blocks": [[{ "type": "calldata", "count": 1 }]] }"#,
+            "nonzero `bytes`",
+        ),
+        (r#"{ "blocks": [] }"#, "no `blocks`"),
+    ] {
+        let err = Scenario::parse(json).unwrap_err();
+        assert!(err.to_string().contains(reason), "{json}: {err}");
+    }
+}
@@ -0,0 +1,68 @@
+//! One setup stage feeds any number of load stages: each load builds on its
+//! own copy of the setup's chain, so loads of different lengths diverge only
+//! after the setup blocks.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use alloy_primitives::B256;
+use common::{manifest, run_in, small_config};
+use reth_provider::BlockHashReader;
+use reth_sandbox::{
+    config::{SimulationConfig, Stage},
+    simulation::RunResult,
+};
+
+/// Stages hand their chain over on disk, so none of them runs in memory.
+fn config(num_of_blocks: Option<u64>, stage: Stage, setup_dir: &Path) -> SimulationConfig {
+    SimulationConfig {
+        num_of_blocks,
+        ..small_config(0x76)
+    }
+    .with_in_memory(false)
+    .with_stage(stage, Some(setup_dir.to_path_buf()))
+}
+
+/// Hashes of blocks `1..=last` in the run's database.
+fn block_hashes(result: &RunResult, last: u64) -> Vec<B256> {
+    let factory = result.database.provider_factory().unwrap();
+    (1..=last)
+        .map(|number| factory.block_hash(number).unwrap().unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn loads_share_the_setup_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup_dir = dir.path().join("setup");
+
+    let setup_out = dir.path().join("setup-out");
+    fs::create_dir(&setup_out).unwrap();
+    let setup = run_in(&setup_out, config(None, Stage::Setup, &setup_dir)).await;
+    let setup_blocks = setup.database.head().unwrap().number;
+    assert!(setup_blocks > 0);
+    let setup_hashes = block_hashes(&setup, setup_blocks);
+    // Close the setup's database before the loads copy it.
+    drop(setup);
+
+    let mut heads = Vec::new();
+    for (name, blocks) in [("short", 2), ("long", 4)] {
+        let out = dir.path().join(name);
+        fs::create_dir(&out).unwrap();
+        let load = run_in(&out, config(Some(blocks), Stage::Load, &setup_dir)).await;
+        assert_eq!(load.blocks, blocks, "{name}");
+        assert_eq!(block_hashes(&load, setup_blocks), setup_hashes, "{name}");
+
+        // Only the load ran: no setup phase generated anything.
+        let manifest = manifest(&load);
+        let phases = manifest["generated"]["per_phase"].as_object().unwrap();
+        assert_eq!(
+            phases.keys().collect::<Vec<_>>(),
+            ["transaction-load"],
+            "{name}"
+        );
+        heads.push(load.database.head().unwrap().number);
+    }
+    assert_eq!(heads, [setup_blocks + 2, setup_blocks + 4]);
+}
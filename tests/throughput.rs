@@ -0,0 +1,45 @@
+//! Every run samples its throughput into `throughput.csv`, ending with the
+//! final totals, and reports the rates in the run manifest.
+
+mod common;
+
+use std::fs;
+
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::SimulationConfig;
+use serde_json::Value;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn last_sample_matches_the_run_totals() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SimulationConfig {
+        num_of_blocks: Some(5),
+        unique_accounts: 10,
+        unique_tokens: 1,
+        std_batch_size: 20,
+        ..small_config(0x3c)
+    };
+    let result = run_in(dir.path(), config).await;
+
+    let samples = fs::read_to_string(dir.path().join("throughput.csv")).unwrap();
+    let mut rows = samples.lines();
+    assert_eq!(rows.next(), Some("elapsed_secs,blocks,txs,gas_used"));
+    let last = rows.last().expect("no throughput sample");
+    let fields = last.split(',').collect::<Vec<_>>();
+    assert_eq!(fields[1], result.blocks.to_string());
+    assert_eq!(fields[2], result.txs.to_string());
+    assert_eq!(fields[3], result.gas.to_string());
+
+    let manifest = manifest(&result);
+    let throughput = &manifest["throughput"];
+    assert!(throughput["overall"]["gas_per_sec"].as_f64().unwrap() > 0.0);
+    // A run this short has no second minute to compare against.
+    assert_eq!(throughput["first_60s"], Value::Null);
+    assert_eq!(throughput["degraded"], Value::Bool(false));
+    assert!(
+        result
+            .artifact_paths
+            .iter()
+            .any(|path| path.ends_with("throughput.csv"))
+    );
+}
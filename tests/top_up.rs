@@ -0,0 +1,54 @@
+//! Actors whose estimated balance runs low during load are topped up from the
+//! deployer while the load keeps flowing.
+
+mod common;
+
+use alloy_primitives::{U256, utils::Unit};
+use common::{manifest, run_in, small_config};
+use reth_sandbox::config::{AmountRange, FillStrategy, SimulationConfig, Workload};
+
+const BATCH: u64 = 10;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn low_actors_are_topped_up_during_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let funding = Unit::ETHER.wei();
+    // Any actor that has sent a transfer without receiving one is below the
+    // threshold, so the first check always finds someone to top up.
+    let threshold = funding - U256::from(1);
+    let transfer = 10_000_000_000_000_000;
+    let config = SimulationConfig {
+        num_of_blocks: Some(10),
+        unique_accounts: 4,
+        unique_tokens: 0,
+        std_batch_size: BATCH,
+        ..small_config(0x75)
+    }
+    .with_workload(Workload::TransfersOnly)
+    .with_fill_strategy(FillStrategy::TxCount(BATCH))
+    // A small channel keeps generation close to the chain, so top-ups land
+    // within the run.
+    .with_channel_buffer_size(BATCH as usize)
+    .with_transfer_amounts(AmountRange::new(transfer, transfer), AmountRange::new(1, 1))
+    .with_actor_funding_amount(funding)
+    .with_top_up_every_blocks(Some(1))
+    .with_top_up_threshold(threshold);
+
+    let result = run_in(dir.path(), config).await;
+
+    let manifest = manifest(&result);
+    assert!(manifest["generated"]["top_up_rounds"].as_u64().unwrap() > 0);
+    assert!(
+        manifest["generated"]["per_type"]["funding-top-up"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+
+    let labels = &manifest["labels"];
+    assert!(labels["funding-top-up"]["txs"].as_u64().unwrap() > 0);
+    for label in ["funding-top-up", "eth-transfer"] {
+        assert_eq!(labels[label]["failed"], 0, "{label}");
+        assert_eq!(labels[label]["rejected"], 0, "{label}");
+    }
+}